@@ -1,7 +1,9 @@
+use futures::{Stream, StreamExt};
+
 use crate::{
     helpers::{
         query::{QueryConfig, QueryInput},
-        Transport, TransportCallbacks, TransportImpl,
+        StatsSnapshot, Transport, TransportCallbacks, TransportImpl,
     },
     hpke::{KeyPair, KeyRegistry},
     protocol::QueryId,
@@ -33,55 +35,28 @@ impl Setup {
     pub fn with_key_registry(
         key_registry: KeyRegistry<KeyPair>,
     ) -> (Self, TransportCallbacks<TransportImpl>) {
-        let query_processor = Arc::new(QueryProcessor::new(key_registry));
+        Self::with_processor(QueryProcessor::new(key_registry))
+    }
+
+    /// Sets up the helper to run queries on `query_processor`, configured the way the caller
+    /// wants it.
+    #[must_use]
+    pub fn with_processor(
+        query_processor: QueryProcessor,
+    ) -> (Self, TransportCallbacks<TransportImpl>) {
+        let query_processor = Arc::new(query_processor);
         let this = Self {
             query_processor: Arc::clone(&query_processor),
         };
 
         // TODO: weak reference to query processor to prevent mem leak
-        (this, Self::callbacks(&query_processor))
+        (this, TransportCallbacks::from_processor(query_processor))
     }
 
     /// Instantiate [`HelperApp`] by connecting it to the provided transport implementation
     pub fn connect(self, transport: TransportImpl) -> HelperApp {
         HelperApp::new(transport, self.query_processor)
     }
-
-    /// Create callbacks that tie up query processor and transport.
-    fn callbacks(query_processor: &Arc<QueryProcessor>) -> TransportCallbacks<TransportImpl> {
-        let rqp = Arc::clone(query_processor);
-        let pqp = Arc::clone(query_processor);
-        let iqp = Arc::clone(query_processor);
-        let sqp = Arc::clone(query_processor);
-        let cqp = Arc::clone(query_processor);
-
-        TransportCallbacks {
-            receive_query: Box::new(move |transport: TransportImpl, receive_query| {
-                let processor = Arc::clone(&rqp);
-                Box::pin(async move {
-                    let r = processor.new_query(transport, receive_query).await?;
-
-                    Ok(r.query_id)
-                })
-            }),
-            prepare_query: Box::new(move |transport: TransportImpl, prepare_query| {
-                let processor = Arc::clone(&pqp);
-                Box::pin(async move { processor.prepare(&transport, prepare_query) })
-            }),
-            query_input: Box::new(move |transport: TransportImpl, query_input| {
-                let processor = Arc::clone(&iqp);
-                Box::pin(async move { processor.receive_inputs(transport, query_input) })
-            }),
-            query_status: Box::new(move |_transport: TransportImpl, query_id| {
-                let processor = Arc::clone(&sqp);
-                Box::pin(async move { processor.query_status(query_id) })
-            }),
-            complete_query: Box::new(move |_transport: TransportImpl, query_id| {
-                let processor = Arc::clone(&cqp);
-                Box::pin(async move { processor.complete(query_id).await })
-            }),
-        }
-    }
 }
 
 impl HelperApp {
@@ -123,12 +98,44 @@ impl HelperApp {
         Ok(self.query_processor.query_status(query_id)?)
     }
 
-    /// Waits for a query to complete and returns the result.
+    /// Waits for a query to complete and returns the result. This collects the chunks of
+    /// [`complete_query_stream`] into a single buffer.
+    ///
+    /// [`complete_query_stream`]: Self::complete_query_stream
     ///
     /// ## Errors
     /// Propagates errors from the helper.
     pub async fn complete_query(&self, query_id: QueryId) -> Result<Vec<u8>, Error> {
-        Ok(self.query_processor.complete(query_id).await?.into_bytes())
+        Ok(self.complete_query_stream(query_id).await?.concat().await)
+    }
+
+    /// Waits for a query to complete and returns the result along with the statistics about
+    /// records data this helper exchanged with its peers while executing it.
+    ///
+    /// ## Errors
+    /// Propagates errors from the helper.
+    pub async fn complete_query_with_stats(
+        &self,
+        query_id: QueryId,
+    ) -> Result<(Vec<u8>, StatsSnapshot), Error> {
+        let (result, stats) = self.query_processor.complete_with_stats(query_id).await?;
+        Ok((result.into_bytes(), stats))
+    }
+
+    /// Shuts this helper down, aborting all the queries that are still running on it.
+    pub async fn shutdown(&self) {
+        self.query_processor.shutdown(&self.transport).await;
+    }
+
+    /// Waits for a query to complete and returns the result as a stream of serialized chunks.
+    ///
+    /// ## Errors
+    /// Propagates errors from the helper.
+    pub async fn complete_query_stream(
+        &self,
+        query_id: QueryId,
+    ) -> Result<impl Stream<Item = Vec<u8>>, Error> {
+        Ok(self.query_processor.complete_stream(query_id).await?)
     }
 }
 
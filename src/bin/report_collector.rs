@@ -20,7 +20,7 @@ use ipa::{
     },
     config::NetworkConfig,
     ff::{FieldType, Fp32BitPrime},
-    helpers::query::{IpaQueryConfig, QueryConfig, QuerySize, QueryType},
+    helpers::query::{IpaQueryConfig, QueryConfig, QueryType},
     hpke::{KeyRegistry, PublicKeyOnly},
     net::MpcHelperClient,
     protocol::{BreakdownKey, MatchKey},
@@ -256,11 +256,8 @@ async fn ipa(
     };
 
     let input_rows = input.iter::<TestRawDataRecord>().collect::<Vec<_>>();
-    let query_config = QueryConfig {
-        size: QuerySize::try_from(input_rows.len()).unwrap(),
-        field_type: FieldType::Fp32BitPrime,
-        query_type,
-    };
+    let query_config =
+        QueryConfig::new(query_type, FieldType::Fp32BitPrime, input_rows.len()).unwrap();
     let query_id = helper_clients[0].create_query(query_config).await.unwrap();
 
     let expected = {
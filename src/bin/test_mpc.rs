@@ -9,7 +9,7 @@ use ipa::{
         Verbosity,
     },
     ff::{Field, FieldType, Fp31, Fp32BitPrime, Serializable},
-    helpers::query::{QueryConfig, QueryType::TestMultiply},
+    helpers::query::{QueryConfig, QueryType::TestMultiply, SecurityModel},
     net::MpcHelperClient,
     secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
 };
@@ -53,6 +53,9 @@ pub struct CommandInput {
 
     #[arg(value_enum, long, default_value_t = FieldType::Fp32BitPrime, help = "Convert the input into the given field before sending to helpers")]
     field: FieldType,
+
+    #[arg(value_enum, long, default_value_t = SecurityModel::SemiHonest, help = "Security guarantees helpers must provide while running the query")]
+    security_model: SecurityModel,
 }
 
 impl From<&CommandInput> for InputSource {
@@ -107,7 +110,9 @@ where
 {
     let input = InputSource::from(&args.input);
     let input_rows = input.iter::<(F, F)>().collect::<Vec<_>>();
-    let query_config = QueryConfig::new(TestMultiply, args.input.field, input_rows.len()).unwrap();
+    let query_config = QueryConfig::new(TestMultiply, args.input.field, input_rows.len())
+        .unwrap()
+        .with_security_model(args.input.security_model);
 
     let query_id = helper_clients[0].create_query(query_config).await.unwrap();
     let expected = input_rows.iter().map(|(a, b)| *a * *b).collect::<Vec<_>>();
@@ -159,6 +159,7 @@ where
                 client.query_input(QueryInput {
                     query_id,
                     input_stream,
+                    length: None,
                 })
             }),
     )
@@ -167,9 +168,14 @@ where
 
     let mut delay = Duration::from_millis(125);
     loop {
-        if try_join_all(clients.iter().map(|client| client.query_status(query_id)))
+        let statuses = try_join_all(clients.iter().map(|client| client.query_status(query_id)))
             .await
-            .unwrap()
+            .unwrap();
+        assert!(
+            !statuses.contains(&QueryStatus::Failed),
+            "query {query_id:?} failed on at least one helper"
+        );
+        if statuses
             .into_iter()
             .all(|status| status == QueryStatus::Completed)
         {
@@ -58,6 +58,7 @@ where
                 client.query_input(QueryInput {
                     query_id,
                     input_stream,
+                    length: None,
                 })
             }),
     )
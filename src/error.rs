@@ -1,8 +1,12 @@
-use std::{backtrace::Backtrace, fmt::Debug};
+use std::{backtrace::Backtrace, fmt::Debug, time::Duration};
 
 use thiserror::Error;
 
-use crate::{report::InvalidReportError, task::JoinError};
+use crate::{
+    protocol::{step::Gate, RecordId},
+    report::InvalidReportError,
+    task::JoinError,
+};
 
 /// An error raised by the IPA protocol.
 ///
@@ -32,10 +36,8 @@ pub enum Error {
     TooManyHelpers,
     #[error("failed to parse: {0}")]
     ParseError(BoxError),
-    #[error("malicious security check failed")]
-    MaliciousSecurityCheckFailed,
-    #[error("malicious reveal failed")]
-    MaliciousRevealFailed,
+    #[error(transparent)]
+    Malicious(#[from] MaliciousError),
     #[error("problem during IO: {0}")]
     Io(#[from] std::io::Error),
     // TODO remove if this https://github.com/awslabs/shuttle/pull/109 gets approved
@@ -60,6 +62,30 @@ pub enum Error {
     Unsupported(String),
     #[error("Decompressing invalid elliptic curve point: {0}")]
     DecompressingInvalidCurvePoint(String),
+    #[error("permutation of {permutation} rows can't be applied to {values} rows")]
+    PermutationLengthMismatch { permutation: usize, values: usize },
+    #[error("query did not complete within {0:?}")]
+    QueryTimedOut(Duration),
+    #[error("query panicked: {0}")]
+    QueryPanicked(String),
+}
+
+/// Evidence that one of the helpers deviated from the protocol. Unlike the rest of [`Error`], these
+/// are not caused by the infrastructure, so retrying the query with the same helpers won't help.
+#[derive(Error, Debug)]
+pub enum MaliciousError {
+    /// Information-theoretic MACs accumulated by the malicious validator do not match.
+    #[error("malicious security check failed")]
+    ValidationFailed,
+    /// Peers sent different values for the same share while revealing it. It is reported by the
+    /// helper that received them, with the record and the step of the reveal, so the query fails
+    /// instead of producing a wrong value.
+    #[error("malicious reveal of record {record_id} at step {step} failed")]
+    RevealFailed { record_id: RecordId, step: Gate },
+    /// Shares of the same value held by different helpers are not halves of one replicated
+    /// sharing.
+    #[error("replicated shares are inconsistent")]
+    InconsistentShares,
 }
 
 impl Default for Error {
@@ -37,3 +37,12 @@ pub enum FieldType {
     Fp31,
     Fp32BitPrime,
 }
+
+impl FieldType {
+    /// Every field type supported by this build.
+    pub const ALL: &'static [FieldType] = &[
+        #[cfg(any(test, feature = "weak-field"))]
+        FieldType::Fp31,
+        FieldType::Fp32BitPrime,
+    ];
+}
@@ -24,7 +24,7 @@ impl<F: PrimeField> Serializable for F {
         let mut buf_to = [0u8; 16];
         buf_to[..buf.len()].copy_from_slice(buf);
 
-        Self::try_from(u128::from_le_bytes(buf_to)).unwrap()
+        Self::truncate_from(u128::from_le_bytes(buf_to))
     }
 }
 
@@ -144,22 +144,33 @@ macro_rules! field_impl {
             }
         }
 
+        /// Unlike [`Field::truncate_from`], this fails if `v` is not a canonical element of the
+        /// field, i.e. if it is not less than the modulus.
         impl TryFrom<u128> for $field {
             type Error = crate::error::Error;
 
             fn try_from(v: u128) -> Result<Self, Self::Error> {
-                if u128::BITS - v.leading_zeros() <= Self::BITS {
+                if v < u128::from(Self::PRIME) {
                     Ok(Self::truncate_from(v))
                 } else {
                     Err(crate::error::Error::FieldValueTruncation(format!(
-                        "Storage size {} is too small to hold the value {}.",
-                        Self::BITS,
-                        v
+                        "Value {} is out of range for {}, which has modulus {}.",
+                        v,
+                        stringify!($field),
+                        Self::PRIME
                     )))
                 }
             }
         }
 
+        impl TryFrom<u8> for $field {
+            type Error = crate::error::Error;
+
+            fn try_from(v: u8) -> Result<Self, Self::Error> {
+                Self::try_from(u128::from(v))
+            }
+        }
+
         impl From<$field> for $store {
             fn from(v: $field) -> Self {
                 v.0
@@ -205,7 +216,7 @@ macro_rules! field_impl {
                 let prime = u128::from($field::PRIME);
                 assert_eq!(
                     $field::ZERO,
-                    $field::try_from(prime).unwrap(),
+                    $field::truncate_from(prime),
                     "from takes a modulus",
                 );
                 assert_eq!($field::ZERO, $field::ZERO + $field::ZERO);
@@ -217,6 +228,20 @@ macro_rules! field_impl {
                 assert_eq!($field::ZERO, $field::ZERO * $field::ONE);
             }
 
+            #[test]
+            fn try_from_checks_range() {
+                let prime = u128::from($field::PRIME);
+                assert_eq!($field::ONE, $field::try_from(1_u128).unwrap());
+                assert_eq!($field::ONE, $field::try_from(1_u8).unwrap());
+                assert_eq!(
+                    $field::ZERO - $field::ONE,
+                    $field::try_from(prime - 1).unwrap()
+                );
+                assert!($field::try_from(prime).is_err());
+                assert!($field::try_from(prime + 1).is_err());
+                assert!($field::try_from(u128::MAX).is_err());
+            }
+
             proptest! {
 
                 #[test]
@@ -312,6 +312,24 @@ impl OrderingSender {
         Close { i, sender: self }
     }
 
+    /// Close the sender right away, after whatever has been sent so far, unless it is closed
+    /// already. Unlike [`close`], this does not wait for any index, so it can be used when it is
+    /// not known how many messages are going to be sent.
+    ///
+    /// Messages that are in the buffer are still taken out of it, but nothing can be sent after
+    /// this call.
+    ///
+    /// [`close`]: Self::close
+    ///
+    /// ## Panics
+    /// If the internal mutex is poisoned.
+    pub fn close_now(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !state.closed {
+            state.close();
+        }
+    }
+
     /// Perform the next `send` or `close` operation.
     fn next_op<F>(&self, i: usize, cx: &Context<'_>, f: F) -> Poll<()>
     where
@@ -329,6 +329,12 @@ where
         }
     }
 
+    /// How far ahead a read can be without overflowing.
+    #[cfg(all(test, unit_test))]
+    pub fn capacity(&self) -> usize {
+        self.inner.lock().unwrap().wakers.len()
+    }
+
     /// Receive from the stream at index `i`.
     ///
     /// # Panics
@@ -452,7 +458,7 @@ mod test {
                 let recv = recv.clone();
                 async move {
                     let f: Fp31 = recv.recv(0_usize).await.unwrap();
-                    assert_eq!(f, Fp31::try_from(18).unwrap());
+                    assert_eq!(f, Fp31::try_from(18_u128).unwrap());
                 }
             }),
             spawn({
@@ -39,6 +39,14 @@ pub enum Error {
         // TODO(mt): add more fields, like step and role.
         record_id: RecordId,
     },
+    #[error("Expected to receive {record_id:?} but the stream was cut short by its sender")]
+    TruncatedStream { record_id: RecordId },
+    #[error("Expected to receive {record_id:?} for step {step}, but the peer sent records for {unclaimed:?} instead")]
+    StepMismatch {
+        record_id: RecordId,
+        step: String,
+        unclaimed: Vec<String>,
+    },
     #[error("An error occurred while serializing or deserializing data for {record_id:?} and step {step}: {inner}")]
     SerializationError {
         record_id: RecordId,
@@ -48,6 +56,12 @@ pub enum Error {
     },
     #[error("Encountered unknown identity {0:?}")]
     UnknownIdentity(HelperIdentity),
+    #[error("Expected both peers to send {record_id:?} for step {step}, but {ended:?} hit end of stream")]
+    UnevenStreams {
+        step: String,
+        ended: Role,
+        record_id: RecordId,
+    },
     #[error("record ID {record_id:?} is out of range for {channel_id:?} (expected {total_records:?} records)")]
     TooManyRecords {
         record_id: RecordId,
@@ -67,6 +81,19 @@ impl Error {
         }
     }
 
+    /// Returns `true` if this error is caused by the stream of records that ended before
+    /// the requested record arrived.
+    #[must_use]
+    pub fn is_end_of_stream(&self) -> bool {
+        match self {
+            Self::EndOfStream { .. } => true,
+            Self::ReceiveError { inner, .. } => inner
+                .downcast_ref::<Self>()
+                .map_or(false, Self::is_end_of_stream),
+            _ => false,
+        }
+    }
+
     #[must_use]
     pub fn serialization_error<E: Into<BoxError>>(
         record_id: RecordId,
@@ -6,6 +6,7 @@ mod transport;
 
 use std::num::NonZeroUsize;
 
+use futures::{future::try_join, stream, Stream};
 pub(super) use receive::ReceivingEnd;
 pub(super) use send::SendingEnd;
 #[cfg(all(test, feature = "shuttle"))]
@@ -18,9 +19,9 @@ use crate::{
         gateway::{
             receive::GatewayReceivers, send::GatewaySenders, transport::RoleResolvingTransport,
         },
-        ChannelId, Message, Role, RoleAssignment, TotalRecords, Transport,
+        ChannelId, Direction, Error, Message, Role, RoleAssignment, TotalRecords, Transport,
     },
-    protocol::QueryId,
+    protocol::{step::Gate, QueryId, RecordId},
 };
 
 /// Alias for the currently configured transport.
@@ -62,6 +63,21 @@ pub struct GatewayConfig {
     /// send/receive requests
     #[cfg(feature = "stall-detection")]
     pub progress_check_interval: std::time::Duration,
+
+    /// Seed for the randomness helpers use to set up PRSS for the query. Fixing it makes
+    /// queries reproducible, which must never be possible outside of tests.
+    #[cfg(any(test, feature = "test-fixture"))]
+    seed: Option<u64>,
+
+    /// The number of bytes of records data a peer can make this helper buffer within a query,
+    /// before anyone asked to receive it. Unlimited if not set.
+    receive_buffer_limit: Option<NonZeroUsize>,
+
+    /// The largest number of records receive buffers are sized for, when steps say how many
+    /// records they expect. Buffers are never smaller than [`active_work`].
+    ///
+    /// [`active_work`]: Self::active_work
+    max_receive_capacity: NonZeroUsize,
 }
 
 impl Gateway {
@@ -79,7 +95,6 @@ impl Gateway {
                 query_id,
                 roles,
                 inner: transport,
-                config,
             },
             inner: State::default().into(),
         }
@@ -126,17 +141,109 @@ impl Gateway {
         send::SendingEnd::new(tx, self.role(), channel_id)
     }
 
+    /// Closes every channel of this gateway. Data already sent to any of them is still delivered,
+    /// after which peers see the end of the stream, whether or not all the records they expect have
+    /// been sent. Records received from peers and not taken out of receiving channels are dropped.
+    ///
+    /// Queries close their gateway once they finish, so channels of that query are released right
+    /// away rather than whenever the transport gets around to it.
+    pub fn close(self) {
+        self.inner.senders.close();
+        self.inner.receivers.close();
+    }
+
+    /// Receives records sent for `gate` by both peers of this helper and yields them as
+    /// `(record_id, left, right)` tuples, in the order of record ids. Next pair is received only
+    /// after the previous one is taken out of the stream.
+    ///
+    /// Stream ends when both peers end their streams at the same record. If only one of them does,
+    /// the stream yields [`Error::UnevenStreams`] and ends.
+    #[cfg(not(feature = "stall-detection"))]
+    pub fn receive_both<M: Message>(
+        &self,
+        gate: &Gate,
+    ) -> impl Stream<Item = Result<(RecordId, M, M), Error>> + '_ {
+        receive_pairs(gate, self.role(), |channel_id| {
+            self.get_receiver(channel_id, TotalRecords::Unspecified)
+        })
+    }
+
+    /// Returns the receiving end of the channel. `total_records` is only a hint for sizing the
+    /// receive buffer, taken from whoever asks for the channel first. Receiving more records than
+    /// that is fine.
     #[must_use]
-    pub fn get_receiver<M: Message>(&self, channel_id: &ChannelId) -> receive::ReceivingEnd<M> {
+    pub fn get_receiver<M: Message>(
+        &self,
+        channel_id: &ChannelId,
+        total_records: TotalRecords,
+    ) -> receive::ReceivingEnd<M> {
         receive::ReceivingEnd::new(
             channel_id.clone(),
-            self.inner
-                .receivers
-                .get_or_create(channel_id, || self.transport.receive(channel_id)),
+            self.inner.receivers.get_or_create(channel_id, || {
+                self.transport
+                    .receive(channel_id, self.config.receive_capacity(total_records))
+            }),
         )
     }
 }
 
+/// Turns records received from the left and right peers into a stream of `(record_id, left, right)`
+/// tuples, in the order of record ids. Next pair is requested only after the previous one is taken
+/// out of the stream, so a peer can't get further ahead of the other than the receive buffer of its
+/// channel allows.
+///
+/// Stream ends when both peers end their streams at the same record. If only one of them does,
+/// the stream yields [`Error::UnevenStreams`] and ends.
+fn receive_pairs<M, F>(
+    gate: &Gate,
+    role: Role,
+    get_receiver: F,
+) -> impl Stream<Item = Result<(RecordId, M, M), Error>>
+where
+    M: Message,
+    F: Fn(&ChannelId) -> crate::helpers::ReceivingEnd<M>,
+{
+    async fn try_receive<M: Message>(
+        channel: &crate::helpers::ReceivingEnd<M>,
+        record_id: RecordId,
+    ) -> Result<Option<M>, Error> {
+        match channel.receive(record_id).await {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if e.is_end_of_stream() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    let [left_role, right_role] = [Direction::Left, Direction::Right].map(|d| role.peer(d));
+    let left = get_receiver(&ChannelId::new(left_role, gate.clone()));
+    let right = get_receiver(&ChannelId::new(right_role, gate.clone()));
+    let step = gate.to_string();
+    stream::unfold(Some((RecordId::FIRST, left, right)), move |state| {
+        let step = step.clone();
+        async move {
+            let (record_id, left, right) = state?;
+            let result = match try_join(
+                try_receive(&left, record_id),
+                try_receive(&right, record_id),
+            )
+            .await
+            {
+                Ok((Some(l), Some(r))) => Ok((record_id, l, r)),
+                Ok((None, None)) => return None,
+                Ok((l, _)) => Err(Error::UnevenStreams {
+                    step,
+                    ended: if l.is_none() { left_role } else { right_role },
+                    record_id,
+                }),
+                Err(e) => Err(e),
+            };
+            let next = result.is_ok().then(|| (record_id + 1, left, right));
+
+            Some((result, next))
+        }
+    })
+}
+
 impl Default for GatewayConfig {
     fn default() -> Self {
         Self::new(1024)
@@ -162,6 +269,10 @@ impl GatewayConfig {
             } else {
                 30
             }),
+            #[cfg(any(test, feature = "test-fixture"))]
+            seed: None,
+            receive_buffer_limit: None,
+            max_receive_capacity: NonZeroUsize::new(active).unwrap(),
         }
     }
 
@@ -170,21 +281,299 @@ impl GatewayConfig {
     pub fn active_work(&self) -> NonZeroUsize {
         self.active
     }
+
+    /// Makes the shared randomness of queries deterministic. Every helper derives its own
+    /// randomness from `seed` and its role, so running the same query twice with the same seed
+    /// and inputs produces identical shares.
+    #[cfg(any(test, feature = "test-fixture"))]
+    #[must_use]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The seed set by [`with_seed`], if any.
+    ///
+    /// [`with_seed`]: Self::with_seed
+    #[cfg(any(test, feature = "test-fixture"))]
+    #[must_use]
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Limits the amount of records data every peer can send to this helper within a query ahead
+    /// of receivers. Streams that go over the limit are rejected.
+    #[must_use]
+    pub fn with_receive_buffer_limit(mut self, bytes: NonZeroUsize) -> Self {
+        self.receive_buffer_limit = Some(bytes);
+        self
+    }
+
+    /// The limit set by [`with_receive_buffer_limit`], if any.
+    ///
+    /// [`with_receive_buffer_limit`]: Self::with_receive_buffer_limit
+    #[must_use]
+    pub fn receive_buffer_limit(&self) -> Option<NonZeroUsize> {
+        self.receive_buffer_limit
+    }
+
+    /// Lets receive buffers grow up to `records` when steps expect that many records. By default,
+    /// all receive buffers are sized for [`active_work`] records.
+    ///
+    /// [`active_work`]: Self::active_work
+    #[must_use]
+    pub fn with_max_receive_capacity(mut self, records: NonZeroUsize) -> Self {
+        self.max_receive_capacity = records;
+        self
+    }
+
+    /// The number of records to size the receive buffer for, given how many records the step
+    /// expects.
+    #[must_use]
+    pub fn receive_capacity(&self, total_records: TotalRecords) -> NonZeroUsize {
+        match total_records {
+            TotalRecords::Specified(count) => count.min(self.max_receive_capacity),
+            TotalRecords::Unspecified | TotalRecords::Indeterminate => self.active,
+        }
+        .max(self.active)
+    }
 }
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use std::iter::{repeat, zip};
+    use std::{
+        array,
+        iter::{repeat, zip},
+        num::NonZeroUsize,
+        time::Duration,
+    };
 
+    use futures::{stream, StreamExt};
     use futures_util::future::{join, try_join, try_join_all};
+    use tokio::time::{sleep, Instant};
 
+    use super::Gateway;
     use crate::{
         ff::{Field, Fp31, Fp32BitPrime, Gf2},
-        helpers::{Direction, GatewayConfig, Role, SendingEnd},
-        protocol::{context::Context, RecordId},
+        helpers::{
+            ChannelId, Direction, Error, GatewayConfig, HelperIdentity, InMemoryNetwork, Role,
+            RoleAssignment, RouteId, SendingEnd, TotalRecords, Transport, TransportCallbacks,
+        },
+        protocol::{context::Context, step::Gate, QueryId, RecordId},
         test_fixture::{Runner, TestWorld, TestWorldConfig},
     };
 
+    /// Helper with a small send buffer is blocked until its peer starts receiving, while the
+    /// helper with a large one hands all of its messages over without waiting.
+    #[tokio::test(start_paused = true)]
+    async fn small_buffer_applies_backpressure() {
+        const TOTAL_RECORDS: usize = 512;
+        const RECEIVE_DELAY: Duration = Duration::from_secs(1);
+
+        /// Sends all records from `sender` to `receiver`, which starts receiving them only after
+        /// [`RECEIVE_DELAY`]. Returns for how long the sender was blocked.
+        async fn blocked_for(sender: &Gateway, receiver: &Gateway) -> Duration {
+            let gate = Gate::from("backpressure");
+            let total_records = TotalRecords::from(TOTAL_RECORDS);
+            let value = |record: usize| Fp31::truncate_from(u128::try_from(record).unwrap());
+
+            let send = async {
+                let start = Instant::now();
+                let channel_id = ChannelId::new(receiver.role(), gate.clone());
+                let send_channel = sender.get_sender::<Fp31>(&channel_id, total_records);
+                for record in 0..TOTAL_RECORDS {
+                    send_channel
+                        .send(RecordId::from(record), value(record))
+                        .await
+                        .unwrap();
+                }
+
+                start.elapsed()
+            };
+            let receive = async {
+                sleep(RECEIVE_DELAY).await;
+                let channel_id = ChannelId::new(sender.role(), gate.clone());
+                let receive_channel = receiver.get_receiver::<Fp31>(&channel_id, total_records);
+                for record in 0..TOTAL_RECORDS {
+                    assert_eq!(
+                        value(record),
+                        receive_channel
+                            .receive(RecordId::from(record))
+                            .await
+                            .unwrap()
+                    );
+                }
+            };
+
+            join(send, receive).await.0
+        }
+
+        let configs = [
+            GatewayConfig::new(1),
+            GatewayConfig::new(TOTAL_RECORDS),
+            GatewayConfig::default(),
+        ];
+        let network = InMemoryNetwork::with_config(
+            configs,
+            array::from_fn(|_| TransportCallbacks::default()),
+        );
+        let roles = RoleAssignment::new(network.helper_identities());
+        let mut configs = configs.into_iter();
+        let [small, large, other] = network.transports().map(|transport| {
+            Gateway::new(
+                QueryId::default(),
+                configs.next().unwrap(),
+                roles.clone(),
+                transport,
+            )
+        });
+
+        let large_blocked = blocked_for(&large, &other).await;
+        assert!(
+            large_blocked < RECEIVE_DELAY,
+            "large buffer blocked the sender for {large_blocked:?}"
+        );
+        let small_blocked = blocked_for(&small, &large).await;
+        assert!(
+            small_blocked >= RECEIVE_DELAY,
+            "small buffer blocked the sender for {small_blocked:?} only"
+        );
+    }
+
+    /// Closing the gateway ends every stream it was sending, whether or not all the records were
+    /// sent, and delivers everything that was.
+    #[tokio::test]
+    async fn close_ends_streams() {
+        let network = InMemoryNetwork::default();
+        let roles = RoleAssignment::new(network.helper_identities());
+        let [t1, t2, _] = network.transports();
+        let h1 = Gateway::new(QueryId::default(), GatewayConfig::default(), roles, t1);
+        let [sent, empty] = ["sent", "empty"].map(Gate::from);
+
+        let sender = h1.get_sender::<Fp31>(
+            &ChannelId::new(Role::H2, sent.clone()),
+            TotalRecords::Indeterminate,
+        );
+        for i in 0..2_u32 {
+            sender
+                .send(RecordId::from(i), Fp31::truncate_from(i))
+                .await
+                .unwrap();
+        }
+        drop(sender);
+        // this channel is opened, but nothing is ever sent to it
+        drop(h1.get_sender::<Fp31>(
+            &ChannelId::new(Role::H2, empty.clone()),
+            TotalRecords::from(1),
+        ));
+        h1.close();
+
+        let receive = |gate: Gate| {
+            t2.receive(HelperIdentity::ONE, (QueryId::default(), gate))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![0, 1], receive(sent).await.concat());
+        assert!(receive(empty).await.is_empty());
+    }
+
+    /// Receiving for a step the peer never sent records for, while it sent records for another
+    /// one, is reported as the mismatch of steps between helpers.
+    #[tokio::test]
+    async fn step_mismatch() {
+        let network = InMemoryNetwork::default();
+        let roles = RoleAssignment::new(network.helper_identities());
+        let [t1, t2, _] = network.transports();
+        let h2 = Gateway::new(
+            QueryId::default(),
+            GatewayConfig::default(),
+            roles,
+            Transport::clone_ref(&t2),
+        );
+        let [sent, expected] = ["sent", "expected"].map(Gate::from);
+
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), sent.clone()),
+            stream::iter(vec![vec![1]]),
+        )
+        .await
+        .unwrap();
+        t2.shutdown().await;
+
+        let err = h2
+            .get_receiver::<Fp31>(
+                &ChannelId::new(Role::H1, expected.clone()),
+                TotalRecords::from(1),
+            )
+            .receive(RecordId::FIRST)
+            .await
+            .unwrap_err();
+        let Error::ReceiveError { inner, .. } = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert!(
+            matches!(
+                inner.downcast_ref::<Error>(),
+                Some(Error::StepMismatch { record_id, step, unclaimed })
+                    if *record_id == RecordId::FIRST
+                        && *step == expected.to_string()
+                        && *unclaimed == vec![sent.to_string()]
+            ),
+            "{inner:?}"
+        );
+    }
+
+    /// Total records set for the step size its receive buffer, within the limits set by the
+    /// config. It is only a hint: receiving more records than that works the same way.
+    #[tokio::test]
+    async fn total_records_hint_receive_capacity() {
+        const COUNT: usize = 10;
+        let config =
+            GatewayConfig::new(4).with_max_receive_capacity(NonZeroUsize::new(64).unwrap());
+        let network = InMemoryNetwork::default();
+        let roles = RoleAssignment::new(network.helper_identities());
+        let [h1, h2, _] = network
+            .transports()
+            .map(|transport| Gateway::new(QueryId::default(), config, roles.clone(), transport));
+
+        let capacity = |step: &str, total_records: TotalRecords| {
+            h2.get_receiver::<Fp31>(&ChannelId::new(Role::H1, Gate::from(step)), total_records)
+                .capacity()
+        };
+        assert_eq!(4, capacity("unspecified", TotalRecords::Unspecified));
+        assert_eq!(4, capacity("indeterminate", TotalRecords::Indeterminate));
+        assert_eq!(4, capacity("small", TotalRecords::from(2)));
+        assert_eq!(16, capacity("medium", TotalRecords::from(16)));
+        assert_eq!(64, capacity("large", TotalRecords::from(1000)));
+        // the channel already exists, so it keeps the hint it was created with
+        assert_eq!(16, capacity("medium", TotalRecords::from(1000)));
+
+        let channel_id = ChannelId::new(Role::H1, Gate::from("small"));
+        let sender = h1.get_sender::<Fp31>(
+            &ChannelId::new(Role::H2, channel_id.gate.clone()),
+            TotalRecords::from(COUNT),
+        );
+        let receiver = h2.get_receiver::<Fp31>(&channel_id, TotalRecords::from(2));
+        let (_, received) = try_join(
+            try_join_all((0..COUNT).map(|i| {
+                sender.send(
+                    RecordId::from(i),
+                    Fp31::truncate_from(u128::try_from(i).unwrap()),
+                )
+            })),
+            try_join_all((0..COUNT).map(|i| receiver.receive(RecordId::from(i)))),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            (0..COUNT)
+                .map(|i| Fp31::truncate_from(u128::try_from(i).unwrap()))
+                .collect::<Vec<_>>(),
+            received
+        );
+    }
+
     /// Verifies that [`Gateway`] send buffer capacity is adjusted to the message size.
     /// IPA protocol opens many channels to send values from different fields, while message size
     /// is set per channel, it does not have to be the same across multiple send channels.
@@ -364,6 +753,90 @@ mod tests {
         let _world = unsafe { Box::from_raw(world_ptr) };
     }
 
+    /// Sends `count` records from both peers of H1 and returns what H1 gets out of
+    /// [`Gateway::receive_both`]. Left peer sends `i`, right peer sends `i * 2` for record `i`.
+    async fn receive_both(
+        [left_count, right_count]: [usize; 2],
+    ) -> Vec<Result<(RecordId, Fp32BitPrime, Fp32BitPrime), Error>> {
+        // the one that helpers use, it may be instrumented
+        use crate::helpers::Gateway;
+
+        let network = InMemoryNetwork::default();
+        let roles = RoleAssignment::new(network.helper_identities());
+        let [h1, h2, h3] = network.transports().map(|transport| {
+            Gateway::new(
+                QueryId::default(),
+                GatewayConfig::new(4),
+                roles.clone(),
+                transport,
+            )
+        });
+        let gate = Gate::from("receive-both");
+
+        let send = |gateway: &Gateway, count: usize, multiplier: u128| {
+            let sender = gateway.get_sender::<Fp32BitPrime>(
+                &ChannelId::new(Role::H1, gate.clone()),
+                TotalRecords::from(count),
+            );
+            async move {
+                for i in 0..count {
+                    let v = Fp32BitPrime::truncate_from(u128::try_from(i).unwrap() * multiplier);
+                    sender.send(RecordId::from(i), v).await.unwrap();
+                }
+            }
+        };
+        assert_eq!(Role::H3, Role::H1.peer(Direction::Left));
+
+        let (received, _) = join(
+            h1.receive_both::<Fp32BitPrime>(&gate).collect::<Vec<_>>(),
+            join(send(&h3, left_count, 1), send(&h2, right_count, 2)),
+        )
+        .await;
+
+        received
+    }
+
+    #[tokio::test]
+    async fn receive_both_pairs_records() {
+        const COUNT: usize = 50;
+
+        let received = receive_both([COUNT, COUNT])
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            (0..COUNT)
+                .map(|i| {
+                    let v = u128::try_from(i).unwrap();
+                    (
+                        RecordId::from(i),
+                        Fp32BitPrime::truncate_from(v),
+                        Fp32BitPrime::truncate_from(v * 2),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            received
+        );
+    }
+
+    #[tokio::test]
+    async fn receive_both_uneven_streams() {
+        let mut received = receive_both([10, 9]).await;
+
+        assert!(matches!(
+            received.pop(),
+            Some(Err(Error::UnevenStreams {
+                ended: Role::H2,
+                record_id,
+                ..
+            })) if record_id == RecordId::from(9_u32)
+        ));
+        assert_eq!(9, received.len());
+        assert!(received.iter().all(Result::is_ok));
+    }
+
     fn make_world() -> (&'static TestWorld, *mut TestWorld) {
         let world = Box::leak(Box::<TestWorld>::default());
         let world_ptr = world as *mut _;
@@ -4,7 +4,11 @@ use dashmap::{mapref::entry::Entry, DashMap};
 use futures::Stream;
 
 use crate::{
-    helpers::{buffers::UnorderedReceiver, ChannelId, Error, Message, Transport, TransportImpl},
+    helpers::{
+        buffers::UnorderedReceiver,
+        transport::{Truncated, Truncation},
+        ChannelId, Error, Message, Transport, TransportImpl,
+    },
     protocol::RecordId,
 };
 
@@ -12,13 +16,14 @@ use crate::{
 pub struct ReceivingEnd<M: Message> {
     channel_id: ChannelId,
     unordered_rx: UR,
+    truncated: Truncated,
     _phantom: PhantomData<M>,
 }
 
 /// Receiving channels, indexed by (role, step).
 #[derive(Default)]
 pub(super) struct GatewayReceivers {
-    pub(super) inner: DashMap<ChannelId, UR>,
+    pub(super) inner: DashMap<ChannelId, (UR, Truncated)>,
 }
 
 pub(super) type UR = UnorderedReceiver<
@@ -27,10 +32,11 @@ pub(super) type UR = UnorderedReceiver<
 >;
 
 impl<M: Message> ReceivingEnd<M> {
-    pub(super) fn new(channel_id: ChannelId, rx: UR) -> Self {
+    pub(super) fn new(channel_id: ChannelId, (rx, truncated): (UR, Truncated)) -> Self {
         Self {
             channel_id,
             unordered_rx: rx,
+            truncated,
             _phantom: PhantomData,
         }
     }
@@ -49,16 +55,51 @@ impl<M: Message> ReceivingEnd<M> {
         self.unordered_rx
             .recv::<M, _>(record_id)
             .await
-            .map_err(|e| Error::ReceiveError {
-                source: self.channel_id.role,
-                step: self.channel_id.gate.to_string(),
-                inner: Box::new(e),
+            .map_err(|e| {
+                // Stream that was cut short ends too, but it must not pass for a finished one.
+                // If it never arrived while the peer sent streams no one received, helpers
+                // narrowed into different steps, so the full path of this one is reported.
+                let e = match (e, self.truncated.cause()) {
+                    (
+                        Error::EndOfStream { record_id },
+                        Some(Truncation::NeverArrived { unclaimed }),
+                    ) if !unclaimed.is_empty() => Error::StepMismatch {
+                        record_id,
+                        step: self.channel_id.gate.to_string(),
+                        unclaimed: unclaimed.iter().map(ToString::to_string).collect(),
+                    },
+                    (Error::EndOfStream { record_id }, Some(_)) => {
+                        Error::TruncatedStream { record_id }
+                    }
+                    (e, _) => e,
+                };
+                Error::ReceiveError {
+                    source: self.channel_id.role,
+                    step: self.channel_id.gate.to_string(),
+                    inner: Box::new(e),
+                }
             })
     }
 }
 
+#[cfg(all(test, unit_test))]
+impl<M: Message> ReceivingEnd<M> {
+    pub(super) fn capacity(&self) -> usize {
+        self.unordered_rx.capacity()
+    }
+}
+
 impl GatewayReceivers {
-    pub fn get_or_create<F: FnOnce() -> UR>(&self, channel_id: &ChannelId, ctr: F) -> UR {
+    /// Drops every channel, releasing records buffered for it.
+    pub(super) fn close(&self) {
+        self.inner.clear();
+    }
+
+    pub fn get_or_create<F: FnOnce() -> (UR, Truncated)>(
+        &self,
+        channel_id: &ChannelId,
+        ctr: F,
+    ) -> (UR, Truncated) {
         // TODO: raw entry API if it becomes available to avoid cloning the key
         match self.inner.entry(channel_id.clone()) {
             Entry::Occupied(entry) => entry.get().clone(),
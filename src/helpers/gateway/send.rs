@@ -78,6 +78,11 @@ impl GatewaySender {
         Ok(())
     }
 
+    /// Ends the stream of this channel after whatever has been sent so far.
+    pub fn close(&self) {
+        self.ordering_tx.close_now();
+    }
+
     #[cfg(feature = "stall-detection")]
     pub fn waiting(&self) -> Vec<usize> {
         self.ordering_tx.waiting()
@@ -160,9 +165,16 @@ impl GatewaySenders {
                         .expect("capacity should not overflow")
                 };
 
+                // Spare capacity must be larger than a single message, otherwise the sender
+                // can't make progress. Most messages are small, but vectorized shares can be
+                // much larger than the default.
+                let spare = SPARE
+                    .unwrap()
+                    .max(NonZeroUsize::new(M::Size::USIZE + 1).unwrap());
+
                 let sender = Arc::new(GatewaySender::new(
                     channel_id.clone(),
-                    OrderingSender::new(write_size, SPARE.unwrap()),
+                    OrderingSender::new(write_size, spare),
                     total_records,
                 ));
                 entry.insert(Arc::clone(&sender));
@@ -176,6 +188,16 @@ impl GatewaySenders {
     }
 }
 
+impl GatewaySenders {
+    /// Closes every channel and forgets about it.
+    pub(super) fn close(&self) {
+        for sender in &self.inner {
+            sender.close();
+        }
+        self.inner.clear();
+    }
+}
+
 impl Stream for GatewaySendStream {
     type Item = Vec<u8>;
 
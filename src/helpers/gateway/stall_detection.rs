@@ -68,15 +68,16 @@ impl<T: ObserveState> Observed<T> {
 
 mod gateway {
     use delegate::delegate;
+    use futures::Stream;
 
     use super::*;
     use crate::{
         helpers::{
-            gateway::{Gateway, State},
-            ChannelId, GatewayConfig, Message, ReceivingEnd, Role, RoleAssignment, SendingEnd,
-            TotalRecords, TransportImpl,
+            gateway::{receive_pairs, Gateway, State},
+            ChannelId, Error, GatewayConfig, Message, ReceivingEnd, Role, RoleAssignment,
+            SendingEnd, TotalRecords, TransportImpl,
         },
-        protocol::QueryId,
+        protocol::{step::Gate, QueryId, RecordId},
         sync::Arc,
     };
 
@@ -145,6 +146,10 @@ mod gateway {
             r
         }
 
+        pub fn close(self) {
+            self.inner.gateway.close();
+        }
+
         #[must_use]
         pub fn get_sender<M: Message>(
             &self,
@@ -157,11 +162,24 @@ mod gateway {
             )
         }
 
+        pub fn receive_both<M: Message>(
+            &self,
+            gate: &Gate,
+        ) -> impl Stream<Item = Result<(RecordId, M, M), Error>> + '_ {
+            receive_pairs(gate, self.role(), |channel_id| {
+                self.get_receiver(channel_id, TotalRecords::Unspecified)
+            })
+        }
+
         #[must_use]
-        pub fn get_receiver<M: Message>(&self, channel_id: &ChannelId) -> ReceivingEnd<M> {
+        pub fn get_receiver<M: Message>(
+            &self,
+            channel_id: &ChannelId,
+            total_records: TotalRecords,
+        ) -> ReceivingEnd<M> {
             Observed::wrap(
                 Weak::clone(self.get_sn()),
-                self.inner().gateway.get_receiver(channel_id),
+                self.inner().gateway.get_receiver(channel_id, total_records),
             )
         }
 
@@ -257,7 +275,7 @@ mod receive {
             let mut map = BTreeMap::default();
             for entry in &self.inner {
                 let channel = entry.key();
-                if let Some(waiting) = super::to_ranges(entry.value().waiting()).get_state() {
+                if let Some(waiting) = super::to_ranges(entry.value().0.waiting()).get_state() {
                     map.insert(channel.clone(), waiting);
                 }
             }
@@ -1,8 +1,11 @@
+use std::num::NonZeroUsize;
+
 use crate::{
     helpers::{
         buffers::UnorderedReceiver,
         gateway::{receive::UR, send::GatewaySendStream},
-        ChannelId, GatewayConfig, Role, RoleAssignment, RouteId, Transport, TransportImpl,
+        transport::Truncated,
+        ChannelId, Role, RoleAssignment, RouteId, Transport, TransportImpl,
     },
     protocol::QueryId,
 };
@@ -15,7 +18,6 @@ use crate::{
 pub(super) struct RoleResolvingTransport {
     pub query_id: QueryId,
     pub roles: RoleAssignment,
-    pub config: GatewayConfig,
     pub inner: TransportImpl,
 }
 
@@ -41,7 +43,13 @@ impl RoleResolvingTransport {
             .await
     }
 
-    pub(crate) fn receive(&self, channel_id: &ChannelId) -> UR {
+    /// Returns the receiver for `channel_id`, along with the flag that tells whether its stream was
+    /// cut short by the sender.
+    pub(crate) fn receive(
+        &self,
+        channel_id: &ChannelId,
+        capacity: NonZeroUsize,
+    ) -> (UR, Truncated) {
         let peer = self.roles.identity(channel_id.role);
         assert_ne!(
             peer,
@@ -49,12 +57,14 @@ impl RoleResolvingTransport {
             "can't receive message from itself"
         );
 
-        UnorderedReceiver::new(
-            Box::pin(
-                self.inner
-                    .receive(peer, (self.query_id, channel_id.gate.clone())),
-            ),
-            self.config.active_work(),
+        let stream = self
+            .inner
+            .receive(peer, (self.query_id, channel_id.gate.clone()));
+        let truncated = stream.truncated();
+
+        (
+            UnorderedReceiver::new(Box::pin(stream), capacity),
+            truncated,
         )
     }
 
@@ -1,6 +1,7 @@
 use std::{
     fmt::{Debug, Display, Formatter},
     num::NonZeroUsize,
+    str::FromStr,
 };
 
 use generic_array::GenericArray;
@@ -46,14 +47,24 @@ pub use gateway::{TransportError, TransportImpl};
 pub use gateway_exports::{Gateway, ReceivingEnd, SendingEnd};
 pub use prss_protocol::negotiate as negotiate_prss;
 #[cfg(feature = "web-app")]
+pub(crate) use transport::frame as frame_records;
+#[cfg(feature = "web-app")]
+pub(crate) use transport::trace as transport_trace;
+#[cfg(feature = "web-app")]
+pub(crate) use transport::Drain as TransportDrain;
+#[cfg(feature = "web-app")]
 pub use transport::WrappedAxumBodyStream;
 pub use transport::{
-    callbacks::*, query, BodyStream, BytesStream, LengthDelimitedStream, LogErrors,
-    NoResourceIdentifier, QueryIdBinding, ReceiveRecords, RecordsStream, RouteId, RouteParams,
-    StepBinding, StreamCollection, StreamKey, Transport, WrappedBoxBodyStream,
+    callbacks::*, merge_ordered, query, BodyStream, BytesStream, ChannelStats,
+    LengthDelimitedStream, LogErrors, MergeOrdered, NoResourceIdentifier, Priority, QueryIdBinding,
+    ReceiveRecords, RecordsStream, RouteId, RouteParams, StatsKey, StatsSnapshot, StepBinding,
+    StreamCollection, StreamKey, Transport, TransportStats, UnsupportedTransport,
+    WrappedBoxBodyStream, SHUTDOWN_DEADLINE,
 };
 #[cfg(feature = "in-memory-infra")]
-pub use transport::{InMemoryNetwork, InMemoryTransport};
+pub use transport::{
+    InMemoryNetwork, InMemorySetup, InMemoryTransport, NetworkConfig, SendInterceptor, SendObserver,
+};
 use typenum::{Unsigned, U8};
 use x25519_dalek::PublicKey;
 
@@ -134,6 +145,34 @@ impl Debug for HelperIdentity {
     }
 }
 
+/// Writes the number of the helper, the same way it is serialized. [`FromStr`] parses it back.
+impl Display for HelperIdentity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+/// Parses helper identities written in configuration files and command line arguments, either as
+/// the number of the helper, `"1"` to `"3"`, or prefixed with `H`, `"H1"` to `"H3"`.
+impl FromStr for HelperIdentity {
+    type Err = InvalidHelperIdentity;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "1" | "H1" => Ok(Self::ONE),
+            "2" | "H2" => Ok(Self::TWO),
+            "3" | "H3" => Ok(Self::THREE),
+            _ => Err(InvalidHelperIdentity(s.to_owned())),
+        }
+    }
+}
+
+/// Returned when parsing a string that does not name a helper, see [`HelperIdentity`]'s
+/// [`FromStr`] implementation.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("{0:?} is not a valid helper identity, expected one of 1, 2, 3 or H1, H2, H3")]
+pub struct InvalidHelperIdentity(String);
+
 #[cfg(feature = "web-app")]
 impl From<HelperIdentity> for hyper::header::HeaderValue {
     fn from(id: HelperIdentity) -> Self {
@@ -157,19 +196,33 @@ impl HelperIdentity {
     pub const TWO: Self = Self { id: 2 };
     pub const THREE: Self = Self { id: 3 };
 
-    /// Given a helper identity, return an array of the identities of the other two helpers.
-    // The order that helpers are returned here is not intended to be meaningful, however,
-    // it is currently used directly to determine the assignment of roles in
-    // `Processor::new_query`.
+    /// Returns the identity of the helper that follows this one on the ring `A -> B -> C -> A`.
     #[must_use]
-    pub fn others(&self) -> [HelperIdentity; 2] {
-        match self.id {
-            1 => [Self::TWO, Self::THREE],
-            2 => [Self::THREE, Self::ONE],
-            3 => [Self::ONE, Self::TWO],
-            _ => unreachable!("helper identity out of range"),
+    pub fn next(&self) -> HelperIdentity {
+        Self {
+            id: self.id % 3 + 1,
         }
     }
+
+    /// Returns the identity of the helper that precedes this one on the ring `A -> B -> C -> A`.
+    #[must_use]
+    pub fn prev(&self) -> HelperIdentity {
+        Self {
+            id: (self.id + 1) % 3 + 1,
+        }
+    }
+
+    /// Given a helper identity, return an array of the identities of the other two helpers:
+    /// [`next`] first, then [`prev`]. `Processor::new_query` relies on this order to assign
+    /// roles, so that the helper that starts the query becomes `H1`, its next peer `H2`
+    /// and its previous peer `H3`.
+    ///
+    /// [`next`]: Self::next
+    /// [`prev`]: Self::prev
+    #[must_use]
+    pub fn others(&self) -> [HelperIdentity; 2] {
+        [self.next(), self.prev()]
+    }
 }
 
 impl HelperIdentity {
@@ -555,6 +608,63 @@ mod tests {
         }
     }
 
+    mod helper_identity_tests {
+        use super::*;
+
+        #[test]
+        fn next_and_prev() {
+            assert_eq!(HelperIdentity::TWO, HelperIdentity::ONE.next());
+            assert_eq!(HelperIdentity::THREE, HelperIdentity::TWO.next());
+            assert_eq!(HelperIdentity::ONE, HelperIdentity::THREE.next());
+            assert_eq!(HelperIdentity::THREE, HelperIdentity::ONE.prev());
+            assert_eq!(HelperIdentity::ONE, HelperIdentity::TWO.prev());
+            assert_eq!(HelperIdentity::TWO, HelperIdentity::THREE.prev());
+        }
+
+        #[test]
+        fn ring_is_consistent() {
+            for id in HelperIdentity::make_three() {
+                assert_eq!(id, id.next().prev());
+                assert_eq!(id, id.prev().next());
+                assert_eq!(id.prev(), id.next().next());
+                assert_eq!([id.next(), id.prev()], id.others());
+                assert!(!id.others().contains(&id));
+            }
+        }
+
+        #[test]
+        fn from_str() {
+            for (s, id) in [
+                ("1", HelperIdentity::ONE),
+                ("2", HelperIdentity::TWO),
+                ("3", HelperIdentity::THREE),
+                ("H1", HelperIdentity::ONE),
+                ("H2", HelperIdentity::TWO),
+                ("H3", HelperIdentity::THREE),
+            ] {
+                assert_eq!(Ok(id), s.parse::<HelperIdentity>(), "{s}");
+            }
+
+            for s in [
+                "", "0", "4", "01", "+1", " 1", "h1", "H", "H0", "H4", "A", "one",
+            ] {
+                assert_eq!(
+                    Err(InvalidHelperIdentity(s.to_owned())),
+                    s.parse::<HelperIdentity>(),
+                    "{s:?}"
+                );
+            }
+        }
+
+        #[test]
+        fn display_round_trips() {
+            for id in HelperIdentity::make_three() {
+                assert_eq!(u8::from(id).to_string(), id.to_string());
+                assert_eq!(Ok(id), id.to_string().parse());
+            }
+        }
+    }
+
     mod role_assignment_tests {
         use super::*;
         use crate::{
@@ -848,7 +958,7 @@ mod concurrency_tests {
                     let results = app
                         .execute_query(
                             inputs.into_iter(),
-                            QueryConfig::new(TestMultiply, FieldType::Fp31, sz).unwrap(),
+                            QueryConfig::new(TestMultiply, FieldType::Fp31, sz / 2).unwrap(),
                         )
                         .await
                         .unwrap();
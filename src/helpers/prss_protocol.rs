@@ -39,8 +39,8 @@ pub async fn negotiate<R: RngCore + CryptoRng>(
 
     let left_sender = gateway.get_sender::<PublicKey>(&left_channel, total_records);
     let right_sender = gateway.get_sender::<PublicKey>(&right_channel, total_records);
-    let left_receiver = gateway.get_receiver::<PublicKey>(&left_channel);
-    let right_receiver = gateway.get_receiver::<PublicKey>(&right_channel);
+    let left_receiver = gateway.get_receiver::<PublicKey>(&left_channel, total_records);
+    let right_receiver = gateway.get_receiver::<PublicKey>(&right_channel, total_records);
 
     // setup local prss endpoint
     let ep_setup = prss::Endpoint::prepare(rng);
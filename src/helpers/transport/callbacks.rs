@@ -1,12 +1,16 @@
 use std::{future::Future, pin::Pin};
 
 use crate::{
-    helpers::query::{PrepareQuery, QueryConfig, QueryInput},
+    helpers::{
+        query::{PrepareQuery, QueryConfig, QueryInput},
+        TransportImpl,
+    },
     protocol::QueryId,
     query::{
         NewQueryError, PrepareQueryError, ProtocolResult, QueryCompletionError, QueryInputError,
-        QueryStatus, QueryStatusError,
+        QueryProcessor, QueryStatus, QueryStatusError,
     },
+    sync::Arc,
 };
 
 /// Macro for defining transport callbacks.
@@ -78,23 +82,64 @@ impl<T> Default for TransportCallbacks<T> {
     fn default() -> Self {
         // `TransportCallbacks::default()` is commonly used with struct update syntax
         // (`..Default::default()`) to fill out the callbacks that aren't relevant to a particular
-        // test. In that scenario, a call that does occur is "unexpected" in the sense the term
-        // is used by mocks.
+        // test. A call that does occur is rejected, as a helper that does not run queries would
+        // do. Panicking here would bring down the transport loop that invoked the callback,
+        // leaving every sender to this helper hanging.
         Self {
             receive_query: Box::new(move |_, _| {
-                Box::pin(async { panic!("unexpected call to receive_query") })
+                Box::pin(async { Err(NewQueryError::NotAccepted) })
             }),
             prepare_query: Box::new(move |_, _| {
-                Box::pin(async { panic!("unexpected call to prepare_query") })
+                Box::pin(async { Err(PrepareQueryError::NotAccepted) })
+            }),
+            query_input: Box::new(move |_, input| {
+                Box::pin(async move { Err(QueryInputError::NoSuchQuery(input.query_id)) })
+            }),
+            query_status: Box::new(move |_, query_id| {
+                Box::pin(async move { Err(QueryStatusError::NoSuchQuery(query_id)) })
+            }),
+            complete_query: Box::new(move |_, query_id| {
+                Box::pin(async move { Err(QueryCompletionError::NoSuchQuery(query_id)) })
+            }),
+        }
+    }
+}
+
+impl TransportCallbacks<TransportImpl> {
+    /// Creates callbacks that hand every request over to the corresponding method of
+    /// `query_processor`.
+    #[must_use]
+    pub fn from_processor(query_processor: Arc<QueryProcessor>) -> Self {
+        let rqp = Arc::clone(&query_processor);
+        let pqp = Arc::clone(&query_processor);
+        let iqp = Arc::clone(&query_processor);
+        let sqp = Arc::clone(&query_processor);
+        let cqp = query_processor;
+
+        Self {
+            receive_query: Box::new(move |transport: TransportImpl, receive_query| {
+                let processor = Arc::clone(&rqp);
+                Box::pin(async move {
+                    let r = processor.new_query(transport, receive_query).await?;
+
+                    Ok(r.query_id)
+                })
+            }),
+            prepare_query: Box::new(move |transport: TransportImpl, prepare_query| {
+                let processor = Arc::clone(&pqp);
+                Box::pin(async move { processor.prepare(&transport, prepare_query) })
             }),
-            query_input: Box::new(move |_, _| {
-                Box::pin(async { panic!("unexpected call to query_input") })
+            query_input: Box::new(move |transport: TransportImpl, query_input| {
+                let processor = Arc::clone(&iqp);
+                Box::pin(async move { processor.receive_inputs(transport, query_input) })
             }),
-            query_status: Box::new(move |_, _| {
-                Box::pin(async { panic!("unexpected call to query_status") })
+            query_status: Box::new(move |_transport: TransportImpl, query_id| {
+                let processor = Arc::clone(&sqp);
+                Box::pin(async move { processor.query_status(query_id) })
             }),
-            complete_query: Box::new(move |_, _| {
-                Box::pin(async { panic!("unexpected call to complete_query") })
+            complete_query: Box::new(move |_transport: TransportImpl, query_id| {
+                let processor = Arc::clone(&cqp);
+                Box::pin(async move { processor.complete(query_id).await })
             }),
         }
     }
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{
+    stream::{AbortHandle, Abortable},
+    Stream,
+};
+use pin_project::pin_project;
+use tokio::sync::Notify;
+
+use crate::sync::{Arc, Mutex};
+
+/// How long [`Transport::shutdown`] waits for peers to consume the records streams that were
+/// sent to them, before terminating these streams early.
+///
+/// [`Transport::shutdown`]: crate::helpers::Transport::shutdown
+pub const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(5);
+
+/// Keeps track of records streams that transport handed over to its peers, but peers haven't
+/// finished consuming yet. Once closed, it refuses to track new streams, which allows transport
+/// to stop accepting new sends and wait for the outstanding ones to drain.
+#[derive(Clone)]
+pub(crate) struct Drain {
+    inner: Arc<DrainInner>,
+}
+
+struct DrainInner {
+    state: Mutex<DrainState>,
+    drained: Notify,
+}
+
+#[derive(Default)]
+struct DrainState {
+    closed: bool,
+    next_id: usize,
+    in_flight: HashMap<usize, AbortHandle>,
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(DrainInner {
+                state: Mutex::new(DrainState::default()),
+                drained: Notify::new(),
+            }),
+        }
+    }
+}
+
+impl Drain {
+    /// Starts tracking `data` until it is exhausted or dropped.
+    ///
+    /// Returns `None` if this drain has been closed and no more data can be sent.
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    pub fn track<S: Stream>(&self, data: S) -> Option<Tracked<S>> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.closed {
+            return None;
+        }
+
+        let (handle, registration) = AbortHandle::new_pair();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.in_flight.insert(id, handle);
+
+        Some(Tracked {
+            inner: Abortable::new(data, registration),
+            guard: Some(InFlightGuard {
+                id,
+                drain: Arc::clone(&self.inner),
+            }),
+        })
+    }
+
+    /// Returns `true` if [`close`] has been called on this drain.
+    ///
+    /// [`close`]: Self::close
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    pub fn is_closed(&self) -> bool {
+        self.inner.state.lock().unwrap().closed
+    }
+
+    /// Stops tracking new streams and waits for at most `deadline` for the ones in flight to be
+    /// consumed. Streams that are still in flight after that are terminated, so their receivers
+    /// observe the end of stream instead of waiting for more data forever.
+    ///
+    /// Returns the number of streams that had to be terminated.
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    pub async fn close(&self, deadline: Duration) -> usize {
+        self.inner.state.lock().unwrap().closed = true;
+
+        let drained = ::tokio::time::timeout(deadline, async {
+            while self.in_flight() > 0 {
+                self.inner.drained.notified().await;
+            }
+        })
+        .await;
+
+        if drained.is_ok() {
+            return 0;
+        }
+
+        let in_flight = std::mem::take(&mut self.inner.state.lock().unwrap().in_flight);
+        for handle in in_flight.values() {
+            handle.abort();
+        }
+
+        in_flight.len()
+    }
+
+    fn in_flight(&self) -> usize {
+        self.inner.state.lock().unwrap().in_flight.len()
+    }
+}
+
+/// Stream that is tracked by [`Drain`]. It stops being tracked when it yields its last item or
+/// gets dropped, whichever happens first.
+#[pin_project]
+pub(crate) struct Tracked<S> {
+    #[pin]
+    inner: Abortable<S>,
+    guard: Option<InFlightGuard>,
+}
+
+impl<S: Stream> Stream for Tracked<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let next = this.inner.poll_next(cx);
+        if let Poll::Ready(None) = next {
+            this.guard.take();
+        }
+
+        next
+    }
+}
+
+struct InFlightGuard {
+    id: usize,
+    drain: Arc<DrainInner>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let Ok(mut state) = self.drain.state.lock() else {
+            return;
+        };
+        state.in_flight.remove(&self.id);
+        if state.in_flight.is_empty() {
+            // `notify_one` stores the permit if nobody is waiting yet, so `close` can't miss it.
+            self.drain.drained.notify_one();
+        }
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{stream, StreamExt};
+    use tokio::sync::mpsc::channel;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    use super::Drain;
+
+    #[tokio::test]
+    async fn waits_for_streams_to_drain() {
+        let drain = Drain::default();
+        let tracked = drain.track(stream::iter(vec![vec![1], vec![2]])).unwrap();
+        let consume = tokio::spawn(tracked.collect::<Vec<_>>());
+
+        assert_eq!(0, drain.close(Duration::from_secs(60)).await);
+        assert_eq!(vec![vec![1], vec![2]], consume.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_streams_after_close() {
+        let drain = Drain::default();
+        assert!(!drain.is_closed());
+
+        assert_eq!(0, drain.close(Duration::from_millis(1)).await);
+        assert!(drain.is_closed());
+        assert!(drain.track(stream::empty::<Vec<u8>>()).is_none());
+    }
+
+    #[tokio::test]
+    async fn terminates_streams_after_deadline() {
+        let drain = Drain::default();
+        let (tx, rx) = channel(1);
+        let mut tracked = drain.track(ReceiverStream::new(rx)).unwrap();
+
+        tx.send(vec![1]).await.unwrap();
+        assert_eq!(Some(vec![1]), tracked.next().await);
+
+        // sender is still alive, so without the drain this stream would never end.
+        assert_eq!(1, drain.close(Duration::from_millis(10)).await);
+        assert_eq!(None, tracked.next().await);
+        drop(tx);
+    }
+}
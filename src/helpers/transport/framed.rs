@@ -0,0 +1,128 @@
+use std::future::ready;
+
+use futures::{stream, Stream, StreamExt};
+
+/// Size of the header of every frame: the length of its payload, as a 32 bit little-endian integer.
+const HEADER_SIZE: usize = 4;
+
+/// Frames records `data` for sending to a peer. Every chunk becomes a frame that carries its
+/// length, and the stream is finished with an empty frame once `data` is over. Empty chunks are
+/// skipped, so the empty frame is only ever sent at the end.
+///
+/// A stream that is cut short, either because the sender was shut down or because the network
+/// failed, lacks the last frame, so [`Unframe`] can tell it apart from the one that is complete.
+pub(crate) fn frame<S>(data: S) -> impl Stream<Item = Vec<u8>>
+where
+    S: Stream<Item = Vec<u8>>,
+{
+    data.filter_map(|chunk| ready((!chunk.is_empty()).then(|| encode(&chunk))))
+        .chain(stream::once(ready(vec![0; HEADER_SIZE])))
+}
+
+/// Makes a frame out of `payload`. Empty payload makes the frame that finishes the stream.
+pub(crate) fn encode(payload: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(payload.len()).expect("records chunk must fit into a frame");
+    let mut frame = Vec::with_capacity(HEADER_SIZE + payload.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(payload);
+
+    frame
+}
+
+/// Takes payloads out of the frames that [`frame`] produced. Chunks of data may split and join
+/// frames arbitrarily, so incomplete frames are kept until the rest of them arrives.
+#[derive(Debug, Default)]
+pub(crate) struct Unframe {
+    buf: Vec<u8>,
+    finished: bool,
+}
+
+impl Unframe {
+    /// Adds `chunk` to the data received so far and returns the payloads of all frames that are
+    /// complete now, one after another. The result is empty if none of them are.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        if self.finished {
+            tracing::warn!(
+                len = chunk.len(),
+                "records data received after the end of stream"
+            );
+            return Vec::new();
+        }
+        self.buf.extend_from_slice(chunk);
+
+        let mut payload = Vec::new();
+        let mut offset = 0;
+        while let Some(header) = self.buf.get(offset..offset + HEADER_SIZE) {
+            let len = usize::try_from(u32::from_le_bytes(header.try_into().unwrap())).unwrap();
+            if len == 0 {
+                self.finished = true;
+                offset += HEADER_SIZE;
+                break;
+            }
+            let Some(frame) = self
+                .buf
+                .get(offset + HEADER_SIZE..offset + HEADER_SIZE + len)
+            else {
+                break;
+            };
+            payload.extend_from_slice(frame);
+            offset += HEADER_SIZE + len;
+        }
+        self.buf.drain(..offset);
+
+        payload
+    }
+
+    /// Returns `true` once the frame that finishes the stream was received.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use futures::{stream, StreamExt};
+
+    use super::{frame, Unframe};
+
+    async fn framed(chunks: Vec<Vec<u8>>) -> Vec<u8> {
+        frame(stream::iter(chunks)).concat().await
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let chunks = vec![vec![1, 2, 3], vec![], vec![4], vec![5; 300]];
+        let data = framed(chunks.clone()).await;
+
+        // frames are split at every possible position
+        for split in 1..data.len() {
+            let mut unframe = Unframe::default();
+            let mut payload = Vec::new();
+            for chunk in data.chunks(split) {
+                assert!(!unframe.is_finished());
+                payload.extend(unframe.push(chunk));
+            }
+
+            assert!(unframe.is_finished());
+            assert_eq!(chunks.concat(), payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_stream_is_finished() {
+        let mut unframe = Unframe::default();
+        assert!(unframe.push(&framed(Vec::new()).await).is_empty());
+        assert!(unframe.is_finished());
+    }
+
+    /// Stream that is cut short is never finished, even if it is cut right between two frames.
+    #[tokio::test]
+    async fn truncation_is_detected() {
+        let data = framed(vec![vec![1, 2, 3], vec![4, 5]]).await;
+        for len in 0..data.len() {
+            let mut unframe = Unframe::default();
+            unframe.push(&data[..len]);
+            assert!(!unframe.is_finished(), "{len}");
+        }
+    }
+}
@@ -1,9 +1,11 @@
+mod read_ahead;
+mod replay;
 mod transport;
 
-pub use transport::Setup;
+pub use transport::{NetworkConfig, SendInterceptor, SendObserver, Setup};
 
 use crate::{
-    helpers::{HelperIdentity, TransportCallbacks},
+    helpers::{GatewayConfig, HelperIdentity, TransportCallbacks},
     sync::{Arc, Weak},
 };
 
@@ -29,8 +31,40 @@ impl Default for InMemoryNetwork {
 impl InMemoryNetwork {
     #[must_use]
     pub fn new(callbacks: [TransportCallbacks<InMemoryTransport>; 3]) -> Self {
-        let [mut first, mut second, mut third]: [_; 3] =
-            HelperIdentity::make_three().map(Setup::new);
+        Self::from_setups(HelperIdentity::make_three().map(Setup::new), callbacks)
+    }
+
+    /// Creates a network where the transport of each helper is set up for its own gateway
+    /// configuration. Configurations are assigned to helpers in the same order as
+    /// [`helper_identities`] returns them, and gateways of each helper should be given the same
+    /// configuration as its transport.
+    ///
+    /// [`helper_identities`]: Self::helper_identities
+    #[must_use]
+    pub fn with_config(
+        gateway_configs: [GatewayConfig; 3],
+        callbacks: [TransportCallbacks<InMemoryTransport>; 3],
+    ) -> Self {
+        let [c1, c2, c3] = gateway_configs;
+        let [id1, id2, id3] = HelperIdentity::make_three();
+
+        Self::from_setups(
+            [
+                Setup::new(id1).with_gateway_config(c1),
+                Setup::new(id2).with_gateway_config(c2),
+                Setup::new(id3).with_gateway_config(c3),
+            ],
+            callbacks,
+        )
+    }
+
+    /// Connects helpers configured by `setups` with each other and starts them.
+    #[must_use]
+    pub fn from_setups(
+        setups: [Setup; 3],
+        callbacks: [TransportCallbacks<InMemoryTransport>; 3],
+    ) -> Self {
+        let [mut first, mut second, mut third] = setups;
 
         first.connect(&mut second);
         second.connect(&mut third);
@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use ::tokio::sync::{mpsc, oneshot};
+use futures::{Future, Stream, StreamExt};
+#[cfg(all(feature = "shuttle", test))]
+use shuttle::future as tokio;
+
+use super::transport::InMemoryStream;
+use crate::{
+    helpers::HelperIdentity,
+    protocol::{step::Gate, QueryId},
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "{from:?} exceeded the limit of {limit} bytes of records data buffered for query \
+     {query_id:?}, stream for step {gate} is rejected"
+)]
+pub struct BufferLimitExceeded {
+    from: HelperIdentity,
+    query_id: QueryId,
+    gate: Gate,
+    limit: usize,
+}
+
+/// In-memory records streams are pulled by receivers, so nothing is buffered until someone asks
+/// for the data. Real networks push data to the receiving helper whether it asked for it or not.
+/// [`ReadAhead`] does the same for in-memory transport: it reads records streams as soon as they
+/// arrive and keeps the data until receivers take it.
+///
+/// The amount of data every peer can make this helper buffer within a query is limited. Stream
+/// that goes over the limit is rejected: it is cut short and, unless it made the sender wait for
+/// the receiver before that, the sender is told about it.
+pub(super) struct ReadAhead {
+    limit: usize,
+    buffered: Mutex<HashMap<(HelperIdentity, QueryId), usize>>,
+}
+
+impl ReadAhead {
+    pub fn new(limit: NonZeroUsize) -> Self {
+        Self {
+            limit: limit.get(),
+            buffered: Mutex::default(),
+        }
+    }
+
+    /// Starts reading `stream` sent by `from`. Returns the stream to hand over to the receiver
+    /// and the future that resolves once the sender does not need to wait for the outcome any
+    /// longer: either `stream` has nothing else to give right now, or it was rejected.
+    pub fn start(
+        self: &Arc<Self>,
+        from: HelperIdentity,
+        query_id: QueryId,
+        gate: Gate,
+        stream: InMemoryStream,
+    ) -> (
+        InMemoryStream,
+        impl Future<Output = Result<(), BufferLimitExceeded>>,
+    ) {
+        let key = (from, query_id);
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (accepted_tx, accepted_rx) = oneshot::channel();
+
+        tokio::spawn({
+            let this = Arc::clone(self);
+            async move {
+                let mut stream = stream;
+                let mut accepted = Some(accepted_tx);
+                loop {
+                    let next = match futures::poll!(stream.next()) {
+                        Poll::Ready(next) => next,
+                        Poll::Pending => {
+                            if let Some(accepted) = accepted.take() {
+                                accepted.send(Ok(())).ok();
+                            }
+                            stream.next().await
+                        }
+                    };
+                    let Some(chunk) = next else {
+                        break;
+                    };
+
+                    let len = chunk.len();
+                    if !this.charge(key, len) {
+                        tracing::warn!(
+                            ?from,
+                            ?query_id,
+                            step = %gate,
+                            limit = this.limit,
+                            "records stream is rejected, peer sent too much data ahead of receivers"
+                        );
+                        if let Some(accepted) = accepted.take() {
+                            accepted
+                                .send(Err(BufferLimitExceeded {
+                                    from,
+                                    query_id,
+                                    gate,
+                                    limit: this.limit,
+                                }))
+                                .ok();
+                        }
+                        return;
+                    }
+                    if tx.send(chunk).is_err() {
+                        // receiver is gone, nobody will take this data
+                        this.refund(key, len);
+                        break;
+                    }
+                }
+
+                if let Some(accepted) = accepted.take() {
+                    accepted.send(Ok(())).ok();
+                }
+            }
+        });
+
+        let buffered = InMemoryStream::wrap(Buffered {
+            key,
+            rx,
+            read_ahead: Arc::clone(self),
+        });
+        // If the reading task is gone without saying anything, the stream has nothing else to give.
+        let accepted = async move { accepted_rx.await.unwrap_or(Ok(())) };
+
+        (buffered, accepted)
+    }
+
+    /// Forgets the data buffered for the given query.
+    pub fn remove_query(&self, query_id: QueryId) {
+        self.buffered
+            .lock()
+            .unwrap()
+            .retain(|&(_, id), _| id != query_id);
+    }
+
+    pub fn clear(&self) {
+        self.buffered.lock().unwrap().clear();
+    }
+
+    fn charge(&self, key: (HelperIdentity, QueryId), len: usize) -> bool {
+        let mut buffered = self.buffered.lock().unwrap();
+        let total = buffered.get(&key).copied().unwrap_or_default() + len;
+        if total > self.limit {
+            return false;
+        }
+        buffered.insert(key, total);
+
+        true
+    }
+
+    fn refund(&self, key: (HelperIdentity, QueryId), len: usize) {
+        let mut buffered = self.buffered.lock().unwrap();
+        if let Some(total) = buffered.get_mut(&key) {
+            *total = total.saturating_sub(len);
+            if *total == 0 {
+                buffered.remove(&key);
+            }
+        }
+    }
+}
+
+/// Receiving end of the stream that [`ReadAhead`] reads. Data counts towards the limit until it
+/// is taken out of here or this stream is dropped.
+struct Buffered {
+    key: (HelperIdentity, QueryId),
+    rx: mpsc::UnboundedReceiver<Vec<u8>>,
+    read_ahead: Arc<ReadAhead>,
+}
+
+impl Stream for Buffered {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let next = this.rx.poll_recv(cx);
+        if let Poll::Ready(Some(chunk)) = &next {
+            this.read_ahead.refund(this.key, chunk.len());
+        }
+
+        next
+    }
+}
+
+impl Drop for Buffered {
+    fn drop(&mut self) {
+        self.rx.close();
+        while let Ok(chunk) = self.rx.try_recv() {
+            self.read_ahead.refund(self.key, chunk.len());
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{Stream, StreamExt};
+
+use super::transport::InMemoryStream;
+use crate::{
+    helpers::transport::{
+        framed::{encode, Unframe},
+        StreamCollection, StreamKey,
+    },
+    protocol::QueryId,
+    sync::{Arc, Mutex},
+};
+
+/// Keeps the data of records streams that were received with [`receive_from`], so a receiver
+/// that was interrupted can be replaced by another one that picks the stream up at a later
+/// record. Streams taken by [`receive`] are not kept. Every time a stream is resumed, the data
+/// before the record it is resumed at is dropped, so only the part that the last receiver could
+/// have missed is kept.
+///
+/// [`receive`]: crate::helpers::Transport::receive
+/// [`receive_from`]: crate::helpers::Transport::receive_from
+#[derive(Default)]
+pub(super) struct Replays {
+    streams: Mutex<HashMap<StreamKey, Arc<Mutex<Replay>>>>,
+}
+
+struct Replay {
+    key: StreamKey,
+    source: Source,
+    unframe: Unframe,
+    /// Data of the stream starting at byte `base`, up to byte `received`. Empty if `base` is
+    /// past `received`, data is dropped as it arrives until it gets to `base` then.
+    data: Vec<u8>,
+    base: usize,
+    received: usize,
+}
+
+enum Source {
+    /// Stream did not arrive yet.
+    Pending(StreamCollection<InMemoryStream>),
+    Ready(InMemoryStream),
+    /// Stream is over, or it is known that it will never arrive.
+    Done,
+}
+
+impl Replay {
+    fn push(&mut self, payload: &[u8]) {
+        if self.received + payload.len() > self.base {
+            let skip = self.base.saturating_sub(self.received);
+            self.data.extend_from_slice(&payload[skip..]);
+        }
+        self.received += payload.len();
+    }
+
+    /// Drops the data before byte `to`. Returns `false` if some of the data from `to` onwards
+    /// is dropped already.
+    fn trim(&mut self, to: usize) -> bool {
+        if to < self.base {
+            return false;
+        }
+        let len = (to - self.base).min(self.data.len());
+        self.data.drain(..len);
+        self.base = to;
+
+        true
+    }
+}
+
+impl Replays {
+    /// Returns the stream that yields the data that arrives with `key` from byte `from`, taking
+    /// it out of `streams` when it arrives, unless it was taken already. Returns `None` if the
+    /// stream was taken by a receiver that did not ask for it to be kept, or it was resumed at a
+    /// later byte before.
+    pub fn resume(
+        &self,
+        key: &StreamKey,
+        from: usize,
+        streams: &StreamCollection<InMemoryStream>,
+    ) -> Option<InMemoryStream> {
+        let replay = match self.streams.lock().unwrap().entry(key.clone()) {
+            Entry::Occupied(entry) => Arc::clone(entry.get()),
+            Entry::Vacant(_) if streams.is_consumed(key) => return None,
+            Entry::Vacant(entry) => Arc::clone(entry.insert(Arc::new(Mutex::new(Replay {
+                key: key.clone(),
+                source: Source::Pending(streams.clone()),
+                unframe: Unframe::default(),
+                data: Vec::new(),
+                base: 0,
+                received: 0,
+            })))),
+        };
+        if !replay.lock().unwrap().trim(from) {
+            return None;
+        }
+
+        Some(InMemoryStream::wrap(Reader {
+            replay,
+            offset: from,
+            finished: false,
+        }))
+    }
+
+    /// Drops the data of all streams that belong to `query_id`.
+    pub fn remove_query(&self, query_id: QueryId) {
+        self.streams
+            .lock()
+            .unwrap()
+            .retain(|(qid, _, _), _| *qid != query_id);
+    }
+
+    /// Drops the data of all streams.
+    pub fn clear(&self) {
+        self.streams.lock().unwrap().clear();
+    }
+
+    #[cfg(all(test, unit_test))]
+    pub fn count(&self) -> usize {
+        self.streams.lock().unwrap().len()
+    }
+}
+
+/// Reads a stream kept by [`Replays`], starting at `offset` bytes, and frames it again. Data is
+/// pulled from the stream itself only after everything that was kept is read. Only one reader
+/// of the same stream is expected to be active at a time, the one that was created last.
+struct Reader {
+    replay: Arc<Mutex<Replay>>,
+    offset: usize,
+    /// Set once the frame that finishes the stream is yielded.
+    finished: bool,
+}
+
+impl Stream for Reader {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::get_mut(self);
+        let mut guard = this.replay.lock().unwrap();
+        let replay = &mut *guard;
+        loop {
+            if this.offset < replay.base {
+                // stream was resumed by another reader past this one
+                return Poll::Ready(None);
+            }
+            if this.offset < replay.received {
+                let chunk = encode(&replay.data[this.offset - replay.base..]);
+                this.offset = replay.received;
+                return Poll::Ready(Some(chunk));
+            }
+            match &mut replay.source {
+                Source::Pending(streams) => match streams.add_waker(&replay.key, cx.waker()) {
+                    Poll::Ready(Ok(stream)) => replay.source = Source::Ready(stream),
+                    Poll::Ready(Err(_)) => replay.source = Source::Done,
+                    Poll::Pending => return Poll::Pending,
+                },
+                Source::Ready(stream) => match stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(chunk)) => {
+                        let payload = replay.unframe.push(&chunk);
+                        replay.push(&payload);
+                    }
+                    Poll::Ready(None) => replay.source = Source::Done,
+                    Poll::Pending => return Poll::Pending,
+                },
+                Source::Done => {
+                    // stream that was cut short is not finished for the receiver either
+                    if replay.unframe.is_finished() && !this.finished {
+                        this.finished = true;
+                        return Poll::Ready(Some(encode(&[])));
+                    }
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        // the next reader waits for the stream with its own waker
+        let replay = self.replay.lock().unwrap();
+        if let Source::Pending(streams) = &replay.source {
+            streams.cancel(&replay.key);
+        }
+    }
+}
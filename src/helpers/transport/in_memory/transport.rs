@@ -3,32 +3,42 @@ use std::{
     collections::{HashMap, HashSet},
     convert,
     fmt::{Debug, Formatter},
-    io,
+    io, iter,
     pin::Pin,
     sync::{Arc, Weak},
     task::{Context, Poll},
+    time::Duration,
 };
 
 use ::tokio::sync::{
     mpsc::{channel, Receiver, Sender},
-    oneshot,
+    oneshot, watch,
 };
 use async_trait::async_trait;
-use futures::{Stream, StreamExt};
+use futures::{stream::BoxStream, Stream, StreamExt};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
 #[cfg(all(feature = "shuttle", test))]
 use shuttle::future as tokio;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::Instrument;
 
+use super::{read_ahead::ReadAhead, replay::Replays};
 use crate::{
     error::BoxError,
     helpers::{
         query::{PrepareQuery, QueryConfig},
-        HelperIdentity, NoResourceIdentifier, QueryIdBinding, ReceiveRecords, RouteId, RouteParams,
-        StepBinding, StreamCollection, Transport, TransportCallbacks,
+        transport::{
+            frame,
+            step_dictionary::{StepDecoder, StepEncoder, StepRef},
+            trace, Drain,
+        },
+        GatewayConfig, HelperIdentity, NoResourceIdentifier, Priority, QueryIdBinding,
+        ReceiveRecords, RouteId, RouteParams, StepBinding, StreamCollection, Transport,
+        TransportCallbacks, TransportStats, SHUTDOWN_DEADLINE,
     },
-    protocol::{step::Gate, QueryId},
+    protocol::{step::Gate, QueryId, RecordId},
 };
 
 type Packet = (Addr, InMemoryStream, oneshot::Sender<Result<(), Error>>);
@@ -36,6 +46,42 @@ type ConnectionTx = Sender<Packet>;
 type ConnectionRx = Receiver<Packet>;
 type StreamItem = Vec<u8>;
 
+/// Sending end of the link to a helper. Control requests and records data are queued separately,
+/// so that the former never wait for the helper to take in the latter.
+#[derive(Clone)]
+struct Connection {
+    control: ConnectionTx,
+    data: ConnectionTx,
+}
+
+impl Connection {
+    fn channel(&self, route: RouteId) -> &ConnectionTx {
+        match route.priority() {
+            Priority::Control => &self.control,
+            Priority::Data => &self.data,
+        }
+    }
+}
+
+/// Observes every chunk of records data sent by in-memory transport. It is called synchronously
+/// with the sender, the destination, query and step the data belongs to, before the chunk is
+/// delivered.
+pub type SendObserver =
+    dyn Fn(HelperIdentity, HelperIdentity, QueryId, &Gate, &[u8]) + Send + Sync + 'static;
+
+/// Intercepts every records stream sent by in-memory transport. It is called with the sender,
+/// the destination and the step of the stream, and returns the stream to deliver in its place.
+/// Tests use it to make helpers misbehave, see `test_fixture::Tampering`.
+pub type SendInterceptor = dyn Fn(
+        HelperIdentity,
+        HelperIdentity,
+        &Gate,
+        BoxStream<'static, StreamItem>,
+    ) -> BoxStream<'static, StreamItem>
+    + Send
+    + Sync
+    + 'static;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
@@ -49,6 +95,8 @@ pub enum Error {
         #[source]
         inner: BoxError,
     },
+    #[error("Transport is shut down")]
+    ShutDown,
 }
 
 /// In-memory implementation of [`Transport`] backed by Tokio mpsc channels.
@@ -56,17 +104,59 @@ pub enum Error {
 /// incoming messages.
 pub struct InMemoryTransport {
     identity: HelperIdentity,
-    connections: HashMap<HelperIdentity, ConnectionTx>,
+    connections: HashMap<HelperIdentity, Connection>,
     record_streams: StreamCollection<InMemoryStream>,
+    /// Records streams that are not bound to any step. Every peer gets one per query, keyed by
+    /// the root gate.
+    query_streams: StreamCollection<InMemoryStream>,
+    stats: TransportStats,
+    drain: Drain,
+    shutdown_deadline: Duration,
+    network_config: NetworkConfig,
+    send_observer: Option<Arc<SendObserver>>,
+    send_interceptor: Option<Arc<SendInterceptor>>,
+    step_encoder: StepEncoder,
+    step_decoder: Arc<StepDecoder>,
+    /// Set if gateway configuration limits the amount of data peers can send ahead of receivers.
+    read_ahead: Option<Arc<ReadAhead>>,
+    /// Data of records streams that were received with [`receive_from`].
+    ///
+    /// [`receive_from`]: Transport::receive_from
+    replays: Arc<Replays>,
+    /// Dropped together with this transport, which makes it stop listening for requests. Peers
+    /// find the channels to it closed after that.
+    alive: watch::Sender<()>,
 }
 
 impl InMemoryTransport {
     #[must_use]
-    fn new(identity: HelperIdentity, connections: HashMap<HelperIdentity, ConnectionTx>) -> Self {
+    fn new(
+        identity: HelperIdentity,
+        connections: HashMap<HelperIdentity, Connection>,
+        shutdown_deadline: Duration,
+        gateway_config: Option<GatewayConfig>,
+        network_config: NetworkConfig,
+        send_observer: Option<Arc<SendObserver>>,
+        send_interceptor: Option<Arc<SendInterceptor>>,
+    ) -> Self {
         Self {
             identity,
             connections,
             record_streams: StreamCollection::default(),
+            query_streams: StreamCollection::default(),
+            stats: TransportStats::default(),
+            drain: Drain::default(),
+            shutdown_deadline,
+            network_config,
+            send_observer,
+            send_interceptor,
+            step_encoder: StepEncoder::default(),
+            step_decoder: Arc::new(StepDecoder::default()),
+            read_ahead: gateway_config
+                .and_then(|config| config.receive_buffer_limit())
+                .map(|limit| Arc::new(ReadAhead::new(limit))),
+            replays: Arc::default(),
+            alive: watch::channel(()).0,
         }
     }
 
@@ -79,15 +169,33 @@ impl InMemoryTransport {
     /// out and processes it, the same way as query processor does. That will allow all tasks to be
     /// created in one place (driver). It does not affect the [`Transport`] interface,
     /// so I'll leave it as is for now.
-    fn listen(self: &Arc<Self>, callbacks: TransportCallbacks<Weak<Self>>, mut rx: ConnectionRx) {
+    fn listen(
+        self: &Arc<Self>,
+        callbacks: TransportCallbacks<Weak<Self>>,
+        control_rx: ConnectionRx,
+        data_rx: ConnectionRx,
+    ) {
+        self.listen_control(callbacks, control_rx);
+        self.listen_data(data_rx);
+    }
+
+    /// Serves control requests. It runs independently of [`listen_data`], so records data piling
+    /// up in front of this helper does not prevent it from taking part in new queries.
+    ///
+    /// [`listen_data`]: Self::listen_data
+    fn listen_control(
+        self: &Arc<Self>,
+        callbacks: TransportCallbacks<Weak<Self>>,
+        mut rx: ConnectionRx,
+    ) {
         tokio::spawn(
             {
-                let streams = self.record_streams.clone();
                 let this = Arc::downgrade(self);
                 let dest = this.identity();
+                let mut alive = self.alive.subscribe();
                 async move {
                     let mut active_queries = HashSet::new();
-                    while let Some((addr, stream, ack)) = rx.recv().await {
+                    while let Some((addr, _, ack)) = recv_while_alive(&mut rx, &mut alive).await {
                         tracing::trace!("received new message: {addr:?}");
 
                         let result = match addr.route {
@@ -106,13 +214,6 @@ impl InMemoryTransport {
                                         inner: Box::new(e),
                                     })
                             }
-                            RouteId::Records => {
-                                let query_id = addr.query_id.unwrap();
-                                let gate = addr.gate.unwrap();
-                                let from = addr.origin.unwrap();
-                                streams.add_stream((query_id, from, gate), stream);
-                                Ok(())
-                            }
                             RouteId::PrepareQuery => {
                                 let input = addr.into::<PrepareQuery>();
                                 (callbacks.prepare_query)(Transport::clone_ref(&this), input)
@@ -122,9 +223,14 @@ impl InMemoryTransport {
                                         inner: Box::new(e),
                                     })
                             }
+                            RouteId::Records => {
+                                unreachable!("records are never sent over the control channel")
+                            }
                         };
 
-                        ack.send(result).unwrap();
+                        // requester may no longer wait for the response, e.g. because another
+                        // request it sent along with this one failed
+                        ack.send(result).ok();
                     }
                 }
             }
@@ -132,7 +238,69 @@ impl InMemoryTransport {
         );
     }
 
-    fn get_channel(&self, dest: HelperIdentity) -> ConnectionTx {
+    fn listen_data(self: &Arc<Self>, mut rx: ConnectionRx) {
+        tokio::spawn(
+            {
+                let streams = self.record_streams.clone();
+                let query_streams = self.query_streams.clone();
+                let steps = Arc::clone(&self.step_decoder);
+                let read_ahead = self.read_ahead.clone();
+                let dest = self.identity;
+                let mut alive = self.alive.subscribe();
+                async move {
+                    while let Some((addr, stream, ack)) =
+                        recv_while_alive(&mut rx, &mut alive).await
+                    {
+                        tracing::trace!("received new message: {addr:?}");
+
+                        let query_id = addr.query_id.unwrap();
+                        let from = addr.origin.unwrap();
+                        let Some(step) = addr.step else {
+                            tracing::trace!(?query_id, ?from, "query records stream delivered");
+                            query_streams.add_stream((query_id, from, Gate::default()), stream);
+                            ack.send(Ok(())).unwrap();
+                            continue;
+                        };
+                        let gate = match steps.decode(from, query_id, step) {
+                            Ok(gate) => gate,
+                            Err(e) => {
+                                ack.send(Err(Error::Rejected {
+                                    dest,
+                                    inner: Box::new(e),
+                                }))
+                                .unwrap();
+                                continue;
+                            }
+                        };
+
+                        let _span = tracing::trace_span!("deliver", ?query_id, step = ?gate, ?from)
+                            .entered();
+                        tracing::trace!("records stream delivered");
+                        let key = (query_id, from, gate.clone());
+                        if let Some(read_ahead) = &read_ahead {
+                            // Sender learns whether the stream is accepted only after this helper
+                            // read what the stream had to give.
+                            let (stream, accepted) = read_ahead.start(from, query_id, gate, stream);
+                            streams.add_stream(key, stream);
+                            tokio::spawn(async move {
+                                let result = accepted.await.map_err(|e| Error::Rejected {
+                                    dest,
+                                    inner: Box::new(e),
+                                });
+                                ack.send(result).ok();
+                            });
+                        } else {
+                            streams.add_stream(key, stream);
+                            ack.send(Ok(())).unwrap();
+                        }
+                    }
+                }
+            }
+            .instrument(tracing::info_span!("data_loop", id=?self.identity).or_current()),
+        );
+    }
+
+    fn get_connection(&self, dest: HelperIdentity) -> Connection {
         self.connections
             .get(&dest)
             .unwrap_or_else(|| {
@@ -147,6 +315,36 @@ impl InMemoryTransport {
     /// Resets this transport, making it forget its state and be ready for processing another query.
     pub fn reset(&self) {
         self.record_streams.clear();
+        self.query_streams.clear();
+        self.step_encoder.clear();
+        self.step_decoder.clear();
+        self.replays.clear();
+        if let Some(read_ahead) = &self.read_ahead {
+            read_ahead.clear();
+        }
+    }
+
+    /// Returns the number of channels this transport keeps any state for: records streams and
+    /// steps registered in its dictionaries, for all queries.
+    #[cfg(all(test, unit_test))]
+    pub(crate) fn channel_count(&self) -> usize {
+        self.record_streams.count()
+            + self.query_streams.count()
+            + self.step_encoder.count()
+            + self.step_decoder.count()
+            + self.replays.count()
+    }
+}
+
+/// Receives the next request from `rx`, unless the transport listening to it is dropped first.
+async fn recv_while_alive(
+    rx: &mut ConnectionRx,
+    alive: &mut watch::Receiver<()>,
+) -> Option<Packet> {
+    ::tokio::select! {
+        packet = rx.recv() => packet,
+        // Nothing is ever sent to `alive`, so this completes only when the transport is dropped.
+        _ = alive.changed() => None,
     }
 }
 
@@ -175,24 +373,69 @@ impl Transport for Weak<InMemoryTransport> {
         Option<Gate>: From<S>,
     {
         let this = self.upgrade().unwrap();
-        let channel = this.get_channel(dest);
-        let addr = Addr::from_route(this.identity, route);
+        let mut addr = Addr::from_route(this.identity, route);
+        let channel = this.get_connection(dest).channel(addr.route).clone();
+        let span = trace::send_span(dest, addr.route, addr.query_id, addr.gate.as_ref());
+        let data = trace::record_bytes(span.clone(), data);
+        let data = match (addr.route, addr.query_id, &addr.gate) {
+            (RouteId::Records, Some(query_id), Some(gate)) => {
+                let data = this.stats.count_sent(query_id, gate.clone(), dest, data);
+                let data = match &this.send_interceptor {
+                    Some(interceptor) => {
+                        InMemoryStream::wrap(interceptor(this.identity, dest, gate, data.boxed()))
+                    }
+                    None => InMemoryStream::wrap(data),
+                };
+                let data = match this.send_observer.clone() {
+                    Some(observer) => {
+                        let (origin, gate) = (this.identity, gate.clone());
+                        InMemoryStream::wrap(data.inspect(move |chunk| {
+                            observer(origin, dest, query_id, &gate, chunk);
+                        }))
+                    }
+                    None => InMemoryStream::wrap(data),
+                };
+                InMemoryStream::wrap(frame(this.network_config.deliver(
+                    this.identity,
+                    dest,
+                    gate,
+                    data,
+                )))
+            }
+            (RouteId::Records, _, _) => InMemoryStream::wrap(frame(data)),
+            _ => InMemoryStream::wrap(data),
+        };
+        // Shutdown cuts tracked streams short, so the frame that finishes them is tracked too.
+        let Some(data) = this.drain.track(data) else {
+            return Err(Error::ShutDown);
+        };
+        let data = InMemoryStream::wrap(data);
         let (ack_tx, ack_rx) = oneshot::channel();
 
-        channel
-            .send((addr, InMemoryStream::wrap(data), ack_tx))
-            .await
-            .map_err(|_e| {
+        async move {
+            let permit = channel.reserve().await.map_err(|_e| {
                 io::Error::new::<String>(io::ErrorKind::ConnectionAborted, "channel closed".into())
             })?;
+            match (addr.query_id, addr.gate.clone()) {
+                (Some(query_id), Some(gate)) if addr.route == RouteId::Records => {
+                    this.step_encoder.encode(dest, query_id, &gate, |step| {
+                        addr.set_step(step);
+                        permit.send((addr, data, ack_tx));
+                    });
+                }
+                _ => permit.send((addr, data, ack_tx)),
+            }
 
-        ack_rx
-            .await
-            .map_err(|_recv_error| Error::Rejected {
-                dest,
-                inner: "channel closed".into(),
-            })
-            .and_then(convert::identity)
+            ack_rx
+                .await
+                .map_err(|_recv_error| Error::Rejected {
+                    dest,
+                    inner: "channel closed".into(),
+                })
+                .and_then(convert::identity)
+        }
+        .instrument(span)
+        .await
     }
 
     fn receive<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
@@ -200,11 +443,100 @@ impl Transport for Weak<InMemoryTransport> {
         from: HelperIdentity,
         route: R,
     ) -> Self::RecordsStream {
+        let this = self.upgrade().unwrap();
         ReceiveRecords::new(
             (route.query_id(), from, route.gate()),
-            self.upgrade().unwrap().record_streams.clone(),
+            this.record_streams.clone(),
+            this.stats.clone(),
+        )
+    }
+
+    /// Streams received with this method are kept until their query is cleaned up, so they can
+    /// be resumed at any record that is not before the one they were resumed at last time.
+    /// Streams taken by [`receive`] are not kept, so they end right away and are reported as
+    /// truncated, the same as they are with HTTP transport.
+    ///
+    /// [`receive`]: Transport::receive
+    fn receive_from<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+        start: RecordId,
+        record_size: usize,
+    ) -> Self::RecordsStream {
+        let this = self.upgrade().unwrap();
+        let key = (route.query_id(), from, route.gate());
+        let offset = usize::from(start) * record_size;
+        match this.replays.resume(&key, offset, &this.record_streams) {
+            Some(stream) => ReceiveRecords::from_stream(key, stream, this.stats.clone()),
+            None => ReceiveRecords::consumed(key, this.stats.clone()),
+        }
+    }
+
+    fn receive_query_records(
+        &self,
+        from: HelperIdentity,
+        query_id: QueryId,
+    ) -> Self::RecordsStream {
+        let this = self.upgrade().unwrap();
+        ReceiveRecords::new(
+            (query_id, from, Gate::default()),
+            this.query_streams.clone(),
+            this.stats.clone(),
         )
     }
+
+    fn stats(&self) -> TransportStats {
+        self.upgrade().unwrap().stats.clone()
+    }
+
+    /// Peers are in the same process, so there is nothing to wait for: `dest` is alive if it is
+    /// connected to this helper and still listens to requests.
+    async fn ping(&self, dest: HelperIdentity, _timeout: Duration) -> Result<(), io::Error> {
+        let this = self.upgrade().unwrap();
+        match this.connections.get(&dest) {
+            Some(connection) if !connection.control.is_closed() => Ok(()),
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                format!("{dest:?} is gone"),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("{:?} is not connected to {dest:?}", this.identity),
+            )),
+        }
+    }
+
+    async fn shutdown(&self) {
+        let Some(this) = self.upgrade() else {
+            return;
+        };
+
+        let terminated = this.drain.close(this.shutdown_deadline).await;
+        if terminated > 0 {
+            tracing::warn!(
+                terminated,
+                "records streams were not consumed by peers before shutdown"
+            );
+        }
+        this.record_streams.close();
+        this.query_streams.close();
+    }
+
+    fn cleanup(&self, query_id: QueryId) {
+        let Some(this) = self.upgrade() else {
+            return;
+        };
+
+        this.record_streams.remove_query(query_id);
+        this.query_streams.remove_query(query_id);
+        this.step_encoder.remove_query(query_id);
+        this.step_decoder.remove_query(query_id);
+        this.replays.remove_query(query_id);
+        if let Some(read_ahead) = &this.read_ahead {
+            read_ahead.remove_query(query_id);
+        }
+    }
 }
 
 /// Convenience struct to support heterogeneous in-memory streams
@@ -219,7 +551,7 @@ impl InMemoryStream {
         Self::from_iter(std::iter::empty())
     }
 
-    fn wrap<S: Stream<Item = StreamItem> + Send + 'static>(value: S) -> Self {
+    pub(super) fn wrap<S: Stream<Item = StreamItem> + Send + 'static>(value: S) -> Self {
         Self {
             inner: Box::pin(value),
         }
@@ -265,7 +597,16 @@ struct Addr {
     route: RouteId,
     origin: Option<HelperIdentity>,
     query_id: Option<QueryId>,
+    /// Step of records streams, known to the sender. It is replaced by [`step`] before the
+    /// stream leaves the sender.
+    ///
+    /// [`step`]: Self::step
     gate: Option<Gate>,
+    /// Reference to the step of records streams in the step dictionary, which is what receivers
+    /// see instead of [`gate`].
+    ///
+    /// [`gate`]: Self::gate
+    step: Option<StepRef>,
     params: String,
 }
 
@@ -284,21 +625,37 @@ impl Addr {
             origin: Some(origin),
             query_id: route.query_id().into(),
             gate: route.gate().into(),
+            step: None,
             params: route.extra().borrow().to_string(),
         }
     }
 
+    fn set_step(&mut self, step: StepRef) {
+        self.gate = None;
+        self.step = Some(step);
+    }
+
     fn into<T: DeserializeOwned>(self) -> T {
         serde_json::from_str(&self.params).unwrap()
     }
 
+    /// Addresses a records stream the way senders do the first time they use `gate`. Every
+    /// step gets a number of its own, derived from its path, so tests can send streams for
+    /// different steps without keeping track of the numbers.
     #[cfg(all(test, unit_test))]
     fn records(from: HelperIdentity, query_id: QueryId, gate: Gate) -> Self {
+        let digest = Sha256::digest(<Gate as AsRef<str>>::as_ref(&gate));
+        let id = u32::from_le_bytes(digest[..4].try_into().unwrap());
+
         Self {
             route: RouteId::Records,
             origin: Some(from),
             query_id: Some(query_id),
-            gate: Some(gate),
+            gate: None,
+            step: Some(StepRef {
+                id,
+                gate: Some(gate),
+            }),
             params: String::new(),
         }
     }
@@ -309,30 +666,144 @@ impl Debug for Addr {
         write!(
             f,
             "Addr[route={:?}, query_id={:?}, step={:?}, params={}]",
-            self.route, self.query_id, self.gate, self.params
+            self.route,
+            self.query_id,
+            self.step
+                .as_ref()
+                .map_or(self.gate.as_ref(), |step| step.gate.as_ref()),
+            self.params
+        )
+    }
+}
+
+/// Conditions of the simulated network between in-memory helpers. Every chunk of records data
+/// reaches its destination `latency + random(0..=jitter)` after it was sent. Delays are measured
+/// with Tokio timers, so tests can use paused time to avoid waiting for them.
+///
+/// Default configuration delivers data immediately.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetworkConfig {
+    pub latency: Duration,
+    pub jitter: Duration,
+    /// Seed for jitter. Delays of a given channel are the same for every run with the same seed.
+    pub seed: u64,
+}
+
+impl NetworkConfig {
+    fn deliver<S>(
+        &self,
+        from: HelperIdentity,
+        to: HelperIdentity,
+        gate: &Gate,
+        data: S,
+    ) -> InMemoryStream
+    where
+        S: Stream<Item = StreamItem> + Send + 'static,
+    {
+        if self.latency.is_zero() && self.jitter.is_zero() {
+            return InMemoryStream::wrap(data);
+        }
+
+        // Delays must not depend on the Rust release helpers are built with, so the channel is
+        // hashed with SHA-256 rather than `DefaultHasher`.
+        let mut hasher = Sha256::new();
+        hasher.update(self.seed.to_le_bytes());
+        hasher.update([u8::from(from), u8::from(to)]);
+        hasher.update(<Gate as AsRef<str>>::as_ref(gate));
+        let mut rng = StdRng::from_seed(hasher.finalize().into());
+        let Self {
+            latency, jitter, ..
+        } = *self;
+        let mut delays = iter::repeat_with(move || latency + jitter.mul_f64(rng.gen()));
+
+        // Chunks leave the sender as soon as they are produced, but the receiver sees each of
+        // them only after its delay has elapsed. This keeps chunks in flight concurrently,
+        // like a real network would.
+        let (tx, rx) = channel(16);
+        tokio::spawn(async move {
+            futures::pin_mut!(data);
+            while let Some(chunk) = data.next().await {
+                let deliver_at = ::tokio::time::Instant::now() + delays.next().unwrap();
+                if tx.send((deliver_at, chunk)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        InMemoryStream::wrap(
+            ReceiverStream::new(rx).then(|(deliver_at, chunk)| async move {
+                ::tokio::time::sleep_until(deliver_at).await;
+                chunk
+            }),
         )
     }
 }
 
 pub struct Setup {
     identity: HelperIdentity,
-    tx: ConnectionTx,
-    rx: ConnectionRx,
-    connections: HashMap<HelperIdentity, ConnectionTx>,
+    tx: Connection,
+    control_rx: ConnectionRx,
+    data_rx: ConnectionRx,
+    connections: HashMap<HelperIdentity, Connection>,
+    shutdown_deadline: Duration,
+    gateway_config: Option<GatewayConfig>,
+    network_config: NetworkConfig,
+    send_observer: Option<Arc<SendObserver>>,
+    send_interceptor: Option<Arc<SendInterceptor>>,
 }
 
 impl Setup {
     #[must_use]
     pub fn new(identity: HelperIdentity) -> Self {
-        let (tx, rx) = channel(16);
+        let (control_tx, control_rx) = channel(16);
+        let (data_tx, data_rx) = channel(16);
         Self {
             identity,
-            tx,
-            rx,
+            tx: Connection {
+                control: control_tx,
+                data: data_tx,
+            },
+            control_rx,
+            data_rx,
             connections: HashMap::default(),
+            shutdown_deadline: SHUTDOWN_DEADLINE,
+            gateway_config: None,
+            network_config: NetworkConfig::default(),
+            send_observer: None,
+            send_interceptor: None,
         }
     }
 
+    /// Makes this transport hold back records data that peers send ahead of its receivers, as
+    /// far as `config` allows. Gateways created on top of it should be given the same `config`.
+    #[must_use]
+    pub fn with_gateway_config(mut self, config: GatewayConfig) -> Self {
+        self.gateway_config = Some(config);
+        self
+    }
+
+    /// Makes records data sent by this helper go through the network with the given conditions.
+    #[must_use]
+    pub fn with_network_config(mut self, config: NetworkConfig) -> Self {
+        self.network_config = config;
+        self
+    }
+
+    /// Makes this helper report every chunk of records data it sends to `observer`.
+    #[must_use]
+    pub fn with_send_observer(mut self, observer: Arc<SendObserver>) -> Self {
+        self.send_observer = Some(observer);
+        self
+    }
+
+    /// Lets `interceptor` replace records streams this helper sends, before they are observed
+    /// and delivered.
+    #[must_use]
+    pub fn with_send_interceptor(mut self, interceptor: Arc<SendInterceptor>) -> Self {
+        self.send_interceptor = Some(interceptor);
+        self
+    }
+
     /// Establishes a link between this helper and another one
     ///
     /// ## Panics
@@ -351,9 +822,17 @@ impl Setup {
     fn into_active_conn(
         self,
         callbacks: TransportCallbacks<Weak<InMemoryTransport>>,
-    ) -> (ConnectionTx, Arc<InMemoryTransport>) {
-        let transport = Arc::new(InMemoryTransport::new(self.identity, self.connections));
-        transport.listen(callbacks, self.rx);
+    ) -> (Connection, Arc<InMemoryTransport>) {
+        let transport = Arc::new(InMemoryTransport::new(
+            self.identity,
+            self.connections,
+            self.shutdown_deadline,
+            self.gateway_config,
+            self.network_config,
+            self.send_observer,
+            self.send_interceptor,
+        ));
+        transport.listen(callbacks, self.control_rx, self.data_rx);
 
         (self.tx, transport)
     }
@@ -378,16 +857,28 @@ mod tests {
     use crate::{
         ff::{FieldType, Fp31},
         helpers::{
-            query::QueryType::TestMultiply, transport::in_memory::InMemoryNetwork, HelperIdentity,
-            OrderingSender,
+            query::QueryType::TestMultiply,
+            transport::{in_memory::InMemoryNetwork, NoStep, Truncation},
+            HelperIdentity, OrderingSender, RoleAssignment,
         },
+        test_fixture::SpanCapture,
     };
 
     const STEP: &str = "in-memory-transport";
 
-    async fn send_and_ack(sender: &ConnectionTx, addr: Addr, data: InMemoryStream) {
+    /// Hands `data` over to the transport the same way [`InMemoryTransport::send`] does, records
+    /// streams are framed.
+    async fn send_and_ack(sender: &Connection, addr: Addr, data: InMemoryStream) {
+        let data = match addr.route {
+            RouteId::Records => InMemoryStream::wrap(frame(data)),
+            _ => data,
+        };
         let (tx, rx) = oneshot::channel();
-        sender.send((addr, data, tx)).await.unwrap();
+        sender
+            .channel(addr.route)
+            .send((addr, data, tx))
+            .await
+            .unwrap();
         rx.await
             .map_err(|_e| Error::Io {
                 inner: io::Error::new(ErrorKind::ConnectionRefused, "channel closed"),
@@ -413,7 +904,7 @@ mod tests {
                             .expect("query callback invoked more than once")
                             .send(query_config)
                             .unwrap();
-                        Ok(QueryId)
+                        Ok(QueryId::default())
                     })
                 }),
                 ..Default::default()
@@ -430,6 +921,98 @@ mod tests {
         assert_eq!(expected, signal_rx.await.unwrap());
     }
 
+    #[tokio::test]
+    async fn default_callbacks_reject_queries() {
+        let (tx, _transport) =
+            Setup::new(HelperIdentity::ONE).into_active_conn(TransportCallbacks::default());
+        let query_config = QueryConfig::new(TestMultiply, FieldType::Fp31, 1u32).unwrap();
+
+        // transport must keep serving requests after rejecting one.
+        for _ in 0..2 {
+            let (ack_tx, ack_rx) = oneshot::channel();
+            tx.control
+                .send((
+                    Addr::from_route(HelperIdentity::TWO, &query_config),
+                    InMemoryStream::empty(),
+                    ack_tx,
+                ))
+                .await
+                .unwrap();
+
+            assert!(matches!(
+                ack_rx.await.unwrap(),
+                Err(Error::Rejected {
+                    dest: HelperIdentity::ONE,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn control_does_not_wait_for_data() {
+        let mut sender = Setup::new(HelperIdentity::ONE);
+        let mut receiver = Setup::new(HelperIdentity::TWO);
+        sender.connect(&mut receiver);
+
+        let sender_transport = sender.start(TransportCallbacks::default());
+        let sender = Arc::downgrade(&sender_transport);
+        let (prepared_tx, prepared_rx) = oneshot::channel();
+        let prepared_tx = Mutex::new(Some(prepared_tx));
+        let receiver_transport = Arc::new(InMemoryTransport::new(
+            receiver.identity,
+            receiver.connections,
+            receiver.shutdown_deadline,
+            None,
+            NetworkConfig::default(),
+            None,
+            None,
+        ));
+        receiver_transport.listen_control(
+            TransportCallbacks {
+                prepare_query: Box::new(move |_, prepare| {
+                    prepared_tx
+                        .lock()
+                        .unwrap()
+                        .take()
+                        .unwrap()
+                        .send(prepare.query_id)
+                        .unwrap();
+                    Box::pin(async { Ok(()) })
+                }),
+                ..Default::default()
+            },
+            receiver.control_rx,
+        );
+        // nobody takes records data in on the receiving side, so it piles up until the queue is
+        // full.
+        let _data_rx = receiver.data_rx;
+        while receiver
+            .tx
+            .data
+            .try_send((
+                Addr::records(HelperIdentity::ONE, QueryId::default(), Gate::from(STEP)),
+                InMemoryStream::empty(),
+                oneshot::channel().0,
+            ))
+            .is_ok()
+        {}
+
+        let prepare = PrepareQuery {
+            query_id: QueryId::default(),
+            config: QueryConfig::new(TestMultiply, FieldType::Fp31, 1u32).unwrap(),
+            roles: RoleAssignment::new(HelperIdentity::make_three()),
+        };
+        ::tokio::time::timeout(
+            Duration::from_secs(5),
+            sender.send(HelperIdentity::TWO, &prepare, futures::stream::empty()),
+        )
+        .await
+        .expect("prepare request must not wait for records data")
+        .unwrap();
+        assert_eq!(QueryId::default(), prepared_rx.await.unwrap());
+    }
+
     #[tokio::test]
     async fn receive_not_ready() {
         let (tx, transport) =
@@ -437,7 +1020,8 @@ mod tests {
         let transport = Arc::downgrade(&transport);
         let expected = vec![vec![1], vec![2]];
 
-        let mut stream = transport.receive(HelperIdentity::TWO, (QueryId, Gate::from(STEP)));
+        let mut stream =
+            transport.receive(HelperIdentity::TWO, (QueryId::default(), Gate::from(STEP)));
 
         // make sure it is not ready as it hasn't received the records stream yet.
         assert!(matches!(
@@ -446,7 +1030,7 @@ mod tests {
         ));
         send_and_ack(
             &tx,
-            Addr::records(HelperIdentity::TWO, QueryId, Gate::from(STEP)),
+            Addr::records(HelperIdentity::TWO, QueryId::default(), Gate::from(STEP)),
             InMemoryStream::from_iter(expected.clone()),
         )
         .await;
@@ -462,13 +1046,13 @@ mod tests {
 
         send_and_ack(
             &tx,
-            Addr::records(HelperIdentity::TWO, QueryId, Gate::from(STEP)),
+            Addr::records(HelperIdentity::TWO, QueryId::default(), Gate::from(STEP)),
             InMemoryStream::from_iter(expected.clone()),
         )
         .await;
 
-        let stream =
-            Arc::downgrade(&transport).receive(HelperIdentity::TWO, (QueryId, Gate::from(STEP)));
+        let stream = Arc::downgrade(&transport)
+            .receive(HelperIdentity::TWO, (QueryId::default(), Gate::from(STEP)));
 
         assert_eq!(expected, stream.collect::<Vec<_>>().await);
     }
@@ -487,14 +1071,18 @@ mod tests {
             let to_transport = transports.get(&to).unwrap();
             let gate = Gate::from(STEP);
 
-            let mut recv = to_transport.receive(from, (QueryId, gate.clone()));
+            let mut recv = to_transport.receive(from, (QueryId::default(), gate.clone()));
             assert!(matches!(
                 poll_immediate(&mut recv).next().await,
                 Some(Poll::Pending)
             ));
 
             from_transport
-                .send(to, (RouteId::Records, QueryId, gate.clone()), stream)
+                .send(
+                    to,
+                    (RouteId::Records, QueryId::default(), gate.clone()),
+                    stream,
+                )
                 .await
                 .unwrap();
             stream_tx.send(vec![1, 2, 3]).await.unwrap();
@@ -540,10 +1128,11 @@ mod tests {
         let stream = InMemoryStream::from(stream_rx);
         let transport = Arc::downgrade(&owned_transport);
 
-        let mut recv_stream = transport.receive(HelperIdentity::TWO, (QueryId, gate.clone()));
+        let mut recv_stream =
+            transport.receive(HelperIdentity::TWO, (QueryId::default(), gate.clone()));
         send_and_ack(
             &tx,
-            Addr::records(HelperIdentity::TWO, QueryId, gate.clone()),
+            Addr::records(HelperIdentity::TWO, QueryId::default(), gate.clone()),
             stream,
         )
         .await;
@@ -552,7 +1141,8 @@ mod tests {
         assert_eq!(vec![4, 5, 6], recv_stream.next().await.unwrap());
 
         // the same stream cannot be received again
-        let mut err_recv = transport.receive(HelperIdentity::TWO, (QueryId, gate.clone()));
+        let mut err_recv =
+            transport.receive(HelperIdentity::TWO, (QueryId::default(), gate.clone()));
         let err = AssertUnwindSafe(err_recv.next()).catch_unwind().await;
         assert_eq!(
             Some(true),
@@ -563,7 +1153,8 @@ mod tests {
 
         // even after the input stream is closed
         drop(stream_tx);
-        let mut err_recv = transport.receive(HelperIdentity::TWO, (QueryId, gate.clone()));
+        let mut err_recv =
+            transport.receive(HelperIdentity::TWO, (QueryId::default(), gate.clone()));
         let err = AssertUnwindSafe(err_recv.next()).catch_unwind().await;
         assert_eq!(
             Some(true),
@@ -573,6 +1164,396 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn shutdown_terminates_receive() {
+        let mut setup1 = Setup::new(HelperIdentity::ONE);
+        let mut setup2 = Setup::new(HelperIdentity::TWO);
+        setup1.shutdown_deadline = Duration::from_millis(10);
+        setup1.connect(&mut setup2);
+
+        let owned_transport1 = setup1.start(TransportCallbacks::default());
+        let owned_transport2 = setup2.start(TransportCallbacks::default());
+        let transport1 = Arc::downgrade(&owned_transport1);
+        let transport2 = Arc::downgrade(&owned_transport2);
+        let gate = Gate::from(STEP);
+
+        // The sending end of this stream is never closed, the same way as it wouldn't be
+        // if the query that owns it got stuck.
+        let (stream_tx, stream_rx) = channel(1);
+        transport1
+            .send(
+                HelperIdentity::TWO,
+                (RouteId::Records, QueryId::default(), gate.clone()),
+                InMemoryStream::from(stream_rx),
+            )
+            .await
+            .unwrap();
+        let mut peer_recv =
+            transport2.receive(HelperIdentity::ONE, (QueryId::default(), gate.clone()));
+        stream_tx.send(vec![1, 2, 3]).await.unwrap();
+        assert_eq!(vec![1, 2, 3], peer_recv.next().await.unwrap());
+
+        // This stream never arrives
+        let mut recv = transport1.receive(HelperIdentity::TWO, (QueryId::default(), gate.clone()));
+        assert!(matches!(
+            poll_immediate(&mut recv).next().await,
+            Some(Poll::Pending)
+        ));
+
+        transport1.shutdown().await;
+
+        // Neither stream was finished by its sender, so receivers can tell them from the ones
+        // that were.
+        assert_eq!(None, peer_recv.next().await);
+        assert!(peer_recv.truncated().is_raised());
+        assert_eq!(None, recv.next().await);
+        assert!(recv.truncated().is_raised());
+        assert!(matches!(
+            transport1
+                .send(
+                    HelperIdentity::TWO,
+                    (RouteId::Records, QueryId::default(), gate),
+                    InMemoryStream::empty(),
+                )
+                .await,
+            Err(Error::ShutDown)
+        ));
+
+        drop(stream_tx);
+    }
+
+    #[tokio::test]
+    async fn cleanup_releases_query_channels() {
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let [consumed, unconsumed, missing] = ["consumed", "unconsumed", "missing"]
+            .map(|step| Gate::from(format!("{STEP}/{step}").as_str()));
+
+        for gate in [&consumed, &unconsumed] {
+            t1.send(
+                HelperIdentity::TWO,
+                (RouteId::Records, QueryId::default(), gate.clone()),
+                InMemoryStream::from_iter(vec![vec![1, 2, 3]]),
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(
+            vec![vec![1, 2, 3]],
+            t2.receive(HelperIdentity::ONE, (QueryId::default(), consumed))
+                .collect::<Vec<_>>()
+                .await
+        );
+        // This stream never arrives
+        let mut recv = t2.receive(HelperIdentity::ONE, (QueryId::default(), missing));
+        assert!(matches!(
+            poll_immediate(&mut recv).next().await,
+            Some(Poll::Pending)
+        ));
+        assert_ne!(0, network.transports[0].channel_count());
+        assert_ne!(0, network.transports[1].channel_count());
+
+        t1.cleanup(QueryId::default());
+        t2.cleanup(QueryId::default());
+
+        assert_eq!(None, recv.next().await);
+        assert_eq!(
+            Some(Truncation::NeverArrived {
+                unclaimed: vec![unconsumed]
+            }),
+            recv.truncated().cause()
+        );
+        for transport in &network.transports {
+            assert_eq!(0, transport.channel_count());
+        }
+    }
+
+    /// Receivers that are dropped before they learn the fate of their stream do not leave their
+    /// entries behind.
+    #[tokio::test]
+    async fn dropped_receivers_release_channels() {
+        let network = InMemoryNetwork::default();
+        let [_, t2, _] = network.transports();
+        let [waiting, terminated] =
+            ["waiting", "terminated"].map(|step| Gate::from(format!("{STEP}/{step}").as_str()));
+
+        for gate in [waiting, terminated.clone()] {
+            let mut recv = t2.receive(HelperIdentity::ONE, (QueryId::default(), gate.clone()));
+            assert!(matches!(
+                poll_immediate(&mut recv).next().await,
+                Some(Poll::Pending)
+            ));
+            assert_eq!(1, network.transports[1].channel_count());
+
+            if gate == terminated {
+                t2.cleanup(QueryId::default());
+                assert_eq!(1, network.transports[1].channel_count());
+            }
+            drop(recv);
+            assert_eq!(0, network.transports[1].channel_count());
+        }
+    }
+
+    /// Every query numbers its steps on its own. Streams of two queries that run at the same time
+    /// carry the same numbers for different steps, and each of them must reach its receiver.
+    #[tokio::test]
+    async fn concurrent_queries_share_step_numbers() {
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let queries = [QueryId::from(1), QueryId::from(2)];
+        let steps = ["a", "b"].map(|step| Gate::from(format!("{STEP}/{step}").as_str()));
+        let chunk = |query: usize, step: usize, round: u8| {
+            vec![
+                u8::try_from(query).unwrap(),
+                u8::try_from(step).unwrap(),
+                round,
+            ]
+        };
+
+        // Queries start their steps in the opposite order, so the first number goes to `a` in
+        // one of them and to `b` in the other.
+        let mut senders = Vec::new();
+        for (query, query_id) in queries.iter().enumerate() {
+            for i in 0..steps.len() {
+                let step = (i + query) % steps.len();
+                let (tx, rx) = channel(2);
+                t1.send(
+                    HelperIdentity::TWO,
+                    (RouteId::Records, *query_id, steps[step].clone()),
+                    InMemoryStream::from(rx),
+                )
+                .await
+                .unwrap();
+                senders.push((query, step, tx));
+            }
+        }
+        // Chunks of all streams are interleaved.
+        for round in 0..2 {
+            for (query, step, tx) in &senders {
+                tx.send(chunk(*query, *step, round)).await.unwrap();
+            }
+        }
+        drop(senders);
+
+        for (query, query_id) in queries.iter().enumerate() {
+            for (step, gate) in steps.iter().enumerate() {
+                assert_eq!(
+                    [chunk(query, step, 0), chunk(query, step, 1)].concat(),
+                    t2.receive(HelperIdentity::ONE, (*query_id, gate.clone()))
+                        .collect::<Vec<_>>()
+                        .await
+                        .concat()
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn query_records_without_step() {
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let gate = Gate::from(STEP);
+
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), NoStep),
+            InMemoryStream::from_iter(vec![vec![1, 2, 3]]),
+        )
+        .await
+        .unwrap();
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), gate.clone()),
+            InMemoryStream::from_iter(vec![vec![4, 5, 6]]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            vec![vec![1, 2, 3]],
+            t2.receive_query_records(HelperIdentity::ONE, QueryId::default())
+                .collect::<Vec<_>>()
+                .await
+        );
+        // step-less records do not take the place of the stream on any step
+        assert_eq!(
+            vec![vec![4, 5, 6]],
+            t2.receive(HelperIdentity::ONE, (QueryId::default(), gate))
+                .collect::<Vec<_>>()
+                .await
+        );
+
+        t2.cleanup(QueryId::default());
+        assert_eq!(0, network.transports[1].channel_count());
+    }
+
+    #[tokio::test]
+    async fn receive_from_resumes() {
+        const RECORD_SIZE: usize = 4;
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let gate = Gate::from(STEP);
+        let records = (0..10_u32).map(u32::to_le_bytes).collect::<Vec<_>>();
+
+        // chunks do not line up with records
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), gate.clone()),
+            InMemoryStream::from_iter(
+                records
+                    .concat()
+                    .chunks(6)
+                    .map(<[u8]>::to_vec)
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .await
+        .unwrap();
+
+        let receive_from = |start: usize| {
+            t2.receive_from(
+                HelperIdentity::ONE,
+                (QueryId::default(), gate.clone()),
+                RecordId::from(start),
+                RECORD_SIZE,
+            )
+        };
+
+        let mut recv = receive_from(0);
+        let mut received = Vec::new();
+        while received.len() < 5 * RECORD_SIZE {
+            received.extend(recv.next().await.unwrap());
+        }
+        assert_eq!(records[..5].concat(), received[..5 * RECORD_SIZE]);
+        // the receiver is interrupted
+        drop(recv);
+
+        let rest = receive_from(5).collect::<Vec<_>>().await;
+        assert_eq!(records[5..].concat(), rest.concat());
+
+        // records before the last resumption are gone
+        let mut recv = receive_from(4);
+        assert_eq!(None, recv.next().await);
+        assert_eq!(Some(Truncation::Consumed), recv.truncated().cause());
+
+        t2.cleanup(QueryId::default());
+        assert_eq!(0, network.transports[1].channel_count());
+    }
+
+    /// Streams taken by `receive` are not kept, so they can't be resumed.
+    #[tokio::test]
+    async fn receive_from_consumed() {
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let gate = Gate::from(STEP);
+
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), gate.clone()),
+            InMemoryStream::from_iter(vec![vec![1, 2, 3]]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            vec![vec![1, 2, 3]],
+            t2.receive(HelperIdentity::ONE, (QueryId::default(), gate.clone()))
+                .collect::<Vec<_>>()
+                .await
+        );
+
+        let mut recv = t2.receive_from(
+            HelperIdentity::ONE,
+            (QueryId::default(), gate),
+            RecordId::from(1),
+            1,
+        );
+        assert_eq!(None, recv.next().await);
+        assert_eq!(Some(Truncation::Consumed), recv.truncated().cause());
+    }
+
+    #[tokio::test]
+    async fn receive_buffer_limit() {
+        let config =
+            GatewayConfig::default().with_receive_buffer_limit(NonZeroUsize::new(16).unwrap());
+        let network = InMemoryNetwork::with_config([config; 3], Default::default());
+        let [t1, t2, t3] = network.transports();
+        let send = |from: &Weak<InMemoryTransport>, step: &str, chunks: Vec<Vec<u8>>| {
+            let from = Weak::clone(from);
+            let gate = Gate::from(format!("{STEP}/{step}").as_str());
+            async move {
+                from.send(
+                    HelperIdentity::ONE,
+                    (RouteId::Records, QueryId::default(), gate),
+                    InMemoryStream::from_iter(chunks),
+                )
+                .await
+            }
+        };
+
+        // nobody receives this stream, so all of it has to be buffered
+        assert!(matches!(
+            send(&t2, "flood", vec![vec![0; 8]; 4]).await,
+            Err(Error::Rejected { .. })
+        ));
+
+        // data from other peers is accepted as before
+        send(&t3, "fine", vec![vec![1, 2, 3]]).await.unwrap();
+        assert_eq!(
+            vec![vec![1, 2, 3]],
+            t1.receive(
+                HelperIdentity::THREE,
+                (
+                    QueryId::default(),
+                    Gate::from(format!("{STEP}/fine").as_str())
+                )
+            )
+            .collect::<Vec<_>>()
+            .await
+        );
+
+        // and so is the data the same peer sends once the query is over
+        t1.cleanup(QueryId::default());
+        send(&t2, "next", vec![vec![4, 5, 6]]).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn send_span_fields() {
+        let capture = SpanCapture::default();
+        let _guard = capture.install();
+
+        let network = InMemoryNetwork::default();
+        let transport1 = network.transport(HelperIdentity::ONE);
+        let transport2 = network.transport(HelperIdentity::TWO);
+        let gate = Gate::from(STEP);
+
+        transport1
+            .send(
+                HelperIdentity::TWO,
+                (RouteId::Records, QueryId::default(), gate.clone()),
+                futures::stream::iter(vec![vec![1, 2, 3], vec![4]]),
+            )
+            .await
+            .unwrap();
+        let received = transport2
+            .receive(HelperIdentity::ONE, (QueryId::default(), gate))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(vec![vec![1, 2, 3], vec![4]], received);
+
+        let sends = capture.find("send");
+        assert_eq!(1, sends.len());
+        let send = &sends[0];
+        assert!(send["query_id"].contains("QueryId"));
+        assert!(send["step"].contains(STEP));
+        assert_eq!("Records", send["route"]);
+        assert_eq!(format!("{:?}", HelperIdentity::TWO), send["dest"]);
+        assert_eq!("4", send["bytes"]);
+
+        let receives = capture.find("receive");
+        assert_eq!(1, receives.len());
+        assert_eq!("4", receives[0]["bytes"]);
+    }
+
     #[tokio::test]
     async fn can_consume_ordering_sender() {
         let tx = Arc::new(OrderingSender::new(
@@ -588,12 +1569,12 @@ mod tests {
         transport1
             .send(
                 HelperIdentity::TWO,
-                (RouteId::Records, QueryId, gate.clone()),
+                (RouteId::Records, QueryId::default(), gate.clone()),
                 rx,
             )
             .await
             .unwrap();
-        let mut recv = transport2.receive(HelperIdentity::ONE, (QueryId, gate));
+        let mut recv = transport2.receive(HelperIdentity::ONE, (QueryId::default(), gate));
 
         tx.send(0, Fp31::try_from(0_u128).unwrap()).await;
         // can't receive the value at index 0 because of buffering inside the sender
@@ -607,4 +1588,42 @@ mod tests {
         // must be received by now
         assert_eq!(vec![vec![0, 1]], recv.collect::<Vec<_>>().await);
     }
+
+    #[tokio::test]
+    async fn ping_dropped_peer() {
+        const TIMEOUT: Duration = Duration::from_secs(1);
+
+        let mut first = Setup::new(HelperIdentity::ONE);
+        let mut second = Setup::new(HelperIdentity::TWO);
+        first.connect(&mut second);
+        let first = first.start(TransportCallbacks::default());
+        let second = second.start(TransportCallbacks::default());
+        let transport = Arc::downgrade(&first);
+
+        transport.ping(HelperIdentity::TWO, TIMEOUT).await.unwrap();
+        assert_eq!(
+            ErrorKind::NotConnected,
+            transport
+                .ping(HelperIdentity::THREE, TIMEOUT)
+                .await
+                .unwrap_err()
+                .kind()
+        );
+
+        drop(second);
+        // Peer stops listening in the background.
+        first
+            .get_connection(HelperIdentity::TWO)
+            .control
+            .closed()
+            .await;
+        assert_eq!(
+            ErrorKind::ConnectionAborted,
+            transport
+                .ping(HelperIdentity::TWO, TIMEOUT)
+                .await
+                .unwrap_err()
+                .kind()
+        );
+    }
 }
@@ -1,28 +1,47 @@
-use std::borrow::Borrow;
+use std::{
+    any::{type_name, Any},
+    borrow::Borrow,
+    io,
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use futures::Stream;
 
 use crate::{
-    helpers::HelperIdentity,
-    protocol::{step::Gate, QueryId},
+    helpers::{HelperIdentity, TransportImpl},
+    protocol::{step::Gate, QueryId, RecordId},
 };
 
 pub mod callbacks;
+mod drain;
+mod framed;
 #[cfg(feature = "in-memory-infra")]
 mod in_memory;
 pub mod query;
 mod receive;
+mod stats;
+#[cfg(feature = "in-memory-infra")]
+mod step_dictionary;
 mod stream;
+pub(crate) mod trace;
 
+pub(crate) use drain::Drain;
+pub use drain::SHUTDOWN_DEADLINE;
+pub(crate) use framed::frame;
 #[cfg(feature = "in-memory-infra")]
-pub use in_memory::{InMemoryNetwork, InMemoryTransport};
+pub use in_memory::{
+    InMemoryNetwork, InMemoryTransport, NetworkConfig, SendInterceptor, SendObserver,
+    Setup as InMemorySetup,
+};
 pub use receive::{LogErrors, ReceiveRecords};
+pub(crate) use receive::{Truncated, Truncation};
+pub use stats::{ChannelStats, StatsKey, StatsSnapshot, TransportStats};
 #[cfg(feature = "web-app")]
 pub use stream::WrappedAxumBodyStream;
 pub use stream::{
-    BodyStream, BytesStream, LengthDelimitedStream, RecordsStream, StreamCollection, StreamKey,
-    WrappedBoxBodyStream,
+    merge_ordered, BodyStream, BytesStream, LengthDelimitedStream, MergeOrdered, RecordsStream,
+    StreamCollection, StreamKey, WrappedBoxBodyStream,
 };
 
 pub trait ResourceIdentifier: Sized {}
@@ -41,13 +60,37 @@ pub struct NoResourceIdentifier;
 pub struct NoQueryId;
 pub struct NoStep;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum RouteId {
     Records,
     ReceiveQuery,
     PrepareQuery,
 }
 
+/// Requests of different routes compete for the same link between helpers. Transports must make
+/// sure that [`Control`] requests do not wait behind [`Data`], so a helper busy exchanging
+/// records for one query can still set up another one.
+///
+/// [`Control`]: Priority::Control
+/// [`Data`]: Priority::Data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Priority {
+    /// Requests that create and manage queries.
+    Control,
+    /// Records data exchanged while queries run.
+    Data,
+}
+
+impl RouteId {
+    #[must_use]
+    pub fn priority(self) -> Priority {
+        match self {
+            RouteId::Records => Priority::Data,
+            RouteId::ReceiveQuery | RouteId::PrepareQuery => Priority::Control,
+        }
+    }
+}
+
 impl ResourceIdentifier for NoResourceIdentifier {}
 impl ResourceIdentifier for RouteId {}
 
@@ -123,6 +166,36 @@ impl RouteParams<RouteId, QueryId, Gate> for (RouteId, QueryId, Gate) {
     }
 }
 
+/// Records that belong to a query as a whole rather than to one of its steps. Transports keep a
+/// single channel per query and sender for them, see [`Transport::receive_query_records`].
+impl RouteParams<RouteId, QueryId, NoStep> for (RouteId, QueryId, NoStep) {
+    type Params = &'static str;
+
+    fn resource_identifier(&self) -> RouteId {
+        self.0
+    }
+
+    fn query_id(&self) -> QueryId {
+        self.1
+    }
+
+    fn gate(&self) -> NoStep {
+        NoStep
+    }
+
+    fn extra(&self) -> Self::Params {
+        ""
+    }
+}
+
+/// Returned by [`Transport::into_impl`] when transport can't be used to run queries on this helper.
+#[derive(Debug, thiserror::Error)]
+#[error("{actual} is not supported, queries on this helper can only run on {expected}")]
+pub struct UnsupportedTransport {
+    actual: &'static str,
+    expected: &'static str,
+}
+
 /// Transport that supports per-query,per-step channels
 #[async_trait]
 pub trait Transport: Clone + Send + Sync + 'static {
@@ -156,6 +229,76 @@ pub trait Transport: Clone + Send + Sync + 'static {
         route: R,
     ) -> Self::RecordsStream;
 
+    /// Like [`receive`], but the stream starts at record `start` and skips everything before it.
+    /// Records are `record_size` bytes each. This lets the receiver resume the stream after it was
+    /// interrupted, instead of reading it from record zero again.
+    ///
+    /// [`receive`]: Self::receive
+    fn receive_from<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+        start: RecordId,
+        record_size: usize,
+    ) -> Self::RecordsStream;
+
+    /// Returns the stream of records that helper `from` sends to query `query_id` as a whole,
+    /// rather than to one of its steps, see the `(RouteId::Records, QueryId, NoStep)` route.
+    fn receive_query_records(&self, from: HelperIdentity, query_id: QueryId)
+        -> Self::RecordsStream;
+
+    /// Checks that helper `dest` is alive, so that a dead peer is detected early instead of
+    /// leaving [`receive`] waiting for its records forever. Waits no longer than `timeout` for the
+    /// peer to respond.
+    ///
+    /// Transports that have no way to reach the peer outside of records streams consider it alive.
+    ///
+    /// ## Errors
+    /// If `dest` is unreachable or does not respond in time.
+    ///
+    /// [`receive`]: Self::receive
+    async fn ping(&self, _dest: HelperIdentity, _timeout: Duration) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    /// Returns the collector of statistics about records data moved by this transport.
+    fn stats(&self) -> TransportStats;
+
+    /// Shuts this transport down gracefully. After this call, transport rejects any new
+    /// [`send`] request and waits, up to a deadline, for peers to consume the records it sent to
+    /// them already. Receivers awaiting records that haven't arrived yet observe the end of
+    /// stream rather than waiting forever.
+    ///
+    /// Transports that don't keep any resources between requests don't need to do anything here.
+    ///
+    /// [`send`]: Self::send
+    async fn shutdown(&self) {}
+
+    /// Releases everything this transport keeps for the given query: records streams, whether
+    /// they were consumed or not, and anything else it remembers about the channels of this
+    /// query. Receivers still waiting for a stream of this query observe the end of stream.
+    ///
+    /// Query processor calls it once the query reaches a terminal state. Transports that don't
+    /// keep any per-query state don't need to do anything here.
+    fn cleanup(&self, _query_id: QueryId) {}
+
+    /// Converts this transport into [`TransportImpl`], the transport that query processing is
+    /// built for. The default implementation succeeds only if `Self` is `TransportImpl` already.
+    /// Transports that wrap it must not hand out the inner transport, as the query would then
+    /// run without whatever the wrapper does.
+    ///
+    /// ## Errors
+    /// If this transport can't be converted into [`TransportImpl`].
+    fn into_impl(self) -> Result<TransportImpl, UnsupportedTransport> {
+        let this: Box<dyn Any> = Box::new(self);
+        this.downcast::<TransportImpl>()
+            .map(|transport| *transport)
+            .map_err(|_| UnsupportedTransport {
+                actual: type_name::<Self>(),
+                expected: type_name::<TransportImpl>(),
+            })
+    }
+
     /// Alias for `Clone::clone`.
     ///
     /// `Transport` is implemented for `Weak<InMemoryTranport>` and `Arc<HttpTransport>`. Clippy won't
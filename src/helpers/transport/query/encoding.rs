@@ -0,0 +1,391 @@
+//! Binary encoding of [`PrepareQuery`] requests.
+//!
+//! The first byte is the version of the format, the rest is laid out as follows (integers are
+//! little-endian):
+//!
+//! ```text
+//! query id: u64
+//! query size: u32
+//! field type: u8
+//! query type: u8, followed by its parameters
+//!     IPA: per user credit cap: u32, max breakdown key: u32,
+//!          attribution window in seconds: u32 (0 if not set), number of multi bits: u32,
+//!          plaintext match keys: u8
+//!     sparse aggregate: contribution bits: u32, number of contributions: u32
+//! security model: u8
+//! timeout in milliseconds: u64 (0 if not set)
+//! roles: u8 identities of the helpers that play H1, H2 and H3
+//! ```
+
+use std::{num::NonZeroU32, time::Duration};
+
+use crate::{
+    ff::FieldType,
+    helpers::{
+        query::{
+            ContributionBits, IpaQueryConfig, PrepareQuery, QueryConfig, QuerySize, QueryType,
+            SecurityModel, SparseAggregateQueryConfig,
+        },
+        HelperIdentity, Role, RoleAssignment,
+    },
+    protocol::QueryId,
+};
+
+const VERSION: u8 = 4;
+
+#[cfg(any(test, feature = "weak-field"))]
+const FP31: u8 = 0;
+const FP32_BIT_PRIME: u8 = 1;
+
+#[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+const TEST_MULTIPLY: u8 = 0;
+const SEMI_HONEST_IPA: u8 = 1;
+const MALICIOUS_IPA: u8 = 2;
+const SEMI_HONEST_SPARSE_AGGREGATE: u8 = 3;
+const MALICIOUS_SPARSE_AGGREGATE: u8 = 4;
+const OPRF_IPA: u8 = 5;
+#[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+const TEST_ADD: u8 = 6;
+
+const SEMI_HONEST: u8 = 0;
+const MALICIOUS: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("Unsupported version of the encoding: {0}")]
+    UnsupportedVersion(u8),
+    #[error("Input ended before {0} could be read")]
+    UnexpectedEnd(&'static str),
+    #[error("{0} bytes left after the end of the encoded value")]
+    TrailingBytes(usize),
+    #[error("Unknown {what}: {value}")]
+    UnknownTag { what: &'static str, value: u8 },
+    #[error("Invalid {what}: {reason}")]
+    InvalidValue { what: &'static str, reason: String },
+}
+
+impl PrepareQuery {
+    /// ## Panics
+    /// If the query timeout does not fit into `u64` milliseconds.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![VERSION];
+        buf.extend_from_slice(&u64::from(self.query_id).to_le_bytes());
+        let QueryConfig {
+            size,
+            field_type,
+            query_type,
+            security_model,
+            timeout,
+        } = self.config;
+
+        buf.extend_from_slice(&u32::from(size).to_le_bytes());
+        buf.push(match field_type {
+            #[cfg(any(test, feature = "weak-field"))]
+            FieldType::Fp31 => FP31,
+            FieldType::Fp32BitPrime => FP32_BIT_PRIME,
+        });
+        match query_type {
+            #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+            QueryType::TestMultiply => buf.push(TEST_MULTIPLY),
+            #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+            QueryType::TestAdd => buf.push(TEST_ADD),
+            QueryType::SemiHonestIpa(config) => write_ipa(&mut buf, SEMI_HONEST_IPA, &config),
+            QueryType::MaliciousIpa(config) => write_ipa(&mut buf, MALICIOUS_IPA, &config),
+            QueryType::OprfIpa(config) => write_ipa(&mut buf, OPRF_IPA, &config),
+            QueryType::SemiHonestSparseAggregate(config) => {
+                write_sparse_aggregate(&mut buf, SEMI_HONEST_SPARSE_AGGREGATE, config);
+            }
+            QueryType::MaliciousSparseAggregate(config) => {
+                write_sparse_aggregate(&mut buf, MALICIOUS_SPARSE_AGGREGATE, config);
+            }
+        }
+        buf.push(match security_model {
+            SecurityModel::SemiHonest => SEMI_HONEST,
+            SecurityModel::Malicious => MALICIOUS,
+        });
+        let timeout = timeout.map_or(0, |timeout| {
+            u64::try_from(timeout.as_millis()).expect("query timeout must fit into u64 millis")
+        });
+        buf.extend_from_slice(&timeout.to_le_bytes());
+        buf.extend(
+            Role::all()
+                .iter()
+                .map(|&role| u8::from(self.roles.identity(role))),
+        );
+
+        buf
+    }
+
+    /// ## Errors
+    /// If `bytes` were not produced by [`to_bytes`] of a supported version.
+    ///
+    /// [`to_bytes`]: Self::to_bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        let version = reader.u8("version")?;
+        if version != VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+
+        let query_id = QueryId::from(reader.u64("query id")?);
+        let size = QuerySize::try_from(reader.u32("query size")?).map_err(|e| {
+            DecodeError::InvalidValue {
+                what: "query size",
+                reason: e.to_string(),
+            }
+        })?;
+        let field_type = match reader.u8("field type")? {
+            #[cfg(any(test, feature = "weak-field"))]
+            FP31 => FieldType::Fp31,
+            FP32_BIT_PRIME => FieldType::Fp32BitPrime,
+            value => {
+                return Err(DecodeError::UnknownTag {
+                    what: "field type",
+                    value,
+                })
+            }
+        };
+        let query_type = match reader.u8("query type")? {
+            #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+            TEST_MULTIPLY => QueryType::TestMultiply,
+            #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+            TEST_ADD => QueryType::TestAdd,
+            SEMI_HONEST_IPA => QueryType::SemiHonestIpa(reader.ipa()?),
+            MALICIOUS_IPA => QueryType::MaliciousIpa(reader.ipa()?),
+            OPRF_IPA => QueryType::OprfIpa(reader.ipa()?),
+            SEMI_HONEST_SPARSE_AGGREGATE => {
+                QueryType::SemiHonestSparseAggregate(reader.sparse_aggregate()?)
+            }
+            MALICIOUS_SPARSE_AGGREGATE => {
+                QueryType::MaliciousSparseAggregate(reader.sparse_aggregate()?)
+            }
+            value => {
+                return Err(DecodeError::UnknownTag {
+                    what: "query type",
+                    value,
+                })
+            }
+        };
+        let security_model = match reader.u8("security model")? {
+            SEMI_HONEST => SecurityModel::SemiHonest,
+            MALICIOUS => SecurityModel::Malicious,
+            value => {
+                return Err(DecodeError::UnknownTag {
+                    what: "security model",
+                    value,
+                })
+            }
+        };
+        let timeout = match reader.u64("timeout")? {
+            0 => None,
+            millis => Some(Duration::from_millis(millis)),
+        };
+        let roles = reader.roles()?;
+
+        if !reader.0.is_empty() {
+            return Err(DecodeError::TrailingBytes(reader.0.len()));
+        }
+
+        Ok(Self {
+            query_id,
+            config: QueryConfig {
+                size,
+                field_type,
+                query_type,
+                security_model,
+                timeout,
+            },
+            roles,
+        })
+    }
+}
+
+fn write_ipa(buf: &mut Vec<u8>, tag: u8, config: &IpaQueryConfig) {
+    buf.push(tag);
+    for value in [
+        config.per_user_credit_cap,
+        config.max_breakdown_key,
+        config.attribution_window_seconds.map_or(0, NonZeroU32::get),
+        config.num_multi_bits,
+    ] {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+    buf.push(u8::from(config.plaintext_match_keys));
+}
+
+fn write_sparse_aggregate(buf: &mut Vec<u8>, tag: u8, config: SparseAggregateQueryConfig) {
+    buf.push(tag);
+    buf.extend_from_slice(&config.contribution_bits.0.to_le_bytes());
+    buf.extend_from_slice(&config.num_contributions.to_le_bytes());
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self, what: &'static str) -> Result<[u8; N], DecodeError> {
+        if self.0.len() < N {
+            return Err(DecodeError::UnexpectedEnd(what));
+        }
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u8(&mut self, what: &'static str) -> Result<u8, DecodeError> {
+        Ok(self.bytes::<1>(what)?[0])
+    }
+
+    fn u32(&mut self, what: &'static str) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.bytes(what)?))
+    }
+
+    fn u64(&mut self, what: &'static str) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.bytes(what)?))
+    }
+
+    fn ipa(&mut self) -> Result<IpaQueryConfig, DecodeError> {
+        Ok(IpaQueryConfig {
+            per_user_credit_cap: self.u32("per user credit cap")?,
+            max_breakdown_key: self.u32("max breakdown key")?,
+            attribution_window_seconds: NonZeroU32::new(self.u32("attribution window")?),
+            num_multi_bits: self.u32("number of multi bits")?,
+            plaintext_match_keys: match self.u8("plaintext match keys")? {
+                0 => false,
+                1 => true,
+                value => {
+                    return Err(DecodeError::UnknownTag {
+                        what: "plaintext match keys flag",
+                        value,
+                    })
+                }
+            },
+        })
+    }
+
+    fn sparse_aggregate(&mut self) -> Result<SparseAggregateQueryConfig, DecodeError> {
+        Ok(SparseAggregateQueryConfig {
+            contribution_bits: ContributionBits::try_from(self.u32("contribution bits")?).map_err(
+                |reason| DecodeError::InvalidValue {
+                    what: "contribution bits",
+                    reason,
+                },
+            )?,
+            num_contributions: self.u32("number of contributions")?,
+        })
+    }
+
+    fn roles(&mut self) -> Result<RoleAssignment, DecodeError> {
+        let invalid = |reason| DecodeError::InvalidValue {
+            what: "roles",
+            reason,
+        };
+        let mut identities = Vec::with_capacity(3);
+        for id in self.bytes::<3>("roles")? {
+            let id = HelperIdentity::try_from(usize::from(id)).map_err(invalid)?;
+            if identities.contains(&id) {
+                return Err(invalid(format!("helper {id:?} has more than one role")));
+            }
+            identities.push(id);
+        }
+
+        Ok(RoleAssignment::new(identities.try_into().unwrap()))
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{num::NonZeroU32, time::Duration};
+
+    use super::DecodeError;
+    use crate::{
+        ff::FieldType,
+        helpers::{
+            query::{IpaQueryConfig, PrepareQuery, QueryConfig, QueryType, SecurityModel},
+            HelperIdentity, RoleAssignment,
+        },
+        protocol::QueryId,
+    };
+
+    fn prepare(query_type: QueryType, field_type: FieldType) -> PrepareQuery {
+        PrepareQuery {
+            query_id: QueryId::from(0x0123_4567_89ab_cdef),
+            config: QueryConfig::new(query_type, field_type, 100).unwrap(),
+            roles: RoleAssignment::new([
+                HelperIdentity::TWO,
+                HelperIdentity::THREE,
+                HelperIdentity::ONE,
+            ]),
+        }
+    }
+
+    #[test]
+    fn round_trip_test_queries() {
+        for query_type in [QueryType::TestMultiply, QueryType::TestAdd] {
+            for security_model in [SecurityModel::SemiHonest, SecurityModel::Malicious] {
+                let mut query = prepare(query_type, FieldType::Fp31);
+                query.config = query.config.with_security_model(security_model);
+                assert_eq!(query, PrepareQuery::from_bytes(&query.to_bytes()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_timeout() {
+        let mut query = prepare(QueryType::TestMultiply, FieldType::Fp31);
+        query.config = query.config.with_timeout(Duration::from_millis(90_500));
+        assert_eq!(query, PrepareQuery::from_bytes(&query.to_bytes()).unwrap());
+    }
+
+    #[test]
+    fn round_trip_ipa() {
+        let configs = [
+            IpaQueryConfig::default(),
+            IpaQueryConfig {
+                attribution_window_seconds: NonZeroU32::new(86_400),
+                plaintext_match_keys: true,
+                ..IpaQueryConfig::new(7, 32, 1, 2)
+            },
+        ];
+
+        for config in configs {
+            for query_type in [
+                QueryType::SemiHonestIpa(config),
+                QueryType::MaliciousIpa(config),
+                QueryType::OprfIpa(config),
+            ] {
+                let query = prepare(query_type, FieldType::Fp32BitPrime);
+                assert_eq!(query, PrepareQuery::from_bytes(&query.to_bytes()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let bytes = prepare(QueryType::TestMultiply, FieldType::Fp31).to_bytes();
+
+        let mut newer = bytes.clone();
+        newer[0] += 1;
+        assert!(matches!(
+            PrepareQuery::from_bytes(&newer),
+            Err(DecodeError::UnsupportedVersion(5))
+        ));
+        assert!(matches!(
+            PrepareQuery::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd("roles"))
+        ));
+        assert!(matches!(
+            PrepareQuery::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+            Err(DecodeError::TrailingBytes(1))
+        ));
+
+        let mut same_helper = bytes;
+        let len = same_helper.len();
+        same_helper[len - 1] = same_helper[len - 2];
+        assert!(matches!(
+            PrepareQuery::from_bytes(&same_helper),
+            Err(DecodeError::InvalidValue { what: "roles", .. })
+        ));
+    }
+}
@@ -1,10 +1,13 @@
+mod encoding;
 pub mod oprf_shuffle;
 
 use std::{
     fmt::{Debug, Display, Formatter},
     num::NonZeroU32,
+    time::Duration,
 };
 
+pub use encoding::DecodeError;
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
@@ -91,12 +94,37 @@ pub struct QueryConfig {
     pub size: QuerySize,
     pub field_type: FieldType,
     pub query_type: QueryType,
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub security_model: SecurityModel,
+    /// Deadline for the query, counted from the moment its inputs arrive. Queries that do not
+    /// complete in time are aborted. By default, queries may run for as long as they need.
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub timeout: Option<Duration>,
+}
+
+/// Security guarantees helpers provide when they run a query. Malicious security costs more: every
+/// share carries a MAC and helpers validate their computation before revealing the result.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SecurityModel {
+    #[default]
+    SemiHonest,
+    Malicious,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum QueryConfigError {
     #[error(transparent)]
     BadQuerySize(#[from] BadQuerySizeError),
+    #[error("IPA query parameter {0} must be greater than 0")]
+    ZeroIpaParameter(&'static str),
+    #[error("Query type {query_type} requires {expected:?} security, got: {requested:?}")]
+    SecurityModelMismatch {
+        query_type: &'static str,
+        expected: SecurityModel,
+        requested: SecurityModel,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -142,7 +170,11 @@ impl From<&QueryConfig> for GatewayConfig {
 }
 
 impl QueryConfig {
-    /// Initialize new query configuration.
+    /// Initialize new query configuration. Security model is the one `query_type` implies: malicious
+    /// for malicious IPA and aggregation, semi-honest for everything else. Test queries can run
+    /// under either, see [`with_security_model`].
+    ///
+    /// [`with_security_model`]: Self::with_security_model
     ///
     /// ## Errors
     /// If query size is too large or 0.
@@ -158,8 +190,53 @@ impl QueryConfig {
             size: size.try_into()?,
             field_type,
             query_type,
+            security_model: query_type.implied_security_model().unwrap_or_default(),
+            timeout: None,
         })
     }
+
+    #[must_use]
+    pub fn with_security_model(mut self, security_model: SecurityModel) -> Self {
+        self.security_model = security_model;
+        self
+    }
+
+    /// Sets a deadline for this query. Every helper enforces it on its own, so it travels to
+    /// followers along with the rest of the configuration when they are asked to prepare.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Checks that query parameters make sense before the query is started, so that bad
+    /// configurations are rejected upfront rather than failing somewhere deep inside the protocol.
+    ///
+    /// ## Errors
+    /// If any of the IPA parameters that must be positive is 0, or if the security model is not
+    /// the one query type implies.
+    pub fn validate(&self) -> Result<(), QueryConfigError> {
+        if let Some(expected) = self.query_type.implied_security_model() {
+            if expected != self.security_model {
+                return Err(QueryConfigError::SecurityModelMismatch {
+                    query_type: self.query_type.name(),
+                    expected,
+                    requested: self.security_model,
+                });
+            }
+        }
+
+        match self.query_type {
+            QueryType::SemiHonestIpa(config)
+            | QueryType::MaliciousIpa(config)
+            | QueryType::OprfIpa(config) => config.validate(),
+            #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+            QueryType::TestMultiply | QueryType::TestAdd => Ok(()),
+            QueryType::SemiHonestSparseAggregate(_) | QueryType::MaliciousSparseAggregate(_) => {
+                Ok(())
+            }
+        }
+    }
 }
 
 impl RouteParams<RouteId, QueryId, NoStep> for &PrepareQuery {
@@ -191,6 +268,29 @@ impl RouteParams<RouteId, QueryId, NoStep> for &PrepareQuery {
 pub struct QueryInput {
     pub query_id: QueryId,
     pub input_stream: BodyStream,
+    /// Total length of the input in bytes, if it is known before the input stream is consumed.
+    pub length: Option<usize>,
+}
+
+impl QueryInput {
+    /// Serializes `records` and wraps them into an input for the given query.
+    #[cfg(any(test, feature = "test-fixture"))]
+    #[must_use]
+    pub fn from_records<T: crate::ff::Serializable>(query_id: QueryId, records: &[T]) -> Self {
+        use generic_array::GenericArray;
+        use typenum::Unsigned;
+
+        let mut buf = vec![0_u8; records.len() * T::Size::USIZE];
+        for (chunk, record) in buf.chunks_exact_mut(T::Size::USIZE).zip(records) {
+            record.serialize(GenericArray::from_mut_slice(chunk));
+        }
+
+        Self {
+            query_id,
+            length: Some(buf.len()),
+            input_stream: BodyStream::from(buf),
+        }
+    }
 }
 
 impl Debug for QueryInput {
@@ -204,6 +304,8 @@ impl Debug for QueryInput {
 pub enum QueryType {
     #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
     TestMultiply,
+    #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+    TestAdd,
     SemiHonestIpa(IpaQueryConfig),
     MaliciousIpa(IpaQueryConfig),
     SemiHonestSparseAggregate(SparseAggregateQueryConfig),
@@ -213,19 +315,36 @@ pub enum QueryType {
 
 impl QueryType {
     pub const TEST_MULTIPLY_STR: &'static str = "test-multiply";
+    pub const TEST_ADD_STR: &'static str = "test-add";
     pub const SEMIHONEST_IPA_STR: &'static str = "semihonest-ipa";
     pub const MALICIOUS_IPA_STR: &'static str = "malicious-ipa";
     pub const SEMIHONEST_AGGREGATE_STR: &'static str = "semihonest-sparse-aggregate";
     pub const MALICIOUS_AGGREGATE_STR: &'static str = "malicious-sparse-aggregate";
     pub const OPRF_IPA_STR: &'static str = "oprf_ipa";
-}
 
-/// TODO: should this `AsRef` impl (used for `Substep`) take into account config of IPA?
-impl AsRef<str> for QueryType {
-    fn as_ref(&self) -> &str {
+    /// Security model that protocol of this query is written for, if there is only one.
+    #[must_use]
+    pub fn implied_security_model(&self) -> Option<SecurityModel> {
+        match self {
+            #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
+            QueryType::TestMultiply | QueryType::TestAdd => None,
+            QueryType::SemiHonestIpa(_)
+            | QueryType::SemiHonestSparseAggregate(_)
+            | QueryType::OprfIpa(_) => Some(SecurityModel::SemiHonest),
+            QueryType::MaliciousIpa(_) | QueryType::MaliciousSparseAggregate(_) => {
+                Some(SecurityModel::Malicious)
+            }
+        }
+    }
+
+    /// Name of this query type, the same one [`AsRef`] gives, but not tied to `self`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
         match self {
             #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
             QueryType::TestMultiply => Self::TEST_MULTIPLY_STR,
+            #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+            QueryType::TestAdd => Self::TEST_ADD_STR,
             QueryType::SemiHonestIpa(_) => Self::SEMIHONEST_IPA_STR,
             QueryType::MaliciousIpa(_) => Self::MALICIOUS_IPA_STR,
             QueryType::SemiHonestSparseAggregate(_) => Self::SEMIHONEST_AGGREGATE_STR,
@@ -235,6 +354,13 @@ impl AsRef<str> for QueryType {
     }
 }
 
+/// TODO: should this `AsRef` impl (used for `Substep`) take into account config of IPA?
+impl AsRef<str> for QueryType {
+    fn as_ref(&self) -> &str {
+        self.name()
+    }
+}
+
 impl Step for QueryType {}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -293,6 +419,22 @@ impl IpaQueryConfig {
         }
     }
 
+    /// ## Errors
+    /// If `per_user_credit_cap`, `max_breakdown_key` or `num_multi_bits` is 0.
+    pub fn validate(&self) -> Result<(), QueryConfigError> {
+        for (name, value) in [
+            ("per_user_credit_cap", self.per_user_credit_cap),
+            ("max_breakdown_key", self.max_breakdown_key),
+            ("num_multi_bits", self.num_multi_bits),
+        ] {
+            if value == 0 {
+                return Err(QueryConfigError::ZeroIpaParameter(name));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Creates an IPA query config that does not specify attribution window. That leads to short-cutting
     /// some of the IPA steps inside attribution circuit and getting the answer faster. What it practically
     /// means is that any trigger event can be attributed if there is at least one preceding source event
@@ -358,3 +500,57 @@ impl Default for SparseAggregateQueryConfig {
         }
     }
 }
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+
+    fn ipa_config(config: IpaQueryConfig) -> QueryConfig {
+        QueryConfig::new(QueryType::SemiHonestIpa(config), FieldType::Fp31, 1).unwrap()
+    }
+
+    fn assert_rejects(config: IpaQueryConfig, param: &str) {
+        assert!(matches!(
+            ipa_config(config).validate(),
+            Err(QueryConfigError::ZeroIpaParameter(name)) if name == param
+        ));
+    }
+
+    #[test]
+    fn valid_config() {
+        ipa_config(IpaQueryConfig::default()).validate().unwrap();
+    }
+
+    #[test]
+    fn zero_per_user_credit_cap() {
+        assert_rejects(
+            IpaQueryConfig {
+                per_user_credit_cap: 0,
+                ..Default::default()
+            },
+            "per_user_credit_cap",
+        );
+    }
+
+    #[test]
+    fn zero_max_breakdown_key() {
+        assert_rejects(
+            IpaQueryConfig {
+                max_breakdown_key: 0,
+                ..Default::default()
+            },
+            "max_breakdown_key",
+        );
+    }
+
+    #[test]
+    fn zero_num_multi_bits() {
+        assert_rejects(
+            IpaQueryConfig {
+                num_multi_bits: 0,
+                ..Default::default()
+            },
+            "num_multi_bits",
+        );
+    }
+}
@@ -5,11 +5,17 @@ use std::{
 
 use futures::Stream;
 use futures_util::StreamExt;
-use tracing::error;
+use tracing::{error, Span};
 
 use crate::{
     error::BoxError,
-    helpers::transport::stream::{StreamCollection, StreamKey},
+    helpers::transport::{
+        framed::Unframe,
+        stream::{StreamCollection, StreamKey},
+        trace, TransportStats,
+    },
+    protocol::step::Gate,
+    sync::{Arc, Mutex},
 };
 
 /// Adapt a stream of `Result<T: Into<Vec<u8>>, Error>` to a stream of `Vec<u8>`.
@@ -66,23 +72,162 @@ where
 /// If stream is not received yet, each poll generates a waker that is used internally to wake up
 /// the task when stream is received.
 /// Once stream is received, it is moved to this struct and it acts as a proxy to it.
+///
+/// Senders frame records streams, see [`frame`]. This stream yields the payloads only. If the
+/// stream ends before its last frame arrives, [`Truncated`] is raised.
+///
+/// [`frame`]: crate::helpers::transport::frame
 pub struct ReceiveRecords<S> {
     inner: ReceiveRecordsInner<S>,
+    key: StreamKey,
+    stats: TransportStats,
+    span: Span,
+    bytes: usize,
+    /// Number of bytes at the start of the stream that are dropped instead of being yielded.
+    skip: usize,
+    unframe: Unframe,
+    truncated: Truncated,
+}
+
+/// Raised by [`ReceiveRecords`] if its stream ended before the sender finished it. Clones share
+/// the same flag, so it can be checked after the stream itself is handed over to the receiver.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Truncated(Arc<Mutex<Option<Truncation>>>);
+
+/// The way a records stream was cut short.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Truncation {
+    /// Stream arrived, but ended before its last frame.
+    Ended,
+    /// Stream never arrived. Holds the steps of streams the same helper sent for the same query
+    /// that nobody received, if there were any, helpers most likely disagree on the step.
+    NeverArrived { unclaimed: Vec<Gate> },
+    /// Stream was taken by another receiver and its data can't be received again.
+    Consumed,
+}
+
+impl Truncated {
+    #[cfg(all(test, unit_test))]
+    #[must_use]
+    pub fn is_raised(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+
+    /// Returns how the stream was cut short, if it was.
+    #[must_use]
+    pub fn cause(&self) -> Option<Truncation> {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn raise(&self, cause: Truncation) {
+        *self.0.lock().unwrap() = Some(cause);
+    }
 }
 
 impl<S> ReceiveRecords<S> {
-    pub(crate) fn new(key: StreamKey, coll: StreamCollection<S>) -> Self {
+    pub(crate) fn new(key: StreamKey, coll: StreamCollection<S>, stats: TransportStats) -> Self {
+        let span = trace::receive_span(key.1, key.0, &key.2);
+        Self {
+            inner: ReceiveRecordsInner::Pending(key.clone(), coll),
+            key,
+            stats,
+            span,
+            bytes: 0,
+            skip: 0,
+            unframe: Unframe::default(),
+            truncated: Truncated::default(),
+        }
+    }
+
+    /// Creates the stream of records that proxies `stream`, which is available already.
+    #[cfg(feature = "in-memory-infra")]
+    pub(crate) fn from_stream(key: StreamKey, stream: S, stats: TransportStats) -> Self {
+        let span = trace::receive_span(key.1, key.0, &key.2);
+        Self {
+            inner: ReceiveRecordsInner::Ready(stream),
+            key,
+            stats,
+            span,
+            bytes: 0,
+            skip: 0,
+            unframe: Unframe::default(),
+            truncated: Truncated::default(),
+        }
+    }
+
+    /// Creates the stream of records that ends right away, because the stream with this key was
+    /// taken by another receiver already. It is reported as [`Truncation::Consumed`].
+    pub(crate) fn consumed(key: StreamKey, stats: TransportStats) -> Self {
+        let span = trace::receive_span(key.1, key.0, &key.2);
         Self {
-            inner: ReceiveRecordsInner::Pending(key, coll),
+            inner: ReceiveRecordsInner::Consumed,
+            key,
+            stats,
+            span,
+            bytes: 0,
+            skip: 0,
+            unframe: Unframe::default(),
+            truncated: Truncated::default(),
         }
     }
+
+    /// Makes this stream drop the first `bytes` bytes it receives. Skipped data is not counted
+    /// as received.
+    #[must_use]
+    pub(crate) fn skip_bytes(mut self, bytes: usize) -> Self {
+        self.skip = bytes;
+        self
+    }
+
+    /// Returns the flag this stream raises if it turns out to be truncated.
+    pub(crate) fn truncated(&self) -> Truncated {
+        self.truncated.clone()
+    }
 }
 
-impl<S: Stream + Unpin> Stream for ReceiveRecords<S> {
+impl<S: Stream<Item = Vec<u8>> + Unpin> Stream for ReceiveRecords<S> {
     type Item = S::Item;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::get_mut(self).inner.poll_next_unpin(cx)
+        let this = Pin::get_mut(self);
+        let _span = this.span.enter();
+        loop {
+            let mut chunk = match this.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(chunk)) => this.unframe.push(&chunk),
+                Poll::Ready(None) => {
+                    if let ReceiveRecordsInner::Missing(unclaimed) = &this.inner {
+                        tracing::warn!(?unclaimed, "records stream never arrived");
+                        this.truncated.raise(Truncation::NeverArrived {
+                            unclaimed: unclaimed.clone(),
+                        });
+                    } else if matches!(this.inner, ReceiveRecordsInner::Consumed) {
+                        tracing::warn!("records stream was taken by another receiver already");
+                        this.truncated.raise(Truncation::Consumed);
+                    } else if !this.unframe.is_finished() {
+                        tracing::warn!("records stream ended before its sender finished it");
+                        this.truncated.raise(Truncation::Ended);
+                    }
+                    return Poll::Ready(None);
+                }
+                Poll::Pending => return Poll::Pending,
+            };
+            if chunk.is_empty() {
+                continue;
+            }
+            if this.skip >= chunk.len() {
+                this.skip -= chunk.len();
+                continue;
+            }
+            chunk.drain(..this.skip);
+            this.skip = 0;
+
+            let (query_id, from, gate) = &this.key;
+            this.stats
+                .record_received(*query_id, gate, *from, chunk.len());
+            this.bytes += chunk.len();
+            this.span.record("bytes", this.bytes);
+            return Poll::Ready(Some(chunk));
+        }
     }
 }
 
@@ -90,6 +235,10 @@ impl<S: Stream + Unpin> Stream for ReceiveRecords<S> {
 enum ReceiveRecordsInner<S> {
     Pending(StreamKey, StreamCollection<S>),
     Ready(S),
+    /// Stream is never going to arrive, see [`Truncation::NeverArrived`].
+    Missing(Vec<Gate>),
+    /// Stream was taken by another receiver, see [`Truncation::Consumed`].
+    Consumed,
 }
 
 impl<S: Stream + Unpin> Stream for ReceiveRecordsInner<S> {
@@ -99,15 +248,22 @@ impl<S: Stream + Unpin> Stream for ReceiveRecordsInner<S> {
         let this = Pin::get_mut(self);
         loop {
             match this {
-                Self::Pending(key, streams) => {
-                    if let Some(stream) = streams.add_waker(key, cx.waker()) {
-                        *this = Self::Ready(stream);
-                    } else {
-                        return Poll::Pending;
-                    }
-                }
+                Self::Pending(key, streams) => match streams.add_waker(key, cx.waker()) {
+                    Poll::Ready(Ok(stream)) => *this = Self::Ready(stream),
+                    Poll::Ready(Err(unclaimed)) => *this = Self::Missing(unclaimed),
+                    Poll::Pending => return Poll::Pending,
+                },
                 Self::Ready(stream) => return stream.poll_next_unpin(cx),
+                Self::Missing(_) | Self::Consumed => return Poll::Ready(None),
             }
         }
     }
 }
+
+impl<S> Drop for ReceiveRecordsInner<S> {
+    fn drop(&mut self) {
+        if let Self::Pending(key, streams) = self {
+            streams.cancel(key);
+        }
+    }
+}
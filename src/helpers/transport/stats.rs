@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use futures::{Stream, StreamExt};
+
+use crate::{
+    helpers::HelperIdentity,
+    protocol::{step::Gate, QueryId},
+    sync::{Arc, Mutex},
+};
+
+/// The number of messages (chunks of data) and bytes moved over a single channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub messages: usize,
+    pub bytes: usize,
+}
+
+impl ChannelStats {
+    fn record(&mut self, bytes: usize) {
+        self.messages += 1;
+        self.bytes += bytes;
+    }
+}
+
+impl std::ops::AddAssign for ChannelStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.messages += rhs.messages;
+        self.bytes += rhs.bytes;
+    }
+}
+
+/// Identifies a channel inside [`StatsSnapshot`]. `peer` is the destination helper for the data
+/// sent and the origin for the data received.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StatsKey {
+    pub query_id: QueryId,
+    pub step: String,
+    pub peer: HelperIdentity,
+}
+
+/// Point-in-time copy of the statistics collected by [`TransportStats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub sent: HashMap<StatsKey, ChannelStats>,
+    pub received: HashMap<StatsKey, ChannelStats>,
+}
+
+impl StatsSnapshot {
+    /// Total number of messages and bytes sent to all peers.
+    #[must_use]
+    pub fn total_sent(&self) -> ChannelStats {
+        Self::total(&self.sent)
+    }
+
+    /// Total number of messages and bytes received from all peers.
+    #[must_use]
+    pub fn total_received(&self) -> ChannelStats {
+        Self::total(&self.received)
+    }
+
+    /// Adds the statistics from `other` to these ones.
+    pub fn merge(&mut self, other: StatsSnapshot) {
+        for (mine, theirs) in [
+            (&mut self.sent, other.sent),
+            (&mut self.received, other.received),
+        ] {
+            for (key, stats) in theirs {
+                *mine.entry(key).or_default() += stats;
+            }
+        }
+    }
+
+    fn total(stats: &HashMap<StatsKey, ChannelStats>) -> ChannelStats {
+        stats.values().fold(ChannelStats::default(), |mut acc, s| {
+            acc += *s;
+            acc
+        })
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    sent: HashMap<(QueryId, Gate, HelperIdentity), ChannelStats>,
+    received: HashMap<(QueryId, Gate, HelperIdentity), ChannelStats>,
+}
+
+/// Collects the number of messages and bytes each query moved between helpers. Transports
+/// record every chunk of records data they send or receive here.
+///
+/// This type is cheap to clone, all clones share the same underlying storage.
+#[derive(Clone, Default)]
+pub struct TransportStats {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl TransportStats {
+    /// Records a chunk of `bytes` sent to `dest`.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn record_sent(&self, query_id: QueryId, gate: &Gate, dest: HelperIdentity, bytes: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .sent
+            .entry((query_id, gate.clone(), dest))
+            .or_default()
+            .record(bytes);
+    }
+
+    /// Records a chunk of `bytes` received from `from`.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn record_received(
+        &self,
+        query_id: QueryId,
+        gate: &Gate,
+        from: HelperIdentity,
+        bytes: usize,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .received
+            .entry((query_id, gate.clone(), from))
+            .or_default()
+            .record(bytes);
+    }
+
+    /// Wraps the records stream `data` sent to `dest` to record every chunk it yields.
+    pub(crate) fn count_sent<D>(
+        &self,
+        query_id: QueryId,
+        gate: Gate,
+        dest: HelperIdentity,
+        data: D,
+    ) -> impl Stream<Item = Vec<u8>>
+    where
+        D: Stream<Item = Vec<u8>>,
+    {
+        let this = self.clone();
+        data.map(move |chunk| {
+            this.record_sent(query_id, &gate, dest, chunk.len());
+            chunk
+        })
+    }
+
+    /// Returns the statistics collected so far. If `collapse_steps` is set, the statistics for all
+    /// steps that share the same first path component below the root gate are aggregated together.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn snapshot(&self, collapse_steps: bool) -> StatsSnapshot {
+        let inner = self.inner.lock().unwrap();
+        StatsSnapshot {
+            sent: aggregate(inner.sent.iter(), collapse_steps),
+            received: aggregate(inner.received.iter(), collapse_steps),
+        }
+    }
+
+    /// Removes the statistics collected for the given query and returns them.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn take(&self, query_id: QueryId, collapse_steps: bool) -> StatsSnapshot {
+        let mut inner = self.inner.lock().unwrap();
+        let take = |stats: &mut HashMap<(QueryId, Gate, HelperIdentity), ChannelStats>| {
+            let (taken, rest) = std::mem::take(stats)
+                .into_iter()
+                .partition::<HashMap<_, _>, _>(|((id, _, _), _)| *id == query_id);
+            *stats = rest;
+            aggregate(taken.iter(), collapse_steps)
+        };
+
+        StatsSnapshot {
+            sent: take(&mut inner.sent),
+            received: take(&mut inner.received),
+        }
+    }
+}
+
+fn aggregate<'a, I>(stats: I, collapse_steps: bool) -> HashMap<StatsKey, ChannelStats>
+where
+    I: Iterator<Item = (&'a (QueryId, Gate, HelperIdentity), &'a ChannelStats)>,
+{
+    let mut result = HashMap::<StatsKey, ChannelStats>::new();
+    for ((query_id, gate, peer), channel_stats) in stats {
+        let step = if collapse_steps {
+            collapse(gate)
+        } else {
+            gate.as_ref().to_owned()
+        };
+        *result
+            .entry(StatsKey {
+                query_id: *query_id,
+                step,
+                peer: *peer,
+            })
+            .or_default() += *channel_stats;
+    }
+
+    result
+}
+
+/// Returns the first path component of `gate` below the root gate.
+fn collapse(gate: &Gate) -> String {
+    let root = Gate::default();
+    let path = gate.as_ref();
+    path.strip_prefix(root.as_ref())
+        .unwrap_or(path)
+        .split('/')
+        .find(|component| !component.is_empty())
+        .unwrap_or(root.as_ref())
+        .to_owned()
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+    use crate::protocol::step::StepNarrow;
+
+    #[test]
+    fn snapshot() {
+        let stats = TransportStats::default();
+        let root = Gate::default();
+        let a = root.narrow("a");
+        let ab = a.narrow("b");
+        let c = root.narrow("c");
+
+        stats.record_sent(QueryId::default(), &a, HelperIdentity::TWO, 10);
+        stats.record_sent(QueryId::default(), &ab, HelperIdentity::TWO, 5);
+        stats.record_sent(QueryId::default(), &c, HelperIdentity::THREE, 1);
+        stats.record_received(QueryId::default(), &ab, HelperIdentity::THREE, 7);
+
+        let snapshot = stats.snapshot(false);
+        assert_eq!(3, snapshot.sent.len());
+        assert_eq!(
+            ChannelStats {
+                messages: 1,
+                bytes: 5
+            },
+            snapshot.sent[&StatsKey {
+                query_id: QueryId::default(),
+                step: ab.as_ref().to_owned(),
+                peer: HelperIdentity::TWO,
+            }]
+        );
+        assert_eq!(
+            ChannelStats {
+                messages: 3,
+                bytes: 16
+            },
+            snapshot.total_sent()
+        );
+
+        let collapsed = stats.snapshot(true);
+        assert_eq!(2, collapsed.sent.len());
+        assert_eq!(
+            ChannelStats {
+                messages: 2,
+                bytes: 15
+            },
+            collapsed.sent[&StatsKey {
+                query_id: QueryId::default(),
+                step: "a".to_owned(),
+                peer: HelperIdentity::TWO,
+            }]
+        );
+        assert_eq!(
+            ChannelStats {
+                messages: 1,
+                bytes: 7
+            },
+            collapsed.received[&StatsKey {
+                query_id: QueryId::default(),
+                step: "a".to_owned(),
+                peer: HelperIdentity::THREE,
+            }]
+        );
+    }
+
+    #[test]
+    fn take() {
+        let stats = TransportStats::default();
+        let gate = Gate::default().narrow("a");
+        stats.record_sent(QueryId::default(), &gate, HelperIdentity::TWO, 10);
+        stats.record_received(QueryId::default(), &gate, HelperIdentity::TWO, 10);
+
+        let taken = stats.take(QueryId::default(), true);
+        assert_eq!(10, taken.total_sent().bytes);
+        assert_eq!(10, taken.total_received().bytes);
+        assert_eq!(StatsSnapshot::default(), stats.snapshot(false));
+    }
+}
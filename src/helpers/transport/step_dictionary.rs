@@ -0,0 +1,312 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::{
+    helpers::HelperIdentity,
+    protocol::{step::Gate, QueryId},
+    sync::Mutex,
+};
+
+/// Reference to a step in the framing of records streams. Paths of steps are long, so senders
+/// assign a number to every step they use and transmit the path along with it only once. After
+/// that, the number alone identifies the step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct StepRef {
+    pub id: u32,
+    /// Set when this reference registers `id` on the receiving side.
+    pub gate: Option<Gate>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum StepDictionaryError {
+    #[error("step {id} sent by {from:?} for query {query_id:?} was never registered")]
+    Unknown {
+        from: HelperIdentity,
+        query_id: QueryId,
+        id: u32,
+    },
+    #[error(
+        "step {id} sent by {from:?} for query {query_id:?} is registered as {registered}, \
+         it can't be registered as {gate}"
+    )]
+    Conflict {
+        from: HelperIdentity,
+        query_id: QueryId,
+        id: u32,
+        registered: Gate,
+        gate: Gate,
+    },
+}
+
+/// Sending side of the step dictionary. Numbers are assigned in the order steps are used, separately
+/// for every peer and query. They are never reused for the same peer, even after the query they
+/// were assigned in is removed: gateway may start sending a stream after its query has completed
+/// on this helper, and the peer still knows the numbers that query used.
+#[derive(Default)]
+pub(crate) struct StepEncoder {
+    inner: Mutex<EncoderState>,
+}
+
+#[derive(Default)]
+struct EncoderState {
+    ids: HashMap<(HelperIdentity, QueryId), HashMap<Gate, u32>>,
+    next_id: HashMap<HelperIdentity, u32>,
+}
+
+impl StepEncoder {
+    /// Calls `send` with the reference to send to `dest` for `gate`. It carries the path of the
+    /// step only the first time this step is sent to `dest` in the given query.
+    ///
+    /// Nothing else can be encoded until `send` returns, so the reference that registers a step
+    /// is always sent before the ones that use it, as long as `send` does not defer sending.
+    ///
+    /// ## Panics
+    /// If more than `u32::MAX` steps are sent to the same peer.
+    pub fn encode<T, F: FnOnce(StepRef) -> T>(
+        &self,
+        dest: HelperIdentity,
+        query_id: QueryId,
+        gate: &Gate,
+        send: F,
+    ) -> T {
+        let mut state = self.inner.lock().unwrap();
+        let EncoderState { ids, next_id } = &mut *state;
+        let ids = ids.entry((dest, query_id)).or_default();
+        let step = if let Some(&id) = ids.get(gate) {
+            StepRef { id, gate: None }
+        } else {
+            let next_id = next_id.entry(dest).or_default();
+            let id = *next_id;
+            *next_id = id.checked_add(1).unwrap();
+            ids.insert(gate.clone(), id);
+            StepRef {
+                id,
+                gate: Some(gate.clone()),
+            }
+        };
+
+        send(step)
+    }
+
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().ids.clear();
+    }
+
+    /// Forgets the steps sent to every peer in the given query.
+    pub fn remove_query(&self, query_id: QueryId) {
+        self.inner
+            .lock()
+            .unwrap()
+            .ids
+            .retain(|&(_, id), _| id != query_id);
+    }
+
+    #[cfg(all(test, unit_test))]
+    pub fn count(&self) -> usize {
+        self.inner
+            .lock()
+            .unwrap()
+            .ids
+            .values()
+            .map(HashMap::len)
+            .sum()
+    }
+}
+
+/// Receiving side of the step dictionary. Keeps the steps every peer registered for every query.
+#[derive(Default)]
+pub(crate) struct StepDecoder {
+    gates: Mutex<HashMap<(HelperIdentity, QueryId), HashMap<u32, Gate>>>,
+}
+
+impl StepDecoder {
+    /// Resolves `step` sent by `from` into the gate it refers to, registering it if it carries
+    /// the path. Registering the same step under the same number again is allowed.
+    ///
+    /// ## Errors
+    /// If `step` refers to a number `from` hasn't registered yet or tries to register a number
+    /// that is taken by another step.
+    pub fn decode(
+        &self,
+        from: HelperIdentity,
+        query_id: QueryId,
+        step: StepRef,
+    ) -> Result<Gate, StepDictionaryError> {
+        let mut gates = self.gates.lock().unwrap();
+        let gates = gates.entry((from, query_id)).or_default();
+        match (gates.entry(step.id), step.gate) {
+            (Entry::Occupied(entry), None) => Ok(entry.get().clone()),
+            (Entry::Occupied(entry), Some(gate)) if entry.get() == &gate => Ok(gate),
+            (Entry::Occupied(entry), Some(gate)) => Err(StepDictionaryError::Conflict {
+                from,
+                query_id,
+                id: step.id,
+                registered: entry.get().clone(),
+                gate,
+            }),
+            (Entry::Vacant(entry), Some(gate)) => Ok(entry.insert(gate).clone()),
+            (Entry::Vacant(_), None) => Err(StepDictionaryError::Unknown {
+                from,
+                query_id,
+                id: step.id,
+            }),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.gates.lock().unwrap().clear();
+    }
+
+    /// Forgets the steps every peer registered in the given query.
+    pub fn remove_query(&self, query_id: QueryId) {
+        self.gates
+            .lock()
+            .unwrap()
+            .retain(|&(_, id), _| id != query_id);
+    }
+
+    #[cfg(all(test, unit_test))]
+    pub fn count(&self) -> usize {
+        self.gates.lock().unwrap().values().map(HashMap::len).sum()
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::convert::identity;
+
+    use super::{StepDecoder, StepDictionaryError, StepEncoder, StepRef};
+    use crate::{
+        helpers::HelperIdentity,
+        protocol::{step::Gate, QueryId},
+    };
+
+    #[test]
+    fn path_is_sent_once() {
+        let encoder = StepEncoder::default();
+        let decoder = StepDecoder::default();
+        let gate = Gate::from("protocol/multiply");
+
+        let first = encoder.encode(HelperIdentity::TWO, QueryId::default(), &gate, identity);
+        let second = encoder.encode(HelperIdentity::TWO, QueryId::default(), &gate, identity);
+        assert_eq!(Some(&gate), first.gate.as_ref());
+        assert_eq!(
+            StepRef {
+                id: first.id,
+                gate: None
+            },
+            second
+        );
+
+        for step in [first, second] {
+            assert_eq!(
+                gate,
+                decoder
+                    .decode(HelperIdentity::ONE, QueryId::default(), step)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_step() {
+        let decoder = StepDecoder::default();
+
+        assert!(matches!(
+            decoder.decode(
+                HelperIdentity::ONE,
+                QueryId::default(),
+                StepRef { id: 0, gate: None }
+            ),
+            Err(StepDictionaryError::Unknown { id: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn duplicate_registration() {
+        let decoder = StepDecoder::default();
+        let register = |path: &str| StepRef {
+            id: 0,
+            gate: Some(Gate::from(path)),
+        };
+
+        decoder
+            .decode(
+                HelperIdentity::ONE,
+                QueryId::default(),
+                register("protocol/a"),
+            )
+            .unwrap();
+        // registering the same step again is fine, but the number can't be reused for another one.
+        decoder
+            .decode(
+                HelperIdentity::ONE,
+                QueryId::default(),
+                register("protocol/a"),
+            )
+            .unwrap();
+        assert!(matches!(
+            decoder.decode(
+                HelperIdentity::ONE,
+                QueryId::default(),
+                register("protocol/b")
+            ),
+            Err(StepDictionaryError::Conflict { id: 0, .. })
+        ));
+    }
+
+    /// Every peer numbers steps on its own, so the same number means different steps depending on
+    /// who sent it.
+    #[test]
+    fn peers_have_separate_dictionaries() {
+        let (e1, e2) = (StepEncoder::default(), StepEncoder::default());
+        let decoder = StepDecoder::default();
+        let (a, b) = (Gate::from("protocol/a"), Gate::from("protocol/b"));
+
+        let from_one = e1.encode(HelperIdentity::THREE, QueryId::default(), &a, identity);
+        let from_two = e2.encode(HelperIdentity::THREE, QueryId::default(), &b, identity);
+        assert_eq!(from_one.id, from_two.id);
+
+        assert_eq!(
+            a,
+            decoder
+                .decode(HelperIdentity::ONE, QueryId::default(), from_one)
+                .unwrap()
+        );
+        assert_eq!(
+            b,
+            decoder
+                .decode(HelperIdentity::TWO, QueryId::default(), from_two)
+                .unwrap()
+        );
+        let again = e1.encode(HelperIdentity::THREE, QueryId::default(), &a, identity);
+        assert_eq!(
+            a,
+            decoder
+                .decode(HelperIdentity::ONE, QueryId::default(), again)
+                .unwrap()
+        );
+    }
+
+    /// Streams can be sent after the sender removed their query, while the receiver still runs it.
+    #[test]
+    fn numbers_are_not_reused_after_remove_query() {
+        let encoder = StepEncoder::default();
+        let decoder = StepDecoder::default();
+        let (a, b) = (Gate::from("protocol/a"), Gate::from("protocol/b"));
+
+        let first = encoder.encode(HelperIdentity::TWO, QueryId::default(), &a, identity);
+        encoder.remove_query(QueryId::default());
+        assert_eq!(0, encoder.count());
+        let second = encoder.encode(HelperIdentity::TWO, QueryId::default(), &b, identity);
+        assert_ne!(first.id, second.id);
+
+        for (gate, step) in [(a, first), (b, second)] {
+            assert_eq!(
+                gate,
+                decoder
+                    .decode(HelperIdentity::ONE, QueryId::default(), step)
+                    .unwrap()
+            );
+        }
+    }
+}
@@ -7,7 +7,9 @@ use axum::extract::{BodyStream, FromRequest, RequestParts};
 use futures::{Stream, TryStreamExt};
 use hyper::Body;
 use pin_project::pin_project;
+use tokio::io::AsyncRead;
 
+use super::{reader::ReaderStream, BytesStream};
 use crate::error::BoxError;
 
 type AxumInner = futures::stream::MapErr<BodyStream, fn(axum::Error) -> crate::error::BoxError>;
@@ -27,6 +29,22 @@ impl WrappedAxumBodyStream {
     pub(super) fn new_internal(inner: BodyStream) -> Self {
         Self(inner.map_err(axum::Error::into_inner as fn(axum::Error) -> BoxError))
     }
+
+    /// Streams bytes read from `reader`, like a file or a socket, in chunks of up to `chunk_size`
+    /// bytes as they arrive. If reading fails, the error is the last item of the stream.
+    ///
+    /// ## Panics
+    /// If `chunk_size` is zero.
+    #[must_use]
+    pub fn from_reader<R: AsyncRead + Send + 'static>(reader: R, chunk_size: usize) -> Self {
+        Self::from_body(Body::wrap_stream(ReaderStream::new(reader, chunk_size)))
+    }
+
+    /// Wraps any stream of bytes, for example several body streams chained together.
+    #[must_use]
+    pub fn from_bytes_stream<S: BytesStream + 'static>(stream: S) -> Self {
+        Self::from_body(Body::wrap_stream(stream))
+    }
 }
 
 impl Stream for WrappedAxumBodyStream {
@@ -4,8 +4,10 @@ use std::{
 };
 
 use futures::Stream;
+use tokio::io::AsyncRead;
 
-use crate::helpers::transport::stream::BoxBytesStream;
+use super::reader::ReaderStream;
+use crate::helpers::transport::stream::{BoxBytesStream, BytesStream};
 
 pub struct WrappedBoxBodyStream(BoxBytesStream);
 
@@ -16,6 +18,22 @@ impl WrappedBoxBodyStream {
     pub fn new(inner: axum::extract::BodyStream) -> Self {
         Self(Box::pin(super::WrappedAxumBodyStream::new_internal(inner)))
     }
+
+    /// Streams bytes read from `reader`, like a file or a socket, in chunks of up to `chunk_size`
+    /// bytes as they arrive. If reading fails, the error is the last item of the stream.
+    ///
+    /// ## Panics
+    /// If `chunk_size` is zero.
+    #[must_use]
+    pub fn from_reader<R: AsyncRead + Send + 'static>(reader: R, chunk_size: usize) -> Self {
+        Self(Box::pin(ReaderStream::new(reader, chunk_size)))
+    }
+
+    /// Wraps any stream of bytes, for example several body streams chained together.
+    #[must_use]
+    pub fn from_bytes_stream<S: BytesStream + 'static>(stream: S) -> Self {
+        Self(Box::pin(stream))
+    }
 }
 
 impl Stream for WrappedBoxBodyStream {
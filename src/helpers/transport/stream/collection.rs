@@ -1,7 +1,7 @@
 use std::{
     collections::{hash_map::Entry, HashMap},
     fmt::{Debug, Formatter},
-    task::Waker,
+    task::{Poll, Waker},
 };
 
 use futures::Stream;
@@ -22,14 +22,25 @@ pub type StreamKey = (QueryId, HelperIdentity, Gate);
 ///
 /// Each stream can be inserted and taken away exactly once, any deviation from this behaviour will
 /// result in panic.
+///
+/// Once closed, the collection does not accept new streams and every request to receive a stream
+/// that hasn't arrived yet is resolved with the fact that it never will.
 pub struct StreamCollection<S> {
-    inner: Arc<Mutex<HashMap<StreamKey, StreamState<S>>>>,
+    inner: Arc<Mutex<Streams<S>>>,
+}
+
+struct Streams<S> {
+    streams: HashMap<StreamKey, StreamState<S>>,
+    closed: bool,
 }
 
 impl<S> Default for StreamCollection<S> {
     fn default() -> Self {
         Self {
-            inner: Arc::new(Mutex::new(HashMap::default())),
+            inner: Arc::new(Mutex::new(Streams {
+                streams: HashMap::default(),
+                closed: false,
+            })),
         }
     }
 }
@@ -42,13 +53,53 @@ impl<S> Clone for StreamCollection<S> {
     }
 }
 
+impl<S> StreamCollection<S> {
+    /// Forgets about the receiver of the stream associated with the given key, that is gone
+    /// before the stream arrived or before it learned that the stream never will. Streams that
+    /// were taken out already are left alone, so the same key cannot be used twice.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    pub fn cancel(&self, key: &StreamKey) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Entry::Occupied(entry) = inner.streams.entry(key.clone()) {
+            if matches!(
+                entry.get(),
+                StreamState::Waiting(_) | StreamState::Terminated(_)
+            ) {
+                entry.remove();
+            }
+        }
+    }
+
+    /// Returns `true` if the stream associated with the given key was taken out of this
+    /// collection already, so it can't be received again.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    #[must_use]
+    pub fn is_consumed(&self, key: &StreamKey) -> bool {
+        matches!(
+            self.inner.lock().unwrap().streams.get(key),
+            Some(StreamState::Completed)
+        )
+    }
+}
+
 impl<S: Stream> StreamCollection<S> {
-    /// Adds a new stream associated with the given key.
+    /// Adds a new stream associated with the given key. If this collection is closed, the stream
+    /// is dropped.
     ///
     /// ## Panics
     /// If there was another stream associated with the same key some time in the past.
     pub fn add_stream(&self, key: StreamKey, stream: S) {
-        let mut streams = self.inner.lock().unwrap();
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            tracing::debug!(?key, "records stream is received after shutdown");
+            return;
+        }
+
+        let streams = &mut inner.streams;
         match streams.entry(key) {
             Entry::Occupied(mut entry) => match entry.get_mut() {
                 rs @ StreamState::Waiting(_) => {
@@ -59,10 +110,16 @@ impl<S: Stream> StreamCollection<S> {
                     };
                     waker.wake();
                 }
+                StreamState::Terminated(_) => {
+                    // The receiver of the query this key belonged to is gone, so this stream
+                    // is sent for a new query that reuses the same id.
+                    tracing::debug!(key = ?entry.key(), "records stream is received after cleanup");
+                    entry.insert(StreamState::Ready(stream));
+                }
                 rs @ (StreamState::Ready(_) | StreamState::Completed) => {
                     let state = format!("{rs:?}");
                     let key = entry.key().clone();
-                    drop(streams);
+                    drop(inner);
                     panic!("{key:?} entry state expected to be waiting, got {state:?}");
                 }
             },
@@ -73,21 +130,27 @@ impl<S: Stream> StreamCollection<S> {
     }
 
     /// Adds a new waker to notify when the stream is ready. If stream is ready, this method takes
-    /// it out, leaving a tombstone in its place, and returns it.
+    /// it out, leaving a tombstone in its place, and returns it. If stream hasn't arrived and
+    /// this collection is closed or its query removed, it returns an error to indicate that it
+    /// never will. The error holds the steps of streams that the same helper sent for the same
+    /// query, but nobody received, see [`Self::unclaimed`].
     ///
     /// ## Panics
     /// If [`Waker`] that exists already inside this collection will not wake the given one.
-    pub fn add_waker(&self, key: &StreamKey, waker: &Waker) -> Option<S> {
-        let mut streams = self.inner.lock().unwrap();
+    pub fn add_waker(&self, key: &StreamKey, waker: &Waker) -> Poll<Result<S, Vec<Gate>>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed && !inner.streams.contains_key(key) {
+            return Poll::Ready(Err(Self::unclaimed(&inner.streams, key)));
+        }
 
-        match streams.entry(key.clone()) {
+        match inner.streams.entry(key.clone()) {
             Entry::Occupied(mut entry) => {
                 match entry.get_mut() {
                     StreamState::Waiting(old_waker) => {
                         let will_wake = old_waker.will_wake(waker);
-                        drop(streams); // avoid mutex poisoning
+                        drop(inner); // avoid mutex poisoning
                         assert!(will_wake);
-                        None
+                        Poll::Pending
                     }
                     rs @ StreamState::Ready(_) => {
                         let StreamState::Ready(stream) =
@@ -96,17 +159,30 @@ impl<S: Stream> StreamCollection<S> {
                             unreachable!();
                         };
 
-                        Some(stream)
+                        Poll::Ready(Ok(stream))
                     }
                     StreamState::Completed => {
-                        drop(streams);
+                        drop(inner);
                         panic!("{key:?} stream has been consumed already")
                     }
+                    StreamState::Terminated(_) => {
+                        let StreamState::Terminated(unclaimed) = entry.remove() else {
+                            unreachable!();
+                        };
+                        Poll::Ready(Err(unclaimed))
+                    }
                 }
             }
             Entry::Vacant(entry) => {
+                // Receiving end is polled before any data has arrived for it. This is expected
+                // to happen occasionally, but a query that is stuck with this event as the last
+                // one emitted for its channel likely indicates a deadlock.
+                tracing::debug!(
+                    ?key,
+                    "records stream is awaited before it has been received"
+                );
                 entry.insert(StreamState::Waiting(waker.clone()));
-                None
+                Poll::Pending
             }
         }
     }
@@ -116,8 +192,94 @@ impl<S: Stream> StreamCollection<S> {
     /// ## Panics
     /// if mutex is poisoned.
     pub fn clear(&self) {
-        let mut streams = self.inner.lock().unwrap();
-        streams.clear();
+        let mut inner = self.inner.lock().unwrap();
+        inner.streams.clear();
+    }
+
+    /// Removes every stream of the given query from this collection, whether it was consumed
+    /// already or not. Everyone waiting for a stream of this query is woken up and learns that
+    /// it never arrives, their entries are removed as soon as they do.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    pub fn remove_query(&self, query_id: QueryId) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut unclaimed =
+            Self::unclaimed_by_waiting(&inner.streams, |(id, _, _)| *id == query_id);
+        inner.streams.retain(|key, state| {
+            if key.0 != query_id {
+                return true;
+            }
+            match std::mem::replace(state, StreamState::Terminated(Vec::new())) {
+                StreamState::Waiting(waker) => {
+                    *state = StreamState::Terminated(unclaimed.remove(key).unwrap_or_default());
+                    waker.wake();
+                    true
+                }
+                StreamState::Ready(_) | StreamState::Completed | StreamState::Terminated(_) => {
+                    false
+                }
+            }
+        });
+    }
+
+    /// Returns the number of streams this collection keeps track of.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    #[cfg(all(test, unit_test))]
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.inner.lock().unwrap().streams.len()
+    }
+
+    /// Closes this collection. Streams that arrived already can still be taken out, but everyone
+    /// waiting for a stream that hasn't is woken up and learns that it never arrives.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    pub fn close(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.closed = true;
+        let mut unclaimed = Self::unclaimed_by_waiting(&inner.streams, |_| true);
+        for (key, state) in &mut inner.streams {
+            if let StreamState::Waiting(waker) = state {
+                waker.wake_by_ref();
+                *state = StreamState::Terminated(unclaimed.remove(key).unwrap_or_default());
+            }
+        }
+    }
+
+    /// Returns the steps of streams that the helper `key` is received from sent for its query,
+    /// but nobody received. A receiver that waits for a stream that never arrives while there
+    /// are such streams most likely narrowed into a different step than its peer did.
+    fn unclaimed(
+        streams: &HashMap<StreamKey, StreamState<S>>,
+        (query_id, from, _): &StreamKey,
+    ) -> Vec<Gate> {
+        let mut unclaimed = streams
+            .iter()
+            .filter(|((id, origin, _), state)| {
+                id == query_id && origin == from && matches!(state, StreamState::Ready(_))
+            })
+            .map(|((_, _, gate), _)| gate.clone())
+            .collect::<Vec<_>>();
+        unclaimed.sort();
+
+        unclaimed
+    }
+
+    /// Finds [`Self::unclaimed`] steps for everyone waiting for a stream whose key matches
+    /// `filter`.
+    fn unclaimed_by_waiting<F: Fn(&StreamKey) -> bool>(
+        streams: &HashMap<StreamKey, StreamState<S>>,
+        filter: F,
+    ) -> HashMap<StreamKey, Vec<Gate>> {
+        streams
+            .iter()
+            .filter(|(key, state)| filter(key) && matches!(state, StreamState::Waiting(_)))
+            .map(|(key, _)| (key.clone(), Self::unclaimed(streams, key)))
+            .collect()
     }
 }
 
@@ -130,6 +292,13 @@ enum StreamState<S> {
     /// Stream was successfully received and taken away from [`StreamCollection`].
     /// It may not be requested or received again.
     Completed,
+    /// Query this stream belongs to was cleaned up, or the collection closed, while the receiver
+    /// was waiting for it. The entry is removed once the receiver learns that the stream never
+    /// arrives, or once the receiver is gone.
+    /// Holds the steps that were [unclaimed] at the time.
+    ///
+    /// [unclaimed]: StreamCollection::unclaimed
+    Terminated(Vec<Gate>),
 }
 
 impl<S> Debug for StreamState<S> {
@@ -144,6 +313,9 @@ impl<S> Debug for StreamState<S> {
             StreamState::Completed => {
                 write!(f, "Completed")
             }
+            StreamState::Terminated(_) => {
+                write!(f, "Terminated")
+            }
         }
     }
 }
@@ -468,7 +468,7 @@ mod test {
 
             // since `from_slice` chunks by the entire slice, expect the entire slice in `collected`
             assert_eq!(collected.len(), 1);
-            assert_eq!(collected[0], vec![Fp31::try_from(3).unwrap(); 10]);
+            assert_eq!(collected[0], vec![Fp31::try_from(3_u128).unwrap(); 10]);
         }
 
         #[tokio::test]
@@ -485,7 +485,7 @@ mod test {
             assert_eq!(collected.len(), 1);
             assert_eq!(
                 collected[0],
-                vec![Fp32BitPrime::try_from(0x0707_0707).unwrap(); ARR_SIZE]
+                vec![Fp32BitPrime::try_from(0x0707_0707_u128).unwrap(); ARR_SIZE]
             );
         }
 
@@ -0,0 +1,153 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures::{
+    stream::{Fuse, FusedStream},
+    Stream, StreamExt,
+};
+use pin_project::pin_project;
+
+use crate::protocol::RecordId;
+
+/// Merges two streams of records, each of them ordered by [`RecordId`], into one stream ordered the
+/// same way. Every item of either stream must be a single record, and `record_id` tells which
+/// record it is. Records channels do not carry record ids themselves, so it is up to the protocol
+/// that splits records between `a` and `b` to put them where `record_id` can find them, for example
+/// in a header at the start of every item.
+///
+/// Records with the same id are taken from `a` first. Streams don't need to have the same number
+/// of records: once one of them ends, whatever is left in the other one is passed through as is.
+/// The merged stream ends when both of them do.
+pub fn merge_ordered<S, F>(a: S, b: S, record_id: F) -> MergeOrdered<S, F>
+where
+    S: Stream<Item = Vec<u8>>,
+    F: Fn(&[u8]) -> RecordId,
+{
+    MergeOrdered {
+        a: a.fuse(),
+        b: b.fuse(),
+        next_a: None,
+        next_b: None,
+        record_id,
+    }
+}
+
+/// Stream returned by [`merge_ordered`].
+#[pin_project]
+pub struct MergeOrdered<S: Stream, F> {
+    #[pin]
+    a: Fuse<S>,
+    #[pin]
+    b: Fuse<S>,
+    next_a: Option<Vec<u8>>,
+    next_b: Option<Vec<u8>>,
+    record_id: F,
+}
+
+impl<S, F> Stream for MergeOrdered<S, F>
+where
+    S: Stream<Item = Vec<u8>>,
+    F: Fn(&[u8]) -> RecordId,
+{
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if this.next_a.is_none() {
+            if let Poll::Ready(item) = this.a.as_mut().poll_next(cx) {
+                *this.next_a = item;
+            }
+        }
+        if this.next_b.is_none() {
+            if let Poll::Ready(item) = this.b.as_mut().poll_next(cx) {
+                *this.next_b = item;
+            }
+        }
+
+        // The next record can't be picked until both streams either have one or are finished.
+        if (this.next_a.is_none() && !this.a.is_terminated())
+            || (this.next_b.is_none() && !this.b.is_terminated())
+        {
+            return Poll::Pending;
+        }
+
+        let take_b = match (&this.next_a, &this.next_b) {
+            (Some(a), Some(b)) => (this.record_id)(b) < (this.record_id)(a),
+            (None, _) => true,
+            (Some(_), None) => false,
+        };
+
+        Poll::Ready(if take_b {
+            this.next_b.take()
+        } else {
+            this.next_a.take()
+        })
+    }
+}
+
+impl<S, F> FusedStream for MergeOrdered<S, F>
+where
+    S: Stream<Item = Vec<u8>>,
+    F: Fn(&[u8]) -> RecordId,
+{
+    fn is_terminated(&self) -> bool {
+        self.next_a.is_none()
+            && self.next_b.is_none()
+            && self.a.is_terminated()
+            && self.b.is_terminated()
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use futures::{stream, StreamExt};
+
+    use super::merge_ordered;
+    use crate::protocol::RecordId;
+
+    /// Every record starts with its id, followed by some payload.
+    fn record(id: u32) -> Vec<u8> {
+        let mut record = id.to_le_bytes().to_vec();
+        record.extend_from_slice(b"payload");
+        record
+    }
+
+    fn record_id(record: &[u8]) -> RecordId {
+        RecordId::from(u32::from_le_bytes(record[..4].try_into().unwrap()))
+    }
+
+    async fn merged_ids(a: Vec<u32>, b: Vec<u32>) -> Vec<u32> {
+        merge_ordered(
+            stream::iter(a.into_iter().map(record)),
+            stream::iter(b.into_iter().map(record)),
+            record_id,
+        )
+        .map(|record| u32::from(record_id(&record)))
+        .collect()
+        .await
+    }
+
+    #[tokio::test]
+    async fn interleaves() {
+        let a = (0..20).step_by(2).collect::<Vec<_>>();
+        let b = (1..20).step_by(2).collect::<Vec<_>>();
+
+        assert_eq!((0..20).collect::<Vec<_>>(), merged_ids(a, b).await);
+    }
+
+    #[tokio::test]
+    async fn uneven() {
+        let a = vec![0, 1, 5, 6, 7, 8, 9, 10, 11, 12];
+        let b = vec![2, 3, 4];
+
+        assert_eq!(
+            (0..13).collect::<Vec<_>>(),
+            merged_ids(a.clone(), b.clone()).await
+        );
+        assert_eq!((0..13).collect::<Vec<_>>(), merged_ids(b, a).await);
+        assert_eq!(vec![0, 1, 2], merged_ids(Vec::new(), vec![0, 1, 2]).await);
+        assert!(merged_ids(Vec::new(), Vec::new()).await.is_empty());
+    }
+}
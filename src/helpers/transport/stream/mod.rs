@@ -3,6 +3,8 @@ mod axum_body;
 mod box_body;
 mod collection;
 mod input;
+mod merge;
+mod reader;
 
 use std::pin::Pin;
 
@@ -13,6 +15,7 @@ use bytes::Bytes;
 pub use collection::{StreamCollection, StreamKey};
 use futures::Stream;
 pub use input::{LengthDelimitedStream, RecordsStream};
+pub use merge::{merge_ordered, MergeOrdered};
 
 use crate::error::BoxError;
 
@@ -0,0 +1,144 @@
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::Stream;
+use pin_project::pin_project;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::error::BoxError;
+
+/// Turns an [`AsyncRead`] source, like a file or a socket, into a stream of bytes. Every chunk
+/// holds whatever the source had available when it was polled, up to `chunk_size` bytes.
+///
+/// The stream ends when the source does. If reading fails, the error is the last item.
+#[pin_project]
+pub(super) struct ReaderStream<R> {
+    #[pin]
+    reader: R,
+    buf: Box<[u8]>,
+    done: bool,
+}
+
+impl<R: AsyncRead> ReaderStream<R> {
+    /// ## Panics
+    /// If `chunk_size` is zero.
+    pub fn new(reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be positive");
+        Self {
+            reader,
+            buf: vec![0; chunk_size].into_boxed_slice(),
+            done: false,
+        }
+    }
+}
+
+impl<R: AsyncRead> Stream for ReaderStream<R> {
+    type Item = Result<Bytes, BoxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        let mut buf = ReadBuf::new(&mut this.buf[..]);
+        let next = match ready!(this.reader.poll_read(cx, &mut buf)) {
+            // reading nothing into a non-empty buffer means there is nothing left to read
+            Ok(()) if buf.filled().is_empty() => None,
+            Ok(()) => return Poll::Ready(Some(Ok(Bytes::copy_from_slice(buf.filled())))),
+            Err(e) => Some(Err(e.into())),
+        };
+        *this.done = true;
+
+        Poll::Ready(next)
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use futures::StreamExt;
+    use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+
+    use crate::helpers::transport::stream::{BytesStream, WrappedBoxBodyStream};
+
+    #[tokio::test]
+    async fn reassembles_written_bytes() {
+        const CHUNK_SIZE: usize = 7;
+        let data = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+        let (mut writer, reader) = tokio::io::duplex(64);
+        let write = tokio::spawn({
+            let data = data.clone();
+            async move {
+                for piece in data.chunks(100) {
+                    writer.write_all(piece).await.unwrap();
+                }
+                // dropping the writer closes the stream
+            }
+        });
+
+        let chunks = WrappedBoxBodyStream::from_reader(reader, CHUNK_SIZE)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        write.await.unwrap();
+
+        assert!(chunks
+            .iter()
+            .all(|chunk| !chunk.is_empty() && chunk.len() <= CHUNK_SIZE));
+        assert_eq!(data, chunks.concat());
+    }
+
+    #[tokio::test]
+    async fn empty_reader() {
+        let stream = WrappedBoxBodyStream::from_reader(tokio::io::empty(), 16);
+        assert!(stream.to_vec().await.is_empty());
+    }
+
+    /// Gives out `data` once, then fails.
+    struct FailingReader {
+        data: Option<&'static [u8]>,
+    }
+
+    impl AsyncRead for FailingReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Poll::Ready(match self.data.take() {
+                Some(data) => {
+                    buf.put_slice(data);
+                    Ok(())
+                }
+                None => Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn read_error_ends_stream() {
+        let mut stream = WrappedBoxBodyStream::from_reader(
+            FailingReader {
+                data: Some(b"hello"),
+            },
+            16,
+        );
+
+        assert_eq!(b"hello".as_slice(), stream.next().await.unwrap().unwrap());
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(
+            io::ErrorKind::ConnectionReset,
+            err.downcast::<io::Error>().unwrap().kind()
+        );
+        assert!(stream.next().await.is_none());
+    }
+}
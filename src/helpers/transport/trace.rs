@@ -0,0 +1,56 @@
+use futures::{Stream, StreamExt};
+use tracing::{field, Span};
+
+use crate::{
+    helpers::{HelperIdentity, RouteId},
+    protocol::{step::Gate, QueryId},
+};
+
+/// Creates a span that describes a single request sent to `dest`. The `bytes` field is left empty
+/// and is populated by [`record_bytes`] as the request payload is consumed.
+///
+/// The span is created at `TRACE` level, so it costs close to nothing when there is no subscriber
+/// interested in it.
+pub(crate) fn send_span(
+    dest: HelperIdentity,
+    route: RouteId,
+    query_id: Option<QueryId>,
+    gate: Option<&Gate>,
+) -> Span {
+    tracing::trace_span!(
+        "send",
+        ?query_id,
+        step = ?gate,
+        ?route,
+        ?dest,
+        bytes = field::Empty
+    )
+}
+
+/// Creates a span that describes records received from `from` for the given query and step.
+/// Similarly to [`send_span`], the `bytes` field is populated as records arrive.
+pub(crate) fn receive_span(from: HelperIdentity, query_id: QueryId, gate: &Gate) -> Span {
+    tracing::trace_span!(
+        "receive",
+        ?query_id,
+        step = ?gate,
+        ?from,
+        bytes = field::Empty
+    )
+}
+
+/// Keeps the `bytes` field of `span` up to date with the total number of bytes `data` yielded
+/// so far.
+pub(crate) fn record_bytes<D>(span: Span, data: D) -> impl Stream<Item = Vec<u8>>
+where
+    D: Stream<Item = Vec<u8>>,
+{
+    let mut total = 0_usize;
+    data.map(move |chunk| {
+        if !span.is_disabled() {
+            total += chunk.len();
+            span.record("bytes", total);
+        }
+        chunk
+    })
+}
@@ -368,6 +368,20 @@ impl MpcHelperClient {
         Ok(self.request(req))
     }
 
+    /// Sends records that belong to the query as a whole rather than to one of its steps.
+    /// # Errors
+    /// If the request has illegal arguments, or fails to deliver to helper
+    pub fn query_records<S: Stream<Item = Vec<u8>> + Send + 'static>(
+        &self,
+        query_id: QueryId,
+        data: S,
+    ) -> Result<ResponseFuture, Error> {
+        let body = hyper::Body::wrap_stream::<_, _, Error>(data.map(Ok));
+        let req = http_serde::query::records::Request::new(query_id, body);
+        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+        Ok(self.request(req))
+    }
+
     /// Retrieve the status of a query.
     ///
     /// ## Errors
@@ -434,7 +448,7 @@ pub(crate) mod tests {
     use crate::{
         ff::{FieldType, Fp31},
         helpers::{
-            query::QueryType::TestMultiply, BytesStream, RoleAssignment, Transport,
+            frame_records, query::QueryType::TestMultiply, BytesStream, RoleAssignment, Transport,
             TransportCallbacks, MESSAGE_PAYLOAD_SIZE_BYTES,
         },
         net::{test::TestServer, HttpTransport},
@@ -554,7 +568,7 @@ pub(crate) mod tests {
 
     #[tokio::test]
     async fn create() {
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::default();
         let expected_query_config = QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap();
 
         let cb = TransportCallbacks {
@@ -575,7 +589,7 @@ pub(crate) mod tests {
     #[tokio::test]
     async fn prepare() {
         let input = PrepareQuery {
-            query_id: QueryId,
+            query_id: QueryId::default(),
             config: QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap(),
             roles: RoleAssignment::new(HelperIdentity::make_three()),
         };
@@ -599,7 +613,7 @@ pub(crate) mod tests {
 
     #[tokio::test]
     async fn input() {
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::default();
         let expected_input = &[8u8; 25];
         let cb = TransportCallbacks {
             query_input: Box::new(move |_transport, query_input| {
@@ -616,6 +630,7 @@ pub(crate) mod tests {
                 let data = QueryInput {
                     query_id: expected_query_id,
                     input_stream: expected_input.to_vec().into(),
+                    length: Some(expected_input.len()),
                 };
                 client.query_input(data).await.unwrap()
             },
@@ -629,7 +644,7 @@ pub(crate) mod tests {
         let TestServer {
             client, transport, ..
         } = TestServer::builder().build().await;
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::default();
         let expected_step = Gate::default().narrow("test-step");
         let expected_payload = vec![7u8; MESSAGE_PAYLOAD_SIZE_BYTES];
 
@@ -637,7 +652,7 @@ pub(crate) mod tests {
             .step(
                 expected_query_id,
                 &expected_step,
-                once(ready(expected_payload.clone())),
+                frame_records(once(ready(expected_payload.clone()))),
             )
             .unwrap()
             .await
@@ -645,8 +660,10 @@ pub(crate) mod tests {
 
         MpcHelperClient::resp_ok(resp).await.unwrap();
 
-        let mut stream =
-            Arc::clone(&transport).receive(HelperIdentity::ONE, (QueryId, expected_step.clone()));
+        let mut stream = Arc::clone(&transport).receive(
+            HelperIdentity::ONE,
+            (QueryId::default(), expected_step.clone()),
+        );
 
         assert_eq!(
             poll_immediate(&mut stream).next().await,
@@ -660,7 +677,7 @@ pub(crate) mod tests {
             Fp31::try_from(1u128).unwrap(),
             Fp31::try_from(2u128).unwrap(),
         ))]);
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::default();
         let raw_results = expected_results.to_vec();
         let cb = TransportCallbacks {
             complete_query: Box::new(move |_transport, query_id| {
@@ -25,7 +25,7 @@ pub enum Error {
     BodyAlreadyExtracted(#[from] axum::extract::rejection::BodyAlreadyExtracted),
     #[error(transparent)]
     MissingExtension(#[from] axum::extract::rejection::ExtensionRejection),
-    #[error("query id not found: {}", .0.as_ref())]
+    #[error("query id not found: {0}")]
     QueryIdNotFound(QueryId),
     #[error(transparent)]
     HyperPassthrough(#[from] hyper::Error),
@@ -59,6 +59,8 @@ pub enum Error {
     },
     #[error("{error}")]
     Application { code: StatusCode, error: BoxError },
+    #[error("transport is shut down")]
+    ShutDown,
 }
 
 impl Error {
@@ -154,6 +156,8 @@ impl IntoResponse for Error {
             | Self::BodyAlreadyExtracted(_)
             | Self::MissingExtension(_) => StatusCode::INTERNAL_SERVER_ERROR,
 
+            Self::ShutDown => StatusCode::SERVICE_UNAVAILABLE,
+
             Self::Application { code, .. } => code,
         };
 
@@ -80,14 +80,17 @@ pub mod echo {
 }
 
 pub mod query {
-    use std::fmt::{Display, Formatter};
+    use std::{
+        fmt::{Display, Formatter},
+        time::Duration,
+    };
 
     use async_trait::async_trait;
     use axum::extract::{FromRequest, Query, RequestParts};
 
     use crate::{
         ff::FieldType,
-        helpers::query::{QueryConfig, QuerySize, QueryType},
+        helpers::query::{QueryConfig, QuerySize, QueryType, SecurityModel},
         net::Error,
     };
 
@@ -113,16 +116,23 @@ pub mod query {
                 size: QuerySize,
                 field_type: FieldType,
                 query_type: String,
+                #[serde(default)]
+                security_model: SecurityModel,
+                timeout_ms: Option<u64>,
             }
             let Query(QueryTypeParam {
                 size,
                 field_type,
                 query_type,
+                security_model,
+                timeout_ms,
             }) = req.extract().await?;
 
             let query_type = match query_type.as_str() {
                 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
                 QueryType::TEST_MULTIPLY_STR => Ok(QueryType::TestMultiply),
+                #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+                QueryType::TEST_ADD_STR => Ok(QueryType::TestAdd),
                 QueryType::SEMIHONEST_IPA_STR => {
                     let Query(q) = req.extract().await?;
                     Ok(QueryType::SemiHonestIpa(q))
@@ -149,6 +159,8 @@ pub mod query {
                 size,
                 field_type,
                 query_type,
+                security_model,
+                timeout: timeout_ms.map(Duration::from_millis),
             }))
         }
     }
@@ -157,14 +169,18 @@ pub mod query {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
             write!(
                 f,
-                "query_type={qt}&field_type={f:?}&size={size}",
+                "query_type={qt}&field_type={f:?}&size={size}&security_model={sm:?}",
                 qt = self.query_type.as_ref(),
                 f = self.field_type,
-                size = self.size
+                size = self.size,
+                sm = self.security_model,
             )?;
+            if let Some(timeout) = self.timeout {
+                write!(f, "&timeout_ms={}", timeout.as_millis())?;
+            }
             match self.query_type {
                 #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
-                QueryType::TestMultiply => Ok(()),
+                QueryType::TestMultiply | QueryType::TestAdd => Ok(()),
                 QueryType::SemiHonestIpa(config)
                 | QueryType::MaliciousIpa(config)
                 | QueryType::OprfIpa(config) => {
@@ -297,7 +313,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}?{}",
                         BASE_AXUM_PATH,
-                        self.data.query_id.as_ref(),
+                        self.data.query_id,
                         QueryConfigQueryParams(self.data.config),
                     ))
                     .build()?;
@@ -373,8 +389,7 @@ pub mod query {
                     .authority(authority)
                     .path_and_query(format!(
                         "{}/{}/input",
-                        BASE_AXUM_PATH,
-                        self.query_input.query_id.as_ref(),
+                        BASE_AXUM_PATH, self.query_input.query_id,
                     ))
                     .build()?;
                 let body = Body::wrap_stream(self.query_input.input_stream);
@@ -396,6 +411,7 @@ pub mod query {
                     query_input: QueryInput {
                         query_id,
                         input_stream,
+                        length: None,
                     },
                 })
             }
@@ -449,7 +465,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}/step/{}",
                         BASE_AXUM_PATH,
-                        self.query_id.as_ref(),
+                        self.query_id,
                         self.gate.as_ref()
                     ))
                     .build()?;
@@ -485,6 +501,70 @@ pub mod query {
         pub const AXUM_PATH: &str = "/:query_id/step/*step";
     }
 
+    /// Records that a peer sends to the query as a whole, rather than to one of its steps.
+    pub mod records {
+        use async_trait::async_trait;
+        use axum::{
+            extract::{FromRequest, Path, RequestParts},
+            http::uri,
+        };
+
+        use crate::{
+            helpers::BodyStream,
+            net::{http_serde::query::BASE_AXUM_PATH, Error},
+            protocol::QueryId,
+        };
+
+        // When this type is used on the client side, `B` is `hyper::Body`. When this type
+        // is used on the server side, `B` can be any body type supported by axum.
+        #[derive(Debug)]
+        pub struct Request<B> {
+            pub query_id: QueryId,
+            pub body: B,
+        }
+
+        impl<B> Request<B> {
+            pub fn new(query_id: QueryId, body: B) -> Self {
+                Self { query_id, body }
+            }
+        }
+
+        /// Convert to hyper request. Used on client side.
+        impl Request<hyper::Body> {
+            pub fn try_into_http_request(
+                self,
+                scheme: uri::Scheme,
+                authority: uri::Authority,
+            ) -> Result<hyper::Request<hyper::Body>, Error> {
+                let uri = uri::Uri::builder()
+                    .scheme(scheme)
+                    .authority(authority)
+                    .path_and_query(format!("{}/{}/records", BASE_AXUM_PATH, self.query_id))
+                    .build()?;
+                Ok(hyper::Request::post(uri).body(self.body)?)
+            }
+        }
+
+        /// Convert from axum request. Used on server side.
+        #[async_trait]
+        impl<B> FromRequest<B> for Request<BodyStream>
+        where
+            B: Send,
+            BodyStream: FromRequest<B>,
+            Error: From<<BodyStream as FromRequest<B>>::Rejection>,
+        {
+            type Rejection = Error;
+
+            async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+                let Path(query_id) = req.extract::<Path<_>>().await?;
+                let body = req.extract().await?;
+                Ok(Self { query_id, body })
+            }
+        }
+
+        pub const AXUM_PATH: &str = "/:query_id/records";
+    }
+
     pub mod status {
         use async_trait::async_trait;
         use axum::extract::{FromRequest, Path, RequestParts};
@@ -515,7 +595,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}",
                         crate::net::http_serde::query::BASE_AXUM_PATH,
-                        self.query_id.as_ref()
+                        self.query_id
                     ))
                     .build()?;
                 Ok(hyper::Request::get(uri).body(hyper::Body::empty())?)
@@ -569,7 +649,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}/complete",
                         crate::net::http_serde::query::BASE_AXUM_PATH,
-                        self.query_id.as_ref()
+                        self.query_id
                     ))
                     .build()?;
                 Ok(hyper::Request::get(uri).body(hyper::Body::empty())?)
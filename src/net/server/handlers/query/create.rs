@@ -17,6 +17,11 @@ async fn handler(
     let transport = Transport::clone_ref(&*transport);
     match transport.receive_query(req.query_config).await {
         Ok(query_id) => Ok(Json(http_serde::query::create::ResponseBody { query_id })),
+        Err(
+            err @ (NewQueryError::InvalidConfig { .. }
+            | NewQueryError::UnsupportedField(_)
+            | NewQueryError::ConfigRejected { .. }),
+        ) => Err(Error::application(StatusCode::BAD_REQUEST, err)),
         Err(err @ NewQueryError::State { .. }) => {
             Err(Error::application(StatusCode::CONFLICT, err))
         }
@@ -32,7 +37,7 @@ pub fn router(transport: Arc<HttpTransport>) -> Router {
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use std::{future::ready, num::NonZeroU32};
+    use std::{future::ready, num::NonZeroU32, time::Duration};
 
     use axum::http::Request;
     use hyper::{
@@ -58,7 +63,7 @@ mod tests {
         let cb = TransportCallbacks {
             receive_query: Box::new(move |_transport, query_config| {
                 assert_eq!(query_config, expected_query_config);
-                Box::pin(ready(Ok(QueryId)))
+                Box::pin(ready(Ok(QueryId::default())))
             }),
             ..Default::default()
         };
@@ -77,7 +82,7 @@ mod tests {
 
         let http_serde::query::create::ResponseBody { query_id } =
             serde_json::from_slice(&body_bytes).unwrap();
-        assert_eq!(QueryId, query_id);
+        assert_eq!(QueryId::default(), query_id);
     }
 
     #[tokio::test]
@@ -106,39 +111,44 @@ mod tests {
 
     #[tokio::test]
     async fn create_test_ipa_with_attr_window() {
-        create_test(QueryConfig {
-            size: 1.try_into().unwrap(),
-            field_type: FieldType::Fp32BitPrime,
-            query_type: QueryType::SemiHonestIpa(IpaQueryConfig {
-                per_user_credit_cap: 1,
-                max_breakdown_key: 1,
-                attribution_window_seconds: NonZeroU32::new(86_400),
-                num_multi_bits: 3,
-                plaintext_match_keys: true,
-            }),
-        })
+        create_test(
+            QueryConfig::new(
+                QueryType::SemiHonestIpa(IpaQueryConfig {
+                    per_user_credit_cap: 1,
+                    max_breakdown_key: 1,
+                    attribution_window_seconds: NonZeroU32::new(86_400),
+                    num_multi_bits: 3,
+                    plaintext_match_keys: true,
+                }),
+                FieldType::Fp32BitPrime,
+                1,
+            )
+            .unwrap(),
+        )
         .await;
     }
 
     #[tokio::test]
     async fn create_test_aggregate() {
-        create_test(QueryConfig {
-            size: 1.try_into().unwrap(),
-            field_type: FieldType::Fp31,
-            query_type: QueryType::SemiHonestSparseAggregate(SparseAggregateQueryConfig {
-                contribution_bits: 8.try_into().unwrap(),
-                num_contributions: 20,
-            }),
-        })
-        .await;
-        create_test(QueryConfig {
-            size: 1.try_into().unwrap(),
-            field_type: FieldType::Fp31,
-            query_type: QueryType::MaliciousSparseAggregate(SparseAggregateQueryConfig {
-                contribution_bits: 8.try_into().unwrap(),
-                num_contributions: 20,
-            }),
-        })
+        let config = SparseAggregateQueryConfig {
+            contribution_bits: 8.try_into().unwrap(),
+            num_contributions: 20,
+        };
+        for query_type in [
+            QueryType::SemiHonestSparseAggregate(config),
+            QueryType::MaliciousSparseAggregate(config),
+        ] {
+            create_test(QueryConfig::new(query_type, FieldType::Fp31, 1).unwrap()).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn create_test_with_timeout() {
+        create_test(
+            QueryConfig::new(QueryType::TestMultiply, FieldType::Fp31, 1)
+                .unwrap()
+                .with_timeout(Duration::from_secs(90)),
+        )
         .await;
     }
 
@@ -41,7 +41,7 @@ mod tests {
 
     #[tokio::test]
     async fn input_test() {
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::default();
         let expected_input = &[4u8; 4];
         let cb = TransportCallbacks {
             query_input: Box::new(move |_transport, query_input| {
@@ -57,6 +57,7 @@ mod tests {
         let req = http_serde::query::input::Request::new(QueryInput {
             query_id: expected_query_id,
             input_stream: expected_input.to_vec().into(),
+            length: Some(expected_input.len()),
         });
         handler(Extension(transport), req).await.unwrap();
     }
@@ -83,7 +84,7 @@ mod tests {
     impl Default for OverrideReq {
         fn default() -> Self {
             Self {
-                query_id: QueryId.as_ref().to_string(),
+                query_id: QueryId::default().to_string(),
                 input_stream: vec![4; 4],
             }
         }
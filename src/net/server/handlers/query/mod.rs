@@ -1,6 +1,7 @@
 mod create;
 mod input;
 mod prepare;
+mod records;
 mod results;
 mod status;
 mod step;
@@ -46,6 +47,7 @@ pub fn query_router(transport: Arc<HttpTransport>) -> Router {
 pub fn h2h_router(transport: Arc<HttpTransport>) -> Router {
     Router::new()
         .merge(prepare::router(Arc::clone(&transport)))
+        .merge(records::router(Arc::clone(&transport)))
         .merge(step::router(transport))
         .layer(layer_fn(HelperAuthentication::new))
 }
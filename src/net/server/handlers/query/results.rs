@@ -53,7 +53,7 @@ mod tests {
             Fp31::try_from(1u128).unwrap(),
             Fp31::try_from(2u128).unwrap(),
         ))]);
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::default();
         let raw_results = expected_results.to_vec();
         let cb = TransportCallbacks {
             complete_query: Box::new(move |_transport, query_id| {
@@ -64,7 +64,7 @@ mod tests {
             ..Default::default()
         };
         let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
-        let req = http_serde::query::results::Request::new(QueryId);
+        let req = http_serde::query::results::Request::new(QueryId::default());
         let results = handler(Extension(transport), req.clone()).await.unwrap();
         assert_eq!(results, expected_results.into_bytes());
     }
@@ -1,26 +1,31 @@
 use std::{
     borrow::Borrow,
     future::Future,
+    io,
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::{Stream, TryFutureExt};
+use tracing::Instrument;
 
 use crate::{
     config::{NetworkConfig, ServerConfig},
     error::BoxError,
     helpers::{
+        frame_records,
         query::{PrepareQuery, QueryConfig, QueryInput},
-        BodyStream, CompleteQueryResult, HelperIdentity, LogErrors, NoResourceIdentifier,
-        PrepareQueryResult, QueryIdBinding, QueryInputResult, QueryStatusResult,
-        ReceiveQueryResult, ReceiveRecords, RouteId, RouteParams, StepBinding, StreamCollection,
-        Transport, TransportCallbacks,
+        transport_trace as trace, BodyStream, CompleteQueryResult, HelperIdentity, LogErrors,
+        NoResourceIdentifier, PrepareQueryResult, QueryIdBinding, QueryInputResult,
+        QueryStatusResult, ReceiveQueryResult, ReceiveRecords, RouteId, RouteParams, StepBinding,
+        StreamCollection, Transport, TransportCallbacks, TransportDrain, TransportStats,
+        SHUTDOWN_DEADLINE,
     },
     net::{client::MpcHelperClient, error::Error, MpcHelperServer},
-    protocol::{step::Gate, QueryId},
+    protocol::{step::Gate, QueryId, RecordId},
     sync::Arc,
 };
 
@@ -34,6 +39,11 @@ pub struct HttpTransport {
     // TODO(615): supporting multiple queries likely require a hashmap here. It will be ok if we
     // only allow one query at a time.
     record_streams: StreamCollection<LogHttpErrors>,
+    /// Records streams that are not bound to any step, one per query and peer, keyed by the root
+    /// gate.
+    query_streams: StreamCollection<LogHttpErrors>,
+    stats: TransportStats,
+    drain: TransportDrain,
 }
 
 impl HttpTransport {
@@ -60,6 +70,9 @@ impl HttpTransport {
             callbacks,
             clients,
             record_streams: StreamCollection::default(),
+            query_streams: StreamCollection::default(),
+            stats: TransportStats::default(),
+            drain: TransportDrain::default(),
         })
     }
 
@@ -98,6 +111,7 @@ impl HttpTransport {
         impl Drop for ClearOnDrop {
             fn drop(&mut self) {
                 self.transport.record_streams.clear();
+                self.transport.query_streams.clear();
             }
         }
 
@@ -120,6 +134,19 @@ impl HttpTransport {
         self.record_streams
             .add_stream((query_id, from, gate), LogErrors::new(stream));
     }
+
+    /// Connect an inbound stream of records that are sent to the query as a whole.
+    ///
+    /// This is called by peer helpers via the HTTP server.
+    pub fn receive_query_stream(
+        self: Arc<Self>,
+        query_id: QueryId,
+        from: HelperIdentity,
+        stream: BodyStream,
+    ) {
+        self.query_streams
+            .add_stream((query_id, from, Gate::default()), LogErrors::new(stream));
+    }
 }
 
 #[async_trait]
@@ -146,26 +173,52 @@ impl Transport for Arc<HttpTransport> {
         Option<QueryId>: From<Q>,
         Option<Gate>: From<S>,
     {
+        if self.drain.is_closed() {
+            return Err(Error::ShutDown);
+        }
         let route_id = route.resource_identifier();
         match route_id {
             RouteId::Records => {
                 // TODO(600): These fallible extractions aren't really necessary.
                 let query_id = <Option<QueryId>>::from(route.query_id())
                     .expect("query_id required when sending records");
-                let step =
-                    <Option<Gate>>::from(route.gate()).expect("step required when sending records");
-                let resp_future = self.clients[dest].step(query_id, &step, data)?;
+                let step = <Option<Gate>>::from(route.gate());
+                let span = trace::send_span(dest, route_id, Some(query_id), step.as_ref());
+                let data = trace::record_bytes(span.clone(), data);
+                // Records sent without a step are counted against the root gate, the same one
+                // the receiver keeps them under.
+                let data =
+                    self.stats
+                        .count_sent(query_id, step.clone().unwrap_or_default(), dest, data);
+                // Shutdown cuts tracked streams short, so the frame that finishes them is tracked
+                // too.
+                let Some(data) = self.drain.track(frame_records(data)) else {
+                    return Err(Error::ShutDown);
+                };
+                let resp_future = match step {
+                    Some(step) => self.clients[dest].step(query_id, &step, data)?,
+                    None => self.clients[dest].query_records(query_id, data)?,
+                };
                 // we don't need to spawn a task here. Gateway's sender interface already does that
                 // so this can just poll this future.
                 resp_future
                     .map_err(Into::into)
                     .and_then(MpcHelperClient::resp_ok)
+                    .instrument(span)
                     .await?;
                 Ok(())
             }
             RouteId::PrepareQuery => {
                 let req = serde_json::from_str(route.extra().borrow()).unwrap();
-                self.clients[dest].prepare_query(req).await
+                self.clients[dest]
+                    .prepare_query(req)
+                    .instrument(trace::send_span(
+                        dest,
+                        route_id,
+                        <Option<QueryId>>::from(route.query_id()),
+                        None,
+                    ))
+                    .await
             }
             RouteId::ReceiveQuery => {
                 unimplemented!("attempting to send ReceiveQuery to another helper")
@@ -181,8 +234,80 @@ impl Transport for Arc<HttpTransport> {
         ReceiveRecords::new(
             (route.query_id(), from, route.gate()),
             self.record_streams.clone(),
+            self.stats.clone(),
+        )
+    }
+
+    /// Records data arrives over HTTP only once, so this does not replay streams that were taken
+    /// by [`receive`] already, such streams end right away and are reported as truncated. It is
+    /// only good for skipping records of a stream nobody read yet.
+    ///
+    /// [`receive`]: Transport::receive
+    fn receive_from<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+        start: RecordId,
+        record_size: usize,
+    ) -> Self::RecordsStream {
+        let key = (route.query_id(), from, route.gate());
+        if self.record_streams.is_consumed(&key) {
+            return ReceiveRecords::consumed(key, self.stats.clone());
+        }
+        ReceiveRecords::new(key, self.record_streams.clone(), self.stats.clone())
+            .skip_bytes(usize::from(start) * record_size)
+    }
+
+    fn receive_query_records(
+        &self,
+        from: HelperIdentity,
+        query_id: QueryId,
+    ) -> Self::RecordsStream {
+        ReceiveRecords::new(
+            (query_id, from, Gate::default()),
+            self.query_streams.clone(),
+            self.stats.clone(),
         )
     }
+
+    fn stats(&self) -> TransportStats {
+        self.stats.clone()
+    }
+
+    /// Sends an echo request to `dest` and waits for it to come back.
+    async fn ping(&self, dest: HelperIdentity, timeout: Duration) -> Result<(), io::Error> {
+        const PING: &str = "ping";
+
+        match tokio::time::timeout(timeout, self.clients[dest].echo(PING)).await {
+            Ok(Ok(echo)) if echo == PING => Ok(()),
+            Ok(Ok(echo)) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{dest:?} responded to ping with {echo:?}"),
+            )),
+            Ok(Err(e)) => Err(io::Error::new(io::ErrorKind::ConnectionRefused, e)),
+            Err(_) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{dest:?} did not respond to ping within {timeout:?}"),
+            )),
+        }
+    }
+
+    async fn shutdown(&self) {
+        let terminated = self.drain.close(SHUTDOWN_DEADLINE).await;
+        if terminated > 0 {
+            tracing::warn!(
+                terminated,
+                "records streams were not consumed by peers before shutdown"
+            );
+        }
+        self.record_streams.close();
+        self.query_streams.close();
+    }
+
+    fn cleanup(&self, query_id: QueryId) {
+        self.record_streams.remove_query(query_id);
+        self.query_streams.remove_query(query_id);
+    }
 }
 
 #[cfg(all(test, web_test))]
@@ -215,22 +340,27 @@ mod tests {
 
     #[tokio::test]
     async fn receive_stream() {
-        let (tx, rx) = channel::<Result<Bytes, Box<dyn std::error::Error + Send + Sync>>>(1);
+        let (tx, rx) = channel::<Vec<u8>>(1);
         let expected_chunk1 = vec![0u8, 1, 2, 3];
         let expected_chunk2 = vec![255u8, 254, 253, 252];
 
         let TestServer { transport, .. } = TestServer::default().await;
 
-        let body = BodyStream::from_body(
-            Box::new(ReceiverStream::new(rx)) as Box<dyn Stream<Item = _> + Send>
-        );
+        let body = frame_records(ReceiverStream::new(rx))
+            .map(|chunk| Ok::<_, BoxError>(Bytes::from(chunk)));
+        let body = BodyStream::from_body(Box::new(body) as Box<dyn Stream<Item = _> + Send>);
 
         // Register the stream with the transport (normally called by step data HTTP API handler)
-        Arc::clone(&transport).receive_stream(QueryId, STEP.clone(), HelperIdentity::TWO, body);
+        Arc::clone(&transport).receive_stream(
+            QueryId::default(),
+            STEP.clone(),
+            HelperIdentity::TWO,
+            body,
+        );
 
         // Request step data reception (normally called by protocol)
         let mut stream =
-            Arc::clone(&transport).receive(HelperIdentity::TWO, (QueryId, STEP.clone()));
+            Arc::clone(&transport).receive(HelperIdentity::TWO, (QueryId::default(), STEP.clone()));
 
         // make sure it is not ready as it hasn't received any data yet.
         assert!(matches!(
@@ -239,7 +369,7 @@ mod tests {
         ));
 
         // send and verify first chunk
-        tx.send(Ok(expected_chunk1.clone().into())).await.unwrap();
+        tx.send(expected_chunk1.clone()).await.unwrap();
 
         assert_eq!(
             poll_immediate(&mut stream).next().await,
@@ -247,7 +377,7 @@ mod tests {
         );
 
         // send and verify second chunk
-        tx.send(Ok(expected_chunk2.clone().into())).await.unwrap();
+        tx.send(expected_chunk2.clone()).await.unwrap();
 
         assert_eq!(
             poll_immediate(&mut stream).next().await,
@@ -347,6 +477,7 @@ mod tests {
             let data = QueryInput {
                 query_id,
                 input_stream,
+                length: None,
             };
             handle_resps.push(clients[i].query_input(data));
         }
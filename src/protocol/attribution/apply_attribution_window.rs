@@ -294,4 +294,68 @@ mod tests {
             assert_eq!(b.as_u128(), *active_bit);
         }
     }
+
+    /// Triggers that are 599, 600 and 601 seconds away from their source, with a window of 600
+    /// seconds. The window is inclusive, so only the last one is outside of it. With no window,
+    /// all of them are attributed.
+    #[tokio::test]
+    pub async fn window_boundary() {
+        const ATTRIBUTION_WINDOW: Option<NonZeroU32> = NonZeroU32::new(600);
+
+        async fn apply(window: Option<NonZeroU32>) -> Vec<(u128, u128)> {
+            let input: Vec<GenericReportTestInput<Fp32BitPrime, MatchKey, BreakdownKey>> = attribution_window_test_input!(
+                [
+                    { timestamp: 1000, is_trigger_report: 0, helper_bit: 0, breakdown_key: 1, credit: 0 },
+                    { timestamp: 1599, is_trigger_report: 1, helper_bit: 1, breakdown_key: 0, credit: 3 },
+                    { timestamp: 1600, is_trigger_report: 1, helper_bit: 1, breakdown_key: 0, credit: 5 },
+                    { timestamp: 1601, is_trigger_report: 1, helper_bit: 1, breakdown_key: 0, credit: 7 },
+                ];
+                (Fp32BitPrime, MatchKey, BreakdownKey)
+            );
+
+            let result =
+                TestWorld::default()
+                    .semi_honest(
+                        input.into_iter(),
+                        |ctx,
+                         input: Vec<
+                            ApplyAttributionWindowInputRow<Fp32BitPrime, Replicated<_>>,
+                        >| async move {
+                            let validator = ctx.validator();
+                            let ctx = validator.context();
+                            let (itb, hb): (Vec<_>, Vec<_>) = input
+                                .iter()
+                                .map(|x| (x.is_trigger_report.clone(), x.helper_bit.clone()))
+                                .unzip();
+                            // helper bits of the first row are not used
+                            let stop_bits = compute_stop_bits(ctx.clone(), &itb, &hb[1..])
+                                .await
+                                .unwrap()
+                                .collect::<Vec<_>>();
+
+                            apply_attribution_window(ctx, &input, &stop_bits, window)
+                                .await
+                                .unwrap()
+                        },
+                    )
+                    .await;
+
+            (0..result[0].len())
+                .map(|i| {
+                    let [r0, r1, r2] = [&result[0][i], &result[1][i], &result[2][i]];
+                    let value =
+                        [&r0.trigger_value, &r1.trigger_value, &r2.trigger_value].reconstruct();
+                    let active_bit = [&r0.active_bit, &r1.active_bit, &r2.active_bit].reconstruct();
+                    (value.as_u128(), active_bit.as_u128())
+                })
+                .collect()
+        }
+
+        assert_eq!(
+            vec![(0, 1), (3, 1), (5, 1), (0, 0)],
+            apply(ATTRIBUTION_WINDOW).await
+        );
+        // Without a window, trigger values pass through and active bits are not computed.
+        assert_eq!(vec![(0, 0), (3, 0), (5, 0), (7, 0)], apply(None).await);
+    }
 }
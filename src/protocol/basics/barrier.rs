@@ -0,0 +1,77 @@
+use futures::future::try_join4;
+
+use crate::{
+    error::Error,
+    ff::Gf2,
+    helpers::Direction,
+    protocol::{context::Context, RecordId},
+    secret_sharing::SharedValue,
+};
+
+/// Waits until both peers of this helper reach the same point.
+///
+/// Every helper sends a one-byte token to both of its peers and returns only after it has received
+/// tokens from both of them. A helper that gets ahead of the others waits here until they catch
+/// up. Nothing stops it from being the first to reach the next barrier again, so this only keeps
+/// helpers within one stage of each other.
+///
+/// Barrier runs on the step of `ctx`, so every barrier needs a step of its own.
+///
+/// ## Errors
+/// If communication with either peer fails.
+pub async fn barrier<C: Context>(ctx: C, record_id: RecordId) -> Result<(), Error> {
+    let left = ctx.role().peer(Direction::Left);
+    let right = ctx.role().peer(Direction::Right);
+    try_join4(
+        ctx.send_channel(left).send(record_id, Gf2::ZERO),
+        ctx.send_channel(right).send(record_id, Gf2::ZERO),
+        ctx.recv_channel::<Gf2>(left).receive(record_id),
+        ctx.recv_channel::<Gf2>(right).receive(record_id),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use futures::future::try_join_all;
+
+    use super::barrier;
+    use crate::{
+        helpers::Role,
+        protocol::{context::Context, RecordId},
+        test_fixture::TestWorld,
+    };
+
+    #[tokio::test]
+    async fn waits_for_slowest_helper() {
+        let world = TestWorld::default();
+        let arrived = AtomicBool::new(false);
+
+        try_join_all(world.contexts().map(|ctx| {
+            let arrived = &arrived;
+            async move {
+                let ctx = ctx.set_total_records(1);
+                if ctx.role() == Role::H1 {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    arrived.store(true, Ordering::SeqCst);
+                }
+                barrier(ctx.clone(), RecordId::FIRST).await?;
+                assert!(
+                    arrived.load(Ordering::SeqCst),
+                    "{:?} passed the barrier before H1 reached it",
+                    ctx.role()
+                );
+                Ok::<_, crate::error::Error>(())
+            }
+        }))
+        .await
+        .unwrap();
+    }
+}
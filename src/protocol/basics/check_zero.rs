@@ -4,7 +4,7 @@ use crate::{
     error::Error,
     ff::Field,
     protocol::{
-        basics::{reveal::Reveal, SecureMul},
+        basics::{reveal::Reveal, SecureDotProduct, SecureMul},
         context::Context,
         prss::SharedRandomness,
         RecordId,
@@ -14,6 +14,7 @@ use crate::{
 
 #[derive(Step)]
 pub(crate) enum Step {
+    GenerateR,
     MultiplyWithR,
     RevealR,
 }
@@ -64,17 +65,73 @@ pub async fn check_zero<C: Context, F: Field>(
     Ok(rv == F::ZERO)
 }
 
+/// Checks if every one of `xs` is a sharing of zero, revealing a single value however many there
+/// are.
+///
+/// This is [`check_zero`] applied to `r_1 · x_1 + … + r_n · x_n`, where `r_i` are independent
+/// random values that no helper knows. The sum is computed with [`SecureDotProduct`], so the whole
+/// batch takes one multiplication and one reveal. If all `x_i` are zero, the revealed value is
+/// zero. If any of them is not, the revealed value is uniformly distributed over the field, so
+/// helpers learn nothing about `xs` besides that, and it is zero with probability `1/|F|`. That
+/// is the same chance of a false "true" that [`check_zero`] has, and it does not grow with the
+/// size of the batch. A single random value for all of `xs` would not do: `x` and `-x` would
+/// cancel out.
+///
+/// Like [`check_zero`], this works with any context, including malicious ones, where `xs` are
+/// the semi-honest shares that validation needs to check.
+///
+/// The batch is a single record of `ctx`, so callers can check any number of batches on the same
+/// context, one per `record_id`. Random values of record `record_id` take PRSS indices
+/// `record_id · 2^64 + i`, which don't overlap with those of any other record, whatever the size
+/// of the batches.
+///
+/// Empty `xs` are all zero, and helpers don't communicate to learn that.
+///
+/// ## Errors
+/// If communication with peers fails.
+pub async fn check_all_zero<C: Context, F: Field>(
+    ctx: C,
+    record_id: RecordId,
+    xs: &[Replicated<F>],
+) -> Result<bool, Error> {
+    if xs.is_empty() {
+        return Ok(true);
+    }
+
+    let r_ctx = ctx.narrow(&Step::GenerateR);
+    let prss = r_ctx.prss();
+    let r_sharings = (0_u128..)
+        .take(xs.len())
+        .map(|i| prss.generate_replicated((u128::from(record_id) << 64) + i))
+        .collect::<Vec<_>>();
+
+    let rx_share =
+        Replicated::dot(ctx.narrow(&Step::MultiplyWithR), record_id, &r_sharings, xs).await?;
+    let rx = rx_share
+        .reveal(ctx.narrow(&Step::RevealR), record_id)
+        .await?;
+
+    Ok(rx == F::ZERO)
+}
+
 #[cfg(all(test, unit_test))]
 mod tests {
-    use futures_util::future::try_join3;
+    use futures_util::future::{try_join3, try_join_all};
+    use rand::distributions::{Distribution, Standard};
 
     use crate::{
         error::Error,
-        ff::{Field, Fp31, PrimeField},
-        protocol::{basics::check_zero, context::Context, RecordId},
-        rand::thread_rng,
-        secret_sharing::{IntoShares, SharedValue},
-        test_fixture::TestWorld,
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField},
+        protocol::{
+            basics::{check_all_zero, check_zero},
+            context::{Context, UpgradableContext, Validator},
+            RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::{
+            replicated::semi_honest::AdditiveShare as Replicated, IntoShares, SharedValue,
+        },
+        test_fixture::{Runner, TestWorld},
     };
 
     #[tokio::test]
@@ -131,4 +188,134 @@ mod tests {
 
         Ok(())
     }
+
+    /// Checks `values` with [`check_all_zero`] and returns what all helpers agreed on.
+    async fn all_zero<F: Field>(world: &TestWorld, values: Vec<F>) -> bool
+    where
+        Standard: Distribution<F>,
+    {
+        let result = world
+            .semi_honest(values.into_iter(), |ctx, xs| async move {
+                check_all_zero(ctx.set_total_records(1), RecordId::FIRST, &xs)
+                    .await
+                    .unwrap()
+            })
+            .await;
+
+        assert_eq!(result[0], result[1]);
+        assert_eq!(result[1], result[2]);
+        result[0]
+    }
+
+    fn rng_nonzero() -> Fp32BitPrime {
+        Fp32BitPrime::truncate_from(thread_rng().gen_range(1..Fp32BitPrime::PRIME))
+    }
+
+    #[tokio::test]
+    async fn batch_all_zero() {
+        let world = TestWorld::default();
+        assert!(all_zero(&world, vec![Fp32BitPrime::ZERO; 10]).await);
+        assert!(all_zero(&world, Vec::<Fp32BitPrime>::new()).await);
+    }
+
+    #[tokio::test]
+    async fn batch_one_nonzero() {
+        let world = TestWorld::default();
+        for i in 0..10 {
+            let mut values = vec![Fp32BitPrime::ZERO; 10];
+            values[i] = rng_nonzero();
+            assert!(!all_zero(&world, values).await);
+        }
+    }
+
+    /// Every value is multiplied by its own random value, so values that sum up to zero don't
+    /// cancel out.
+    #[tokio::test]
+    async fn batch_values_do_not_cancel() {
+        let world = TestWorld::default();
+        let x = rng_nonzero();
+        assert!(!all_zero(&world, vec![x, -x, Fp32BitPrime::ZERO]).await);
+    }
+
+    /// A batch that has a non-zero value in it passes the check with probability `1/|F|`, the
+    /// same as a single value does. In `Fp31`, that is about 3 out of 100 attempts.
+    #[tokio::test]
+    async fn batch_false_positives() {
+        const ATTEMPTS: usize = 100;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let mut num_false_positives = 0;
+        for _ in 0..ATTEMPTS {
+            let mut values = vec![Fp31::ZERO; 5];
+            values[rng.gen_range(0..5)] = Fp31::truncate_from(rng.gen_range(1..Fp31::PRIME));
+            if all_zero(&world, values).await {
+                num_false_positives += 1;
+            }
+        }
+
+        // The odds of 15 or more are less than one in a million.
+        assert!(num_false_positives < 15, "{num_false_positives}");
+    }
+
+    /// Batches checked on the same context, one per record, draw their own random values, even
+    /// if they are of different sizes.
+    #[tokio::test]
+    async fn batch_per_record() {
+        let world = TestWorld::default();
+        let x = rng_nonzero();
+        let batches = vec![
+            vec![Fp32BitPrime::ZERO; 3],
+            vec![x, -x],
+            vec![Fp32BitPrime::ZERO],
+            vec![x],
+        ];
+        let expected = batches
+            .iter()
+            .map(|batch| batch.iter().all(|x| *x == Fp32BitPrime::ZERO))
+            .collect::<Vec<_>>();
+
+        let result = world
+            .semi_honest(
+                batches.into_iter().map(Vec::into_iter),
+                |ctx, batches: Vec<Vec<Replicated<Fp32BitPrime>>>| async move {
+                    let ctx = ctx.set_total_records(batches.len());
+                    try_join_all(
+                        batches
+                            .iter()
+                            .enumerate()
+                            .map(|(i, xs)| check_all_zero(ctx.clone(), RecordId::from(i), xs)),
+                    )
+                    .await
+                    .unwrap()
+                },
+            )
+            .await;
+
+        assert_eq!([expected.clone(), expected.clone(), expected], result);
+    }
+
+    /// Malicious protocols check semi-honest shares under an upgraded context, the same way the
+    /// validator does.
+    #[tokio::test]
+    async fn batch_malicious_context() {
+        let world = TestWorld::default();
+        for (values, expected) in [
+            (vec![Fp32BitPrime::ZERO; 4], true),
+            (
+                vec![Fp32BitPrime::ZERO, rng_nonzero(), Fp32BitPrime::ZERO],
+                false,
+            ),
+        ] {
+            let result = world
+                .malicious(values.into_iter(), |ctx, xs| async move {
+                    let v = ctx.validator::<Fp32BitPrime>();
+                    check_all_zero(v.context().set_total_records(1), RecordId::FIRST, &xs)
+                        .await
+                        .unwrap()
+                })
+                .await;
+
+            assert_eq!([expected; 3], result);
+        }
+    }
 }
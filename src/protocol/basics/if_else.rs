@@ -6,6 +6,8 @@ use crate::{
 };
 
 /// Returns `true_value` if `condition` is a share of 1, else `false_value`.
+///
+/// `condition` must be a share of a bit, it takes a single multiplication by `condition`.
 /// # Errors
 /// If the protocol fails to execute.
 pub async fn if_else<F, C, S>(
@@ -0,0 +1,167 @@
+use crate::{
+    error::Error,
+    ff::PrimeField,
+    protocol::{
+        basics::is_zero::nonzero_masked_value, context::UpgradedContext, BasicProtocols, RecordId,
+    },
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum InversionError {
+    #[error("cannot invert zero")]
+    Zero,
+    #[error(transparent)]
+    Protocol(#[from] Error),
+}
+
+/// Computes `[x^-1]`.
+///
+/// This is the inversion protocol of Bar-Ilan and Beaver: `x` is multiplied by a random `r` that
+/// no helper knows and `r · x` is revealed, the same way [`is_zero`] does it. Helpers invert
+/// `r · x` locally and multiply it by their shares of `r`, which gives `r · (r · x)^-1 = x^-1`.
+///
+/// `r` itself is zero with probability `1/|F|`. Helpers can't tell that apart from `x` being zero,
+/// so they make several attempts with a fresh `r` each and use the first non-zero `r · x`. The
+/// number of attempts is picked so that non-zero `x` fails with probability below `2^-40`; if all
+/// of them fail, `x` is taken to be zero. See [`nonzero_masked_value`].
+///
+/// [`is_zero`]: super::is_zero
+/// [`nonzero_masked_value`]: super::is_zero::nonzero_masked_value
+///
+/// ## Errors
+/// [`InversionError::Zero`] if `x` is zero, or if communication with peers fails.
+pub async fn invert<F, C, S>(ctx: C, record_id: RecordId, x: &S) -> Result<S, InversionError>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+{
+    match nonzero_masked_value(&ctx, record_id, x).await? {
+        Some((r, rx)) => Ok(r * inverse(rx)),
+        None => Err(InversionError::Zero),
+    }
+}
+
+/// Computes `x^-1 = x^(p - 2)` by Fermat's little theorem.
+fn inverse<F: PrimeField>(x: F) -> F {
+    let prime: u128 = F::PRIME.into();
+    let mut exp = prime - 2;
+    let mut base = x;
+    let mut result = F::ONE;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result *= base;
+        }
+        base *= base;
+        exp >>= 1;
+    }
+    result
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{distributions::Standard, prelude::Distribution};
+
+    use super::{inverse, invert, InversionError};
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField},
+        protocol::{
+            context::{Context, UpgradableContext, UpgradedContext, Validator},
+            RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::SharedValue,
+        seq_join::SeqJoin,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    /// Inverts random non-zero values in both security models.
+    async fn inverts_random<F: PrimeField>()
+    where
+        Standard: Distribution<F>,
+    {
+        const COUNT: usize = 10;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let prime: u128 = F::PRIME.into();
+        let values = (0..COUNT)
+            .map(|_| F::truncate_from(rng.gen_range(1..prime)))
+            .collect::<Vec<_>>();
+
+        let result: Vec<F> = world
+            .semi_honest(values.clone().into_iter(), |ctx, x| async move {
+                let validator = ctx.validator::<F>();
+                let ctx = validator.context().set_total_records(x.len());
+                ctx.try_join(
+                    x.iter()
+                        .enumerate()
+                        .map(|(i, x)| invert(ctx.clone(), RecordId::from(i), x)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result: Vec<F> = world
+            .upgraded_malicious(values.clone().into_iter(), |ctx, x| async move {
+                let ctx = ctx.set_total_records(x.len());
+                ctx.try_join(
+                    x.iter()
+                        .enumerate()
+                        .map(|(i, x)| invert(ctx.clone(), RecordId::from(i), x)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        for ((x, inv), m_inv) in values.into_iter().zip(result).zip(m_result) {
+            assert_eq!(F::ONE, x * inv, "{x:?}^-1 = {inv:?}");
+            assert_eq!(F::ONE, x * m_inv, "{x:?}^-1 = {m_inv:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn random_fp31() {
+        inverts_random::<Fp31>().await;
+    }
+
+    #[tokio::test]
+    async fn random_fp32_bit_prime() {
+        inverts_random::<Fp32BitPrime>().await;
+    }
+
+    #[tokio::test]
+    async fn zero() {
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(Fp31::ZERO, |ctx, x| async move {
+                let validator = ctx.validator::<Fp31>();
+                let ctx = validator.context().set_total_records(1);
+                invert(ctx, RecordId::FIRST, &x).await.map(|_| ())
+            })
+            .await;
+        let m_result = world
+            .malicious(Fp31::ZERO, |ctx, x| async move {
+                let v = ctx.validator();
+                let m_ctx = v.context().set_total_records(1);
+                let m_x = m_ctx.upgrade(x).await.unwrap();
+                invert(m_ctx, RecordId::FIRST, &m_x).await.map(|_| ())
+            })
+            .await;
+
+        for r in result.iter().chain(&m_result) {
+            assert!(matches!(r, Err(InversionError::Zero)), "{r:?}");
+        }
+    }
+
+    #[test]
+    fn local_inverse() {
+        for x in 1..Fp31::PRIME {
+            let x = Fp31::truncate_from(x);
+            assert_eq!(Fp31::ONE, x * inverse(x));
+        }
+    }
+}
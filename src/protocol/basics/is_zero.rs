@@ -0,0 +1,259 @@
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::PrimeField,
+    protocol::{
+        basics::ZeroPositions, context::UpgradedContext, prss::SharedRandomness, step::BitOpStep,
+        BasicProtocols, RecordId,
+    },
+    secret_sharing::replicated::malicious::ExtendableField,
+};
+
+/// Not a part of any protocol yet, so it is not in `steps.txt`.
+#[derive(Step)]
+pub(crate) enum Step {
+    GenerateR,
+    UpgradeR,
+    MultiplyWithR,
+    RevealR,
+}
+
+/// Computes `[x == 0]`.
+///
+/// This works the same way [`check_zero`] does: `x` is multiplied by a random value `r` that no
+/// helper knows and `r · x` is revealed. If `x` is zero, so is `r · x`. Otherwise `r · x` is
+/// uniformly distributed over non-zero values, whatever `x` is, so helpers learn nothing but
+/// whether `x` is zero. Because of that, the result is shared as a known value.
+///
+/// `r` itself is zero with probability `1/|F|`, and helpers can't tell that apart from `x` being
+/// zero. So they make several attempts with a fresh `r` each and use the first non-zero `r · x`,
+/// the same way [`invert`] does. After as many attempts as it takes for non-zero `x` to fail with
+/// probability below `2^-40`, `x` is taken to be zero. See [`nonzero_masked_value`].
+///
+/// In malicious contexts, `r` is upgraded and multiplied with `x` using the context, so both
+/// operations are checked by its validator.
+///
+/// [`check_zero`]: super::check_zero
+/// [`invert`]: super::invert
+///
+/// ## Errors
+/// If communication with peers fails.
+pub async fn is_zero<F, C, S>(ctx: C, record_id: RecordId, x: &S) -> Result<S, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: BasicProtocols<C, F>,
+{
+    let value = match nonzero_masked_value(&ctx, record_id, x).await? {
+        Some(_) => F::ZERO,
+        None => F::ONE,
+    };
+
+    Ok(ctx.share_known_value(value))
+}
+
+/// Number of times to mask `x` with a fresh `r` before concluding that `x` is zero. Every attempt
+/// fails with probability `1/p < 2^-(BITS - 1)`.
+fn attempts<F: PrimeField>() -> usize {
+    const SECURITY: u32 = 40;
+    usize::try_from((SECURITY + F::BITS - 2) / (F::BITS - 1)).unwrap()
+}
+
+/// Makes all [`attempts`] to mask `x` at once and returns the first one with non-zero `r · x`,
+/// or `None` if there is none. Attempt `i` runs on `BitOpStep::from(i)`.
+///
+/// Every record makes every attempt, even when the first one is enough. Channels send records in
+/// order, so a step that only some of the records get to would wait for the others forever.
+pub(super) async fn nonzero_masked_value<F, C, S>(
+    ctx: &C,
+    record_id: RecordId,
+    x: &S,
+) -> Result<Option<(S, F)>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: BasicProtocols<C, F>,
+{
+    let masked = ctx
+        .parallel_join(
+            (0..attempts::<F>())
+                .map(|attempt| masked_value(ctx.narrow(&BitOpStep::from(attempt)), record_id, x)),
+        )
+        .await?;
+
+    Ok(masked.into_iter().find(|(_, rx)| *rx != F::ZERO))
+}
+
+/// Returns shares of a random `r` that no helper knows, along with `r · x` revealed to all
+/// helpers.
+async fn masked_value<F, C, S>(ctx: C, record_id: RecordId, x: &S) -> Result<(S, F), Error>
+where
+    F: ExtendableField,
+    C: UpgradedContext<F, Share = S>,
+    S: BasicProtocols<C, F>,
+{
+    // Upgrade multiplies `r` by the MAC key, so it must not draw on the same randomness as `r`.
+    let r = ctx
+        .narrow(&Step::GenerateR)
+        .prss()
+        .generate_replicated(record_id);
+    let r = ctx
+        .narrow(&Step::UpgradeR)
+        .upgrade_one(record_id, r, ZeroPositions::Pvvv)
+        .await?;
+    let rx = r
+        .multiply(x, ctx.narrow(&Step::MultiplyWithR), record_id)
+        .await?;
+
+    let rx = rx.reveal(ctx.narrow(&Step::RevealR), record_id).await?;
+
+    Ok((r, rx))
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{distributions::Standard, prelude::Distribution};
+
+    use super::{is_zero, masked_value};
+    use crate::{
+        error::Error,
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField},
+        protocol::{
+            context::{Context, UpgradableContext, Validator},
+            RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::SharedValue,
+        seq_join::SeqJoin,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    /// Runs [`is_zero`] on every value in both security models and checks they agree.
+    async fn run<F: PrimeField>(values: Vec<F>) -> Vec<F>
+    where
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(values.clone().into_iter(), |ctx, x| async move {
+                let validator = ctx.validator::<F>();
+                let ctx = validator.context().set_total_records(x.len());
+                ctx.try_join(
+                    x.iter()
+                        .enumerate()
+                        .map(|(i, x)| is_zero(ctx.clone(), RecordId::from(i), x)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result = world
+            .upgraded_malicious(values.into_iter(), |ctx, x| async move {
+                let ctx = ctx.set_total_records(x.len());
+                ctx.try_join(
+                    x.iter()
+                        .enumerate()
+                        .map(|(i, x)| is_zero(ctx.clone(), RecordId::from(i), x)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(result, m_result, "security models disagree");
+        result
+    }
+
+    #[tokio::test]
+    async fn zero() {
+        let result = run(vec![Fp32BitPrime::ZERO; 4]).await;
+        assert!(result.iter().all(|&b| b == Fp32BitPrime::ONE), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn non_zero() {
+        let mut rng = thread_rng();
+        let mut values = vec![
+            Fp32BitPrime::ONE,
+            Fp32BitPrime::truncate_from(Fp32BitPrime::PRIME - 1),
+        ];
+        values.extend(
+            (0..8).map(|_| Fp32BitPrime::truncate_from(rng.gen_range(1..Fp32BitPrime::PRIME))),
+        );
+
+        let result = run(values).await;
+        assert!(
+            result.iter().all(|&b| b == Fp32BitPrime::ZERO),
+            "{result:?}"
+        );
+    }
+
+    /// `r` is zero about 3% of the time in `Fp31`, that must not make non-zero values look like
+    /// zero.
+    #[tokio::test]
+    async fn non_zero_small_field() {
+        let mut rng = thread_rng();
+        let values = (0..100)
+            .map(|_| Fp31::truncate_from(rng.gen_range(1..Fp31::PRIME)))
+            .collect::<Vec<_>>();
+
+        let result = run(values).await;
+        assert!(result.iter().all(|&b| b == Fp31::ZERO), "{result:?}");
+    }
+
+    /// Revealed `r · x` must look the same for every non-zero `x`: uniformly distributed over
+    /// non-zero values. Zero shows up only if `r` is zero, and then [`is_zero`] tries again with a
+    /// fresh `r`, so it is left out. Compares the histogram of other revealed values to the
+    /// uniform one with Pearson's chi-squared test.
+    #[tokio::test]
+    async fn reveals_nothing_but_zeroness() {
+        const SAMPLES: usize = 31 * 100;
+        // Critical value for 29 degrees of freedom at p = 10^-6.
+        const CRITICAL: f64 = 80.4;
+
+        let world = TestWorld::default();
+        for x in [1_u32, 2, 17, 30] {
+            let x = Fp31::truncate_from(x);
+            let revealed = world
+                .semi_honest(vec![x; SAMPLES].into_iter(), |ctx, x| async move {
+                    let validator = ctx.validator::<Fp31>();
+                    let ctx = validator.context().set_total_records(x.len());
+                    ctx.try_join(x.iter().enumerate().map(|(i, x)| {
+                        let ctx = ctx.clone();
+                        async move {
+                            let (_, rx) = masked_value(ctx, RecordId::from(i), x).await?;
+                            Ok::<_, Error>(rx)
+                        }
+                    }))
+                    .await
+                    .unwrap()
+                })
+                .await;
+            // every helper must see the same values
+            assert_eq!(revealed[0], revealed[1]);
+            assert_eq!(revealed[1], revealed[2]);
+
+            let non_zero = revealed[0]
+                .iter()
+                .filter(|&&v| v != Fp31::ZERO)
+                .collect::<Vec<_>>();
+            let mut histogram = vec![0_u32; usize::from(Fp31::PRIME - 1)];
+            for v in &non_zero {
+                histogram[usize::try_from(v.as_u128()).unwrap() - 1] += 1;
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let expected = non_zero.len() as f64 / f64::from(Fp31::PRIME - 1);
+            let chi_squared = histogram
+                .iter()
+                .map(|&observed| (f64::from(observed) - expected).powi(2) / expected)
+                .sum::<f64>();
+            assert!(
+                chi_squared < CRITICAL,
+                "r · {x:?} is not uniform: {histogram:?}"
+            );
+        }
+    }
+}
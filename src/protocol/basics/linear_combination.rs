@@ -0,0 +1,98 @@
+use crate::{
+    ff::Field,
+    protocol::{basics::ShareKnownValue, context::Context},
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+/// Computes `[c_1 · x_1 + … + c_n · x_n + constant]` for coefficients `c_i` and `constant` that
+/// all helpers know.
+///
+/// Every operation here is linear, so helpers don't need to communicate. Scaling a share by a
+/// constant is `share * c` and needs nothing else, but adding a constant depends on the role of
+/// the helper and, in malicious contexts, on the MAC key. Both are taken from `ctx`, the same way
+/// [`ShareKnownValue`] does it.
+pub fn linear_combination<F, C, S>(ctx: &C, terms: &[(F, &S)], constant: F) -> S
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + ShareKnownValue<C, F>,
+{
+    terms
+        .iter()
+        .fold(S::share_known_value(ctx, constant), |acc, &(c, x)| {
+            acc + x.clone() * c
+        })
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::distributions::{Distribution, Standard};
+
+    use super::linear_combination;
+    use crate::{
+        ff::{Fp31, Fp32BitPrime},
+        rand::{thread_rng, Rng},
+        secret_sharing::replicated::malicious::ExtendableField,
+        telemetry::metrics::RECORDS_SENT,
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig},
+    };
+
+    /// Evaluates a random affine function of random inputs in both security models and checks
+    /// that semi-honest helpers did not send anything.
+    async fn matches_plaintext<F: ExtendableField>()
+    where
+        Standard: Distribution<F>,
+    {
+        const TERMS: usize = 5;
+        const ATTEMPTS: usize = 10;
+
+        let world = TestWorld::new_with(TestWorldConfig::default().enable_metrics());
+        let m_world = TestWorld::default();
+        let mut rng = thread_rng();
+        for _ in 0..ATTEMPTS {
+            let inputs = (0..TERMS).map(|_| rng.gen::<F>()).collect::<Vec<_>>();
+            let coefficients = (0..TERMS).map(|_| rng.gen::<F>()).collect::<Vec<_>>();
+            let constant = rng.gen::<F>();
+            let expected = inputs
+                .iter()
+                .zip(&coefficients)
+                .fold(constant, |acc, (&x, &c)| acc + c * x);
+
+            let result = world
+                .semi_honest(inputs.clone().into_iter(), |ctx, x| {
+                    let coefficients = coefficients.clone();
+                    async move {
+                        let terms = coefficients.into_iter().zip(&x).collect::<Vec<_>>();
+                        linear_combination(&ctx, &terms, constant)
+                    }
+                })
+                .await
+                .reconstruct();
+            let m_result = m_world
+                .upgraded_malicious(inputs.into_iter(), |ctx, x| {
+                    let coefficients = coefficients.clone();
+                    async move {
+                        let terms = coefficients.into_iter().zip(&x).collect::<Vec<_>>();
+                        linear_combination(&ctx, &terms, constant)
+                    }
+                })
+                .await
+                .reconstruct();
+
+            assert_eq!(expected, result);
+            assert_eq!(expected, m_result);
+        }
+
+        assert_eq!(0, world.metrics_snapshot().get_counter(RECORDS_SENT));
+    }
+
+    #[tokio::test]
+    async fn fp31() {
+        matches_plaintext::<Fp31>().await;
+    }
+
+    #[tokio::test]
+    async fn fp32_bit_prime() {
+        matches_plaintext::<Fp32BitPrime>().await;
+    }
+}
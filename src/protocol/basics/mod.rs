@@ -1,18 +1,40 @@
 pub mod apply_permutation;
+mod barrier;
 pub mod check_zero;
 mod if_else;
+#[cfg(feature = "descriptive-gate")]
+mod invert;
+#[cfg(feature = "descriptive-gate")]
+mod is_zero;
+mod linear_combination;
 pub(crate) mod mul;
+#[cfg(feature = "descriptive-gate")]
+mod pow;
+mod prefix_sum;
 mod reshare;
 mod reveal;
 mod share_known_value;
+mod sum_and_reveal;
 pub mod sum_of_product;
 
-pub use check_zero::check_zero;
+pub use barrier::barrier;
+pub use check_zero::{check_all_zero, check_zero};
 pub use if_else::if_else;
-pub use mul::{MultiplyZeroPositions, SecureMul, ZeroPositions};
-pub use reshare::Reshare;
-pub use reveal::Reveal;
+#[cfg(feature = "descriptive-gate")]
+pub use invert::{invert, InversionError};
+#[cfg(feature = "descriptive-gate")]
+pub use is_zero::is_zero;
+pub use linear_combination::linear_combination;
+pub use mul::{
+    multiply_all, MultiplyZeroPositions, SecureDotProduct, SecureMul, SecureSquare, ZeroPositions,
+};
+#[cfg(feature = "descriptive-gate")]
+pub use pow::pow;
+pub use prefix_sum::{prefix_sum, segmented_prefix_sum};
+pub use reshare::{reshare, Reshare};
+pub use reveal::{reveal_to, PartialReveal, Reveal};
 pub use share_known_value::ShareKnownValue;
+pub use sum_and_reveal::{sum_and_reveal, sum_by_group};
 pub use sum_of_product::SumOfProducts;
 
 use crate::{
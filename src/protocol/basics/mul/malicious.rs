@@ -1,6 +1,7 @@
 use futures::future::try_join;
 use ipa_macros::Step;
 
+use super::semi_honest::dot_product as semi_honest_dot_product;
 use crate::{
     error::Error,
     protocol::{
@@ -109,15 +110,153 @@ where
     Ok(malicious_ab)
 }
 
+/// Computes `Σ a_i * b_i` in a single round. Both `Σ a_i * b_i` and `Σ r·a_i * b_i` are computed
+/// with one resharing each, the same way [`multiply`] computes `ab` and `rab`. The sums are
+/// consistent with each other just like the products are, so the validator only needs to see the
+/// aggregate: an additive attack on either sum breaks the MAC the same way it would on a single
+/// product.
+///
+/// ## Errors
+/// If either of the two resharings fails.
+///
+/// ## Panics
+/// If `a` and `b` have different lengths.
+pub async fn dot_product<F>(
+    ctx: UpgradedMaliciousContext<'_, F>,
+    record_id: RecordId,
+    a: &[MaliciousReplicated<F>],
+    b: &[MaliciousReplicated<F>],
+) -> Result<MaliciousReplicated<F>, Error>
+where
+    F: ExtendableField,
+{
+    use crate::{
+        protocol::context::SpecialAccessToUpgradedContext,
+        secret_sharing::replicated::malicious::ThisCodeIsAuthorizedToDowngradeFromMalicious,
+    };
+
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+    let duplicate_multiply_ctx = ctx.narrow(&Step::DuplicateMultiply);
+    let random_constant_ctx = ctx.narrow(&Step::RandomnessForValidation);
+
+    let a_x = a
+        .iter()
+        .map(|a| a.x().access_without_downgrade().clone())
+        .collect::<Vec<_>>();
+    let a_rx = a.iter().map(|a| a.rx().clone()).collect::<Vec<_>>();
+    let b_x = b
+        .iter()
+        .map(|b| b.x().access_without_downgrade().clone())
+        .collect::<Vec<_>>();
+    // See `multiply` for why `r·a_i` is multiplied by induced shares of `b_i`.
+    let b_induced_shares = b_x
+        .iter()
+        .map(|b| Replicated::new(b.left().to_extended(), b.right().to_extended()))
+        .collect::<Vec<_>>();
+
+    let (ab, rab) = try_join(
+        semi_honest_dot_product(ctx.base_context(), record_id, &a_x, &b_x),
+        semi_honest_dot_product(
+            duplicate_multiply_ctx.base_context(),
+            record_id,
+            &a_rx,
+            &b_induced_shares,
+        ),
+    )
+    .await?;
+
+    let malicious_ab = MaliciousReplicated::new(ab, rab);
+    random_constant_ctx.accumulate_macs(record_id, &malicious_ab);
+
+    Ok(malicious_ab)
+}
+
 #[cfg(all(test, unit_test))]
 mod test {
+    use std::sync::Arc;
+
     use crate::{
-        ff::Fp31,
-        protocol::{basics::SecureMul, context::Context, RecordId},
+        error::{Error, MaliciousError},
+        ff::{Fp31, Fp32BitPrime},
+        helpers::HelperIdentity,
+        protocol::{
+            basics::SecureMul,
+            context::{Context, UpgradableContext, UpgradedContext, Validator},
+            step::Gate,
+            RecordId,
+        },
         rand::{thread_rng, Rng},
-        test_fixture::{Reconstruct, Runner, TestWorld},
+        secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
+        sync::atomic::{AtomicUsize, Ordering},
+        test_fixture::{Reconstruct, Runner, Tampering, TestWorld, TestWorldConfig},
     };
 
+    async fn multiply_and_validate(
+        world: &TestWorld,
+        a: Fp32BitPrime,
+        b: Fp32BitPrime,
+    ) -> [Result<Replicated<Fp32BitPrime>, Error>; 3] {
+        world
+            .malicious((a, b), |ctx, (a, b)| async move {
+                let v = ctx.validator();
+                let m_ctx = v.context();
+                let (a, b) = m_ctx.upgrade((a, b)).await.unwrap();
+                let ab = a
+                    .multiply(
+                        &b,
+                        m_ctx.narrow("tampered").set_total_records(1),
+                        RecordId::from(0),
+                    )
+                    .await
+                    .unwrap();
+                v.validate(ab).await
+            })
+            .await
+    }
+
+    #[tokio::test]
+    pub async fn validator_catches_tampering() {
+        let mut rng = thread_rng();
+        let (a, b) = (rng.gen::<Fp32BitPrime>(), rng.gen::<Fp32BitPrime>());
+
+        let honest = multiply_and_validate(&TestWorld::default(), a, b).await;
+        assert_eq!(a * b, honest.map(Result::unwrap).reconstruct());
+
+        // H1 flips the lowest bit of the first share it sends while multiplying. The chance that
+        // this produces a value outside of the field is negligible.
+        let tampered = Arc::new(AtomicUsize::new(0));
+        let world = TestWorld::new_with(TestWorldConfig {
+            tampering: Some(Tampering::in_place({
+                let tampered = Arc::clone(&tampered);
+                move |from, _, gate: &Gate, _, chunk: &mut Vec<u8>| {
+                    if from == HelperIdentity::ONE
+                        && gate.as_ref().contains("tampered")
+                        && tampered.fetch_add(1, Ordering::Relaxed) == 0
+                    {
+                        chunk[0] ^= 1;
+                    }
+                }
+            })),
+            ..TestWorldConfig::default()
+        });
+
+        let results = multiply_and_validate(&world, a, b).await;
+        assert!(
+            tampered.load(Ordering::Relaxed) > 0,
+            "nothing was tampered with"
+        );
+        // Tampering must be reported as such by every helper, not as a failure to communicate.
+        for result in results {
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::Malicious(MaliciousError::ValidationFailed))
+                ),
+                "{result:?}"
+            );
+        }
+    }
+
     #[tokio::test]
     pub async fn simple() {
         let world = TestWorld::default();
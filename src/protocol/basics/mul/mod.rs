@@ -1,4 +1,9 @@
+use std::ops::Mul;
+
 use async_trait::async_trait;
+use generic_array::ArrayLength;
+use ipa_macros::Step;
+use typenum::Prod;
 
 use crate::{
     error::Error,
@@ -9,7 +14,7 @@ use crate::{
     },
     secret_sharing::replicated::{
         malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
-        semi_honest::AdditiveShare as Replicated,
+        semi_honest::{AdditiveShare as Replicated, ReplicatedVec},
     },
 };
 
@@ -45,10 +50,99 @@ pub trait SecureMul<C: Context>: Send + Sync + Sized {
     ) -> Result<Self, Error>
     where
         C: 'fut;
+
+    /// Multiply and return the result of `a` * `b` * `c`.
+    ///
+    /// It takes two rounds of communication: `a` * `b` first, then the product times `c`.
+    /// With replicated sharing, every degree-3 term that mixes the shares of all three helpers
+    /// is held by none of them, so there is no local computation to reshare in a single round.
+    async fn multiply3<'fut>(
+        &self,
+        b: &Self,
+        c: &Self,
+        ctx: C,
+        record_id: RecordId,
+    ) -> Result<Self, Error>
+    where
+        C: 'fut,
+    {
+        let ab = self
+            .multiply(b, ctx.narrow(&Multiply3Step::Ab), record_id)
+            .await?;
+        ab.multiply(c, ctx.narrow(&Multiply3Step::Abc), record_id)
+            .await
+    }
+}
+
+#[derive(Step)]
+pub(crate) enum Multiply3Step {
+    Ab,
+    Abc,
+}
+
+/// Trait to square secret shares. Squaring is a special case of multiplication, implementations
+/// that can do it cheaper than [`SecureMul::multiply`] should override [`square`].
+///
+/// [`square`]: SecureSquare::square
+#[async_trait]
+pub trait SecureSquare<C: Context>: SecureMul<C> {
+    /// Square and return the result of `a` * `a`.
+    async fn square<'fut>(&self, ctx: C, record_id: RecordId) -> Result<Self, Error>
+    where
+        C: 'fut,
+    {
+        self.multiply(self, ctx, record_id).await
+    }
+}
+
+/// Trait to compute dot products of vectors of secret shares.
+#[async_trait]
+pub trait SecureDotProduct<C: Context>: Send + Sync + Sized {
+    /// Compute and return `Σ a_i * b_i`. Takes a single round of communication, however long the
+    /// vectors are.
+    ///
+    /// ## Errors
+    /// If communication with peers fails.
+    ///
+    /// ## Panics
+    /// If `a` and `b` have different lengths.
+    async fn dot<'fut>(ctx: C, record_id: RecordId, a: &[Self], b: &[Self]) -> Result<Self, Error>
+    where
+        C: 'fut;
+}
+
+/// Multiplies every pair of shares in `pairs`. Record ids are assigned sequentially, starting from
+/// `start`, and results are returned in the same order as `pairs`. No more than
+/// [`active_work`] multiplications are in flight at any time.
+///
+/// `ctx` must allow sending as many records as there are pairs, starting from `start`.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+///
+/// [`active_work`]: crate::seq_join::SeqJoin::active_work
+pub async fn multiply_all<C, S, I>(ctx: C, pairs: I, start: RecordId) -> Result<Vec<S>, Error>
+where
+    C: Context,
+    S: SecureMul<C>,
+    I: IntoIterator<Item = (S, S)>,
+    I::IntoIter: Send,
+{
+    ctx.try_join(pairs.into_iter().enumerate().map(|(i, (a, b))| {
+        let ctx = ctx.clone();
+        async move { a.multiply(&b, ctx, start + i).await }
+    }))
+    .await
 }
 
 /// looks like clippy disagrees with itself on whether this attribute is useless or not.
-use {malicious::multiply as malicious_mul, semi_honest::multiply as semi_honest_mul};
+use {
+    malicious::{dot_product as malicious_dot_product, multiply as malicious_mul},
+    semi_honest::{
+        dot_product as semi_honest_dot_product, multiply as semi_honest_mul,
+        multiply_vec as semi_honest_mul_vec, square as semi_honest_square,
+    },
+};
 
 /// Implement secure multiplication for semi-honest contexts with replicated secret sharing.
 #[async_trait]
@@ -67,6 +161,50 @@ impl<C: Context, F: Field> SecureMul<C> for Replicated<F> {
     }
 }
 
+/// Implement secure multiplication for semi-honest contexts with vectors of replicated shares.
+/// All lanes are sent to the peer in a single message.
+#[async_trait]
+impl<C: Context, F: Field, N: ArrayLength> SecureMul<C> for ReplicatedVec<F, N>
+where
+    F::Size: Mul<N>,
+    Prod<F::Size, N>: ArrayLength,
+{
+    async fn multiply_sparse<'fut>(
+        &self,
+        rhs: &Self,
+        ctx: C,
+        record_id: RecordId,
+        zeros_at: MultiplyZeroPositions,
+    ) -> Result<Self, Error>
+    where
+        C: 'fut,
+    {
+        semi_honest_mul_vec(ctx, record_id, self, rhs, zeros_at).await
+    }
+}
+
+/// Implement secure squaring for semi-honest contexts with replicated secret sharing.
+#[async_trait]
+impl<C: Context, F: Field> SecureSquare<C> for Replicated<F> {
+    async fn square<'fut>(&self, ctx: C, record_id: RecordId) -> Result<Self, Error>
+    where
+        C: 'fut,
+    {
+        semi_honest_square(ctx, record_id, self).await
+    }
+}
+
+/// Implement dot product for semi-honest contexts with replicated secret sharing.
+#[async_trait]
+impl<C: Context, F: Field> SecureDotProduct<C> for Replicated<F> {
+    async fn dot<'fut>(ctx: C, record_id: RecordId, a: &[Self], b: &[Self]) -> Result<Self, Error>
+    where
+        C: 'fut,
+    {
+        semi_honest_dot_product(ctx, record_id, a, b).await
+    }
+}
+
 /// Implement secure multiplication for malicious contexts with replicated secret sharing.
 #[async_trait]
 impl<'a, F: ExtendableField> SecureMul<UpgradedMaliciousContext<'a, F>> for MaliciousReplicated<F> {
@@ -83,3 +221,523 @@ impl<'a, F: ExtendableField> SecureMul<UpgradedMaliciousContext<'a, F>> for Mali
         malicious_mul(ctx, record_id, self, rhs, zeros_at).await
     }
 }
+
+/// Malicious squaring has to be validated the same way as multiplication is, so it is done by
+/// multiplying the share by itself.
+impl<'a, F: ExtendableField> SecureSquare<UpgradedMaliciousContext<'a, F>>
+    for MaliciousReplicated<F>
+{
+}
+
+/// Implement dot product for malicious contexts with replicated secret sharing.
+#[async_trait]
+impl<'a, F: ExtendableField> SecureDotProduct<UpgradedMaliciousContext<'a, F>>
+    for MaliciousReplicated<F>
+{
+    async fn dot<'fut>(
+        ctx: UpgradedMaliciousContext<'a, F>,
+        record_id: RecordId,
+        a: &[Self],
+        b: &[Self],
+    ) -> Result<Self, Error>
+    where
+        UpgradedMaliciousContext<'a, F>: 'fut,
+    {
+        malicious_dot_product(ctx, record_id, a, b).await
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::zip;
+
+    use rand::distributions::{Distribution, Standard};
+    use typenum::{Unsigned, U1024};
+
+    use super::{multiply_all, SecureDotProduct, SecureMul, SecureSquare};
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime, Serializable},
+        helpers::{GatewayConfig, HelperIdentity, Role},
+        protocol::{context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        secret_sharing::replicated::{
+            malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
+            semi_honest::{AdditiveShare as Replicated, ReplicatedVec},
+        },
+        seq_join::SeqJoin,
+        telemetry::metrics::RECORDS_SENT,
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
+    };
+
+    #[tokio::test]
+    async fn empty() {
+        let world = TestWorld::default();
+
+        let result = world
+            .semi_honest(
+                (
+                    Vec::<Fp31>::new().into_iter(),
+                    Vec::<Fp31>::new().into_iter(),
+                ),
+                |ctx, (a, b)| async move {
+                    multiply_all(ctx.set_total_records(1), zip(a, b), RecordId::FIRST)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await;
+
+        assert!(result.reconstruct().is_empty());
+    }
+
+    /// Batch is many times larger than the number of multiplications allowed to run at once.
+    /// It is split in two to check that record ids continue from `start`.
+    #[tokio::test]
+    async fn large_batch() {
+        const COUNT: usize = 100;
+        const HALF: usize = COUNT / 2;
+        let world = TestWorld::new_with(TestWorldConfig {
+            gateway_config: GatewayConfig::new(4),
+            ..TestWorldConfig::default()
+        });
+        let mut rng = thread_rng();
+        let a = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let b = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let expected = zip(&a, &b).map(|(&a, &b)| a * b).collect::<Vec<_>>();
+
+        let result = world
+            .semi_honest((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(COUNT);
+                let mut pairs = zip(a, b).collect::<Vec<_>>();
+                let second = pairs.split_off(HALF);
+
+                let mut result = multiply_all(ctx.clone(), pairs, RecordId::FIRST)
+                    .await
+                    .unwrap();
+                result.extend(
+                    multiply_all(ctx, second, RecordId::from(HALF))
+                        .await
+                        .unwrap(),
+                );
+
+                result
+            })
+            .await;
+
+        assert_eq!(expected, result.reconstruct());
+    }
+
+    #[tokio::test]
+    async fn malicious() {
+        const COUNT: usize = 10;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let a = (0..COUNT).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let b = (0..COUNT).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let expected = zip(&a, &b).map(|(&a, &b)| a * b).collect::<Vec<_>>();
+
+        let result = world
+            .upgraded_malicious((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                multiply_all(ctx.set_total_records(COUNT), zip(a, b), RecordId::FIRST)
+                    .await
+                    .unwrap()
+            })
+            .await;
+
+        assert_eq!(expected, result.reconstruct());
+    }
+
+    /// Multiplying vectors of 1024 lanes gives the same result as 1024 multiplications of single
+    /// shares, but every helper sends one message instead of 1024.
+    #[tokio::test]
+    async fn vectorized() {
+        type N = U1024;
+        let mut rng = thread_rng();
+        let a = (0..N::USIZE).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let b = (0..N::USIZE).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let expected = zip(&a, &b).map(|(&a, &b)| a * b).collect::<Vec<_>>();
+
+        let scalar_world = TestWorld::new_with(TestWorldConfig::default().enable_metrics());
+        let scalar = scalar_world
+            .semi_honest(
+                (a.clone().into_iter(), b.clone().into_iter()),
+                |ctx, (a, b)| async move {
+                    multiply_all(ctx.set_total_records(N::USIZE), zip(a, b), RecordId::FIRST)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await;
+
+        let vector_world = TestWorld::new_with(TestWorldConfig::default().enable_metrics());
+        let vector = vector_world
+            .semi_honest((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                let a = ReplicatedVec::<_, N>::from_lanes(a);
+                let b = ReplicatedVec::<_, N>::from_lanes(b);
+                a.multiply(&b, ctx.set_total_records(1), RecordId::FIRST)
+                    .await
+                    .unwrap()
+                    .lanes()
+                    .collect::<Vec<_>>()
+            })
+            .await;
+
+        assert_eq!(expected, scalar.reconstruct());
+        assert_eq!(expected, vector.reconstruct());
+
+        // every helper sends to its right peer only
+        for (world, messages) in [(&scalar_world, N::USIZE), (&vector_world, 1)] {
+            let snapshot = world.metrics_snapshot();
+            let sent = snapshot.assert_metric(RECORDS_SENT);
+            for role in Role::all() {
+                sent.per_helper(role, messages);
+            }
+        }
+    }
+
+    async fn square_matches_multiply<F>()
+    where
+        F: Field,
+        Standard: Distribution<F>,
+    {
+        const COUNT: usize = 10;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let a = (0..COUNT).map(|_| rng.gen::<F>()).collect::<Vec<_>>();
+
+        let squares = world
+            .semi_honest(a.clone().into_iter(), |ctx, a| async move {
+                let ctx = ctx.set_total_records(COUNT);
+                ctx.try_join(a.into_iter().enumerate().map(|(i, a)| {
+                    let ctx = ctx.clone();
+                    async move { a.square(ctx, RecordId::from(i)).await }
+                }))
+                .await
+                .unwrap()
+            })
+            .await;
+        let products = world
+            .semi_honest(a.clone().into_iter(), |ctx, a| async move {
+                let pairs = a.into_iter().map(|a| (a.clone(), a)).collect::<Vec<_>>();
+                multiply_all(ctx.set_total_records(COUNT), pairs, RecordId::FIRST)
+                    .await
+                    .unwrap()
+            })
+            .await;
+
+        assert_eq!(products.reconstruct(), squares.reconstruct());
+        assert_eq!(
+            a.iter().map(|&a| a * a).collect::<Vec<_>>(),
+            squares.reconstruct()
+        );
+    }
+
+    #[tokio::test]
+    async fn square() {
+        square_matches_multiply::<Fp31>().await;
+        square_matches_multiply::<Fp32BitPrime>().await;
+    }
+
+    #[tokio::test]
+    async fn square_malicious() {
+        const COUNT: usize = 10;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let a = (0..COUNT).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let expected = a.iter().map(|&a| a * a).collect::<Vec<_>>();
+
+        let result = world
+            .upgraded_malicious(a.into_iter(), |ctx, a| async move {
+                let ctx = ctx.set_total_records(COUNT);
+                ctx.try_join(a.into_iter().enumerate().map(|(i, a)| {
+                    let ctx = ctx.clone();
+                    async move { a.square(ctx, RecordId::from(i)).await }
+                }))
+                .await
+                .unwrap()
+            })
+            .await;
+
+        assert_eq!(expected, result.reconstruct());
+    }
+
+    /// Squaring takes the same single round as multiplication: every helper sends one share per
+    /// record to exactly one peer.
+    #[tokio::test]
+    async fn square_traffic() {
+        const COUNT: usize = 10;
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let mut rng = thread_rng();
+        let a = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+
+        world
+            .semi_honest(a.into_iter(), |ctx, a| async move {
+                let ctx = ctx.narrow("square").set_total_records(COUNT);
+                ctx.try_join(a.into_iter().enumerate().map(|(i, a)| {
+                    let ctx = ctx.clone();
+                    async move { a.square(ctx, RecordId::from(i)).await }
+                }))
+                .await
+                .unwrap()
+            })
+            .await;
+
+        for helper in HelperIdentity::make_three() {
+            assert_eq!(
+                COUNT * <Fp32BitPrime as Serializable>::Size::USIZE,
+                recorder.bytes_sent_by(helper, "square")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn dot_product() {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+
+        for len in [0, 1, 1000] {
+            let a = (0..len)
+                .map(|_| rng.gen::<Fp32BitPrime>())
+                .collect::<Vec<_>>();
+            let b = (0..len)
+                .map(|_| rng.gen::<Fp32BitPrime>())
+                .collect::<Vec<_>>();
+            let expected = zip(&a, &b).map(|(&a, &b)| a * b).sum::<Fp32BitPrime>();
+
+            let [dot, naive] = world
+                .semi_honest((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                    let (a, b) = (
+                        a.into_iter().collect::<Vec<_>>(),
+                        b.into_iter().collect::<Vec<_>>(),
+                    );
+                    let dot = Replicated::dot(
+                        ctx.narrow("dot").set_total_records(1),
+                        RecordId::FIRST,
+                        &a,
+                        &b,
+                    )
+                    .await
+                    .unwrap();
+                    let naive = multiply_all(
+                        ctx.narrow("naive").set_total_records(len),
+                        zip(a, b),
+                        RecordId::FIRST,
+                    )
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .fold(Replicated::ZERO, |acc, x| acc + x);
+
+                    vec![dot, naive]
+                })
+                .await
+                .reconstruct()
+                .try_into()
+                .unwrap();
+
+            assert_eq!(expected, dot, "dot product of {len} elements");
+            assert_eq!(naive, dot, "dot product of {len} elements");
+        }
+    }
+
+    /// Compares the dot product against multiplying every pair on its own and summing the
+    /// products. The dot product is a single round that sends one value and its counterpart
+    /// multiplied by `r` to one peer, however long the vectors are.
+    #[tokio::test]
+    async fn dot_product_malicious() {
+        type F = Fp32BitPrime;
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let mut rng = thread_rng();
+        let sent_per_helper = <F as Serializable>::Size::USIZE
+            + <<F as ExtendableField>::ExtendedField as Serializable>::Size::USIZE;
+
+        for len in [0, 1, 64] {
+            let a = (0..len).map(|_| rng.gen::<F>()).collect::<Vec<_>>();
+            let b = (0..len).map(|_| rng.gen::<F>()).collect::<Vec<_>>();
+            let expected = zip(&a, &b).map(|(&a, &b)| a * b).sum::<F>();
+
+            let [dot, naive]: [F; 2] = world
+                .upgraded_malicious((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                    let (a, b) = (
+                        a.into_iter().collect::<Vec<_>>(),
+                        b.into_iter().collect::<Vec<_>>(),
+                    );
+                    let dot = SecureDotProduct::dot(
+                        ctx.narrow(format!("dot_{len}").as_str())
+                            .set_total_records(1),
+                        RecordId::FIRST,
+                        &a,
+                        &b,
+                    )
+                    .await
+                    .unwrap();
+                    let naive_ctx = ctx.narrow("naive").set_total_records(len);
+                    let naive =
+                        naive_ctx
+                            .try_join(zip(&a, &b).enumerate().map(|(i, (a, b))| {
+                                a.multiply(b, naive_ctx.clone(), RecordId::from(i))
+                            }))
+                            .await
+                            .unwrap()
+                            .into_iter()
+                            .fold(MaliciousReplicated::ZERO, |acc, x| acc + x);
+
+                    vec![dot, naive]
+                })
+                .await
+                .reconstruct()
+                .try_into()
+                .unwrap();
+
+            assert_eq!(expected, dot, "dot product of {len} elements");
+            assert_eq!(naive, dot, "dot product of {len} elements");
+
+            let step = format!("dot_{len}");
+            for helper in HelperIdentity::make_three() {
+                assert_eq!(
+                    sent_per_helper,
+                    recorder.bytes_sent_by(helper, &step),
+                    "dot product of {len} elements"
+                );
+            }
+        }
+    }
+
+    /// However long the vectors are, every helper sends a single field element to one peer.
+    #[tokio::test]
+    async fn dot_product_traffic() {
+        const LEN: usize = 100;
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let mut rng = thread_rng();
+        let a = (0..LEN)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let b = (0..LEN)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let expected = zip(&a, &b).map(|(&a, &b)| a * b).sum::<Fp32BitPrime>();
+
+        let result = world
+            .semi_honest((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                let (a, b) = (
+                    a.into_iter().collect::<Vec<_>>(),
+                    b.into_iter().collect::<Vec<_>>(),
+                );
+                Replicated::dot(ctx.set_total_records(1), RecordId::FIRST, &a, &b)
+                    .await
+                    .unwrap()
+            })
+            .await;
+        assert_eq!(expected, result.reconstruct());
+
+        for helper in HelperIdentity::make_three() {
+            assert_eq!(
+                <Fp32BitPrime as Serializable>::Size::USIZE,
+                recorder.bytes_sent_by(helper, "protocol")
+            );
+        }
+    }
+
+    async fn multiply3_matches_sequential<F>()
+    where
+        F: Field,
+        Standard: Distribution<F>,
+    {
+        const COUNT: usize = 50;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let input = (0..COUNT)
+            .map(|_| (rng.gen::<F>(), (rng.gen::<F>(), rng.gen::<F>())))
+            .collect::<Vec<_>>();
+        let expected = input
+            .iter()
+            .map(|&(a, (b, c))| a * b * c)
+            .collect::<Vec<_>>();
+
+        let (product, sequential) = world
+            .semi_honest(input.into_iter(), |ctx, input| async move {
+                let ctx = ctx.set_total_records(COUNT);
+                let product = ctx
+                    .try_join(input.iter().enumerate().map(|(i, (a, (b, c)))| {
+                        a.multiply3(b, c, ctx.narrow("multiply3"), RecordId::from(i))
+                    }))
+                    .await
+                    .unwrap();
+                let sequential = ctx
+                    .try_join(input.iter().enumerate().map(|(i, (a, (b, c)))| {
+                        let ctx = ctx.narrow("sequential");
+                        async move {
+                            let ab = a.multiply(b, ctx.narrow("ab"), RecordId::from(i)).await?;
+                            ab.multiply(c, ctx.narrow("abc"), RecordId::from(i)).await
+                        }
+                    }))
+                    .await
+                    .unwrap();
+
+                (product, sequential)
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(sequential, product);
+        assert_eq!(expected, product);
+    }
+
+    #[tokio::test]
+    async fn multiply3() {
+        multiply3_matches_sequential::<Fp31>().await;
+        multiply3_matches_sequential::<Fp32BitPrime>().await;
+    }
+
+    #[tokio::test]
+    async fn multiply3_malicious() {
+        const COUNT: usize = 10;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let input = (0..COUNT)
+            .map(|_| (rng.gen::<Fp31>(), (rng.gen::<Fp31>(), rng.gen::<Fp31>())))
+            .collect::<Vec<_>>();
+        let expected = input
+            .iter()
+            .map(|&(a, (b, c))| a * b * c)
+            .collect::<Vec<_>>();
+
+        // Columns are shared separately, records of tuples can't be upgraded.
+        let (a, bc): (Vec<_>, Vec<_>) = input.into_iter().unzip();
+        let (b, c): (Vec<_>, Vec<_>) = bc.into_iter().unzip();
+        let result = world
+            .upgraded_malicious(
+                (a.into_iter(), (b.into_iter(), c.into_iter())),
+                |ctx, (a, (b, c))| async move {
+                    let ctx = ctx.set_total_records(COUNT);
+                    ctx.try_join(zip(a, zip(b, c)).enumerate().map(|(i, (a, (b, c)))| {
+                        let ctx = ctx.clone();
+                        async move { a.multiply3(&b, &c, ctx, RecordId::from(i)).await }
+                    }))
+                    .await
+                    .unwrap()
+                },
+            )
+            .await;
+
+        assert_eq!(expected, result.reconstruct());
+    }
+}
@@ -1,3 +1,8 @@
+use std::{iter::zip, ops::Mul};
+
+use generic_array::{ArrayLength, GenericArray};
+use typenum::Prod;
+
 use crate::{
     error::Error,
     ff::Field,
@@ -9,7 +14,8 @@ use crate::{
         RecordId,
     },
     secret_sharing::replicated::{
-        semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing,
+        semi_honest::{AdditiveShare as Replicated, ReplicatedVec},
+        ReplicatedSecretSharing,
     },
 };
 
@@ -81,18 +87,176 @@ where
     Ok(Replicated::new(lhs, rhs))
 }
 
+/// Squares `a`. Same as multiplying `a` by itself, but the two cross terms of the product are
+/// equal, so each helper computes one of them and doubles it.
+///
+/// ## Errors
+/// If sending the value to the right helper or receiving one from the left helper fails.
+pub async fn square<C, F>(
+    ctx: C,
+    record_id: RecordId,
+    a: &Replicated<F>,
+) -> Result<Replicated<F>, Error>
+where
+    C: Context,
+    F: Field,
+{
+    let role = ctx.role();
+    let (s0, s1) = ctx.prss().generate_fields(record_id);
+
+    let cross = a.left() * a.right();
+    let right_d = cross + cross - s0;
+    ctx.send_channel(role.peer(Direction::Right))
+        .send(record_id, right_d)
+        .await?;
+    let rhs = a.right() * a.right() + right_d + s1;
+
+    let left_d: F = ctx
+        .recv_channel(role.peer(Direction::Left))
+        .receive(record_id)
+        .await?;
+    let lhs = a.left() * a.left() + left_d + s0;
+
+    Ok(Replicated::new(lhs, rhs))
+}
+
+/// Computes `Σ a_i * b_i`. Every helper sums the cross terms of all products locally and sends
+/// the sum to the right helper, so it takes one resharing no matter how long the vectors are.
+///
+/// ## Errors
+/// If sending the value to the right helper or receiving one from the left helper fails.
+///
+/// ## Panics
+/// If `a` and `b` have different lengths.
+pub async fn dot_product<C, F>(
+    ctx: C,
+    record_id: RecordId,
+    a: &[Replicated<F>],
+    b: &[Replicated<F>],
+) -> Result<Replicated<F>, Error>
+where
+    C: Context,
+    F: Field,
+{
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+    let role = ctx.role();
+    let (s0, s1) = ctx.prss().generate_fields(record_id);
+
+    let (mut lhs, mut rhs, mut cross) = (F::ZERO, F::ZERO, F::ZERO);
+    for (a, b) in zip(a, b) {
+        lhs += a.left() * b.left();
+        rhs += a.right() * b.right();
+        cross += a.left() * b.right() + a.right() * b.left();
+    }
+
+    let right_d = cross - s0;
+    ctx.send_channel(role.peer(Direction::Right))
+        .send(record_id, right_d)
+        .await?;
+    rhs += right_d + s1;
+
+    let left_d: F = ctx
+        .recv_channel(role.peer(Direction::Left))
+        .receive(record_id)
+        .await?;
+    lhs += left_d + s0;
+
+    Ok(Replicated::new(lhs, rhs))
+}
+
+/// Multiplies every lane of `a` by the same lane of `b`. This is [`multiply`] done for all lanes
+/// at once, except that helpers send the values for all of them in a single message. `zeros`
+/// applies to every lane.
+///
+/// Every lane needs its own randomness, so lane `i` of record `r` uses PRSS index `r · N + i`.
+///
+/// ## Errors
+/// If sending the values to the right helper or receiving them from the left helper fails.
+pub async fn multiply_vec<C, F, N>(
+    ctx: C,
+    record_id: RecordId,
+    a: &ReplicatedVec<F, N>,
+    b: &ReplicatedVec<F, N>,
+    zeros: MultiplyZeroPositions,
+) -> Result<ReplicatedVec<F, N>, Error>
+where
+    C: Context,
+    F: Field,
+    N: ArrayLength,
+    F::Size: Mul<N>,
+    Prod<F::Size, N>: ArrayLength,
+{
+    let role = ctx.role();
+    let [need_to_recv, need_to_send, need_random_right] = zeros.work_for(role);
+    for (a, b) in zip(a.lanes(), b.lanes()) {
+        zeros.0.check(role, "a", &a);
+        zeros.1.check(role, "b", &b);
+    }
+
+    let prss = ctx.prss();
+    let width = u128::from(N::U64);
+    let (s0, s1): (Vec<F>, Vec<F>) = (0..width)
+        .map(|lane| prss.generate_fields::<F, _>(u128::from(record_id) * width + lane))
+        .unzip();
+
+    let mut lhs = zip(a.left(), b.left())
+        .map(|(&a, &b)| a * b)
+        .collect::<GenericArray<F, N>>();
+    let mut rhs = zip(a.right(), b.right())
+        .map(|(&a, &b)| a * b)
+        .collect::<GenericArray<F, N>>();
+    if need_to_send {
+        let right_d = (0..N::USIZE)
+            .map(|i| a.left()[i] * b.right()[i] + a.right()[i] * b.left()[i] - s0[i])
+            .collect::<GenericArray<F, N>>();
+        add_assign(&mut rhs, &right_d);
+
+        ctx.send_channel(role.peer(Direction::Right))
+            .send(record_id, right_d)
+            .await?;
+    }
+    if need_random_right {
+        add_assign(&mut rhs, &s1);
+    }
+
+    if need_to_recv {
+        let left_d: GenericArray<F, N> = ctx
+            .recv_channel(role.peer(Direction::Left))
+            .receive(record_id)
+            .await?;
+        add_assign(&mut lhs, &left_d);
+    }
+    if need_to_send {
+        add_assign(&mut lhs, &s0);
+    }
+
+    Ok(ReplicatedVec::new(lhs, rhs))
+}
+
+fn add_assign<F: Field>(acc: &mut [F], values: &[F]) {
+    for (acc, &v) in zip(acc, values) {
+        *acc += v;
+    }
+}
+
 #[cfg(all(test, unit_test))]
 mod test {
-    use std::iter::{repeat, zip};
+    use std::{
+        iter::{repeat, zip},
+        time::Duration,
+    };
 
     use rand::distributions::{Distribution, Standard};
+    use tokio::time::Instant;
+    use typenum::Unsigned;
 
     use crate::{
-        ff::{Field, Fp31},
+        ff::{Field, Fp31, Serializable},
+        helpers::{HelperIdentity, NetworkConfig},
         protocol::{basics::SecureMul, context::Context, RecordId},
         rand::{thread_rng, Rng},
         seq_join::SeqJoin,
-        test_fixture::{Reconstruct, Runner, TestWorld},
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
     };
 
     #[tokio::test]
@@ -127,6 +291,81 @@ mod test {
         assert_eq!(a * b, res.reconstruct());
     }
 
+    #[tokio::test(start_paused = true)]
+    async fn latency_is_paid_once_per_round() {
+        const LATENCY: Duration = Duration::from_millis(50);
+        let world = TestWorld::new_with(TestWorldConfig {
+            network_config: NetworkConfig {
+                latency: LATENCY,
+                ..NetworkConfig::default()
+            },
+            ..TestWorldConfig::default()
+        });
+
+        let mut rng = thread_rng();
+        let (a, b, c) = (rng.gen::<Fp31>(), rng.gen::<Fp31>(), rng.gen::<Fp31>());
+
+        // two multiplications that depend on each other take two rounds.
+        let start = Instant::now();
+        let res = world
+            .semi_honest((a, (b, c)), |ctx, (a, (b, c))| async move {
+                let ctx = ctx.set_total_records(1);
+                let ab = a
+                    .multiply(&b, ctx.narrow("ab"), RecordId::from(0))
+                    .await
+                    .unwrap();
+                ab.multiply(&c, ctx.narrow("abc"), RecordId::from(0))
+                    .await
+                    .unwrap()
+            })
+            .await;
+        let elapsed = start.elapsed();
+
+        assert_eq!(a * b * c, res.reconstruct());
+        assert!(
+            (2 * LATENCY..3 * LATENCY).contains(&elapsed),
+            "expected two rounds of {LATENCY:?} each, took {elapsed:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn traffic_matches_one_round() {
+        const COUNT: usize = 10;
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+
+        let mut rng = thread_rng();
+        let a = (0..COUNT).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let b = (0..COUNT).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let expected: Vec<_> = zip(a.iter(), b.iter()).map(|(&a, &b)| a * b).collect();
+        let results = world
+            .semi_honest(
+                (a.into_iter(), b.into_iter()),
+                |ctx, (a_shares, b_shares)| async move {
+                    let ctx = ctx.narrow("traffic").set_total_records(COUNT);
+                    ctx.try_join(zip(a_shares, b_shares).enumerate().map(|(i, (a, b))| {
+                        let ctx = ctx.clone();
+                        async move { a.multiply(&b, ctx, RecordId::from(i)).await }
+                    }))
+                    .await
+                    .unwrap()
+                },
+            )
+            .await;
+        assert_eq!(expected, results.reconstruct());
+
+        // In one round, every helper sends one share per multiplication to exactly one peer.
+        for helper in HelperIdentity::make_three() {
+            assert_eq!(
+                COUNT * <Fp31 as Serializable>::Size::USIZE,
+                recorder.bytes_sent_by(helper, "traffic")
+            );
+        }
+    }
+
     /// This test ensures that many secure multiplications can run concurrently as long as
     /// they all have unique id associated with it. Basically it validates
     /// `TestHelper`'s ability to distinguish messages of the same type sent towards helpers
@@ -191,12 +191,13 @@ pub(in crate::protocol) mod test {
 
     use futures::future::try_join;
     use rand::distributions::{Distribution, Standard};
+    use typenum::Unsigned;
 
     use crate::{
-        ff::{Field, Fp31, Fp32BitPrime},
+        ff::{Field, Fp31, Fp32BitPrime, Serializable},
         helpers::{
             Direction::{Left, Right},
-            Role,
+            HelperIdentity, Role,
         },
         protocol::{
             basics::{mul::sparse::MultiplyWork, MultiplyZeroPositions, SecureMul, ZeroPositions},
@@ -209,7 +210,7 @@ pub(in crate::protocol) mod test {
             replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
             IntoShares,
         },
-        test_fixture::{Reconstruct, Runner, TestWorld},
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
     };
 
     #[derive(Clone, Copy)]
@@ -435,4 +436,89 @@ pub(in crate::protocol) mod test {
             }
         }
     }
+
+    /// Helpers send a value only if the zero positions leave them something to send. Every helper
+    /// sends when neither input has zeros, and at least one of them doesn't for the sparse inputs
+    /// protocols use.
+    #[tokio::test]
+    async fn sparse_traffic() {
+        let mut rng = thread_rng();
+
+        for &a in ZeroPositions::all() {
+            for &b in ZeroPositions::all() {
+                if ZeroPositions::is_pointless((a, b)) {
+                    continue;
+                }
+
+                let recorder = TrafficRecorder::default();
+                let world = TestWorld::new_with(TestWorldConfig {
+                    send_observer: Some(recorder.observer()),
+                    ..TestWorldConfig::default()
+                });
+                let v1 = SparseField::new(rng.gen::<Fp31>(), a);
+                let v2 = SparseField::new(rng.gen::<Fp31>(), b);
+                let result = world
+                    .semi_honest((v1, v2), |ctx, (v_a, v_b)| async move {
+                        v_a.multiply_sparse(&v_b, ctx.set_total_records(1), RecordId::FIRST, (a, b))
+                            .await
+                            .unwrap()
+                    })
+                    .await;
+                assert_eq!(v1.value() * v2.value(), result.reconstruct());
+
+                let traffic = recorder.with_step_prefix("protocol");
+                let mut senders = 0;
+                for (helper, &role) in zip(HelperIdentity::make_three(), Role::all()) {
+                    let [_, need_to_send, _] = (a, b).work_for(role);
+                    let sent = traffic
+                        .iter()
+                        .filter(|record| record.from == helper)
+                        .map(|record| record.data.len())
+                        .sum::<usize>();
+                    assert_eq!(
+                        usize::from(need_to_send) * <Fp31 as Serializable>::Size::USIZE,
+                        sent,
+                        "{role:?} multiplying {a:?} by {b:?}"
+                    );
+                    senders += usize::from(need_to_send);
+                }
+
+                if (a, b) == ZeroPositions::NONE {
+                    assert_eq!(3, senders);
+                } else if [
+                    ZeroPositions::AVVV_BZZV,
+                    ZeroPositions::AVZZ_BZVZ,
+                    ZeroPositions::AVVZ_BZZV,
+                ]
+                .contains(&(a, b))
+                {
+                    assert!(senders < 3, "{a:?} by {b:?} is not any cheaper");
+                }
+            }
+        }
+    }
+
+    /// Zeros that aren't there are caught before anything is sent.
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    #[should_panic(expected = "expected a zero")]
+    async fn claimed_zero_is_not_zero() {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let v1 = rng.gen::<Fp32BitPrime>();
+        let v2 = rng.gen::<Fp32BitPrime>();
+
+        world
+            .semi_honest((v1, v2), |ctx, (v_a, v_b)| async move {
+                v_a.multiply_sparse(
+                    &v_b,
+                    ctx.set_total_records(1),
+                    RecordId::FIRST,
+                    ZeroPositions::AVVV_BZZV,
+                )
+                .await
+                .unwrap()
+            })
+            .await;
+    }
 }
@@ -0,0 +1,168 @@
+use futures::future::try_join;
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{context::Context, step::BitOpStep, BasicProtocols, RecordId},
+};
+
+/// Not a part of any protocol yet, so it is not in `steps.txt`.
+#[derive(Step)]
+pub(crate) enum Step {
+    Square,
+    Multiply,
+}
+
+/// Computes `[base^exponent]` for an exponent that all helpers know.
+///
+/// This is square-and-multiply, going from the lowest bit of `exponent` to the highest one. For
+/// every bit `i`, `base^(2^i)` is multiplied into the result if the bit is set, and squared to get
+/// `base^(2^(i+1))` unless it is the highest one. Both multiplications only depend on the previous
+/// bit, so they run in parallel and every bit takes one round. Bit `i` runs on
+/// `BitOpStep::from(i)`. Exponent `e` takes `floor(log2(e))` squarings and one multiplication
+/// fewer than there are bits set in it, so at most `2 · log2(e)` multiplications.
+///
+/// Exponent `0` gives a share of one and exponent `1` gives `base`, neither of them talks to peers.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+pub async fn pow<F, C, S>(ctx: C, record_id: RecordId, base: &S, exponent: u32) -> Result<S, Error>
+where
+    F: Field,
+    C: Context,
+    S: BasicProtocols<C, F>,
+{
+    let mut result = S::share_known_value(&ctx, F::ONE);
+    let mut result_is_one = true;
+    let mut power = base.clone();
+
+    let bits = u32::BITS - exponent.leading_zeros();
+    for i in 0..bits {
+        let ctx = ctx.narrow(&BitOpStep::from(i));
+        let (product, square) = try_join(
+            async {
+                if (exponent >> i) & 1 == 0 {
+                    Ok(None)
+                } else if result_is_one {
+                    Ok(Some(power.clone()))
+                } else {
+                    result
+                        .multiply(&power, ctx.narrow(&Step::Multiply), record_id)
+                        .await
+                        .map(Some)
+                }
+            },
+            async {
+                if i + 1 == bits {
+                    Ok(None)
+                } else {
+                    power
+                        .multiply(&power, ctx.narrow(&Step::Square), record_id)
+                        .await
+                        .map(Some)
+                }
+            },
+        )
+        .await?;
+
+        if let Some(product) = product {
+            result = product;
+            result_is_one = false;
+        }
+        if let Some(square) = square {
+            power = square;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::distributions::{Distribution, Standard};
+
+    use super::pow;
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime},
+        protocol::{context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        secret_sharing::replicated::malicious::ExtendableField,
+        telemetry::metrics::RECORDS_SENT,
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig},
+    };
+
+    const EXPONENTS: [u32; 9] = [0, 1, 2, 3, 7, 8, 31, 100, 1000];
+
+    fn local_pow<F: Field>(base: F, exponent: u32) -> F {
+        (0..exponent).fold(F::ONE, |acc, _| acc * base)
+    }
+
+    async fn matches_local<F: ExtendableField>()
+    where
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        let base = thread_rng().gen::<F>();
+
+        for exponent in EXPONENTS {
+            let result = world
+                .semi_honest(base, |ctx, base| async move {
+                    pow(ctx.set_total_records(1), RecordId::FIRST, &base, exponent)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+            let m_result = world
+                .upgraded_malicious(base, |ctx, base| async move {
+                    pow(ctx.set_total_records(1), RecordId::FIRST, &base, exponent)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+
+            let expected = local_pow(base, exponent);
+            assert_eq!(expected, result, "{base:?}^{exponent}");
+            assert_eq!(expected, m_result, "{base:?}^{exponent}");
+        }
+    }
+
+    #[tokio::test]
+    async fn fp31() {
+        matches_local::<Fp31>().await;
+    }
+
+    #[tokio::test]
+    async fn fp32_bit_prime() {
+        matches_local::<Fp32BitPrime>().await;
+    }
+
+    /// Every multiplication makes every helper send one record.
+    #[tokio::test]
+    async fn multiplications() {
+        for exponent in EXPONENTS {
+            let world = TestWorld::new_with(TestWorldConfig::default().enable_metrics());
+            world
+                .semi_honest(Fp31::ONE, |ctx, base| async move {
+                    pow(ctx.set_total_records(1), RecordId::FIRST, &base, exponent)
+                        .await
+                        .unwrap()
+                })
+                .await;
+
+            let (squarings, products) = match exponent {
+                0 => (0, 0),
+                e => (u32::BITS - 1 - e.leading_zeros(), e.count_ones() - 1),
+            };
+            assert!(products <= squarings);
+            let expected = squarings + products;
+            assert_eq!(
+                3 * u64::from(expected),
+                world.metrics_snapshot().get_counter(RECORDS_SENT),
+                "multiplications for exponent {exponent}"
+            );
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{
+        attribution::do_the_binary_tree_thing,
+        basics::{SecureMul, ShareKnownValue},
+        context::Context,
+    },
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+/// Computes inclusive prefix sums of `shares`, i.e. `i`-th output is the sum of `shares[0..=i]`.
+///
+/// Addition is linear, so this protocol is communication-free: every helper sums up the shares it
+/// holds locally.
+#[must_use]
+pub fn prefix_sum<F, S>(shares: &[S]) -> Vec<S>
+where
+    F: Field,
+    S: LinearSecretSharing<F>,
+{
+    shares
+        .iter()
+        .scan(S::ZERO, |acc, share| {
+            *acc += share;
+            Some(acc.clone())
+        })
+        .collect()
+}
+
+/// Same as [`prefix_sum`], but the sum restarts at every row where `boundaries` has a share of `1`,
+/// so every segment of `shares` gets its own prefix sums. Boundary bit of the first row is ignored,
+/// because it always starts a new segment.
+///
+/// Unlike [`prefix_sum`], this one needs to multiply, using `O(log N)` rounds of communication
+/// where `N` is the number of rows.
+///
+/// ## Errors
+/// Fails if the multiplication protocol fails.
+///
+/// ## Panics
+/// If `shares` and `boundaries` have different lengths.
+pub async fn segmented_prefix_sum<F, C, S>(
+    ctx: C,
+    shares: &[S],
+    boundaries: &[S],
+) -> Result<Vec<S>, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + SecureMul<C> + ShareKnownValue<C, F>,
+{
+    assert_eq!(shares.len(), boundaries.len());
+
+    // Prefix sums are suffix sums of the reversed input, which is what attribution computes when
+    // it accumulates credits. In reversed order, every row picks up the one that follows it,
+    // unless that would cross the segment boundary.
+    let one = S::share_known_value(&ctx, F::ONE);
+    let continues = boundaries
+        .iter()
+        .rev()
+        .map(|boundary| one.clone() - boundary)
+        .collect::<Vec<_>>();
+    let mut sums = shares.iter().rev().cloned().collect::<Vec<_>>();
+
+    do_the_binary_tree_thing(ctx, continues, &mut sums).await?;
+    sums.reverse();
+
+    Ok(sums)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::zip;
+
+    use super::{prefix_sum, segmented_prefix_sum};
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime},
+        rand::{thread_rng, Rng},
+        secret_sharing::SharedValue,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    fn fp31(values: &[u8]) -> Vec<Fp31> {
+        values.iter().copied().map(Fp31::truncate_from).collect()
+    }
+
+    #[tokio::test]
+    async fn prefix_sum_matches_plaintext() {
+        let input = fp31(&[3, 0, 7, 1, 12, 30, 5]);
+        let expected = input
+            .iter()
+            .scan(Fp31::ZERO, |acc, &v| {
+                *acc += v;
+                Some(*acc)
+            })
+            .collect::<Vec<_>>();
+
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(input.into_iter(), |_ctx, shares| async move {
+                prefix_sum(&shares)
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(expected, result);
+    }
+
+    #[tokio::test]
+    async fn segmented_prefix_sum_matches_plaintext() {
+        let values = fp31(&[3, 0, 7, 1, 12, 30, 5, 4, 9]);
+        let boundaries = fp31(&[1, 0, 0, 1, 1, 0, 1, 0, 0]);
+        let expected = zip(&values, &boundaries)
+            .scan(Fp31::ZERO, |acc, (&v, &boundary)| {
+                *acc = if boundary == Fp31::ONE { v } else { *acc + v };
+                Some(*acc)
+            })
+            .collect::<Vec<_>>();
+
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(
+                (values.into_iter(), boundaries.into_iter()),
+                |ctx, (shares, boundaries)| async move {
+                    segmented_prefix_sum(ctx, &shares, &boundaries)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct();
+
+        assert_eq!(expected, result);
+    }
+
+    #[tokio::test]
+    async fn segmented_prefix_sum_random_boundaries() {
+        const COUNT: usize = 50;
+        let mut rng = thread_rng();
+        let values = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let boundaries = (0..COUNT)
+            .map(|_| Fp32BitPrime::truncate_from(rng.gen_bool(0.2)))
+            .collect::<Vec<_>>();
+        let expected = zip(&values, &boundaries)
+            .scan(Fp32BitPrime::ZERO, |acc, (&v, &boundary)| {
+                *acc = if boundary == Fp32BitPrime::ONE {
+                    v
+                } else {
+                    *acc + v
+                };
+                Some(*acc)
+            })
+            .collect::<Vec<_>>();
+
+        let world = TestWorld::default();
+        let input = (values.into_iter(), boundaries.into_iter());
+        let result = world
+            .semi_honest(input.clone(), |ctx, (shares, boundaries)| async move {
+                segmented_prefix_sum(ctx, &shares, &boundaries)
+                    .await
+                    .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result = world
+            .upgraded_malicious(input, |ctx, (shares, boundaries)| async move {
+                segmented_prefix_sum(ctx, &shares, &boundaries)
+                    .await
+                    .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(expected, result);
+        assert_eq!(expected, m_result);
+    }
+}
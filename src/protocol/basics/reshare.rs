@@ -53,6 +53,28 @@ pub trait Reshare<C: Context, B: RecordBinding>: Sized {
         C: 'fut;
 }
 
+/// Renews shares of `share` toward `to_helper`: all helpers end up with a fresh random sharing of
+/// the same secret, and `to_helper`'s new shares come from PRSS alone, so it learns nothing about
+/// the value it held before. See [`Reshare`] for the protocol.
+///
+/// The malicious version reshares both the value and its MAC and adds the result to the validator
+/// of `ctx`.
+///
+/// ## Errors
+/// If communication with the peer helper fails.
+pub async fn reshare<C, S>(
+    ctx: C,
+    record_id: RecordId,
+    share: &S,
+    to_helper: Role,
+) -> Result<S, Error>
+where
+    C: Context,
+    S: Reshare<C, RecordId>,
+{
+    share.reshare(ctx, record_id, to_helper).await
+}
+
 #[async_trait]
 /// Reshare(i, \[x\])
 /// This implements semi-honest reshare algorithm of "Efficient Secure Three-Party Sorting Protocol with an Honest Majority" at communication cost of 2R.
@@ -166,6 +188,29 @@ where
     }
 }
 
+/// Reshares every element of a row on a step of its own, like [`BitDecomposed`] does.
+#[async_trait]
+impl<S, C: Context> Reshare<C, RecordId> for Vec<S>
+where
+    S: Reshare<C, RecordId> + Send + Sync,
+{
+    async fn reshare<'fut>(
+        &self,
+        ctx: C,
+        record_binding: RecordId,
+        to_helper: Role,
+    ) -> Result<Vec<S>, Error>
+    where
+        C: 'fut,
+    {
+        ctx.parallel_join(self.iter().enumerate().map(|(i, x)| {
+            let c = ctx.narrow(&InnerVectorElementStep::from(i));
+            async move { x.reshare(c, record_binding, to_helper).await }
+        }))
+        .await
+    }
+}
+
 #[async_trait]
 impl<S, C: Context> Reshare<C, NoRecord> for Vec<S>
 where
@@ -196,7 +241,12 @@ mod tests {
         use crate::{
             ff::Fp32BitPrime,
             helpers::Role,
-            protocol::{basics::Reshare, context::Context, prss::SharedRandomness, RecordId},
+            protocol::{
+                basics::{reshare, Reshare},
+                context::Context,
+                prss::SharedRandomness,
+                RecordId,
+            },
             rand::{thread_rng, Rng},
             test_fixture::{Reconstruct, Runner, TestWorld},
         };
@@ -232,6 +282,36 @@ mod tests {
             }
         }
 
+        /// Every helper, including the one reshare is done toward, must get shares that are
+        /// different from the ones it had.
+        #[tokio::test]
+        async fn rerandomizes() {
+            let world = TestWorld::default();
+
+            for &role in Role::all() {
+                let secret = thread_rng().gen::<Fp32BitPrime>();
+                let result = world
+                    .semi_honest(secret, |ctx, share| async move {
+                        let new_share =
+                            reshare(ctx.set_total_records(1), RecordId::FIRST, &share, role)
+                                .await
+                                .unwrap();
+                        (share, new_share)
+                    })
+                    .await;
+
+                for (share, new_share) in &result {
+                    // Equal with probability 1/|F|^2, which is negligible for this field.
+                    assert_ne!(share, new_share, "reshare to {role:?}");
+                }
+                let (_, new_shares): (Vec<_>, Vec<_>) = result.into_iter().unzip();
+                assert_eq!(
+                    secret,
+                    <[_; 3]>::try_from(new_shares).unwrap().reconstruct()
+                );
+            }
+        }
+
         /// This test validates the correctness of the protocol, relying on `generates_unique_shares`
         /// to ensure security. It does not verify that helpers actually attempt to generate new shares
         /// so a naive implementation of reshare that just output shares `[O]` = `[I]` where `[I]` is
@@ -261,7 +341,7 @@ mod tests {
         use rand::{distributions::Standard, prelude::Distribution};
 
         use crate::{
-            error::Error,
+            error::{Error, MaliciousError},
             ff::{Field, Fp32BitPrime, Gf2, Gf32Bit},
             helpers::{Direction, Role},
             protocol::{
@@ -447,7 +527,10 @@ mod tests {
                             match v.validate(m_reshared_a).await {
                                 Ok(result) => panic!("Got a result {result:?}"),
                                 Err(err) => {
-                                    assert!(matches!(err, Error::MaliciousSecurityCheckFailed));
+                                    assert!(matches!(
+                                        err,
+                                        Error::Malicious(MaliciousError::ValidationFailed)
+                                    ));
                                 }
                             }
                         })
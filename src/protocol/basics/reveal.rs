@@ -5,9 +5,9 @@ use embed_doc_image::embed_doc_image;
 use futures::future::try_join;
 
 use crate::{
-    error::Error,
+    error::{Error, MaliciousError},
     ff::Field,
-    helpers::Direction,
+    helpers::{Direction, Role},
     protocol::{
         context::{Context, UpgradedMaliciousContext},
         sort::generate_permutation::ShuffledPermutationWrapper,
@@ -112,7 +112,128 @@ impl<'a, F: ExtendableField> Reveal<UpgradedMaliciousContext<'a, F>, RecordId>
         if share_from_left == share_from_right {
             Ok(left + right + share_from_left)
         } else {
-            Err(Error::MaliciousRevealFailed)
+            Err(Error::Malicious(MaliciousError::RevealFailed {
+                record_id,
+                step: ctx.gate().clone(),
+            }))
+        }
+    }
+}
+
+/// Trait for reveal protocol to open a shared secret to just one of the helpers.
+#[async_trait]
+pub trait PartialReveal<C: Context>: Sized {
+    type Output;
+    /// Reveal the secret to `to_helper` only. It gets `Some` value, while the other two helpers get
+    /// `None` and learn nothing.
+    async fn reveal_to<'fut>(
+        &self,
+        ctx: C,
+        record_id: RecordId,
+        to_helper: Role,
+    ) -> Result<Option<Self::Output>, Error>
+    where
+        C: 'fut;
+}
+
+/// Reveals `share` to `to_helper` only, which gets `Some` value. The other two helpers get `None`.
+///
+/// ## Errors
+/// If communication between helpers fails or, in malicious contexts, if the shares `to_helper`
+/// received from its peers don't match.
+pub async fn reveal_to<C, S>(
+    ctx: C,
+    record_id: RecordId,
+    share: &S,
+    to_helper: Role,
+) -> Result<Option<S::Output>, Error>
+where
+    C: Context,
+    S: PartialReveal<C>,
+{
+    share.reveal_to(ctx, record_id, to_helper).await
+}
+
+/// Semi-honest reveal to one helper. `to_helper` has two shares out of three, the one it is
+/// missing is held by both of its peers. Its left peer sends it, the same way every helper sends
+/// its left share to the right in [`Reveal`].
+#[async_trait]
+impl<C: Context, V: WeakSharedValue> PartialReveal<C> for Replicated<V> {
+    type Output = V;
+
+    async fn reveal_to<'fut>(
+        &self,
+        ctx: C,
+        record_id: RecordId,
+        to_helper: Role,
+    ) -> Result<Option<V>, Error>
+    where
+        C: 'fut,
+    {
+        let (left, right) = self.as_tuple();
+
+        if ctx.role() == to_helper {
+            let share = ctx
+                .recv_channel(ctx.role().peer(Direction::Left))
+                .receive(record_id)
+                .await?;
+            Ok(Some(left + right + share))
+        } else {
+            if ctx.role().peer(Direction::Right) == to_helper {
+                ctx.send_channel(to_helper).send(record_id, left).await?;
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Malicious reveal to one helper. Both peers of `to_helper` send it the share it is missing and
+/// it checks that they match, the same way malicious [`Reveal`] does.
+#[async_trait]
+impl<'a, F: ExtendableField> PartialReveal<UpgradedMaliciousContext<'a, F>>
+    for MaliciousReplicated<F>
+{
+    type Output = F;
+
+    async fn reveal_to<'fut>(
+        &self,
+        ctx: UpgradedMaliciousContext<'a, F>,
+        record_id: RecordId,
+        to_helper: Role,
+    ) -> Result<Option<F>, Error>
+    where
+        UpgradedMaliciousContext<'a, F>: 'fut,
+    {
+        use crate::secret_sharing::replicated::malicious::ThisCodeIsAuthorizedToDowngradeFromMalicious;
+
+        let (left, right) = self.x().access_without_downgrade().as_tuple();
+
+        if ctx.role() == to_helper {
+            let (share_from_left, share_from_right) = try_join(
+                ctx.recv_channel::<F>(ctx.role().peer(Direction::Left))
+                    .receive(record_id),
+                ctx.recv_channel::<F>(ctx.role().peer(Direction::Right))
+                    .receive(record_id),
+            )
+            .await?;
+
+            if share_from_left == share_from_right {
+                Ok(Some(left + right + share_from_left))
+            } else {
+                Err(Error::Malicious(MaliciousError::RevealFailed {
+                    record_id,
+                    step: ctx.gate().clone(),
+                }))
+            }
+        } else {
+            // The missing share is the left one of the left peer and the right one of the right peer.
+            let share = if ctx.role().peer(Direction::Right) == to_helper {
+                left
+            } else {
+                right
+            };
+            ctx.send_channel(to_helper).send(record_id, share).await?;
+            Ok(None)
         }
     }
 }
@@ -154,19 +275,27 @@ where
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use std::iter::zip;
+    use std::{
+        iter::zip,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
 
     use futures::future::{try_join, try_join3};
+    use typenum::Unsigned;
 
     use crate::{
-        error::Error,
-        ff::{Field, Fp31},
-        helpers::Direction,
+        error::{Error, MaliciousError},
+        ff::{Field, Fp31, Fp32BitPrime, Serializable},
+        helpers::{Direction, HelperIdentity, Role},
         protocol::{
-            basics::Reveal,
+            basics::{reveal_to, Reveal},
             context::{
                 Context, UpgradableContext, UpgradedContext, UpgradedMaliciousContext, Validator,
             },
+            step::Gate,
             RecordId,
         },
         rand::{thread_rng, Rng},
@@ -177,7 +306,8 @@ mod tests {
             },
             IntoShares,
         },
-        test_fixture::{join3v, Runner, TestWorld},
+        seq_join::SeqJoin,
+        test_fixture::{join3v, Runner, Tampering, TestWorld, TestWorldConfig},
     };
 
     #[tokio::test]
@@ -265,11 +395,153 @@ mod tests {
         )
         .await;
 
-        assert!(matches!(result, Err(Error::MaliciousRevealFailed)));
+        assert!(matches!(
+            result,
+            Err(Error::Malicious(MaliciousError::RevealFailed { .. }))
+        ));
 
         Ok(())
     }
 
+    /// Only the target helper must learn the value, whichever one it is.
+    #[tokio::test]
+    pub async fn reveal_to_one() {
+        let mut rng = thread_rng();
+        let world = TestWorld::default();
+
+        for &target in Role::all() {
+            let input = rng.gen::<Fp31>();
+            let results = world
+                .semi_honest(input, |ctx, share| async move {
+                    reveal_to(ctx.set_total_records(1), RecordId::FIRST, &share, target)
+                        .await
+                        .unwrap()
+                })
+                .await;
+            let m_results = world
+                .malicious(input, |ctx, share| async move {
+                    let v = ctx.validator();
+                    let m_ctx = v.context().set_total_records(1);
+                    let m_share = m_ctx.upgrade(share).await.unwrap();
+                    reveal_to(m_ctx, RecordId::FIRST, &m_share, target)
+                        .await
+                        .unwrap()
+                })
+                .await;
+
+            for (role, (result, m_result)) in zip(Role::all(), zip(results, m_results)) {
+                let expected = (*role == target).then_some(input);
+                assert_eq!(expected, result, "{role:?} when revealing to {target:?}");
+                assert_eq!(expected, m_result, "{role:?} when revealing to {target:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    pub async fn malicious_reveal_to_fail() {
+        let mut rng = thread_rng();
+        let world = TestWorld::default();
+        let sh_ctx = world.malicious_contexts();
+        let v = sh_ctx.map(UpgradableContext::validator);
+        let m_ctx: [_; 3] = v
+            .iter()
+            .map(|v| v.context().set_total_records(1))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let record_id = RecordId::from(0);
+        let input: Fp31 = rng.gen();
+
+        let m_shares = join3v(
+            zip(m_ctx.iter(), input.share_with(&mut rng))
+                .map(|(m_ctx, share)| async { m_ctx.upgrade(share).await }),
+        )
+        .await;
+        // H2 is the right peer of H1, it is supposed to send its right share.
+        let (_, right) = m_shares[1].x().access_without_downgrade().as_tuple();
+        let result = try_join3(
+            reveal_to(m_ctx[0].clone(), record_id, &m_shares[0], Role::H1),
+            async {
+                m_ctx[1]
+                    .send_channel(Role::H1)
+                    .send(record_id, right + Fp31::ONE)
+                    .await?;
+                Ok::<_, Error>(None::<Fp31>)
+            },
+            reveal_to(m_ctx[2].clone(), record_id, &m_shares[2], Role::H1),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Malicious(MaliciousError::RevealFailed { .. }))
+        ));
+    }
+
+    /// H1 flips a bit in one of the shares it sends to H2. H2 must reject the reveal of that very
+    /// record instead of revealing a wrong value, the other helpers are not affected.
+    #[tokio::test]
+    pub async fn malicious_reveal_reports_tampered_record() {
+        const COUNT: usize = 10;
+        const TAMPERED: usize = 3;
+        let share_size = <Fp32BitPrime as Serializable>::Size::USIZE;
+
+        // Chunks may hold any number of records, so count bytes to find the one to tamper with.
+        let sent = Arc::new(AtomicUsize::new(0));
+        let world = TestWorld::new_with(TestWorldConfig {
+            tampering: Some(Tampering::in_place({
+                let sent = Arc::clone(&sent);
+                move |from, to, gate: &Gate, _, chunk: &mut Vec<u8>| {
+                    if from == HelperIdentity::ONE
+                        && to == HelperIdentity::TWO
+                        && gate.as_ref().contains("tampered")
+                    {
+                        let start = sent.fetch_add(chunk.len(), Ordering::Relaxed);
+                        if let Some(byte) = (TAMPERED * share_size)
+                            .checked_sub(start)
+                            .and_then(|i| chunk.get_mut(i))
+                        {
+                            *byte ^= 1;
+                        }
+                    }
+                }
+            })),
+            ..TestWorldConfig::default()
+        });
+
+        let mut rng = thread_rng();
+        let inputs = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let results = world
+            .malicious(inputs.clone().into_iter(), |ctx, shares| async move {
+                let v = ctx.validator();
+                let m_ctx = v.context();
+                let m_shares = m_ctx.upgrade(shares).await.unwrap();
+                let ctx = m_ctx.narrow("tampered").set_total_records(COUNT);
+                ctx.try_join(
+                    m_shares
+                        .iter()
+                        .enumerate()
+                        .map(|(i, share)| share.reveal(ctx.clone(), RecordId::from(i))),
+                )
+                .await
+            })
+            .await;
+
+        let [h1, h2, h3] = results;
+        assert_eq!(inputs, h1.unwrap());
+        assert_eq!(inputs, h3.unwrap());
+        match h2 {
+            Err(Error::Malicious(MaliciousError::RevealFailed { record_id, step })) => {
+                assert_eq!(RecordId::from(TAMPERED), record_id);
+                assert!(step.as_ref().contains("tampered"), "{step}");
+            }
+            other => panic!("expected reveal of record {TAMPERED} to fail, got {other:?}"),
+        }
+    }
+
     pub async fn reveal_with_additive_attack<F: ExtendableField>(
         ctx: UpgradedMaliciousContext<'_, F>,
         record_id: RecordId,
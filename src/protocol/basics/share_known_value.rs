@@ -1,12 +1,10 @@
 use crate::{
     ff::Field,
-    helpers::Role,
     protocol::context::{Context, UpgradedContext, UpgradedMaliciousContext},
     secret_sharing::{
         replicated::{
             malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
             semi_honest::AdditiveShare as Replicated,
-            ReplicatedSecretSharing,
         },
         SharedValue,
     },
@@ -18,11 +16,7 @@ pub trait ShareKnownValue<C: Context, V: SharedValue> {
 
 impl<C: Context, F: Field> ShareKnownValue<C, F> for Replicated<F> {
     fn share_known_value(ctx: &C, value: F) -> Self {
-        match ctx.role() {
-            Role::H1 => Self::new(value, F::ZERO),
-            Role::H2 => Self::new(F::ZERO, F::ZERO),
-            Role::H3 => Self::new(F::ZERO, value),
-        }
+        Self::ZERO.add_constant(value, ctx.role())
     }
 }
 
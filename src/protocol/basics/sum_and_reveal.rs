@@ -0,0 +1,207 @@
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{basics::Reveal, context::Context, RecordId},
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+/// Sums up `shares` and reveals the total to all helpers.
+///
+/// Addition is linear, so every helper sums up the shares it holds locally and the only round of
+/// communication is the reveal. In malicious contexts, helpers check that their peers sent
+/// consistent shares of the total, as malicious [`Reveal`] always does. Empty input reveals zero.
+///
+/// ## Errors
+/// If communication with peers fails or, in malicious contexts, if the shares of the total
+/// helpers received don't match.
+pub async fn sum_and_reveal<F, C, S>(ctx: C, shares: &[S]) -> Result<F, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + Reveal<C, RecordId, Output = F>,
+{
+    let total = shares.iter().fold(S::ZERO, |mut acc, share| {
+        acc += share;
+        acc
+    });
+
+    total
+        .reveal(ctx.set_total_records(1), RecordId::FIRST)
+        .await
+}
+
+/// Sums up `values` that fall into the same group and reveals the totals of every group. Group of
+/// `values[i]` is `group_of[i]`, which all helpers know. Groups that none of the values fall into
+/// reveal zero.
+///
+/// Like [`sum_and_reveal`], this takes a single round: totals of all groups are revealed in
+/// parallel, group `g` as record `g`.
+///
+/// ## Errors
+/// If communication with peers fails or, in malicious contexts, if the shares of any total
+/// helpers received don't match.
+///
+/// ## Panics
+/// If `values` and `group_of` have different lengths, or if any group is not less than
+/// `group_count`.
+pub async fn sum_by_group<F, C, S>(
+    ctx: C,
+    values: &[S],
+    group_count: usize,
+    group_of: &[usize],
+) -> Result<Vec<F>, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + Reveal<C, RecordId, Output = F>,
+{
+    assert_eq!(values.len(), group_of.len());
+
+    let mut totals = vec![S::ZERO; group_count];
+    for (value, &group) in values.iter().zip(group_of) {
+        assert!(
+            group < group_count,
+            "group {group} is out of range, there are {group_count} groups"
+        );
+        totals[group] += value;
+    }
+
+    let ctx = ctx.set_total_records(group_count);
+    ctx.try_join(
+        totals
+            .iter()
+            .enumerate()
+            .map(|(group, total)| total.reveal(ctx.clone(), RecordId::from(group))),
+    )
+    .await
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::{sum_and_reveal, sum_by_group};
+    use crate::{
+        error::{Error, MaliciousError},
+        ff::{Field, Fp31, Fp32BitPrime},
+        helpers::Role,
+        protocol::context::{Context, UpgradableContext, UpgradedContext, Validator},
+        rand::{thread_rng, Rng},
+        secret_sharing::{
+            replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
+            SharedValue,
+        },
+        test_fixture::{Runner, TestWorld},
+    };
+
+    #[tokio::test]
+    async fn sum() {
+        const COUNT: usize = 20;
+        let mut rng = thread_rng();
+        let values = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let expected = values
+            .iter()
+            .copied()
+            .fold(Fp32BitPrime::ZERO, |a, b| a + b);
+
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(values.clone().into_iter(), |ctx, shares| async move {
+                sum_and_reveal(ctx, &shares).await.unwrap()
+            })
+            .await;
+        let m_result = world
+            .malicious(values.into_iter(), |ctx, shares| async move {
+                let v = ctx.validator();
+                let m_ctx = v.context();
+                let m_shares = m_ctx.upgrade(shares).await.unwrap();
+                sum_and_reveal(m_ctx, &m_shares).await.unwrap()
+            })
+            .await;
+
+        assert_eq!([expected; 3], result);
+        assert_eq!([expected; 3], m_result);
+    }
+
+    #[tokio::test]
+    async fn empty() {
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(Vec::<Fp31>::new().into_iter(), |ctx, shares| async move {
+                sum_and_reveal(ctx, &shares).await.unwrap()
+            })
+            .await;
+
+        assert_eq!([Fp31::ZERO; 3], result);
+    }
+
+    #[tokio::test]
+    async fn groups() {
+        const GROUPS: usize = 4;
+        let values = [3_u32, 5, 7, 11, 13, 17].map(Fp31::truncate_from).to_vec();
+        // Nothing falls into group 2.
+        let group_of = [0, 3, 0, 1, 3, 3];
+        let expected = [10_u32, 11, 0, 4].map(Fp31::truncate_from).to_vec();
+
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(values.into_iter(), |ctx, shares| async move {
+                sum_by_group(ctx, &shares, GROUPS, &group_of).await.unwrap()
+            })
+            .await;
+
+        assert!(
+            result.iter().all(|totals| totals == &expected),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn single_group() {
+        let values = [3_u32, 5, 7].map(Fp31::truncate_from).to_vec();
+
+        let world = TestWorld::default();
+        let result = world
+            .malicious(values.into_iter(), |ctx, shares| async move {
+                let v = ctx.validator();
+                let m_ctx = v.context();
+                let m_shares = m_ctx.upgrade(shares).await.unwrap();
+                sum_by_group(m_ctx, &m_shares, 1, &[0; 3]).await.unwrap()
+            })
+            .await;
+
+        let expected = vec![Fp31::truncate_from(15_u32)];
+        assert!(
+            result.iter().all(|totals| totals == &expected),
+            "{result:?}"
+        );
+    }
+
+    /// A helper that changes one of its shares is caught by the peer that holds the other copy of
+    /// it.
+    #[tokio::test]
+    async fn tampered_share() {
+        let values = [3_u32, 5, 7].map(Fp31::truncate_from).to_vec();
+
+        let world = TestWorld::default();
+        let result = world
+            .malicious(values.into_iter(), |ctx, mut shares| async move {
+                if ctx.role() == Role::H1 {
+                    shares[0] = Replicated::new(shares[0].left() + Fp31::ONE, shares[0].right());
+                }
+                let v = ctx.validator();
+                let m_ctx = v.context();
+                let m_shares = m_ctx.upgrade(shares).await.unwrap();
+                sum_and_reveal(m_ctx, &m_shares).await
+            })
+            .await;
+
+        assert!(
+            result.iter().any(|r| matches!(
+                r,
+                Err(Error::Malicious(MaliciousError::RevealFailed { .. }))
+            )),
+            "{result:?}"
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Addition of two bitwise-shared integers, `[a]_B + [b]_B`.
+//!
+//! Bits are shared in [`Gf2`], so XOR is addition and costs nothing, while AND is a
+//! multiplication. Once the carry into every bit `c_i` is known, sum bits are local:
+//! `s_i = a_i ⊕ b_i ⊕ c_i`. Circuits differ in how they get the carries:
+//! * [`AdderCircuit::RippleCarry`] finds them one bit at a time, with a single AND per bit:
+//!   `c_{i+1} = c_i ⊕ ((a_i ⊕ c_i) · (b_i ⊕ c_i))`. The AND for bit `i` runs on
+//!   `BitOpStep::from(0)`, narrowed by the bit position.
+//! * [`AdderCircuit::CarryLookahead`] first computes, for every bit position, whether it generates
+//!   a carry, `g_i = a_i · b_i`, and whether it propagates an incoming one, `p_i = a_i ⊕ b_i`, so
+//!   that `c_{i+1} = g_i ⊕ p_i · c_i`. All `g_i` are computed in a single round on
+//!   `BitOpStep::from(0)`, narrowed by the bit position. Then it runs a Kogge-Stone parallel
+//!   prefix over `(g_i, p_i)` pairs. Level `l` runs on `BitOpStep::from(1 + l)`. Within it,
+//!   generate multiplications narrow to `BitOpStep::from(0)` and propagate multiplications narrow
+//!   to `BitOpStep::from(1)`, followed by the bit position.
+
+use futures::future::try_join;
+
+use crate::{
+    error::Error,
+    ff::Gf2,
+    protocol::{basics::SecureMul, context::Context, step::BitOpStep, BasicProtocols, RecordId},
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+/// Selects the circuit [`bitwise_add`] uses to compute carries of `n`-bit inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdderCircuit {
+    /// `n` rounds and `n` multiplications, one AND per bit.
+    RippleCarry,
+    /// `1 + ⌈log2 n⌉` rounds, at the cost of roughly `n log2 n` additional multiplications.
+    CarryLookahead,
+}
+
+/// Adds two bitwise-shared integers of the same width, least significant bits first.
+///
+/// The output has one bit more than the inputs. Its first `n` bits are `(a + b) mod 2^n`, so if
+/// only the first `n` bits are kept, the addition wraps around on overflow the same way
+/// [`u32::wrapping_add`] does. The last bit is the carry out of the most significant position: it
+/// is a share of `1` iff the sum overflowed `n` bits. Adding no bits at all yields a single bit
+/// that is a share of `0`.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+///
+/// ## Panics
+/// If `a_bits` and `b_bits` are not of the same length.
+pub async fn bitwise_add<C, S>(
+    ctx: C,
+    record_id: RecordId,
+    a_bits: &[S],
+    b_bits: &[S],
+    circuit: AdderCircuit,
+) -> Result<Vec<S>, Error>
+where
+    C: Context,
+    S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
+{
+    assert_eq!(
+        a_bits.len(),
+        b_bits.len(),
+        "bitwise addition requires inputs of the same width"
+    );
+    if a_bits.is_empty() {
+        return Ok(vec![S::ZERO]);
+    }
+
+    let carries = match circuit {
+        AdderCircuit::RippleCarry => ripple_carries(ctx, record_id, a_bits, b_bits).await?,
+        AdderCircuit::CarryLookahead => lookahead_carries(ctx, record_id, a_bits, b_bits).await?,
+    };
+
+    // `carries[i]` is the carry out of bit `i`, nothing comes into the least significant one.
+    let mut output = Vec::with_capacity(a_bits.len() + 1);
+    for (i, (a, b)) in a_bits.iter().zip(b_bits).enumerate() {
+        let mut sum = a.clone() + b;
+        if i > 0 {
+            sum += &carries[i - 1];
+        }
+        output.push(sum);
+    }
+    output.push(carries.last().unwrap().clone());
+
+    Ok(output)
+}
+
+/// Returns carries out of every bit, computing them one after another.
+async fn ripple_carries<C, S>(
+    ctx: C,
+    record_id: RecordId,
+    a_bits: &[S],
+    b_bits: &[S],
+) -> Result<Vec<S>, Error>
+where
+    C: Context,
+    S: LinearSecretSharing<Gf2> + SecureMul<C>,
+{
+    let mut carries = Vec::<S>::with_capacity(a_bits.len());
+    for (i, (a, b)) in a_bits.iter().zip(b_bits).enumerate() {
+        let carry = if i == 0 {
+            S::ZERO
+        } else {
+            carries[i - 1].clone()
+        };
+        let flipped = (a.clone() + &carry)
+            .multiply(
+                &(b.clone() + &carry),
+                ctx.narrow_indexed(&BitOpStep::from(0), i),
+                record_id,
+            )
+            .await?;
+        carries.push(carry + &flipped);
+    }
+
+    Ok(carries)
+}
+
+/// Returns carries out of every bit, combining `(g, p)` pairs over spans that double in length
+/// with every level.
+async fn lookahead_carries<C, S>(
+    ctx: C,
+    record_id: RecordId,
+    a_bits: &[S],
+    b_bits: &[S],
+) -> Result<Vec<S>, Error>
+where
+    C: Context,
+    S: LinearSecretSharing<Gf2> + SecureMul<C>,
+{
+    let mut generate = ctx
+        .parallel_join(a_bits.iter().zip(b_bits).enumerate().map(|(i, (a, b))| {
+            a.multiply(b, ctx.narrow_indexed(&BitOpStep::from(0), i), record_id)
+        }))
+        .await?;
+    let mut propagate = a_bits
+        .iter()
+        .zip(b_bits)
+        .map(|(a, b)| a.clone() + b)
+        .collect::<Vec<_>>();
+
+    let n = generate.len();
+    let mut stride = 1;
+    let mut level = 0_usize;
+    while stride < n {
+        let level_ctx = ctx.narrow(&BitOpStep::from(1 + level));
+        // Spans that reach bit 0 already know their carry, so their propagate bit is never used
+        // again. That is the case for everything below `2 * stride` after this level.
+        let (generated, propagated) = try_join(
+            ctx.parallel_join((stride..n).map(|i| {
+                propagate[i].multiply(
+                    &generate[i - stride],
+                    level_ctx.narrow_indexed(&BitOpStep::from(0), i),
+                    record_id,
+                )
+            })),
+            ctx.parallel_join((2 * stride..n).map(|i| {
+                propagate[i].multiply(
+                    &propagate[i - stride],
+                    level_ctx.narrow_indexed(&BitOpStep::from(1), i),
+                    record_id,
+                )
+            })),
+        )
+        .await?;
+        // A span that generates a carry never propagates one, so XOR is the same as OR here.
+        for (i, g) in (stride..).zip(generated) {
+            generate[i] += &g;
+        }
+        for (i, p) in (2 * stride..).zip(propagated) {
+            propagate[i] = p;
+        }
+        stride *= 2;
+        level += 1;
+    }
+
+    Ok(generate)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{bitwise_add, AdderCircuit};
+    use crate::{
+        ff::{Field, Gf2},
+        protocol::{context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        test_fixture::{
+            bits_to_value, Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder,
+        },
+    };
+
+    fn into_bits(x: u64, width: u32) -> Vec<Gf2> {
+        (0..width)
+            .map(|i| Gf2::truncate_from((x >> i) & 1))
+            .collect()
+    }
+
+    /// Adds `a` and `b` in both security models and returns the sum, without the carry bit, and
+    /// the carry.
+    async fn add(
+        world: &TestWorld,
+        a: u64,
+        b: u64,
+        width: u32,
+        circuit: AdderCircuit,
+    ) -> (u64, bool) {
+        let input = (
+            into_bits(a, width).into_iter(),
+            into_bits(b, width).into_iter(),
+        );
+        let result = world
+            .semi_honest(input.clone(), |ctx, (a, b)| async move {
+                bitwise_add(ctx.set_total_records(1), RecordId::FIRST, &a, &b, circuit)
+                    .await
+                    .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result = world
+            .upgraded_malicious(input, |ctx, (a, b)| async move {
+                bitwise_add(ctx.set_total_records(1), RecordId::FIRST, &a, &b, circuit)
+                    .await
+                    .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(
+            result, m_result,
+            "{circuit:?} disagrees between security models"
+        );
+        assert_eq!(usize::try_from(width).unwrap() + 1, result.len());
+        let (carry, sum) = result.split_last().unwrap();
+        (
+            u64::try_from(bits_to_value(sum)).unwrap(),
+            *carry == Gf2::ONE,
+        )
+    }
+
+    async fn random_sums(width: u32) {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let max = u64::MAX >> (u64::BITS - width);
+
+        let mut inputs = vec![(0, 0), (max, 1), (1, max), (max, max), (max, 0)];
+        inputs.extend((0..4).map(|_| (rng.gen_range(0..=max), rng.gen_range(0..=max))));
+
+        for circuit in [AdderCircuit::RippleCarry, AdderCircuit::CarryLookahead] {
+            for &(a, b) in &inputs {
+                let (sum, carry) = add(&world, a, b, width, circuit).await;
+                // The sum wraps around, overflow is only visible in the carry bit.
+                assert_eq!(
+                    (a + b) & max,
+                    sum,
+                    "{a} + {b} in {width} bits with {circuit:?}"
+                );
+                assert_eq!(
+                    a + b > max,
+                    carry,
+                    "{a} + {b} in {width} bits with {circuit:?}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn add_32_bits() {
+        random_sums(32).await;
+    }
+
+    #[tokio::test]
+    async fn add_40_bits() {
+        random_sums(40).await;
+    }
+
+    #[tokio::test]
+    async fn add_no_bits() {
+        let world = TestWorld::default();
+        for circuit in [AdderCircuit::RippleCarry, AdderCircuit::CarryLookahead] {
+            assert_eq!((0, false), add(&world, 0, 0, 0, circuit).await);
+        }
+    }
+
+    /// Ripple carry uses one multiplication per bit. Carry lookahead pays for fewer rounds with
+    /// more multiplications.
+    #[tokio::test]
+    async fn multiplications() {
+        const WIDTH: u32 = 40;
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let input = (
+            into_bits(0x12_3456_789a, WIDTH).into_iter(),
+            into_bits(0xfe_dcba_9876, WIDTH).into_iter(),
+        );
+
+        let circuits = [
+            ("ripple", AdderCircuit::RippleCarry),
+            ("lookahead", AdderCircuit::CarryLookahead),
+        ];
+        world
+            .semi_honest(input, |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(1);
+                for (name, circuit) in circuits {
+                    bitwise_add(ctx.narrow(name), RecordId::FIRST, &a, &b, circuit)
+                        .await
+                        .unwrap();
+                }
+            })
+            .await;
+
+        let multiplications = |name: &str| {
+            recorder
+                .with_step_prefix("protocol")
+                .into_iter()
+                .filter(|record| record.gate.as_ref().split('/').any(|s| s == name))
+                .map(|record| record.gate)
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        let n = usize::try_from(WIDTH).unwrap();
+        // 40 bits take 6 levels of lookahead, with strides from 1 to 32.
+        let lookahead = (0..6)
+            .map(|level| (n - (1 << level)) + n.saturating_sub(2 << level))
+            .sum::<usize>();
+        assert_eq!(n, multiplications("ripple"));
+        assert_eq!(n + lookahead, multiplications("lookahead"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Bit decomposition of an arithmetically shared field element, `[a] -> ([a_0], …, [a_{l-1}])`.
+
+use futures::future::try_join;
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::PrimeField,
+    protocol::{
+        boolean::{add_constant::add_constant, random_bits_generator::RandomBitsGenerator},
+        context::UpgradedContext,
+        BasicProtocols, RecordId,
+    },
+    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
+};
+
+/// Converts `[a]` into shares of its bits, from the least significant to the most significant one.
+/// There are as many bits as it takes to write down `p - 1`.
+///
+/// This is the bit decomposition protocol from section 5 of "Unconditionally Secure
+/// Constant-Rounds Multi-party Computation for Equality, Comparison, Bits, and Exponentiation",
+/// I. Damgård et al. `a` is masked with a random `r < p` whose bits are known in shared form, and
+/// `c = a - r mod p` is revealed. Then `a = c + r` if that sum is less than `p`, and
+/// `a = c + r - p` otherwise. Both candidates are computed by adding a known constant to the bits
+/// of `r`: `c` for the first one and `c + 2^l - p` for the second. The carry out of the second sum
+/// tells which one is right.
+///
+/// Random masks are drawn from `rbg`. All records decomposed on the same context must share it.
+///
+/// ## Errors
+/// If communication with peers fails.
+pub async fn bit_decompose<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    rbg: &RandomBitsGenerator<F, C, S>,
+    a: &S,
+) -> Result<Vec<S>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    let prime: u128 = F::PRIME.into();
+    let l = u128::BITS - prime.leading_zeros();
+    bit_decompose_low(ctx, record_id, rbg, a, l as usize).await
+}
+
+/// Converts `[a]` into shares of its `num_bits` least significant bits, from the least
+/// significant one. Values that are known to be less than `2^num_bits` recombine from them,
+/// larger ones give `a mod 2^num_bits`.
+///
+/// This runs [`bit_decompose`] up to the point where it picks one of the two candidates. Only the
+/// bits that are returned get picked, so this takes one multiplication fewer for every bit that is
+/// left out. Both additions still run over all the bits, as every one of them contributes to the
+/// carry that tells the candidates apart.
+///
+/// ## Errors
+/// If communication with peers fails.
+///
+/// ## Panics
+/// If `num_bits` is more than it takes to write down `p - 1`.
+pub async fn bit_decompose_low<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    rbg: &RandomBitsGenerator<F, C, S>,
+    a: &S,
+    num_bits: usize,
+) -> Result<Vec<S>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    let prime: u128 = F::PRIME.into();
+    let l = usize::try_from(u128::BITS - prime.leading_zeros()).unwrap();
+    assert!(
+        num_bits <= l,
+        "cannot decompose into {num_bits} bits, field elements only have {l}"
+    );
+
+    let r = rbg.generate(record_id).await?;
+    debug_assert_eq!(l, r.b_b.len());
+    let c = (a.clone() - &r.b_p)
+        .reveal(
+            ctx.narrow(&BitDecompositionStep::RevealMaskedValue),
+            record_id,
+        )
+        .await?
+        .as_u128();
+
+    let (sum, wrapped) = try_join(
+        add_constant(
+            ctx.narrow(&BitDecompositionStep::AddMask),
+            record_id,
+            &r.b_b,
+            c,
+        ),
+        add_constant(
+            ctx.narrow(&BitDecompositionStep::AddMaskMinusPrime),
+            record_id,
+            &r.b_b,
+            c + (1 << l) - prime,
+        ),
+    )
+    .await?;
+
+    // `c + r ≥ p` iff `c + r + 2^l - p` does not fit into `l` bits.
+    let wrapped_around = &wrapped[l];
+    let differences = wrapped
+        .iter()
+        .zip(&sum)
+        .take(num_bits)
+        .map(|(w, s)| w.clone() - s)
+        .collect::<Vec<_>>();
+    let corrections = ctx
+        .parallel_join(differences.iter().enumerate().map(|(i, difference)| {
+            wrapped_around.multiply(
+                difference,
+                ctx.narrow_indexed(&BitDecompositionStep::SelectBits, i),
+                record_id,
+            )
+        }))
+        .await?;
+
+    Ok(sum
+        .into_iter()
+        .take(num_bits)
+        .zip(corrections)
+        .map(|(s, correction)| s + &correction)
+        .collect())
+}
+
+#[derive(Step)]
+pub(crate) enum BitDecompositionStep {
+    /// Not used by [`bit_decompose`] itself. Protocols that decompose values narrow the context of
+    /// their [`RandomBitsGenerator`] with it.
+    RandomMask,
+    RevealMaskedValue,
+    AddMask,
+    AddMaskMinusPrime,
+    SelectBits,
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::distributions::{Distribution, Standard};
+
+    use super::{bit_decompose, bit_decompose_low};
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField},
+        protocol::{
+            boolean::random_bits_generator::RandomBitsGenerator,
+            context::{Context, UpgradableContext, Validator},
+            RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::replicated::malicious::ExtendableField,
+        seq_join::SeqJoin,
+        telemetry::metrics::RECORDS_SENT,
+        test_fixture::{bits_to_value, Reconstruct, Runner, TestWorld, TestWorldConfig},
+    };
+
+    /// Decomposes every value in both security models and checks they agree.
+    async fn decompose<F>(values: Vec<F>) -> Vec<Vec<F>>
+    where
+        F: PrimeField + ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        let result: Vec<Vec<F>> = world
+            .semi_honest(values.clone().into_iter(), |ctx, x| async move {
+                let validator = ctx.validator::<F>();
+                let ctx = validator.context().set_total_records(x.len());
+                let rbg = RandomBitsGenerator::new(ctx.narrow("rbg"));
+                ctx.try_join(
+                    x.iter()
+                        .enumerate()
+                        .map(|(i, x)| bit_decompose(ctx.clone(), RecordId::from(i), &rbg, x)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        // Bits of all values come out in one vector, the ones that malicious runs can validate.
+        let m_result: Vec<F> = world
+            .upgraded_malicious(values.into_iter(), |ctx, x| async move {
+                let ctx = ctx.set_total_records(x.len());
+                let rbg = RandomBitsGenerator::new(ctx.narrow("rbg"));
+                ctx.try_join(
+                    x.iter()
+                        .enumerate()
+                        .map(|(i, x)| bit_decompose(ctx.clone(), RecordId::from(i), &rbg, x)),
+                )
+                .await
+                .unwrap()
+                .concat()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(result.concat(), m_result, "security models disagree");
+        result
+    }
+
+    async fn round_trip<F>()
+    where
+        F: PrimeField + ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let p: u128 = F::PRIME.into();
+        let mut rng = thread_rng();
+        let mut values = [0, 1, 2, p / 2, p - 3, p - 2, p - 1]
+            .into_iter()
+            .map(F::truncate_from)
+            .collect::<Vec<_>>();
+        values.extend((0..32).map(|_| rng.gen::<F>()));
+
+        let bits = decompose(values.clone()).await;
+        let width = u128::BITS - p.leading_zeros();
+        for (x, bits) in values.into_iter().zip(bits) {
+            assert_eq!(usize::try_from(width).unwrap(), bits.len(), "{x:?}");
+            assert!(
+                bits.iter().all(|&b| b == F::ZERO || b == F::ONE),
+                "{x:?} decomposed into {bits:?}"
+            );
+            assert_eq!(x.as_u128(), bits_to_value(&bits), "{x:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn fp31() {
+        round_trip::<Fp31>().await;
+    }
+
+    #[tokio::test]
+    async fn fp32_bit_prime() {
+        round_trip::<Fp32BitPrime>().await;
+    }
+
+    /// Runs [`bit_decompose_low`] on every value and returns the bits along with the number of
+    /// records helpers sent.
+    async fn decompose_low(
+        values: Vec<Fp32BitPrime>,
+        num_bits: usize,
+    ) -> (Vec<Vec<Fp32BitPrime>>, u64) {
+        let world = TestWorld::new_with(TestWorldConfig::default().enable_metrics());
+        let bits = world
+            .semi_honest(values.into_iter(), |ctx, x| async move {
+                let validator = ctx.validator::<Fp32BitPrime>();
+                let ctx = validator.context().set_total_records(x.len());
+                let rbg = RandomBitsGenerator::new(ctx.narrow("rbg"));
+                ctx.try_join(x.iter().enumerate().map(|(i, x)| {
+                    bit_decompose_low(ctx.clone(), RecordId::from(i), &rbg, x, num_bits)
+                }))
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        (bits, world.metrics_snapshot().get_counter(RECORDS_SENT))
+    }
+
+    #[tokio::test]
+    async fn low_bits() {
+        const NUM_BITS: usize = 16;
+        let mut rng = thread_rng();
+        let mut values = [0_u128, 1, 0x8000, 0xFFFF, 0x1_2345]
+            .into_iter()
+            .map(Fp32BitPrime::truncate_from)
+            .collect::<Vec<_>>();
+        values.extend(
+            (0..8).map(|_| Fp32BitPrime::truncate_from(rng.gen_range(0..1_u128 << NUM_BITS))),
+        );
+
+        let (bits, _) = decompose_low(values.clone(), NUM_BITS).await;
+        for (x, bits) in values.into_iter().zip(bits) {
+            assert_eq!(NUM_BITS, bits.len(), "{x:?}");
+            for (i, &b) in bits.iter().enumerate() {
+                let expected = (x.as_u128() >> i) & 1;
+                assert_eq!(Fp32BitPrime::truncate_from(expected), b, "bit {i} of {x:?}");
+            }
+            assert_eq!(x.as_u128() % (1 << NUM_BITS), bits_to_value(&bits), "{x:?}");
+        }
+    }
+
+    /// Every bit left out saves one multiplication per record.
+    #[tokio::test]
+    async fn low_bits_save_multiplications() {
+        const NUM_BITS: usize = 8;
+        const RECORDS: usize = 4;
+        let values = vec![Fp32BitPrime::ONE; RECORDS];
+
+        let (_, all) = decompose_low(values.clone(), 32).await;
+        let (_, low) = decompose_low(values, NUM_BITS).await;
+        assert_eq!(
+            3 * u64::try_from(RECORDS * (32 - NUM_BITS)).unwrap(),
+            all - low
+        );
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "cannot decompose into 6 bits")]
+    async fn too_many_low_bits() {
+        let world = TestWorld::default();
+        world
+            .semi_honest(Fp31::ONE, |ctx, x| async move {
+                let validator = ctx.validator::<Fp31>();
+                let ctx = validator.context().set_total_records(1);
+                let rbg = RandomBitsGenerator::new(ctx.narrow("rbg"));
+                bit_decompose_low(ctx, RecordId::FIRST, &rbg, &x, 6)
+                    .await
+                    .unwrap()
+            })
+            .await;
+    }
+}
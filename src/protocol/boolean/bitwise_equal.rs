@@ -1,12 +1,61 @@
 use std::iter::zip;
 
+use ipa_macros::Step;
+
 use crate::{
     error::Error,
-    ff::{Field, Gf2},
-    protocol::{boolean::all_zeroes, context::Context, BasicProtocols, RecordId},
+    ff::{Field, Gf2, PrimeField},
+    protocol::{
+        boolean::{all_zeroes, random_bits_generator::RandomBitsGenerator},
+        context::{Context, UpgradedContext},
+        BasicProtocols, RecordId,
+    },
     secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
 };
 
+#[derive(Step)]
+pub(crate) enum EqualStep {
+    Reveal,
+    Compare,
+}
+
+/// Compares two arithmetic-shared values and returns a share of `1` iff `a == b`, without
+/// revealing either of them.
+///
+/// `a - b` is masked with a random `r`, for which the bitwise decomposition is known, and
+/// the masked value is revealed. The difference is zero exactly when the revealed value is `r`,
+/// which is then checked bit by bit.
+///
+/// # Errors
+/// Propagates errors from generating random bits, reveal and multiplications
+pub async fn equals<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    rbg: &RandomBitsGenerator<F, C, S>,
+    a: &S,
+    b: &S,
+) -> Result<S, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    let r = rbg.generate(record_id).await?;
+
+    let masked = (r.b_p + &(a - b))
+        .reveal(ctx.narrow(&EqualStep::Reveal), record_id)
+        .await?;
+
+    bitwise_equal_constant(
+        ctx.narrow(&EqualStep::Compare),
+        record_id,
+        &r.b_b,
+        masked.as_u128(),
+    )
+    .await
+}
+
 /// Compares `[a]` and `c`, and returns 1 iff `a == c`
 ///
 /// # Errors
@@ -69,13 +118,58 @@ where
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use super::bitwise_equal_constant;
+    use super::{bitwise_equal_constant, equals};
     use crate::{
         ff::{Field, Fp31, Fp32BitPrime},
-        protocol::{context::Context, RecordId},
+        protocol::{
+            boolean::random_bits_generator::RandomBitsGenerator,
+            context::{Context, UpgradableContext, Validator},
+            RecordId,
+        },
+        seq_join::SeqJoin,
         test_fixture::{get_bits, Reconstruct, Runner, TestWorld},
     };
 
+    #[tokio::test]
+    pub async fn equals_fp31() {
+        let pairs: [(u8, u8); 8] = [
+            (0, 0),
+            (0, 1),
+            (1, 0),
+            (7, 7),
+            (30, 30),
+            (30, 0),
+            (0, 30),
+            (17, 4),
+        ];
+        let input = pairs
+            .iter()
+            .map(|&(a, b)| (Fp31::truncate_from(a), Fp31::truncate_from(b)))
+            .collect::<Vec<_>>();
+
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(input.into_iter(), |ctx, input| async move {
+                let validator = ctx.validator();
+                let ctx = validator.context().set_total_records(input.len());
+                let rbg = RandomBitsGenerator::new(ctx.clone());
+                ctx.try_join(
+                    input
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (a, b))| equals(ctx.clone(), RecordId::from(i), &rbg, a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        for ((a, b), eq) in pairs.into_iter().zip(result) {
+            assert_eq!(Fp31::truncate_from(a == b), eq, "{a} == {b}");
+        }
+    }
+
     #[tokio::test]
     pub async fn constant() {
         assert_eq!(1, run_bitwise_equal_constant(45, 45, 9).await);
@@ -9,7 +9,6 @@ use crate::{
     protocol::{
         boolean::{any_ones, multiply_all_shares, or::or},
         context::Context,
-        step::BitOpStep,
         BasicProtocols, RecordId,
     },
     secret_sharing::Linear as LinearSecretSharing,
@@ -127,11 +126,7 @@ impl BitwiseLessThanPrime {
         // Meaning the least significant three bits are exactly [1, 1, 0]
         if prime == (1 << l) - 5 {
             let (check_least_significant_bits, most_significant_bits_all_ones) = try_join(
-                Self::check_least_significant_bits(
-                    ctx.narrow(&Step::CheckLeastSignificantBits),
-                    record_id,
-                    &x[0..3],
-                ),
+                Self::check_least_significant_bits(ctx.clone(), record_id, &x[0..3]),
                 // To check if a list of shares are all shares of one, we just need to multiply them all together (in any order)
                 multiply_all_shares(ctx.narrow(&Step::CheckIfAllOnes), record_id, &x[3..]),
             )
@@ -159,6 +154,8 @@ impl BitwiseLessThanPrime {
     /// 2.) The final one is exactly [1 1 0]
     /// We can check if either of these conditions is true with just 3 multiplications
     ///
+    /// Multiplications run on iterations of `Step::CheckLeastSignificantBits` under `ctx`.
+    ///
     /// # Errors
     /// Fails if the multiplication protocol fails.
     pub async fn check_least_significant_bits<F, C, S>(
@@ -177,12 +174,16 @@ impl BitwiseLessThanPrime {
 
         let one = S::share_known_value(&ctx, F::ONE);
         let least_significant_two_bits_both_one = x[0]
-            .multiply(&x[1], ctx.narrow(&BitOpStep::from(0)), record_id)
+            .multiply(
+                &x[1],
+                ctx.narrow_indexed(&Step::CheckLeastSignificantBits, 0),
+                record_id,
+            )
             .await?;
         let least_significant_bits_are_one_one_zero = (one - &x[2])
             .multiply(
                 &least_significant_two_bits_both_one,
-                ctx.narrow(&BitOpStep::from(1)),
+                ctx.narrow_indexed(&Step::CheckLeastSignificantBits, 1),
                 record_id,
             )
             .await?;
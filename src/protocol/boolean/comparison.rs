@@ -1,13 +1,12 @@
 use ipa_macros::Step;
 
-use super::or::or;
+use super::prefix_or;
 use crate::{
     error::Error,
     ff::{Field, PrimeField},
     protocol::{
         boolean::random_bits_generator::RandomBitsGenerator,
         context::{Context, UpgradedContext},
-        step::BitOpStep,
         BasicProtocols, RecordId,
     },
     secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
@@ -159,6 +158,9 @@ pub(crate) enum GreaterThanConstantStep {
 /// Eleftheria Makri, et al.
 /// <https://eprint.iacr.org/2021/119.pdf>
 ///
+/// If `c` does not fit into as many bits as `a` has, the result is known to be `0` without any
+/// communication. The same goes for `a` without any bits, which is `0`.
+///
 /// # Errors
 /// Lots of things may go wrong here, from timeouts to bad output. They will be signalled
 /// back via the error response
@@ -179,6 +181,10 @@ where
 {
     assert!(a.len() <= 128);
 
+    if a.is_empty() || exceeds_bits(c, a.len()) {
+        return Ok(S::ZERO);
+    }
+
     let first_diff_bit = first_differing_bit(&ctx, record_id, a, c).await?;
 
     // Compute the dot-product [a] x `first_diff_bit`. 1 iff a > c.
@@ -192,6 +198,12 @@ where
 /// Eleftheria Makri, et al.
 /// <https://eprint.iacr.org/2021/119.pdf>
 ///
+/// Bits of `a` go from the least significant to the most significant one. The most significant bit
+/// where `a` and `c` differ is found with [`prefix_or`], so `l`-bit inputs take `⌈log2 l⌉` rounds
+/// of communication for that, plus one more for the dot product. Some constants do not need
+/// communication at all: nothing is less than `c = 0`, and everything is less than `c` that does
+/// not fit into `l` bits, including `a` that has no bits.
+///
 /// # Errors
 /// Lots of things may go wrong here, from timeouts to bad output. They will be signalled
 /// back via the error response
@@ -212,6 +224,13 @@ where
 {
     assert!(a.len() <= 128);
 
+    if c == 0 {
+        return Ok(S::ZERO);
+    }
+    if exceeds_bits(c, a.len()) {
+        return Ok(S::share_known_value(&ctx, F::ONE));
+    }
+
     let first_diff_bit = first_differing_bit(&ctx, record_id, a, c).await?;
 
     let not_a = a
@@ -244,7 +263,7 @@ where
     let one = S::share_known_value(ctx, F::ONE);
 
     // Compute `[a] ^ b`. This step gives us the bits of values where they differ.
-    let xored_bits = a
+    let mut xored_bits = a
         .iter()
         .enumerate()
         .map(|(i, a_bit)| {
@@ -257,41 +276,32 @@ where
         })
         .collect::<Vec<_>>();
 
-    // Compute prefix-or of the xor'ed bits. This yields 0's followed by 1's with the transition
-    // from 0 to 1 occurring at the index of the first different bit.
-    let prefix_or_context = ctx.narrow(&Step::PrefixOr);
-    let mut first_diff_bit = Vec::with_capacity(xored_bits.len());
-    let mut previous_bit = xored_bits.last().cloned().unwrap();
-    first_diff_bit.push(previous_bit.clone());
-    // Process from MSB to LSB
-    for (i, bit) in xored_bits
-        .iter()
-        .take(xored_bits.len() - 1)
-        .rev()
-        .enumerate()
-    {
-        let result = or(
-            prefix_or_context.narrow(&BitOpStep::from(i)),
-            record_id,
-            &previous_bit,
-            bit,
-        )
-        .await?;
-
-        // Subtract the previous or'ed bit to yield a single 1 at the index of the first
-        // differing bit. Note that at the index where the transition from 0 to 1 happens,
-        // `prefix_or[i + 1] > prefix_or[i]`. Do not change the order of the subtraction
-        // unless we use Fp2, or the result will be `[p-1]`.
-        first_diff_bit.push(&result - &previous_bit);
-
-        previous_bit = result;
-    }
+    // Compute prefix-or of the xor'ed bits, from MSB to LSB. This yields 0's followed by 1's
+    // with the transition from 0 to 1 occurring at the index of the first different bit.
+    xored_bits.reverse();
+    let prefix = prefix_or(ctx.narrow(&Step::PrefixOr), record_id, &xored_bits).await?;
+
+    // Subtract the previous or'ed bit to yield a single 1 at the index of the first differing
+    // bit. Note that at the index where the transition from 0 to 1 happens,
+    // `prefix_or[i + 1] > prefix_or[i]`. Do not change the order of the subtraction unless we
+    // use Fp2, or the result will be `[p-1]`.
+    let mut first_diff_bit = Vec::with_capacity(prefix.len());
+    first_diff_bit.push(prefix[0].clone());
+    first_diff_bit.extend(prefix.windows(2).map(|w| &w[1] - &w[0]));
     // Change the order back to the little-endian format.
     first_diff_bit.reverse();
 
     Ok(first_diff_bit)
 }
 
+/// Returns `true` if `c` can't be represented with `bits` bits.
+fn exceeds_bits(c: u128, bits: usize) -> bool {
+    u32::try_from(bits)
+        .ok()
+        .and_then(|bits| c.checked_shr(bits))
+        .map_or(false, |high| high != 0)
+}
+
 #[derive(Step)]
 pub(crate) enum Step {
     PrefixOr,
@@ -556,6 +566,33 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    pub async fn bw_lt_edge_cases() {
+        let c = Fp32BitPrime::truncate_from::<u32>;
+        let zero = Fp32BitPrime::ZERO;
+        let one = Fp32BitPrime::ONE;
+        let max = Fp32BitPrime::PRIME - 1;
+        let world = TestWorld::default();
+
+        // nothing is less than zero
+        for a in [0, 1, max] {
+            assert_eq!(zero, bitwise_lt(&world, c(a), 0).await, "{a} < 0");
+        }
+
+        // equal values are not less than each other
+        for a in [1, 2, u32::from(u16::MAX), max] {
+            assert_eq!(zero, bitwise_lt(&world, c(a), a.into()).await, "{a} < {a}");
+        }
+
+        // everything that fits into 32 bits is less than constants that don't
+        for b in [1 << 32, (1 << 32) + 1, u128::MAX] {
+            for a in [0, max] {
+                assert_eq!(one, bitwise_lt(&world, c(a), b).await, "{a} < {b}");
+            }
+        }
+        assert_eq!(one, bitwise_lt(&world, c(max), u32::MAX.into()).await);
+    }
+
     proptest! {
         #[test]
         #[allow(clippy::ignored_unit_patterns)] // https://github.com/proptest-rs/proptest/issues/371
@@ -0,0 +1,188 @@
+//! AND and OR of many shares of bits at once.
+//!
+//! Inputs are combined pairwise in a balanced binary tree, so `k` bits take `⌈log2 k⌉` rounds of
+//! communication and `k - 1` multiplications. Multiplication `i` at a given level of the tree runs
+//! on `TreeStep { level, node: i }`.
+
+use super::flip_bits;
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{basics::SecureMul, context::Context, step::TreeStep, BasicProtocols, RecordId},
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+/// Computes `[b_0 · b_1 · … · b_{k-1}]`. The AND of no bits is `1`.
+///
+/// Zero or one input bits do not need any communication.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+pub async fn fan_in_and<F, C, S>(ctx: C, record_id: RecordId, bits: &[S]) -> Result<S, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+{
+    match bits {
+        [] => Ok(S::share_known_value(&ctx, F::ONE)),
+        [bit] => Ok(bit.clone()),
+        _ => multiply_tree(ctx, record_id, bits.to_vec()).await,
+    }
+}
+
+/// Computes `[b_0 ∨ b_1 ∨ … ∨ b_{k-1}]` as `1 - AND(1 - b_i)`. The OR of no bits is `0`.
+///
+/// Zero or one input bits do not need any communication.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+pub async fn fan_in_or<F, C, S>(ctx: C, record_id: RecordId, bits: &[S]) -> Result<S, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+{
+    match bits {
+        [] => Ok(S::ZERO),
+        [bit] => Ok(bit.clone()),
+        _ => {
+            let one = S::share_known_value(&ctx, F::ONE);
+            let none = multiply_tree(ctx, record_id, flip_bits(one.clone(), bits)).await?;
+            Ok(one - &none)
+        }
+    }
+}
+
+async fn multiply_tree<F, C, S>(ctx: C, record_id: RecordId, mut level: Vec<S>) -> Result<S, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + SecureMul<C>,
+{
+    let mut depth = 0_usize;
+    while level.len() > 1 {
+        let pairs = level.chunks_exact(2);
+        let odd = pairs.remainder().first().cloned();
+        let mut next = ctx
+            .parallel_join(pairs.enumerate().map(|(i, pair)| {
+                let step = TreeStep {
+                    level: depth,
+                    node: i,
+                };
+                pair[0].multiply(&pair[1], ctx.narrow_tree(step), record_id)
+            }))
+            .await?;
+        next.extend(odd);
+        level = next;
+        depth += 1;
+    }
+
+    Ok(level.pop().unwrap())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::{fan_in_and, fan_in_or};
+    use crate::{
+        ff::{Field, Fp31},
+        protocol::{context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
+    };
+
+    /// `⌈log2 k⌉`, and `0` for no bits at all.
+    fn expected_rounds(k: usize) -> usize {
+        usize::try_from(k.next_power_of_two().trailing_zeros()).unwrap()
+    }
+
+    /// Returns `k` bits that are all the same except for at most one of them, so that both
+    /// their AND and their OR can go either way.
+    fn input_bits<R: Rng>(rng: &mut R, k: usize) -> Vec<bool> {
+        let value = rng.gen::<bool>();
+        let odd_one = rng.gen_range(0..=k);
+        (0..k).map(|i| value ^ (i == odd_one)).collect()
+    }
+
+    #[tokio::test]
+    async fn semi_honest() {
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let mut rng = thread_rng();
+
+        for k in 0_usize..=64 {
+            let bits = input_bits(&mut rng, k);
+            let input = bits
+                .iter()
+                .map(|&b| Fp31::truncate_from(b))
+                .collect::<Vec<_>>();
+
+            let (and, or) = world
+                .semi_honest(input.into_iter(), |ctx, bits| async move {
+                    let ctx = ctx.set_total_records(1);
+                    let and = fan_in_and(
+                        ctx.narrow(format!("and_{k}").as_str()),
+                        RecordId::FIRST,
+                        &bits,
+                    );
+                    let or = fan_in_or(
+                        ctx.narrow(format!("or_{k}").as_str()),
+                        RecordId::FIRST,
+                        &bits,
+                    );
+                    (and.await.unwrap(), or.await.unwrap())
+                })
+                .await
+                .reconstruct();
+
+            assert_eq!(
+                Fp31::truncate_from(bits.iter().all(|&b| b)),
+                and,
+                "{bits:?}"
+            );
+            assert_eq!(Fp31::truncate_from(bits.iter().any(|&b| b)), or, "{bits:?}");
+            for name in [format!("and_{k}"), format!("or_{k}")] {
+                assert_eq!(
+                    (expected_rounds(k), k.saturating_sub(1)),
+                    recorder.rounds_and_multiplications(&name),
+                    "{name}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn malicious() {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+
+        for k in [0_usize, 1, 2, 3, 17, 64] {
+            let bits = input_bits(&mut rng, k);
+            let input = bits
+                .iter()
+                .map(|&b| Fp31::truncate_from(b))
+                .collect::<Vec<_>>();
+
+            let result = world
+                .upgraded_malicious(input.into_iter(), |ctx, bits| async move {
+                    let ctx = ctx.set_total_records(1);
+                    let and = fan_in_and(ctx.narrow("and"), RecordId::FIRST, &bits);
+                    let or = fan_in_or(ctx.narrow("or"), RecordId::FIRST, &bits);
+                    vec![and.await.unwrap(), or.await.unwrap()]
+                })
+                .await
+                .reconstruct();
+            let [and, or] = <[Fp31; 2]>::try_from(result).unwrap();
+
+            assert_eq!(
+                Fp31::truncate_from(bits.iter().all(|&b| b)),
+                and,
+                "{bits:?}"
+            );
+            assert_eq!(Fp31::truncate_from(bits.iter().any(|&b| b)), or, "{bits:?}");
+        }
+    }
+}
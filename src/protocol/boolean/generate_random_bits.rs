@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use std::{cmp::min, marker::PhantomData, ops::Range};
 
 use futures::stream::{iter as stream_iter, Stream, StreamExt};
 
@@ -13,6 +13,7 @@ use crate::{
             convert_bits, convert_some_bits, BitConversionTriple, ToBitConversionTriples,
         },
         prss::SharedRandomness,
+        step::BitOpStep,
         RecordId,
     },
     secret_sharing::{
@@ -127,6 +128,68 @@ pub async fn one_random_bit<F, C>(
     ctx: C,
     record_id: RecordId,
 ) -> Result<BitDecomposed<C::Share>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F>,
+    C::Share: LinearSecretSharing<F> + SecureMul<C>,
+{
+    convert_random_bits::<F, _>(
+        ctx,
+        record_id,
+        0..(u128::BITS - F::PRIME.into().leading_zeros()),
+    )
+    .await
+}
+
+/// Generates shares of `count` uniformly random bits for the given record, none of them known to
+/// any helper.
+///
+/// Bits come from PRSS values that every helper shares with each of its peers. Each bit is the XOR
+/// of three of them, one for every pair of helpers, so every helper is missing one. Modulus
+/// conversion turns that XOR into a share in `F`. Nothing is rejected, so this always produces
+/// exactly `count` bits.
+///
+/// One PRSS value gives as many bits as there are in the prime of `F`. More bits than that are
+/// drawn in chunks, each on its own step, `BitOpStep::from(chunk)`. In malicious contexts, bits
+/// are upgraded, so the validator checks them.
+///
+/// ## Errors
+/// If the conversion is unsuccessful (usually the result of communication errors).
+pub async fn generate_random_bits<F, C>(
+    ctx: C,
+    record_id: RecordId,
+    count: u32,
+) -> Result<Vec<C::Share>, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F>,
+    C::Share: LinearSecretSharing<F> + SecureMul<C>,
+{
+    let width = u128::BITS - F::PRIME.into().leading_zeros();
+    let chunks = ctx
+        .parallel_join(
+            (0..count)
+                .step_by(width as usize)
+                .enumerate()
+                .map(|(chunk, start)| {
+                    convert_random_bits::<F, _>(
+                        ctx.narrow(&BitOpStep::from(chunk)),
+                        record_id,
+                        0..min(width, count - start),
+                    )
+                }),
+        )
+        .await?;
+
+    Ok(chunks.into_iter().flatten().collect())
+}
+
+/// Converts the given bits of the raw random value drawn for the record.
+async fn convert_random_bits<F, C>(
+    ctx: C,
+    record_id: RecordId,
+    bits: Range<u32>,
+) -> Result<BitDecomposed<C::Share>, Error>
 where
     F: PrimeField,
     C: UpgradedContext<F>,
@@ -137,7 +200,6 @@ where
         record_id,
         _f: PhantomData,
     };
-    let bits = 0..(u128::BITS - F::PRIME.into().leading_zeros());
     Box::pin(convert_some_bits(
         ctx,
         // TODO: For some reason, the input stream is polled 16 times, despite this function only calling "next()" once.
@@ -151,3 +213,99 @@ where
     .unwrap()
     .map(|(v, ())| v)
 }
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{distributions::Standard, prelude::Distribution};
+
+    use super::generate_random_bits;
+    use crate::{
+        ff::{Fp31, Fp32BitPrime, PrimeField},
+        protocol::{
+            context::{Context, UpgradableContext, Validator},
+            RecordId,
+        },
+        secret_sharing::replicated::malicious::ExtendableField,
+        seq_join::SeqJoin,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    const RECORDS: usize = 40;
+
+    /// Generates `count` bits for every record in both security models and returns all of them.
+    async fn generate<F>(count: u32) -> Vec<F>
+    where
+        F: PrimeField + ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        let bits = world
+            .semi_honest((), |ctx, ()| async move {
+                let validator = ctx.validator::<F>();
+                let ctx = validator.context().set_total_records(RECORDS);
+                ctx.try_join(
+                    (0..RECORDS)
+                        .map(|i| generate_random_bits(ctx.clone(), RecordId::from(i), count)),
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+            })
+            .await
+            .reconstruct();
+        let m_bits = world
+            .upgraded_malicious((), |ctx, ()| async move {
+                let ctx = ctx.set_total_records(RECORDS);
+                ctx.try_join(
+                    (0..RECORDS)
+                        .map(|i| generate_random_bits(ctx.clone(), RecordId::from(i), count)),
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+            })
+            .await
+            .reconstruct();
+
+        let expected_len = RECORDS * usize::try_from(count).unwrap();
+        assert_eq!(expected_len, bits.len());
+        assert_eq!(expected_len, m_bits.len());
+        bits.into_iter().chain(m_bits).collect()
+    }
+
+    /// Checks that all values are bits and that roughly half of them are ones.
+    fn assert_uniform_bits<F: PrimeField>(bits: &[F]) {
+        assert!(
+            bits.iter().all(|&b| b == F::ZERO || b == F::ONE),
+            "not all values are bits"
+        );
+        let ones = bits.iter().filter(|&&b| b == F::ONE).count();
+        // The standard deviation of the number of ones is `sqrt(n)/2`, stay within 6 of them.
+        #[allow(clippy::cast_precision_loss)]
+        let (n, ones) = (bits.len() as f64, ones as f64);
+        assert!(
+            (ones - n / 2.0).abs() < 3.0 * n.sqrt(),
+            "{ones} ones out of {n} bits"
+        );
+    }
+
+    #[tokio::test]
+    async fn fp31() {
+        // Fp31 gives 5 bits at a time, so this takes several chunks per record, the last one short.
+        assert_uniform_bits(&generate::<Fp31>(23).await);
+    }
+
+    #[tokio::test]
+    async fn fp32_bit_prime() {
+        assert_uniform_bits(&generate::<Fp32BitPrime>(100).await);
+    }
+
+    #[tokio::test]
+    async fn no_bits() {
+        assert!(generate::<Fp31>(0).await.is_empty());
+    }
+}
@@ -12,19 +12,34 @@ use crate::{
     secret_sharing::{Linear as LinearSecretSharing, SecretSharing},
 };
 
+pub mod add;
 pub mod add_constant;
+#[cfg(feature = "descriptive-gate")]
+pub mod bit_decomposition;
 pub mod bitwise_equal;
 pub mod bitwise_less_than_prime;
 pub mod comparison;
+pub mod fan_in;
 pub mod generate_random_bits;
+pub mod ops;
 pub mod or;
+pub mod prefix_or;
 pub mod random_bits_generator;
 pub mod saturating_sum;
+pub mod select;
 pub mod solved_bits;
 mod xor;
 
-pub use comparison::greater_than_constant;
-pub use generate_random_bits::random_bits;
+pub use add::{bitwise_add, AdderCircuit};
+#[cfg(feature = "descriptive-gate")]
+pub use bit_decomposition::bit_decompose;
+pub use bitwise_equal::equals;
+pub use comparison::{bitwise_less_than_constant, greater_than_constant};
+pub use fan_in::{fan_in_and, fan_in_or};
+pub use generate_random_bits::{generate_random_bits, random_bits};
+pub use ops::BooleanOps;
+pub use prefix_or::prefix_or;
+pub use select::select_all;
 pub use solved_bits::RandomBitsShare;
 pub use xor::{xor, xor_sparse};
 
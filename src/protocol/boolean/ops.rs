@@ -0,0 +1,210 @@
+//! Boolean operations on shares of bits, `a, b ∈ {0,1} ⊆ F_p`.
+//!
+//! Every operation takes exactly one multiplication. It runs on the step of the context it is
+//! called on, the same way [`SecureMul::multiply`] does, so callers narrow the context to a
+//! step of their own protocol before using it. That keeps these operations usable with compact
+//! gates without adding steps to `steps.txt`.
+
+use async_trait::async_trait;
+
+use super::{or::or, xor::xor};
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{
+        basics::{if_else, SecureMul},
+        context::Context,
+        RecordId,
+    },
+    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
+};
+
+/// Extension trait that lets protocol contexts combine shares of bits.
+#[async_trait]
+pub trait BooleanOps<F: Field, S>: Context
+where
+    S: LinearSecretSharing<F> + SecureMul<Self>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    /// Computes `[a] + [b] - 2[ab]`.
+    ///
+    /// ## Errors
+    /// When communication fails.
+    async fn xor(&self, record_id: RecordId, a: &S, b: &S) -> Result<S, Error>;
+
+    /// Computes `[ab]`.
+    ///
+    /// ## Errors
+    /// When communication fails.
+    async fn and(&self, record_id: RecordId, a: &S, b: &S) -> Result<S, Error>;
+
+    /// Computes `[a] + [b] - [ab]`.
+    ///
+    /// ## Errors
+    /// When communication fails.
+    async fn or(&self, record_id: RecordId, a: &S, b: &S) -> Result<S, Error>;
+
+    /// Computes `[b] + [cond]([a] - [b])`, which is `a` if `cond` is `1` and `b` if it is `0`.
+    /// See [`if_else`].
+    ///
+    /// `cond` must be a share of a bit. Nothing checks that, any other value yields a combination
+    /// of `a` and `b` that is neither of them.
+    ///
+    /// ## Errors
+    /// When communication fails.
+    async fn select(&self, record_id: RecordId, cond: &S, a: &S, b: &S) -> Result<S, Error>;
+}
+
+#[async_trait]
+impl<F, C, S> BooleanOps<F, S> for C
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + SecureMul<C>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    async fn xor(&self, record_id: RecordId, a: &S, b: &S) -> Result<S, Error> {
+        xor::<F, _, _>(self.clone(), record_id, a, b).await
+    }
+
+    async fn and(&self, record_id: RecordId, a: &S, b: &S) -> Result<S, Error> {
+        a.multiply(b, self.clone(), record_id).await
+    }
+
+    async fn or(&self, record_id: RecordId, a: &S, b: &S) -> Result<S, Error> {
+        or::<F, _, _>(self.clone(), record_id, a, b).await
+    }
+
+    async fn select(&self, record_id: RecordId, cond: &S, a: &S, b: &S) -> Result<S, Error> {
+        if_else(self.clone(), record_id, cond, a, b).await
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use rand::{distributions::Standard, prelude::Distribution};
+
+    use super::BooleanOps;
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime},
+        protocol::{context::Context, RecordId},
+        secret_sharing::{replicated::malicious::ExtendableField, SharedValue},
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    #[derive(Clone, Copy, Debug)]
+    enum Op {
+        Xor,
+        And,
+        Or,
+    }
+
+    impl Op {
+        fn expected(self, a: bool, b: bool) -> bool {
+            match self {
+                Op::Xor => a ^ b,
+                Op::And => a & b,
+                Op::Or => a | b,
+            }
+        }
+    }
+
+    /// Runs `op` in both security models and checks they agree.
+    async fn run<F>(world: &TestWorld, op: Op, a: bool, b: bool) -> bool
+    where
+        F: ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let (a, b) = (
+            F::truncate_from(u128::from(a)),
+            F::truncate_from(u128::from(b)),
+        );
+
+        let result = world
+            .semi_honest((a, b), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(1);
+                match op {
+                    Op::Xor => ctx.xor(RecordId::FIRST, &a, &b),
+                    Op::And => ctx.and(RecordId::FIRST, &a, &b),
+                    Op::Or => ctx.or(RecordId::FIRST, &a, &b),
+                }
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result = world
+            .upgraded_malicious((a, b), |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(1);
+                match op {
+                    Op::Xor => ctx.xor(RecordId::FIRST, &a, &b),
+                    Op::And => ctx.and(RecordId::FIRST, &a, &b),
+                    Op::Or => ctx.or(RecordId::FIRST, &a, &b),
+                }
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(result, m_result, "{op:?} disagrees between security models");
+        assert!(
+            result == F::ZERO || result == F::ONE,
+            "{op:?} result is not a bit"
+        );
+        result == F::ONE
+    }
+
+    #[tokio::test]
+    async fn all_combinations() {
+        let world = TestWorld::default();
+
+        for op in [Op::Xor, Op::And, Op::Or] {
+            for (a, b) in [(false, false), (false, true), (true, false), (true, true)] {
+                assert_eq!(
+                    op.expected(a, b),
+                    run::<Fp31>(&world, op, a, b).await,
+                    "{op:?}({a}, {b}) in Fp31"
+                );
+                assert_eq!(
+                    op.expected(a, b),
+                    run::<Fp32BitPrime>(&world, op, a, b).await,
+                    "{op:?}({a}, {b}) in Fp32BitPrime"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn select() {
+        let world = TestWorld::default();
+        let (a, b) = (
+            Fp32BitPrime::truncate_from(17_u128),
+            Fp32BitPrime::truncate_from(42_u128),
+        );
+
+        for (cond, expected) in [(Fp32BitPrime::ZERO, b), (Fp32BitPrime::ONE, a)] {
+            let result = world
+                .semi_honest((cond, (a, b)), |ctx, (cond, (a, b))| async move {
+                    ctx.set_total_records(1)
+                        .select(RecordId::FIRST, &cond, &a, &b)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+            let m_result = world
+                .upgraded_malicious((cond, (a, b)), |ctx, (cond, (a, b))| async move {
+                    ctx.set_total_records(1)
+                        .select(RecordId::FIRST, &cond, &a, &b)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+
+            assert_eq!(expected, result, "select({cond:?}, {a:?}, {b:?})");
+            assert_eq!(expected, m_result, "select({cond:?}, {a:?}, {b:?})");
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Prefix-OR of shares of bits: `c_i = b_0 ∨ b_1 ∨ … ∨ b_i` for every `i`.
+//!
+//! This is a Hillis–Steele scan over the complements of the input bits: `c_i = 1 - Π (1 - b_j)`,
+//! where prefix products are built by multiplying every element with the one `2^level` positions
+//! before it. `k` bits take `⌈log2 k⌉` rounds and `Σ (k - 2^level)` multiplications. The
+//! multiplication that computes position `i` at a given level runs on `TreeStep { level, node: i }`.
+
+use super::flip_bits;
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{context::Context, step::TreeStep, BasicProtocols, RecordId},
+    secret_sharing::Linear as LinearSecretSharing,
+};
+
+/// Computes `[b_0 ∨ … ∨ b_i]` for every `i`. Output has the same length as `bits`.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+pub async fn prefix_or<F, C, S>(ctx: C, record_id: RecordId, bits: &[S]) -> Result<Vec<S>, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+{
+    let one = S::share_known_value(&ctx, F::ONE);
+    let mut none = flip_bits(one.clone(), bits);
+
+    let mut stride = 1;
+    let mut level = 0_usize;
+    while stride < none.len() {
+        let products = ctx
+            .parallel_join((stride..none.len()).map(|i| {
+                none[i].multiply(
+                    &none[i - stride],
+                    ctx.narrow_tree(TreeStep { level, node: i }),
+                    record_id,
+                )
+            }))
+            .await?;
+        for (i, product) in (stride..).zip(products) {
+            none[i] = product;
+        }
+        stride *= 2;
+        level += 1;
+    }
+
+    Ok(none.into_iter().map(|x| one.clone() - &x).collect())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::prefix_or;
+    use crate::{
+        ff::{Field, Fp31},
+        protocol::{context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
+    };
+
+    /// Returns random bits that are mostly zeroes, so prefix-OR of them isn't all ones right away.
+    fn input_bits<R: Rng>(rng: &mut R, k: usize) -> Vec<bool> {
+        (0..k).map(|_| rng.gen_ratio(1, 8)).collect()
+    }
+
+    fn expected(bits: &[bool]) -> Vec<Fp31> {
+        bits.iter()
+            .scan(false, |acc, &b| {
+                *acc |= b;
+                Some(Fp31::truncate_from(*acc))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn semi_honest() {
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let mut rng = thread_rng();
+
+        for k in 1_usize..=40 {
+            let bits = input_bits(&mut rng, k);
+            let input = bits
+                .iter()
+                .map(|&b| Fp31::truncate_from(b))
+                .collect::<Vec<_>>();
+
+            let result = world
+                .semi_honest(input.into_iter(), |ctx, bits| async move {
+                    let ctx = ctx
+                        .narrow(format!("prefix_or_{k}").as_str())
+                        .set_total_records(1);
+                    prefix_or(ctx, RecordId::FIRST, &bits).await.unwrap()
+                })
+                .await
+                .reconstruct();
+            assert_eq!(expected(&bits), result, "{bits:?}");
+
+            let rounds = usize::try_from(k.next_power_of_two().trailing_zeros()).unwrap();
+            let multiplications = (0..rounds).map(|level| k - (1 << level)).sum::<usize>();
+            assert_eq!(
+                (rounds, multiplications),
+                recorder.rounds_and_multiplications(&format!("prefix_or_{k}")),
+                "{k} bits"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn malicious() {
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+
+        for k in 1_usize..=40 {
+            let bits = input_bits(&mut rng, k);
+            let input = bits
+                .iter()
+                .map(|&b| Fp31::truncate_from(b))
+                .collect::<Vec<_>>();
+
+            let result = world
+                .upgraded_malicious(input.into_iter(), |ctx, bits| async move {
+                    prefix_or(ctx.set_total_records(1), RecordId::FIRST, &bits)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+            assert_eq!(expected(&bits), result, "{bits:?}");
+        }
+    }
+}
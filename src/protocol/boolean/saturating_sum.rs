@@ -1,10 +1,19 @@
+use ipa_macros::Step;
+
 use crate::{
     error::Error,
     ff::{Field, Gf2},
-    protocol::{boolean::or::or, context::Context, step::BitOpStep, BasicProtocols, RecordId},
+    protocol::{boolean::or::or, context::Context, BasicProtocols, RecordId},
     secret_sharing::{BitDecomposed, Linear as LinearSecretSharing, LinearRefOps},
 };
 
+#[derive(Step)]
+pub(crate) enum Step {
+    AddBits,
+    IsSaturated,
+    SubtractBits,
+}
+
 #[derive(Debug)]
 pub struct SaturatingSum<S: LinearSecretSharing<Gf2>> {
     pub sum: BitDecomposed<S>,
@@ -42,7 +51,7 @@ impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
         let mut carry_in = S::ZERO;
         let zero = S::ZERO;
         for i in 0..self.sum.len() {
-            let c = ctx.narrow(&BitOpStep::from(i));
+            let c = ctx.narrow_indexed(&Step::AddBits, i);
             // When adding a value with fewer bits than the saturating sum can express
             // we still must compute the carries, which still requires a single multiplication
             // so there is no savings
@@ -52,7 +61,7 @@ impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
             output_sum.push(sum_bit);
         }
         let is_saturated = or(
-            ctx.narrow(&BitOpStep::from(self.sum.len())),
+            ctx.narrow(&Step::IsSaturated),
             record_id,
             &carry_in,
             &self.is_saturated,
@@ -82,6 +91,37 @@ impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
         record_id: RecordId,
         num_bits: u32,
     ) -> Result<BitDecomposed<S>, Error>
+    where
+        C: Context,
+        S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
+        for<'a> &'a S: LinearRefOps<'a, S, Gf2>,
+    {
+        self.truncated_delta_to_cap(ctx, record_id, 1 << self.sum.len(), num_bits)
+            .await
+    }
+
+    ///
+    /// Same as [`truncated_delta_to_saturation_point`], but for any `cap`: returns the least
+    /// significant `num_bits` of `cap - sum`.
+    ///
+    /// NOTE: ignores the `is_saturated` flag. The return value is non-sensical if the sum is larger
+    /// than `cap`.
+    ///
+    /// [`truncated_delta_to_saturation_point`]: Self::truncated_delta_to_saturation_point
+    ///
+    /// # Errors
+    /// If one of the multiplications errors
+    ///
+    /// # Panics
+    /// If you ask for more bits than the `SaturatingSum` is using
+    ///
+    pub async fn truncated_delta_to_cap<C>(
+        &self,
+        ctx: C,
+        record_id: RecordId,
+        cap: u128,
+        num_bits: u32,
+    ) -> Result<BitDecomposed<S>, Error>
     where
         C: Context,
         S: LinearSecretSharing<Gf2> + BasicProtocols<C, Gf2>,
@@ -92,13 +132,14 @@ impl<S: LinearSecretSharing<Gf2>> SaturatingSum<S> {
         let mut carry_in = S::share_known_value(&ctx, Gf2::ONE);
         let mut output = vec![];
         for (i, bit) in self.sum.iter().enumerate().take(num_bits as usize) {
-            let c = ctx.narrow(&BitOpStep::from(i));
+            let c = ctx.narrow_indexed(&Step::SubtractBits, i);
 
             let compute_carry_out = i < (num_bits as usize) - 1;
+            let cap_bit = S::share_known_value(&ctx, Gf2::truncate_from((cap >> i) & 1));
             let difference_bit = one_bit_subtractor(
                 c,
                 record_id,
-                &S::ZERO,
+                &cap_bit,
                 bit,
                 &mut carry_in,
                 compute_carry_out,
@@ -296,6 +337,16 @@ mod tests {
         assert_eq!(1, truncated_delta_to_saturation_point(15, 4, 4).await);
     }
 
+    #[tokio::test]
+    pub async fn truncated_delta_to_cap() {
+        assert_eq!(3, truncated_delta_to(2, 4, 5, 3).await);
+        assert_eq!(0, truncated_delta_to(5, 4, 5, 3).await);
+        assert_eq!(5, truncated_delta_to(0, 4, 5, 3).await);
+        assert_eq!(1, truncated_delta_to(2, 4, 3, 3).await);
+        assert_eq!(2, truncated_delta_to(9, 5, 11, 2).await);
+        assert_eq!(2, truncated_delta_to(30, 5, 1 << 5, 2).await);
+    }
+
     async fn saturating_add(a: u32, num_a_bits: u32, b: u32, num_b_bits: u32) -> u128 {
         let world = TestWorld::default();
 
@@ -337,4 +388,21 @@ mod tests {
 
         foo.reconstruct()
     }
+
+    async fn truncated_delta_to(a: u32, num_a_bits: u32, cap: u128, num_b_bits: u32) -> u128 {
+        let world = TestWorld::default();
+
+        let a_bits = get_bits::<Gf2>(a, num_a_bits);
+
+        let foo = world
+            .semi_honest(a_bits, |ctx, a_bits: BitDecomposed<_>| async move {
+                let a = SaturatingSum::new(a_bits, Replicated::ZERO);
+                a.truncated_delta_to_cap(ctx.set_total_records(1), RecordId(0), cap, num_b_bits)
+                    .await
+                    .unwrap()
+            })
+            .await;
+
+        foo.reconstruct()
+    }
 }
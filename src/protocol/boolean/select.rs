@@ -0,0 +1,156 @@
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::Field,
+    protocol::{
+        basics::{if_else, SecureMul},
+        context::Context,
+        RecordId,
+    },
+    secret_sharing::{Linear as LinearSecretSharing, LinearRefOps},
+};
+
+#[derive(Step)]
+pub(crate) enum Step {
+    SelectBits,
+}
+
+/// Selects `true_values` if `condition` is a share of 1, else `false_values`, element by element.
+///
+/// This is [`if_else`] applied to every pair of values with the same condition. Every element
+/// still needs its own multiplication by `condition`, but all of them are done in parallel, in a
+/// single round. Multiplication for element `i` runs on iteration `i` of `Step::SelectBits`, see
+/// [`Context::narrow_indexed`].
+///
+/// `condition` must be a share of a bit.
+///
+/// ## Errors
+/// If any of the multiplications fails.
+///
+/// ## Panics
+/// If `true_values` and `false_values` are not of the same length.
+pub async fn select_all<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    condition: &S,
+    true_values: &[S],
+    false_values: &[S],
+) -> Result<Vec<S>, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + SecureMul<C>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    assert_eq!(
+        true_values.len(),
+        false_values.len(),
+        "select requires the same number of values for either condition"
+    );
+
+    ctx.parallel_join(
+        true_values
+            .iter()
+            .zip(false_values)
+            .enumerate()
+            .map(|(i, (t, f))| {
+                if_else(
+                    ctx.narrow_indexed(&Step::SelectBits, i),
+                    record_id,
+                    condition,
+                    t,
+                    f,
+                )
+            }),
+    )
+    .await
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::select_all;
+    use crate::{
+        ff::{Field, Fp32BitPrime},
+        protocol::{basics::if_else, context::Context, RecordId},
+        rand::{thread_rng, Rng},
+        secret_sharing::SharedValue,
+        seq_join::SeqJoin,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    #[tokio::test]
+    async fn selects_either_slice() {
+        const COUNT: usize = 8;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let a = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let b = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+
+        for (cond, expected) in [(Fp32BitPrime::ZERO, &b), (Fp32BitPrime::ONE, &a)] {
+            let input = (cond, (a.clone().into_iter(), b.clone().into_iter()));
+            let result = world
+                .semi_honest(input.clone(), |ctx, (cond, (a, b))| async move {
+                    let ctx = ctx.set_total_records(1);
+                    select_all(ctx, RecordId::FIRST, &cond, &a, &b)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+            let m_result = world
+                .upgraded_malicious(input, |ctx, (cond, (a, b))| async move {
+                    let ctx = ctx.set_total_records(1);
+                    select_all(ctx, RecordId::FIRST, &cond, &a, &b)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+
+            assert_eq!(expected, &result, "select_all({cond:?})");
+            assert_eq!(expected, &m_result, "select_all({cond:?})");
+        }
+    }
+
+    /// Selecting all values at once must give the same result as selecting them one by one.
+    #[tokio::test]
+    async fn matches_element_wise() {
+        const COUNT: usize = 5;
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+        let a = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let b = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let cond = Fp32BitPrime::truncate_from(rng.gen::<bool>());
+
+        let (all, each): (Vec<Fp32BitPrime>, Vec<Fp32BitPrime>) = world
+            .semi_honest(
+                (cond, (a.into_iter(), b.into_iter())),
+                |ctx, (cond, (a, b))| async move {
+                    let ctx = ctx.set_total_records(1);
+                    let all = select_all(ctx.narrow("all"), RecordId::FIRST, &cond, &a, &b)
+                        .await
+                        .unwrap();
+                    let each = ctx
+                        .parallel_join(a.iter().zip(&b).enumerate().map(|(i, (a, b))| {
+                            if_else(ctx.narrow_indexed("each", i), RecordId::FIRST, &cond, a, b)
+                        }))
+                        .await
+                        .unwrap();
+                    (all, each)
+                },
+            )
+            .await
+            .reconstruct();
+
+        assert_eq!(each, all);
+    }
+}
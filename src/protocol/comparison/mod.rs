@@ -0,0 +1,390 @@
+//! Comparison of two secret-shared field elements, `[a] < [b]`.
+//!
+//! Both operands are bit-decomposed first, so the comparison works for any pair of field elements.
+//! Bits are then compared from the most significant one down: the first position where they
+//! differ is found with [`prefix_or`] of `a_i ⊕ b_i`, and `a < b` iff `b` has a `1` there.
+
+use async_trait::async_trait;
+use futures::future::try_join;
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::{Field, PrimeField},
+    protocol::{
+        boolean::{
+            bit_decomposition::{bit_decompose, BitDecompositionStep},
+            prefix_or,
+            random_bits_generator::RandomBitsGenerator,
+            xor,
+        },
+        context::{Context, UpgradedContext, UpgradedMaliciousContext, UpgradedSemiHonestContext},
+        BasicProtocols, RecordId,
+    },
+    secret_sharing::{
+        replicated::{
+            malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
+            semi_honest::AdditiveShare as Replicated,
+        },
+        Linear as LinearSecretSharing, LinearRefOps,
+    },
+};
+
+/// Extension trait that lets upgraded contexts compare two shared values.
+#[async_trait]
+pub trait SecureComparison<F: PrimeField>: UpgradedContext<F> {
+    /// Computes `[a < b]`, comparing `a` and `b` as integers in `[0, p)`.
+    ///
+    /// `masks` must be created from this context, see [`ComparisonMasks::new`].
+    ///
+    /// ## Errors
+    /// If communication with peers fails.
+    async fn less_than(
+        &self,
+        record_id: RecordId,
+        masks: &ComparisonMasks<F, Self, Self::Share>,
+        a: &Self::Share,
+        b: &Self::Share,
+    ) -> Result<Self::Share, Error>;
+}
+
+#[async_trait]
+impl<'a, F> SecureComparison<F> for UpgradedSemiHonestContext<'a, F>
+where
+    F: PrimeField + ExtendableField,
+{
+    async fn less_than(
+        &self,
+        record_id: RecordId,
+        masks: &ComparisonMasks<F, Self, Replicated<F>>,
+        a: &Replicated<F>,
+        b: &Replicated<F>,
+    ) -> Result<Replicated<F>, Error> {
+        less_than(self.clone(), record_id, masks, a, b).await
+    }
+}
+
+#[async_trait]
+impl<'a, F> SecureComparison<F> for UpgradedMaliciousContext<'a, F>
+where
+    F: PrimeField + ExtendableField,
+{
+    async fn less_than(
+        &self,
+        record_id: RecordId,
+        masks: &ComparisonMasks<F, Self, MaliciousReplicated<F>>,
+        a: &MaliciousReplicated<F>,
+        b: &MaliciousReplicated<F>,
+    ) -> Result<MaliciousReplicated<F>, Error> {
+        less_than(self.clone(), record_id, masks, a, b).await
+    }
+}
+
+/// Random values that hide the operands of [`less_than`] while they are bit-decomposed, one for
+/// each operand of every record.
+///
+/// Random values are drawn with [`RandomBitsGenerator`], which sometimes needs to try again and
+/// keeps count of those attempts across records. That is why all comparisons running on the same
+/// context must share the same instance.
+#[derive(Debug)]
+pub struct ComparisonMasks<F, C, S> {
+    a: RandomBitsGenerator<F, C, S>,
+    b: RandomBitsGenerator<F, C, S>,
+}
+
+impl<F, C, S> ComparisonMasks<F, C, S>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    /// Creates masks for comparisons that run on `ctx`.
+    #[must_use]
+    pub fn new(ctx: &C) -> Self {
+        let mask_ctx = |step: Step| ctx.narrow(&step).narrow(&BitDecompositionStep::RandomMask);
+        Self {
+            a: RandomBitsGenerator::new(mask_ctx(Step::DecomposeA)),
+            b: RandomBitsGenerator::new(mask_ctx(Step::DecomposeB)),
+        }
+    }
+}
+
+/// Computes `[a < b]`, comparing `a` and `b` as integers in `[0, p)`.
+///
+/// Both operands are decomposed at the same time with [`bit_decompose`], then their bits are
+/// compared with [`bitwise_less_than`].
+///
+/// ## Errors
+/// If communication with peers fails.
+pub async fn less_than<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    masks: &ComparisonMasks<F, C, S>,
+    a: &S,
+    b: &S,
+) -> Result<S, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    let (a_bits, b_bits) = try_join(
+        bit_decompose(ctx.narrow(&Step::DecomposeA), record_id, &masks.a, a),
+        bit_decompose(ctx.narrow(&Step::DecomposeB), record_id, &masks.b, b),
+    )
+    .await?;
+
+    bitwise_less_than(ctx, record_id, &a_bits, &b_bits).await
+}
+
+/// Computes `[a < b]` for two bitwise-shared integers of the same width, least significant bits
+/// first. `l`-bit inputs take one round to find out where the bits differ, `⌈log2 l⌉` rounds to
+/// find the most significant of those positions with [`prefix_or`] and one more for the dot
+/// product that picks the bit of `b` there. Integers with no bits are equal.
+///
+/// ## Errors
+/// If communication with peers fails.
+///
+/// ## Panics
+/// If `a_bits` and `b_bits` are not of the same length.
+pub async fn bitwise_less_than<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    a_bits: &[S],
+    b_bits: &[S],
+) -> Result<S, Error>
+where
+    F: Field,
+    C: Context,
+    S: LinearSecretSharing<F> + BasicProtocols<C, F>,
+    for<'a> &'a S: LinearRefOps<'a, S, F>,
+{
+    assert_eq!(
+        a_bits.len(),
+        b_bits.len(),
+        "bitwise comparison requires inputs of the same width"
+    );
+    if a_bits.is_empty() {
+        return Ok(S::ZERO);
+    }
+
+    let mut differ = ctx
+        .parallel_join(a_bits.iter().zip(b_bits).enumerate().map(|(i, (a, b))| {
+            xor::<F, _, _>(ctx.narrow_indexed(&Step::XorBits, i), record_id, a, b)
+        }))
+        .await?;
+
+    // Prefix-OR from the most significant bit turns into `1` at the first position where `a` and
+    // `b` differ and stays `1` after that.
+    differ.reverse();
+    let prefix = prefix_or(ctx.narrow(&Step::PrefixOr), record_id, &differ).await?;
+    let mut first_differing_bit = Vec::with_capacity(prefix.len());
+    first_differing_bit.push(prefix[0].clone());
+    first_differing_bit.extend(prefix.windows(2).map(|w| &w[1] - &w[0]));
+    first_differing_bit.reverse();
+
+    S::sum_of_products(
+        ctx.narrow(&Step::DotProduct),
+        record_id,
+        &first_differing_bit,
+        b_bits,
+    )
+    .await
+}
+
+#[derive(Step)]
+pub(crate) enum Step {
+    DecomposeA,
+    DecomposeB,
+    XorBits,
+    PrefixOr,
+    DotProduct,
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use rand::{distributions::Standard, prelude::Distribution};
+
+    use super::{ComparisonMasks, SecureComparison};
+    use crate::{
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField},
+        helpers::HelperIdentity,
+        protocol::{
+            context::{Context, UpgradableContext, Validator},
+            RecordId,
+        },
+        rand::{thread_rng, Rng},
+        secret_sharing::replicated::malicious::ExtendableField,
+        seq_join::SeqJoin,
+        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
+    };
+
+    /// Compares every pair in both security models and checks they agree.
+    async fn less_than<F>(world: &TestWorld, pairs: &[(u128, u128)]) -> Vec<bool>
+    where
+        F: PrimeField + ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let (a, b): (Vec<F>, Vec<F>) = pairs
+            .iter()
+            .map(|&(a, b)| (F::truncate_from(a), F::truncate_from(b)))
+            .unzip();
+        let input = (a.into_iter(), b.into_iter());
+
+        let result = world
+            .semi_honest(input.clone(), |ctx, (a, b)| async move {
+                let validator = ctx.validator::<F>();
+                let ctx = validator.context().set_total_records(a.len());
+                let masks = ComparisonMasks::new(&ctx);
+                ctx.try_join(
+                    a.iter()
+                        .zip(&b)
+                        .enumerate()
+                        .map(|(i, (a, b))| ctx.less_than(RecordId::from(i), &masks, a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+        let m_result = world
+            .upgraded_malicious(input, |ctx, (a, b)| async move {
+                let ctx = ctx.set_total_records(a.len());
+                let masks = ComparisonMasks::new(&ctx);
+                ctx.try_join(
+                    a.iter()
+                        .zip(&b)
+                        .enumerate()
+                        .map(|(i, (a, b))| ctx.less_than(RecordId::from(i), &masks, a, b)),
+                )
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(result, m_result, "security models disagree");
+        result
+            .into_iter()
+            .map(|lt| {
+                assert!(lt == F::ZERO || lt == F::ONE, "{lt:?} is not a bit");
+                lt == F::ONE
+            })
+            .collect()
+    }
+
+    async fn check<F>(pairs: &[(u128, u128)])
+    where
+        F: PrimeField + ExtendableField,
+        Standard: Distribution<F>,
+    {
+        let world = TestWorld::default();
+        let result = less_than::<F>(&world, pairs).await;
+        for (&(a, b), lt) in pairs.iter().zip(result) {
+            assert_eq!(a < b, lt, "{a} < {b}");
+        }
+    }
+
+    #[tokio::test]
+    async fn equal_values() {
+        let p = u128::from(Fp32BitPrime::PRIME);
+        let mut rng = thread_rng();
+        let mut values = vec![0, 1, p / 2, p - 1];
+        values.extend((0..4).map(|_| rng.gen_range(0..p)));
+
+        check::<Fp32BitPrime>(&values.into_iter().map(|v| (v, v)).collect::<Vec<_>>()).await;
+    }
+
+    #[tokio::test]
+    async fn random_pairs() {
+        let p = u128::from(Fp32BitPrime::PRIME);
+        let mut rng = thread_rng();
+        let pairs = (0..16)
+            .map(|_| (rng.gen_range(0..p), rng.gen_range(0..p)))
+            .collect::<Vec<_>>();
+
+        check::<Fp32BitPrime>(&pairs).await;
+    }
+
+    /// Pairs that differ by more than `p/2`, which comparing the sign of `a - b` gets wrong.
+    #[tokio::test]
+    async fn straddling_midpoint() {
+        let p = u128::from(Fp32BitPrime::PRIME);
+        let mid = (p - 1) / 2;
+        let pairs = [
+            (mid, mid + 1),
+            (mid + 1, mid),
+            (mid - 1, mid + 2),
+            (0, p - 1),
+            (p - 1, 0),
+            (1, mid + 2),
+            (p - 2, mid),
+        ];
+
+        check::<Fp32BitPrime>(&pairs).await;
+    }
+
+    /// Fp31 is small enough to try every pair, and random values drawn to decompose them fall
+    /// outside of the field often enough for the fallback to be exercised too.
+    #[tokio::test]
+    async fn all_fp31() {
+        let p = u128::from(Fp31::PRIME);
+        let pairs = (0..p)
+            .flat_map(|a| (0..p).map(move |b| (a, b)))
+            .collect::<Vec<_>>();
+
+        check::<Fp31>(&pairs).await;
+    }
+
+    /// Every helper must narrow contexts to the same steps, whatever the values are, or their
+    /// channels would not line up.
+    #[tokio::test]
+    async fn steps() {
+        let recorder = TrafficRecorder::default();
+        let world = TestWorld::new_with(TestWorldConfig {
+            send_observer: Some(recorder.observer()),
+            ..TestWorldConfig::default()
+        });
+        let p = u128::from(Fp32BitPrime::PRIME);
+        for (name, a, b) in [("first", 0, p - 1), ("second", p - 1, p - 1)] {
+            let input = (
+                Fp32BitPrime::truncate_from(a),
+                Fp32BitPrime::truncate_from(b),
+            );
+            world
+                .upgraded_malicious(input, |ctx, (a, b)| async move {
+                    let ctx = ctx.narrow(name).set_total_records(1);
+                    let masks = ComparisonMasks::new(&ctx);
+                    ctx.less_than(RecordId::FIRST, &masks, &a, &b)
+                        .await
+                        .unwrap()
+                })
+                .await;
+        }
+
+        let mut senders = HashMap::<_, HashMap<_, HashSet<HelperIdentity>>>::new();
+        for record in recorder.with_step_prefix("protocol") {
+            let gate = record.gate.as_ref();
+            for name in ["first", "second"] {
+                let marker = format!("/{name}/");
+                if let Some(start) = gate.find(&marker) {
+                    senders
+                        .entry(name)
+                        .or_default()
+                        .entry(gate[start + marker.len()..].to_owned())
+                        .or_default()
+                        .insert(record.from);
+                }
+            }
+        }
+
+        let (first, second) = (&senders["first"], &senders["second"]);
+        // sparse multiplications in the upgrades leave some helpers silent, the same ones always
+        assert!(!first.is_empty());
+        assert_eq!(first, second, "steps depend on the values");
+    }
+}
@@ -333,7 +333,7 @@ impl<'a, F: ExtendableField> super::Context for Upgraded<'a, F> {
     fn recv_channel<M: Message>(&self, role: Role) -> ReceivingEnd<M> {
         self.inner
             .gateway
-            .get_receiver(&ChannelId::new(role, self.gate.clone()))
+            .get_receiver(&ChannelId::new(role, self.gate.clone()), self.total_records)
     }
 }
 
@@ -4,9 +4,10 @@ pub mod semi_honest;
 pub mod upgrade;
 pub mod validator;
 
-use std::{num::NonZeroUsize, sync::Arc};
+use std::{future::Future, num::NonZeroUsize, ops::Range, sync::Arc};
 
 use async_trait::async_trait;
+use futures::stream::TryCollect;
 pub use malicious::{Context as MaliciousContext, Upgraded as UpgradedMaliciousContext};
 use prss::{InstrumentedIndexedSharedRandomness, InstrumentedSequentialSharedRandomness};
 pub use semi_honest::{Context as SemiHonestContext, Upgraded as UpgradedSemiHonestContext};
@@ -15,18 +16,19 @@ pub use validator::Validator;
 
 use crate::{
     error::Error,
+    ff::Field,
     helpers::{ChannelId, Gateway, Message, ReceivingEnd, Role, SendingEnd, TotalRecords},
     protocol::{
         basics::ZeroPositions,
-        prss::Endpoint as PrssEndpoint,
-        step::{Gate, Step, StepNarrow},
+        prss::{Endpoint as PrssEndpoint, SharedRandomness},
+        step::{BitOpStep, Gate, Step, StepNarrow, TreeLevel, TreeStep},
         RecordId,
     },
     secret_sharing::{
         replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
         SecretSharing,
     },
-    seq_join::SeqJoin,
+    seq_join::{SeqJoin, SeqTryJoinAll},
 };
 
 /// Context used by each helper to perform secure computation. Provides access to shared randomness
@@ -39,6 +41,15 @@ pub trait Context: Clone + Send + Sync + SeqJoin {
     #[must_use]
     fn gate(&self) -> &Gate;
 
+    /// Full path of steps this context was narrowed through, starting from the root of the
+    /// protocol. Every step is qualified with the name of its type, so helpers that narrow
+    /// into different steps can be told apart even if the steps are spelled the same way.
+    #[cfg(feature = "step-trace")]
+    #[must_use]
+    fn current_step_path(&self) -> String {
+        self.gate().to_string()
+    }
+
     /// Make a sub-context.
     /// Note that each invocation of this should use a unique value of `step`.
     #[must_use]
@@ -46,8 +57,26 @@ pub trait Context: Clone + Send + Sync + SeqJoin {
     where
         Gate: StepNarrow<S>;
 
+    /// Makes a sub-context for iteration `i` of a loop that runs under `base`, by narrowing to
+    /// `base` and then to [`BitOpStep`] `i`. Every helper that runs the same loop ends up on the
+    /// same steps, and iterations never share one.
+    #[must_use]
+    fn narrow_indexed<S: Step + ?Sized>(&self, base: &S, i: usize) -> Self
+    where
+        Gate: StepNarrow<S>,
+    {
+        self.narrow(base).narrow::<BitOpStep>(&BitOpStep::from(i))
+    }
+
+    /// Makes a sub-context for a node of a tree-shaped circuit, see [`TreeStep`].
+    #[must_use]
+    fn narrow_tree(&self, step: TreeStep) -> Self {
+        self.narrow_indexed(&TreeLevel::from(step.level), step.node)
+    }
+
     /// Sets the context's total number of records field. Communication channels are
-    /// closed based on sending the expected total number of records.
+    /// closed based on sending the expected total number of records. Receiving channels
+    /// only use it as a hint to size their buffers.
     #[must_use]
     fn set_total_records<T: Into<TotalRecords>>(&self, total_records: T) -> Self;
 
@@ -55,6 +84,26 @@ pub trait Context: Clone + Send + Sync + SeqJoin {
     #[must_use]
     fn total_records(&self) -> TotalRecords;
 
+    /// Runs `f` for every record in `records` and returns the results in the same order. `f` gets
+    /// the id of the record and a context that expects `records.end` records in total, so every
+    /// channel is flushed as soon as the last record is sent through it. Like [`try_join`], this
+    /// keeps at most [`active_work`] records in flight.
+    ///
+    /// [`try_join`]: SeqJoin::try_join
+    /// [`active_work`]: SeqJoin::active_work
+    fn parallel_records<F, Fut, O, E>(
+        &self,
+        records: Range<usize>,
+        f: F,
+    ) -> TryCollect<SeqTryJoinAll<PerRecord<Self, F>, Fut>, Vec<O>>
+    where
+        F: Fn(Self, RecordId) -> Fut + Send,
+        Fut: Future<Output = Result<O, E>>,
+    {
+        let ctx = self.set_total_records(records.end);
+        self.try_join(PerRecord { ctx, records, f })
+    }
+
     /// Get the indexed PRSS instance for this step.  It is safe to call this function
     /// multiple times.
     ///
@@ -64,6 +113,21 @@ pub trait Context: Clone + Send + Sync + SeqJoin {
     #[must_use]
     fn prss(&self) -> InstrumentedIndexedSharedRandomness<'_>;
 
+    /// Draws a random field value for `record_id` from the indexed PRSS of this context. This
+    /// helper's value is the sum of the two values it shares with its peers, so the values of
+    /// all three helpers are additive shares of a random value that none of them knows.
+    ///
+    /// PRSS yields 128 bits, which are reduced into the field without rejection. For the prime
+    /// fields used here, the bias this introduces is negligible.
+    ///
+    /// # Panics
+    /// If `prss_rng()` was invoked for the same context, or, in debug builds, if randomness was
+    /// already drawn for `record_id` on this step.
+    #[must_use]
+    fn prss_random_field<F: Field>(&self, record_id: RecordId) -> F {
+        self.prss().random(record_id)
+    }
+
     /// Get a pair of PRSS-based RNGs.  The first is shared with the helper to the "left",
     /// the second is shared with the helper to the "right".
     ///
@@ -82,6 +146,27 @@ pub trait Context: Clone + Send + Sync + SeqJoin {
     fn recv_channel<M: Message>(&self, role: Role) -> ReceivingEnd<M>;
 }
 
+/// Futures that [`Context::parallel_records`] runs, one per record.
+pub struct PerRecord<C, F> {
+    ctx: C,
+    records: Range<usize>,
+    f: F,
+}
+
+impl<C, F, Fut> Iterator for PerRecord<C, F>
+where
+    C: Context,
+    F: Fn(C, RecordId) -> Fut,
+{
+    type Item = Fut;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records
+            .next()
+            .map(|i| (self.f)(self.ctx.clone(), RecordId::from(i)))
+    }
+}
+
 pub trait UpgradableContext: Context {
     type UpgradedContext<F: ExtendableField>: UpgradedContext<F>;
     type Validator<F: ExtendableField>: Validator<Self, F>;
@@ -104,6 +189,14 @@ pub trait UpgradedContext<F: ExtendableField>: Context {
     ) -> Result<Self::Share, Error>;
 
     /// Upgrade an input using this context.
+    ///
+    /// Collections are upgraded in bulk: `Vec`s (or any exact size iterator), tuples and
+    /// [`BitDecomposed`] values upgrade every share they hold, one record per element, with at
+    /// most as many multiplications in flight as the context allows for active work. Every
+    /// upgraded share is checked by the validator that created this context. Use
+    /// [`Validator::validate`] to get semi-honest shares back once the protocol is done.
+    ///
+    /// [`BitDecomposed`]: crate::secret_sharing::BitDecomposed
     /// # Errors
     /// When the multiplication fails. This does not include additive attacks
     /// by other helpers.  These are caught later.
@@ -243,7 +336,7 @@ impl<'a> Context for Base<'a> {
     fn recv_channel<M: Message>(&self, role: Role) -> ReceivingEnd<M> {
         self.inner
             .gateway
-            .get_receiver(&ChannelId::new(role, self.gate.clone()))
+            .get_receiver(&ChannelId::new(role, self.gate.clone()), self.total_records)
     }
 }
 
@@ -266,20 +359,28 @@ impl<'a> Inner<'a> {
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use std::iter::repeat;
+    use std::{
+        collections::HashSet,
+        iter::{repeat, zip},
+    };
 
     use futures_util::{future::join_all, try_join};
     use rand::{
         distributions::{Distribution, Standard},
         Rng,
     };
+    use tokio::time::Instant;
     use typenum::Unsigned;
 
     use super::*;
     use crate::{
-        ff::{Field, Fp31, Serializable},
-        helpers::Direction,
-        protocol::{context::validator::Step::MaliciousProtocol, prss::SharedRandomness, RecordId},
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField, Serializable},
+        helpers::{Direction, GatewayConfig},
+        protocol::{
+            basics::SecureMul, context::validator::Step::MaliciousProtocol, prss::SharedRandomness,
+            RecordId,
+        },
+        rand::thread_rng,
         secret_sharing::replicated::{
             malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
             semi_honest::AdditiveShare as Replicated,
@@ -291,6 +392,162 @@ mod tests {
         test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig},
     };
 
+    #[cfg(feature = "step-trace")]
+    #[tokio::test]
+    async fn step_path_tracks_narrowing() {
+        let world = TestWorld::default();
+        let [ctx, _, _] = world.contexts();
+        let lhs = ctx.narrow("lhs").narrow(&BitOpStep::from(0));
+        let rhs = ctx.narrow("rhs").narrow(&BitOpStep::from(0));
+
+        let (lhs, rhs) = (lhs.current_step_path(), rhs.current_step_path());
+        assert_ne!(lhs, rhs);
+        for (path, step) in [(&lhs, "lhs"), (&rhs, "rhs")] {
+            assert!(path.starts_with(ctx.gate().as_ref()), "{path}");
+            assert!(path.contains(step), "{path}");
+            assert!(path.contains("BitOpStep"), "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn indexed_and_tree_steps() {
+        let world = TestWorld::default();
+        let [ctx, _, _] = world.contexts();
+        let tree = |level, node| ctx.narrow_tree(TreeStep { level, node }).gate().clone();
+
+        assert_eq!(
+            ctx.narrow("loop").narrow(&BitOpStep::from(3)).gate(),
+            ctx.narrow_indexed("loop", 3).gate()
+        );
+        assert_ne!(
+            ctx.narrow_indexed("loop", 3).gate(),
+            ctx.narrow_indexed("loop", 4).gate()
+        );
+        assert_eq!(
+            ctx.narrow(&TreeLevel::from(1))
+                .narrow(&BitOpStep::from(0))
+                .gate(),
+            &tree(1, 0)
+        );
+        assert_ne!(tree(1, 0), tree(0, 1));
+        // loops over loops don't collide with trees
+        assert_ne!(
+            ctx.narrow_indexed(&BitOpStep::from(1), 0).gate(),
+            &tree(1, 0)
+        );
+    }
+
+    /// Values are the sum of raw PRSS outputs reduced into the field, and differ from one record
+    /// to another. A world with the same seed draws the raw outputs again.
+    #[tokio::test]
+    async fn prss_random_field() {
+        const COUNT: usize = 100;
+        const SEED: u64 = 42;
+        let world = TestWorld::new_with(TestWorldConfig::default().with_seed(SEED));
+        let replay = TestWorld::new_with(TestWorldConfig::default().with_seed(SEED));
+        let prime = u128::from(Fp32BitPrime::PRIME);
+
+        let values = world.contexts().map(|ctx| {
+            (0..COUNT)
+                .map(|i| ctx.prss_random_field::<Fp32BitPrime>(RecordId::from(i)))
+                .collect::<Vec<_>>()
+        });
+        let raw = replay.contexts().map(|ctx| {
+            (0..COUNT)
+                .map(|i| ctx.prss().generate_values(RecordId::from(i)))
+                .collect::<Vec<_>>()
+        });
+
+        for (values, raw) in zip(&values, &raw) {
+            for (value, (left, right)) in zip(values, raw) {
+                assert_eq!((left % prime + right % prime) % prime, value.as_u128());
+            }
+            let distinct = values
+                .iter()
+                .map(Fp32BitPrime::as_u128)
+                .collect::<HashSet<_>>();
+            assert_eq!(COUNT, distinct.len());
+        }
+    }
+
+    /// Every helper adds up the values it shares with its peers. A world with the same seed
+    /// draws them again, one pair per helper, on the same step.
+    #[tokio::test]
+    async fn prss_random_field_is_correlated() {
+        const SEED: u64 = 42;
+        let world = TestWorld::new_with(TestWorldConfig::default().with_seed(SEED));
+        let replay = TestWorld::new_with(TestWorldConfig::default().with_seed(SEED));
+
+        let values = world
+            .contexts()
+            .map(|ctx| ctx.prss_random_field::<Fp32BitPrime>(RecordId::FIRST));
+        let pairs = replay.contexts().map(|ctx| {
+            ctx.prss()
+                .generate_fields::<Fp32BitPrime, _>(RecordId::FIRST)
+        });
+
+        for (i, (value, (left, right))) in zip(values, pairs).enumerate() {
+            assert_eq!(left + right, value);
+            // The value this helper shares with the helper to its right.
+            assert_eq!(right, pairs[(i + 1) % 3].0);
+        }
+    }
+
+    /// Channels are closed as soon as the last record is sent, so a protocol that sends far
+    /// fewer records than buffers can hold doesn't wait for a timer to flush them. Time is paused
+    /// and moves forward only if every helper waits for a timer.
+    #[tokio::test(start_paused = true)]
+    async fn parallel_records_flush_on_last_record() {
+        const COUNT: usize = 10;
+        let world = TestWorld::new_with(TestWorldConfig {
+            gateway_config: GatewayConfig::new(4096),
+            ..Default::default()
+        });
+        let mut rng = thread_rng();
+        let inputs = (0..COUNT)
+            .map(|_| (rng.gen::<Fp31>(), rng.gen::<Fp31>()))
+            .collect::<Vec<_>>();
+        let expected = inputs.iter().map(|(a, b)| *a * *b).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let result = world
+            .semi_honest(inputs.into_iter(), |ctx, pairs| async move {
+                ctx.parallel_records(0..COUNT, |ctx, record_id| {
+                    let (a, b): (Replicated<Fp31>, Replicated<Fp31>) =
+                        pairs[usize::from(record_id)].clone();
+                    async move { a.multiply(&b, ctx, record_id).await }
+                })
+                .await
+                .unwrap()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(expected, result);
+        assert_eq!(start, Instant::now());
+    }
+
+    /// Multiplying twice on the same step for the same record reuses shared randomness, which
+    /// debug builds catch.
+    #[tokio::test]
+    #[should_panic(expected = "Generated randomness for index '0' twice")]
+    async fn duplicate_step_is_detected() {
+        let world = TestWorld::default();
+        world
+            .semi_honest(
+                (Fp31::ONE, Fp31::ONE),
+                |ctx, (a, b): (Replicated<Fp31>, Replicated<Fp31>)| async move {
+                    let ctx = ctx.set_total_records(1);
+                    for _ in 0..2 {
+                        a.multiply(&b, ctx.narrow_indexed("multiply", 0), RecordId::FIRST)
+                            .await
+                            .unwrap();
+                    }
+                },
+            )
+            .await;
+    }
+
     trait AsReplicatedTestOnly<F: Field> {
         fn l(&self) -> F;
         fn r(&self) -> F;
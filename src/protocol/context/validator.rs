@@ -9,7 +9,7 @@ use futures::future::try_join;
 use ipa_macros::Step;
 
 use crate::{
-    error::Error,
+    error::{Error, MaliciousError},
     ff::Field,
     helpers::Direction,
     protocol::{
@@ -210,8 +210,8 @@ impl<'a, F: ExtendableField> Validator<MaliciousContext<'a>, F> for Malicious<'a
 
     /// ## Errors
     /// If the two information theoretic MACs are not equal (after multiplying by `r`), this indicates that one of the parties
-    /// must have launched an additive attack. At this point the honest parties should abort the protocol. This method throws an
-    /// error in such a case.
+    /// must have launched an additive attack. At this point the honest parties should abort the protocol. This method returns
+    /// [`MaliciousError::ValidationFailed`] in such a case. Failures to communicate with peers are reported as other errors.
     /// TODO: add a "Drop Guard"
     ///
     /// ## Panics
@@ -240,7 +240,7 @@ impl<'a, F: ExtendableField> Validator<MaliciousContext<'a>, F> for Malicious<'a
             use crate::secret_sharing::replicated::malicious::ThisCodeIsAuthorizedToDowngradeFromMalicious;
             Ok(values.downgrade().await.access_without_downgrade())
         } else {
-            Err(Error::MaliciousSecurityCheckFailed)
+            Err(Error::Malicious(MaliciousError::ValidationFailed))
         }
     }
 }
@@ -315,7 +315,7 @@ mod tests {
     use std::iter::{repeat, zip};
 
     use crate::{
-        error::Error,
+        error::{Error, MaliciousError},
         ff::{Field, Fp31, Fp32BitPrime},
         helpers::Role,
         protocol::{
@@ -332,7 +332,7 @@ mod tests {
             IntoShares,
         },
         seq_join::SeqJoin,
-        test_fixture::{join3v, Reconstruct, Runner, TestWorld},
+        test_fixture::{join3v, Reconstruct, Runner, TestWorld, TestWorldConfig, TrafficRecorder},
     };
 
     /// This is the simplest arithmetic circuit that allows us to test all of the pieces of this validator
@@ -434,7 +434,10 @@ mod tests {
                     let m = v.context().upgrade(a).await.unwrap();
                     match v.validate(m).await {
                         Ok(result) => panic!("Got a result {result:?}"),
-                        Err(err) => assert!(matches!(err, Error::MaliciousSecurityCheckFailed)),
+                        Err(err) => assert!(matches!(
+                            err,
+                            Error::Malicious(MaliciousError::ValidationFailed)
+                        )),
                     }
                 })
                 .await;
@@ -549,4 +552,51 @@ mod tests {
 
         Ok(())
     }
+
+    /// Multiplications only add to the MACs every helper accumulates locally, so validation sends
+    /// the same data, whether it checks one multiplication or ten thousand of them.
+    #[tokio::test]
+    async fn validation_traffic_is_constant() {
+        async fn validation_traffic(count: usize) -> usize {
+            let recorder = TrafficRecorder::default();
+            let world = TestWorld::new_with(TestWorldConfig {
+                send_observer: Some(recorder.observer()),
+                ..TestWorldConfig::default()
+            });
+            let mut rng = thread_rng();
+            let a = (0..count).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+            let b = (0..count).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+            let expected = zip(&a, &b).map(|(&a, &b)| a * b).collect::<Vec<_>>();
+
+            let result = world
+                .malicious((a.into_iter(), b.into_iter()), |ctx, (a, b)| async move {
+                    let v = ctx.validator();
+                    let m_ctx = v.context();
+                    let (a, b) = m_ctx.upgrade((a, b)).await.unwrap();
+                    let ctx = m_ctx.set_total_records(count);
+                    let ab = ctx
+                        .try_join(
+                            zip(a.iter(), b.iter())
+                                .enumerate()
+                                .map(|(i, (a, b))| a.multiply(b, ctx.clone(), RecordId::from(i))),
+                        )
+                        .await
+                        .unwrap();
+                    v.validate(ab).await.unwrap()
+                })
+                .await;
+            assert_eq!(expected, result.reconstruct());
+
+            recorder
+                .with_step_prefix("protocol")
+                .iter()
+                .filter(|record| record.gate.as_ref().split('/').any(|s| s == "validate"))
+                .map(|record| record.data.len())
+                .sum()
+        }
+
+        let one = validation_traffic(1).await;
+        assert!(one > 0, "validation did not send anything");
+        assert_eq!(one, validation_traffic(10_000).await);
+    }
 }
@@ -1105,9 +1105,9 @@ pub mod tests {
                 cap_one(),
                 SemiHonest,
                 PerfMetrics {
-                    records_sent: 14_421,
-                    bytes_sent: 47_100,
-                    indexed_prss: 19_137,
+                    records_sent: 19_713,
+                    bytes_sent: 68_268,
+                    indexed_prss: 24_429,
                     seq_prss: 1118,
                 },
             )
@@ -1120,9 +1120,9 @@ pub mod tests {
                 cap_three(),
                 SemiHonest,
                 PerfMetrics {
-                    records_sent: 21_756,
-                    bytes_sent: 76_440,
-                    indexed_prss: 28_146,
+                    records_sent: 37_632,
+                    bytes_sent: 139_944,
+                    indexed_prss: 44_022,
                     seq_prss: 1118,
                 },
             )
@@ -1135,9 +1135,9 @@ pub mod tests {
                 cap_one(),
                 Malicious,
                 PerfMetrics {
-                    records_sent: 35_163,
-                    bytes_sent: 130_068,
-                    indexed_prss: 72_447,
+                    records_sent: 45_747,
+                    bytes_sent: 172_404,
+                    indexed_prss: 88_323,
                     seq_prss: 1132,
                 },
             )
@@ -1150,9 +1150,9 @@ pub mod tests {
                 cap_three(),
                 Malicious,
                 PerfMetrics {
-                    records_sent: 53_865,
-                    bytes_sent: 204_876,
-                    indexed_prss: 109_734,
+                    records_sent: 85_617,
+                    bytes_sent: 331_884,
+                    indexed_prss: 157_362,
                     seq_prss: 1132,
                 },
             )
@@ -18,7 +18,10 @@ use crate::{
     helpers::Role,
     protocol::{
         basics::{if_else, SecureMul, ShareKnownValue},
-        boolean::{comparison::bitwise_less_than_constant, or::or, saturating_sum::SaturatingSum},
+        boolean::{
+            comparison::bitwise_less_than_constant, or::or, saturating_sum::SaturatingSum,
+            select_all,
+        },
         context::{Context, UpgradableContext, UpgradedContext, Validator},
         modulus_conversion::{convert_bits, BitConversionTriple, ToBitConversionTriples},
         step::BitOpStep,
@@ -85,8 +88,9 @@ impl InputsRequiredFromPrevRow {
     ///     - Trigger events are attributed to the `breakdown_key` of the most recent preceding source event
     /// - Per user capping
     ///     - A cumulative sum of "Attributed Trigger Value" is maintained
-    ///     - Bitwise addition is used, and a single bit indicates if the sum is "saturated"
-    ///     - The only available values for "cap" are powers of 2 (i.e. 1, 2, 4, 8, 16, 32, ...)
+    ///     - Bitwise addition is used, and a single bit indicates if the sum is "saturated", that is it went over the cap
+    ///     - If the cap is a power of 2 larger than any trigger value, the sum saturates as soon as it reaches the cap.
+    ///       Any other cap is compared to the sum on every row
     ///     - Prior to the cumulative sum reaching saturation, attributed trigger values are passed along
     ///     - The row which puts the cumulative sum over the cap is "capped" to the delta between the cumulative sum of the last row and the cap
     ///     - All subsequent rows contribute zero
@@ -104,7 +108,7 @@ impl InputsRequiredFromPrevRow {
         ctx: C,
         record_id: RecordId,
         input_row: &PrfShardedIpaInputRow<BK, TV, TS>,
-        num_saturating_sum_bits: usize,
+        per_user_credit_cap: u32,
         attribution_window_seconds: Option<NonZeroU32>,
     ) -> Result<CappedAttributionOutputs, Error>
     where
@@ -119,6 +123,7 @@ impl InputsRequiredFromPrevRow {
             input_row.timestamp_bits(),
         );
 
+        let num_saturating_sum_bits = saturating_sum_bits(per_user_credit_cap, TV::BITS);
         assert_eq!(self.saturating_sum.sum.len(), num_saturating_sum_bits);
 
         let share_of_one = Replicated::share_known_value(&ctx, Gf2::ONE);
@@ -165,7 +170,7 @@ impl InputsRequiredFromPrevRow {
         )
         .await?;
 
-        let updated_sum = self
+        let mut updated_sum = self
             .saturating_sum
             .add(
                 ctx.narrow(&Step::ComputeSaturatingSum),
@@ -173,6 +178,15 @@ impl InputsRequiredFromPrevRow {
                 &attributed_trigger_value,
             )
             .await?;
+        if u128::from(per_user_credit_cap) != 1_u128 << num_saturating_sum_bits {
+            updated_sum.is_saturated = is_sum_over_cap(
+                ctx.narrow(&Step::IsSumOverCap),
+                record_id,
+                &updated_sum,
+                per_user_credit_cap,
+            )
+            .await?;
+        }
 
         let (is_saturated_and_prev_row_not_saturated, difference_to_cap) = try_join(
             updated_sum.is_saturated.multiply(
@@ -180,9 +194,10 @@ impl InputsRequiredFromPrevRow {
                 ctx.narrow(&Step::IsSaturatedAndPrevRowNotSaturated),
                 record_id,
             ),
-            updated_sum.truncated_delta_to_saturation_point(
+            updated_sum.truncated_delta_to_cap(
                 ctx.narrow(&Step::ComputeDifferenceToCap),
                 record_id,
+                per_user_credit_cap.into(),
                 TV::BITS,
             ),
         )
@@ -293,6 +308,8 @@ pub(crate) enum Step {
     CompareTimeDeltaToAttributionWindow,
     SourceEventTimestamp,
     ComputeSaturatingSum,
+    IsSumOverCap,
+    CompareSumToCap,
     IsSaturatedAndPrevRowNotSaturated,
     ComputeDifferenceToCap,
     ComputedCappedAttributedTriggerValueNotSaturatedCase,
@@ -399,7 +416,7 @@ where
 pub async fn attribution_and_capping_and_aggregation<C, BK, TV, TS, S, F>(
     sh_ctx: C,
     input_rows: Vec<PrfShardedIpaInputRow<BK, TV, TS>>,
-    num_saturating_sum_bits: usize,
+    per_user_credit_cap: u32,
     attribution_window_seconds: Option<NonZeroU32>,
     histogram: &[usize],
 ) -> Result<Vec<S>, Error>
@@ -413,7 +430,6 @@ where
     TS: GaloisField,
     F: PrimeField + ExtendableField,
 {
-    assert!(num_saturating_sum_bits > TV::BITS as usize);
     assert!(TV::BITS > 0);
     assert!(BK::BITS > 0);
     assert!(TS::BITS > 0);
@@ -459,7 +475,7 @@ where
                 contexts,
                 record_ids,
                 rows_for_user,
-                num_saturating_sum_bits,
+                per_user_credit_cap,
                 attribution_window_seconds,
             )
         }
@@ -522,7 +538,7 @@ async fn evaluate_per_user_attribution_circuit<C, BK, TV, TS>(
     ctx_for_row_number: Vec<C>,
     record_id_for_each_depth: Vec<u32>,
     rows_for_user: Vec<PrfShardedIpaInputRow<BK, TV, TS>>,
-    num_saturating_sum_bits: usize,
+    per_user_credit_cap: u32,
     attribution_window_seconds: Option<NonZeroU32>,
 ) -> Result<Vec<CappedAttributionOutputs>, Error>
 where
@@ -539,7 +555,7 @@ where
     let mut prev_row_inputs = initialize_new_device_attribution_variables(
         Replicated::share_known_value(&ctx_for_row_number[0], Gf2::ONE),
         first_row,
-        num_saturating_sum_bits,
+        per_user_credit_cap,
     );
 
     let mut output = Vec::with_capacity(rows_for_user.len() - 1);
@@ -552,7 +568,7 @@ where
                 ctx_for_this_row_depth,
                 record_id_for_this_row_depth,
                 row,
-                num_saturating_sum_bits,
+                per_user_credit_cap,
                 attribution_window_seconds,
             )
             .await?;
@@ -563,6 +579,21 @@ where
     Ok(output)
 }
 
+/// Number of bits the running sum of attributed trigger values is kept in. The sum is saturated
+/// once it does not fit into them, and it must be able to take any trigger value.
+///
+/// If `cap` is a power of 2 that is larger than any trigger value, the sum saturates exactly when
+/// it reaches the cap, so it does not need to be compared to it. Any other cap must fit into the
+/// sum, and [`is_sum_over_cap`] tells if the sum went over it.
+fn saturating_sum_bits(cap: u32, trigger_value_bits: u32) -> usize {
+    let bits = if cap.is_power_of_two() && cap.trailing_zeros() > trigger_value_bits {
+        cap.trailing_zeros()
+    } else {
+        (u32::BITS - cap.leading_zeros()).max(trigger_value_bits + 1)
+    };
+    usize::try_from(bits).unwrap()
+}
+
 ///
 /// Upon encountering the first row of data from a new user (as distinguished by a different OPRF of the match key)
 /// this function encapsulates the variables that must be initialized. No communication is required for this first row.
@@ -570,13 +601,25 @@ where
 fn initialize_new_device_attribution_variables<BK, TV, TS>(
     share_of_one: Replicated<Gf2>,
     input_row: &PrfShardedIpaInputRow<BK, TV, TS>,
-    num_saturating_sum_bits: usize,
+    per_user_credit_cap: u32,
 ) -> InputsRequiredFromPrevRow
 where
     BK: GaloisField,
     TV: GaloisField,
     TS: GaloisField,
 {
+    // Nothing is added to the sum yet, so the first trigger value that goes over the cap is
+    // capped at the cap itself. Only the bits trigger values have matter: larger caps can't be
+    // reached by a single trigger value.
+    let difference_to_cap = BitDecomposed::decompose(TV::BITS, |i| {
+        if (u128::from(per_user_credit_cap) >> i) & 1 == 1 {
+            share_of_one.clone()
+        } else {
+            Replicated::ZERO
+        }
+    });
+    let num_saturating_sum_bits = saturating_sum_bits(per_user_credit_cap, TV::BITS);
+
     InputsRequiredFromPrevRow {
         ever_encountered_a_source_event: share_of_one - &input_row.is_trigger_bit,
         attributed_breakdown_key_bits: BitDecomposed::decompose(BK::BITS, |i| {
@@ -586,9 +629,7 @@ where
             BitDecomposed::new(vec![Replicated::ZERO; num_saturating_sum_bits]),
             Replicated::ZERO,
         ),
-        // This is incorrect in the case that the CAP is less than the maximum value of "trigger value" for a single row
-        // Not a problem if you assume that's an invalid input
-        difference_to_cap: BitDecomposed::new(vec![Replicated::ZERO; TV::BITS as usize]),
+        difference_to_cap,
         source_event_timestamp: BitDecomposed::new(vec![Replicated::ZERO; TS::BITS as usize]),
     }
 }
@@ -770,12 +811,35 @@ where
     }
 }
 
+/// Computes a secret-shared bit indicating if the running sum went over `cap`. That is the case if
+/// the sum is saturated, that is it wrapped around at some point, or if it is not less than `cap + 1`.
+async fn is_sum_over_cap<C>(
+    ctx: C,
+    record_id: RecordId,
+    sum: &SaturatingSum<Replicated<Gf2>>,
+    cap: u32,
+) -> Result<Replicated<Gf2>, Error>
+where
+    C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
+{
+    let is_within_cap = bitwise_less_than_constant(
+        ctx.narrow(&Step::CompareSumToCap),
+        record_id,
+        &sum.sum,
+        u128::from(cap) + 1,
+    )
+    .await?;
+    let is_over_cap = Replicated::share_known_value(&ctx, Gf2::ONE) - &is_within_cap;
+
+    or(ctx, record_id, &sum.is_saturated, &is_over_cap).await
+}
+
 ///
 /// To provide a differential privacy guarantee, we need to bound the maximum contribution from any given user to some cap.
 ///
 /// The following values are computed for each row:
 /// (1) The uncapped "Attributed trigger value" (which is either the original `trigger_value` bits or zero if it was unattributed)
-/// (2) The cumulative sum of "Attributed trigger value" thus far (which "saturates" at a given power of two as indicated by the `is_saturated` flag)
+/// (2) The cumulative sum of "Attributed trigger value" thus far (which "saturates" once it goes over the cap as indicated by the `is_saturated` flag)
 /// (3) The "delta to cap", which is the difference between the "cap" and the cumulative sum (this value is meaningless once the cumulative sum is saturated)
 ///
 /// To perfectly cap each user's contributions at precisely the cap, the "attributed trigger value" will sometimes need to be lowered,
@@ -803,32 +867,28 @@ where
     let narrowed_ctx1 = ctx.narrow(&Step::ComputedCappedAttributedTriggerValueNotSaturatedCase);
     let narrowed_ctx2 = ctx.narrow(&Step::ComputedCappedAttributedTriggerValueJustSaturatedCase);
 
-    let zero = &Replicated::share_known_value(&narrowed_ctx1, Gf2::ZERO);
+    let zeros = vec![Replicated::ZERO; attributed_trigger_value.len()];
+
+    let (not_saturated_case, just_saturated_case) = try_join(
+        select_all(
+            narrowed_ctx1,
+            record_id,
+            is_saturated,
+            &zeros,
+            attributed_trigger_value,
+        ),
+        select_all(
+            narrowed_ctx2,
+            record_id,
+            is_saturated_and_prev_row_not_saturated,
+            prev_row_diff_to_cap,
+            &zeros,
+        ),
+    )
+    .await?;
 
     Ok(BitDecomposed::new(
-        ctx.parallel_join(
-            zip(attributed_trigger_value.iter(), prev_row_diff_to_cap.iter())
-                .enumerate()
-                .map(|(i, (bit, prev_bit))| {
-                    let c1 = narrowed_ctx1.narrow(&BitOpStep::from(i));
-                    let c2 = narrowed_ctx2.narrow(&BitOpStep::from(i));
-                    async move {
-                        let (not_saturated_case, just_saturated_case) = try_join(
-                            if_else(c1, record_id, is_saturated, zero, bit),
-                            if_else(
-                                c2,
-                                record_id,
-                                is_saturated_and_prev_row_not_saturated,
-                                prev_bit,
-                                zero,
-                            ),
-                        )
-                        .await?;
-                        Ok::<_, Error>(not_saturated_case + &just_saturated_case)
-                    }
-                }),
-        )
-        .await?,
+        zip(not_saturated_case, just_saturated_case).map(|(a, b)| a + &b),
     ))
 }
 
@@ -837,14 +897,18 @@ pub mod tests {
     use super::{CappedAttributionOutputs, PrfShardedIpaInputRow};
     use crate::{
         ff::{Field, Fp32BitPrime, GaloisField, Gf2, Gf20Bit, Gf3Bit, Gf5Bit},
+        helpers::query::IpaQueryConfig,
         protocol::ipa_prf::prf_sharding::attribution_and_capping_and_aggregation,
-        rand::Rng,
+        rand::{thread_rng, Rng},
         secret_sharing::{
             replicated::semi_honest::AdditiveShare as Replicated, BitDecomposed, IntoShares,
             SharedValue,
         },
         test_executor::run,
-        test_fixture::{Reconstruct, Runner, TestWorld},
+        test_fixture::{
+            ipa::{ipa_in_the_clear, test_oprf_ipa, CappingOrder, TestRawDataRecord},
+            Reconstruct, Runner, TestWorld,
+        },
     };
 
     struct PreShardedAndSortedOPRFTestInput<BK: GaloisField, TV: GaloisField, TS: GaloisField> {
@@ -1040,7 +1104,7 @@ pub mod tests {
             expected[17] = 7;
             expected[20] = 10;
 
-            let num_saturating_bits: usize = 5;
+            let per_user_cap = 32;
 
             let histogram = [3, 3, 2, 2, 1, 1, 1, 1];
 
@@ -1053,7 +1117,7 @@ pub mod tests {
                         Gf20Bit,
                         Replicated<Fp32BitPrime>,
                         Fp32BitPrime,
-                    >(ctx, input_rows, num_saturating_bits, None, &histogram)
+                    >(ctx, input_rows, per_user_cap, None, &histogram)
                     .await
                     .unwrap()
                 })
@@ -1062,4 +1126,55 @@ pub mod tests {
             assert_eq!(result, &expected);
         });
     }
+
+    /// Caps that are not powers of two are compared to the running sum of every user. Results must
+    /// be the same as what IPA computed in the clear gives.
+    #[test]
+    fn capping_matches_plaintext() {
+        const MAX_BREAKDOWN_KEY: u32 = 8;
+        const NUM_USERS: u64 = 6;
+        const NUM_EVENTS: u64 = 40;
+
+        run(|| async move {
+            let mut rng = thread_rng();
+            // Timestamps go up, as IPA in the clear expects.
+            let records = (0..NUM_EVENTS)
+                .map(|timestamp| {
+                    let is_trigger_report = rng.gen::<bool>();
+                    TestRawDataRecord {
+                        timestamp,
+                        user_id: rng.gen_range(0..NUM_USERS),
+                        is_trigger_report,
+                        breakdown_key: if is_trigger_report {
+                            0
+                        } else {
+                            rng.gen_range(0..MAX_BREAKDOWN_KEY)
+                        },
+                        trigger_value: if is_trigger_report {
+                            rng.gen_range(1..8)
+                        } else {
+                            0
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            for per_user_cap in [3, 5] {
+                let expected = ipa_in_the_clear(
+                    &records,
+                    per_user_cap,
+                    None,
+                    MAX_BREAKDOWN_KEY,
+                    &CappingOrder::CapMostRecentFirst,
+                );
+                test_oprf_ipa::<Fp32BitPrime>(
+                    &TestWorld::default(),
+                    records.clone(),
+                    &expected,
+                    IpaQueryConfig::no_window(per_user_cap, MAX_BREAKDOWN_KEY, 3),
+                )
+                .await;
+            }
+        });
+    }
 }
@@ -2,12 +2,16 @@ pub mod aggregation;
 pub mod attribution;
 pub mod basics;
 pub mod boolean;
+#[cfg(feature = "descriptive-gate")]
+pub mod comparison;
 pub mod context;
 pub mod dp;
 pub mod ipa;
 pub mod ipa_prf;
 pub mod modulus_conversion;
 pub mod prss;
+#[cfg(feature = "descriptive-gate")]
+pub mod shuffle;
 pub mod sort;
 pub mod step;
 
@@ -29,42 +33,44 @@ pub type BreakdownKey = Gf8Bit;
 pub type TriggerValue = Gf3Bit;
 pub type Timestamp = Gf32Bit;
 
-/// Unique identifier of the MPC query requested by report collectors
-/// TODO(615): Generating this unique id may be tricky as it may involve communication between helpers and
-/// them collaborating on constructing this unique id. These details haven't been flushed out yet,
-/// so for now it is just an empty struct. Once we know more about it, we will make necessary
-/// amendments to it
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// Unique identifier of the MPC query requested by report collectors.
+///
+/// The helper that coordinates the query picks its id and tells the other helpers about it when it
+/// asks them to prepare. Query processor assigns ids from a counter that starts at a random value,
+/// so queries coordinated by different helpers are unlikely to get the same id. Helpers refuse to
+/// prepare a query with the id that is already in use.
+///
+/// The default id is meant for code that runs a single query without going through the query
+/// processor, like test fixtures.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(
     feature = "serde",
     derive(serde::Serialize, serde::Deserialize),
-    serde(into = "&'static str", try_from = "&str")
+    serde(into = "String", try_from = "&str")
 )]
-pub struct QueryId;
+pub struct QueryId(u64);
 
 impl Display for QueryId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // fail when query id becomes meaningful and change the display implementation
-        let _: QueryId = QueryId;
-        write!(f, "{self:?}")
+        write!(f, "{}", self.0)
     }
 }
 
-impl QueryId {
-    fn repr() -> &'static str {
-        "0"
+impl From<u64> for QueryId {
+    fn from(value: u64) -> Self {
+        Self(value)
     }
 }
 
-impl AsRef<str> for QueryId {
-    fn as_ref(&self) -> &str {
-        QueryId::repr()
+impl From<QueryId> for u64 {
+    fn from(value: QueryId) -> Self {
+        value.0
     }
 }
 
-impl From<QueryId> for &'static str {
-    fn from(_: QueryId) -> Self {
-        QueryId::repr()
+impl From<QueryId> for String {
+    fn from(value: QueryId) -> Self {
+        value.to_string()
     }
 }
 
@@ -72,11 +78,10 @@ impl TryFrom<&str> for QueryId {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value == QueryId::repr() {
-            Ok(QueryId)
-        } else {
-            Err(Error::path_parse_error(value))
-        }
+        value
+            .parse()
+            .map(Self)
+            .map_err(|_| Error::path_parse_error(value))
     }
 }
 
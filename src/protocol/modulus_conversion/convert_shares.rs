@@ -63,7 +63,7 @@ pub struct BitConversionTriple<S>(pub(crate) [S; 3]);
 
 impl<F: PrimeField> BitConversionTriple<Replicated<F>> {
     /// Convert one bit of an XOR sharing into a triple of replicated sharings of that bit.
-    /// This is not a usable construct, but it can be used with `convert_one_bit` to produce
+    /// This is not a usable construct, but it can be used with [`convert_bit`] to produce
     /// a single replicated sharing of that bit.
     ///
     /// This is an implementation of "Algorithm 3" from <https://eprint.iacr.org/2018/387.pdf>
@@ -253,7 +253,7 @@ where
 /// Convert a locally-decomposed single bit into field elements.
 /// # Errors
 /// Fails only if multiplication fails.
-async fn convert_bit<F, C, S>(
+async fn convert_triple<F, C, S>(
     ctx: C,
     record_id: RecordId,
     locally_converted_bits: &BitConversionTriple<S>,
@@ -278,6 +278,83 @@ where
     xor_sparse(ctx2, record_id, &sh0_xor_sh1, sh2, ZeroPositions::AVVZ_BZZV).await
 }
 
+/// Converts a single XOR-shared bit into a share of the same bit in field `F`.
+///
+/// This is what [`convert_bits`] does for every bit of every record: the bit is split locally into
+/// a [`BitConversionTriple`], upgraded in `ctx` and XOR-ed together, which takes two
+/// multiplications. Converting many bits is cheaper through [`convert_bits`], which keeps the
+/// bit contexts and record ids in order by itself.
+///
+/// # Errors
+/// Fails only if upgrade or multiplication fails.
+pub async fn convert_bit<F, C, S>(
+    ctx: C,
+    record_id: RecordId,
+    bit: &Replicated<Gf2>,
+) -> Result<S, Error>
+where
+    F: PrimeField,
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + SecureMul<C>,
+    for<'u> UpgradeContext<'u, C, F, RecordId>:
+        UpgradeToMalicious<'u, BitConversionTriple<Replicated<F>>, BitConversionTriple<C::Share>>,
+{
+    let triple =
+        BitConversionTriple::new(ctx.role(), bit.left() == Gf2::ONE, bit.right() == Gf2::ONE);
+    let upgraded = ctx
+        .narrow(&ConvertSharesStep::Upgrade)
+        .upgrade_for(record_id, triple)
+        .await?;
+    convert_triple(ctx, record_id, &upgraded).await
+}
+
+/// Converts XOR shares of bits `b_i` of an integer into a share of `sum(2^i * b_i)` in field `F`.
+/// `bits` go from the least significant to the most significant one.
+///
+/// Bits are shared mod 2, so they can't be weighted and summed up locally: every bit is converted
+/// into `F` first, using the same protocol as [`convert_bits`].
+///
+/// Values that don't fit into `F` wrap around the field modulus.
+///
+/// # Errors
+/// Fails only if multiplication fails.
+/// # Panics
+/// If there are more bits than `F` can hold.
+pub async fn bits_to_arithmetic<F, C>(
+    ctx: C,
+    record_id: RecordId,
+    bits: &[Replicated<Gf2>],
+) -> Result<Replicated<F>, Error>
+where
+    F: PrimeField,
+    C: Context,
+    Replicated<F>: SecureMul<C>,
+{
+    assert!(
+        bits.len() <= usize::try_from(F::BITS).unwrap(),
+        "{} bits don't fit into the field with {} bits",
+        bits.len(),
+        F::BITS
+    );
+
+    let role = ctx.role();
+    let bit_contexts = (0..).map(|i| ctx.narrow(&ConvertSharesStep::ConvertBit(i)));
+    let converted = ctx
+        .parallel_join(zip(bit_contexts, bits).map(|(ctx, bit)| async move {
+            let triple =
+                BitConversionTriple::new(role, bit.left() == Gf2::ONE, bit.right() == Gf2::ONE);
+            convert_triple(ctx, record_id, &triple).await
+        }))
+        .await?;
+
+    Ok(converted
+        .into_iter()
+        .enumerate()
+        .fold(Replicated::ZERO, |acc, (i, bit)| {
+            acc + bit * F::truncate_from(1_u128 << i)
+        }))
+}
+
 /// Perform modulus conversion.
 ///
 /// This takes a stream of simple (as in semi-honest or without an extension) replicated `GaloisField` shares.
@@ -367,7 +444,7 @@ where
                         .narrow(&ConvertSharesStep::Upgrade)
                         .upgrade_for(record_id, triple)
                         .await?;
-                    convert_bit(ctx, record_id, &upgraded).await
+                    convert_triple(ctx, record_id, &upgraded).await
                 }));
             Some((
                 (converted, residual),
@@ -385,28 +462,70 @@ where
 mod tests {
     use std::future::ready;
 
-    use futures::stream::{once, StreamExt, TryStreamExt};
+    use futures::stream::{iter, once, StreamExt, TryStreamExt};
 
     use crate::{
-        error::Error,
+        error::{Error, MaliciousError},
         ff::{Field, Fp31, Fp32BitPrime, Gf2, PrimeField},
         helpers::{Direction, Role},
         protocol::{
             context::{Context, UpgradableContext, UpgradedContext, Validator},
             modulus_conversion::{
-                convert_bits, convert_selected_bits, BitConversionTriple, LocalBitConverter,
-                ToBitConversionTriples,
+                bits_to_arithmetic, convert_bit, convert_bits, convert_selected_bits,
+                BitConversionTriple, LocalBitConverter, ToBitConversionTriples,
             },
             MatchKey, RecordId,
         },
         rand::{thread_rng, Rng},
         secret_sharing::{
             replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
-            IntoShares,
+            BitDecomposed, IntoShares, SharedValue,
         },
         test_fixture::{Reconstruct, Runner, TestWorld},
     };
 
+    fn into_bits(value: u32, width: u32) -> Vec<Gf2> {
+        (0..width)
+            .map(|i| Gf2::truncate_from((value >> i) & 1))
+            .collect()
+    }
+
+    #[tokio::test]
+    pub async fn bits_to_arithmetic_fp32_bit_prime() {
+        let world = TestWorld::default();
+        let cases = [
+            (0, 32),
+            (1, 32),
+            (0b1011_0110, 32),
+            (0xdead_beef, 32),
+            (u32::MAX, 32),
+            (0, 1),
+            (1, 1),
+            (0b101, 3),
+        ];
+
+        for (value, width) in cases {
+            let result = world
+                .semi_honest(
+                    into_bits(value, width).into_iter(),
+                    |ctx, bits: Vec<Replicated<Gf2>>| async move {
+                        bits_to_arithmetic::<Fp32BitPrime, _>(
+                            ctx.set_total_records(1),
+                            RecordId::FIRST,
+                            &bits,
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await
+                .reconstruct();
+
+            // values above the prime wrap around it.
+            assert_eq!(Fp32BitPrime::truncate_from(value), result, "{value:#x}");
+        }
+    }
+
     #[tokio::test]
     pub async fn one_bit() {
         const BITNUM: u32 = 4;
@@ -434,6 +553,109 @@ mod tests {
         assert_eq!(Fp31::truncate_from(match_key[BITNUM]), result.reconstruct());
     }
 
+    #[tokio::test]
+    pub async fn single_bit() {
+        let world = TestWorld::default();
+        for bit in [Gf2::ZERO, Gf2::ONE] {
+            let result: [Replicated<Fp31>; 3] = world
+                .semi_honest(bit, |ctx, bit_share| async move {
+                    let v = ctx.validator();
+                    convert_bit(
+                        v.context().set_total_records(1),
+                        RecordId::FIRST,
+                        &bit_share,
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await;
+            let m_result: [Replicated<Fp31>; 3] = world
+                .malicious(bit, |ctx, bit_share| async move {
+                    let v = ctx.validator();
+                    let m_bit = convert_bit(
+                        v.context().set_total_records(1),
+                        RecordId::FIRST,
+                        &bit_share,
+                    )
+                    .await
+                    .unwrap();
+                    v.validate(m_bit).await.unwrap()
+                })
+                .await;
+
+            let expected = Fp31::truncate_from(bit == Gf2::ONE);
+            assert_eq!(expected, result.reconstruct());
+            assert_eq!(expected, m_result.reconstruct());
+        }
+    }
+
+    fn reconstruct_bits(
+        shares: &[Vec<BitDecomposed<Replicated<Fp32BitPrime>>>; 3],
+    ) -> Vec<Vec<Fp32BitPrime>> {
+        (0..shares[0].len())
+            .map(|i| [&shares[0][i][..], &shares[1][i][..], &shares[2][i][..]].reconstruct())
+            .collect()
+    }
+
+    /// Converts every bit of every match key in both security models and checks they agree.
+    async fn convert_match_keys(match_keys: Vec<MatchKey>) -> Vec<Vec<Fp32BitPrime>> {
+        let world = TestWorld::default();
+        let result: [Vec<BitDecomposed<Replicated<Fp32BitPrime>>>; 3] = world
+            .semi_honest(
+                match_keys.clone().into_iter(),
+                |ctx, mk_shares| async move {
+                    let v = ctx.validator();
+                    convert_bits(
+                        v.context().set_total_records(mk_shares.len()),
+                        iter(mk_shares),
+                        0..MatchKey::BITS,
+                    )
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap()
+                },
+            )
+            .await;
+        let m_result: [Vec<BitDecomposed<Replicated<Fp32BitPrime>>>; 3] = world
+            .malicious(match_keys.into_iter(), |ctx, mk_shares| async move {
+                let v = ctx.validator();
+                let m_bits = convert_bits(
+                    v.context().set_total_records(mk_shares.len()),
+                    iter(mk_shares),
+                    0..MatchKey::BITS,
+                )
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap();
+                v.validate(m_bits).await.unwrap()
+            })
+            .await;
+
+        let result = reconstruct_bits(&result);
+        assert_eq!(
+            result,
+            reconstruct_bits(&m_result),
+            "security models disagree"
+        );
+        result
+    }
+
+    #[tokio::test]
+    pub async fn match_keys() {
+        let mut rng = thread_rng();
+        let mut match_keys = vec![MatchKey::ZERO, MatchKey::truncate_from(u128::MAX)];
+        match_keys.extend((0..8).map(|_| rng.gen::<MatchKey>()));
+
+        let result = convert_match_keys(match_keys.clone()).await;
+        assert_eq!(match_keys.len(), result.len());
+        for (match_key, bits) in match_keys.iter().zip(result) {
+            let expected = (0..MatchKey::BITS)
+                .map(|i| Fp32BitPrime::truncate_from(match_key[i]))
+                .collect::<Vec<_>>();
+            assert_eq!(expected, bits, "{match_key:?}");
+        }
+    }
+
     struct TwoBits {
         convert: Replicated<Gf2>,
         keep: Replicated<Gf2>,
@@ -634,14 +856,17 @@ mod tests {
                     let v = ctx.validator();
                     let m_triples = v.context().upgrade([tweaked]).await.unwrap();
                     let m_ctx = v.context().set_total_records(1);
-                    let m_bit = super::convert_bit(m_ctx, RecordId::from(0), &m_triples[0])
+                    let m_bit = super::convert_triple(m_ctx, RecordId::from(0), &m_triples[0])
                         .await
                         .unwrap();
                     let err = v
                         .validate(m_bit)
                         .await
                         .expect_err("This should fail validation");
-                    assert!(matches!(err, Error::MaliciousSecurityCheckFailed));
+                    assert!(matches!(
+                        err,
+                        Error::Malicious(MaliciousError::ValidationFailed)
+                    ));
                 })
                 .await;
         }
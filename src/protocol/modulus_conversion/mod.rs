@@ -3,6 +3,6 @@ pub mod convert_shares;
 // TODO: wean usage off convert_some_bits.
 pub(crate) use convert_shares::convert_some_bits;
 pub use convert_shares::{
-    convert_bits, convert_selected_bits, BitConversionTriple, LocalBitConverter,
-    ToBitConversionTriples,
+    bits_to_arithmetic, convert_bit, convert_bits, convert_selected_bits, BitConversionTriple,
+    LocalBitConverter, ToBitConversionTriples,
 };
@@ -79,6 +79,12 @@ pub trait SharedRandomness {
     }
 
     /// Generate additive shares of zero in a field.
+    ///
+    /// Values that all three helpers get for the same index sum up to zero, and none of them
+    /// needs to communicate. Use `ctx.prss().zero(record_id)` to mask a value with them.
+    ///
+    /// There is no replicated version of this: every helper that holds two out of three shares
+    /// of a known value can work out the third one, so that wouldn't mask anything.
     #[must_use]
     fn zero<F: Field, I: Into<u128>>(&self, index: I) -> F {
         let (l, r): (F, F) = self.generate_fields(index);
@@ -0,0 +1,127 @@
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    protocol::{
+        basics::Reshare,
+        context::Context,
+        sort::{
+            apply_sort::shuffle::shuffle_shares, shuffle::get_two_of_three_random_permutations,
+        },
+        RecordId,
+    },
+};
+
+/// Not a part of any protocol yet, so it is not in `steps.txt`.
+#[derive(Step)]
+pub(crate) enum Step {
+    GeneratePermutations,
+    Shuffle,
+}
+
+/// Shuffles `rows` in an order that none of the helpers knows.
+///
+/// This is the same shuffle that sort uses to hide the sort permutation: every pair of helpers
+/// applies a random permutation they get from PRSS and reshares the rows to the third helper, who
+/// doesn't know it. That takes three rounds, one for each pair. All columns of a row are moved
+/// together, so rows stay intact.
+///
+/// In malicious contexts, shares are reshared with the malicious version of [`Reshare`], so MACs
+/// are shuffled along with them and checked by the validator of the context.
+///
+/// ## Errors
+/// If resharing fails.
+///
+/// ## Panics
+/// If there are more than `u32::MAX` rows.
+pub async fn shuffle<C, S>(ctx: C, rows: Vec<Vec<S>>) -> Result<Vec<Vec<S>>, Error>
+where
+    C: Context,
+    S: Reshare<C, RecordId> + Send + Sync,
+{
+    let permutations = get_two_of_three_random_permutations(
+        u32::try_from(rows.len()).unwrap(),
+        ctx.narrow(&Step::GeneratePermutations).prss_rng(),
+    );
+
+    shuffle_shares(
+        rows,
+        (permutations.0.as_slice(), permutations.1.as_slice()),
+        ctx.narrow(&Step::Shuffle),
+    )
+    .await
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::shuffle;
+    use crate::{
+        ff::{Field, Fp32BitPrime},
+        protocol::context::{UpgradableContext, UpgradedContext, Validator},
+        secret_sharing::BitDecomposed,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    const COUNT: u32 = 20;
+
+    /// Every row holds its index and a couple of values derived from it.
+    fn rows() -> Vec<Vec<Fp32BitPrime>> {
+        (0..COUNT)
+            .map(|i| {
+                [i, 2 * i + 1, 3 * i + 7]
+                    .into_iter()
+                    .map(Fp32BitPrime::truncate_from)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn check(input: &[Vec<Fp32BitPrime>], mut shuffled: Vec<Vec<Fp32BitPrime>>) {
+        assert_ne!(input, &shuffled[..], "rows are in the same order");
+        shuffled.sort_by_key(|row| row[0].as_u128());
+        assert_eq!(input, &shuffled[..]);
+    }
+
+    #[tokio::test]
+    async fn semi_honest() {
+        let world = TestWorld::default();
+        let input = rows();
+
+        let result: Vec<Vec<Fp32BitPrime>> = world
+            .semi_honest(
+                input.clone().into_iter().map(Vec::into_iter),
+                |ctx, rows| async move { shuffle(ctx, rows).await.unwrap() },
+            )
+            .await
+            .reconstruct();
+
+        check(&input, result);
+    }
+
+    #[tokio::test]
+    async fn malicious() {
+        let world = TestWorld::default();
+        let input = rows();
+
+        // Rows are upgraded as `BitDecomposed`, there is no upgrade for vectors of vectors.
+        let result: Vec<Vec<Fp32BitPrime>> = world
+            .malicious(
+                input.clone().into_iter().map(Vec::into_iter),
+                |ctx, rows| async move {
+                    let v = ctx.validator();
+                    let m_ctx = v.context();
+                    let m_rows = m_ctx
+                        .upgrade(rows.into_iter().map(BitDecomposed::new).collect::<Vec<_>>())
+                        .await
+                        .unwrap();
+                    let m_rows = m_rows.into_iter().map(|row| row.to_vec()).collect();
+                    let shuffled = shuffle(m_ctx, m_rows).await.unwrap();
+                    v.validate(shuffled).await.unwrap()
+                },
+            )
+            .await
+            .reconstruct();
+
+        check(&input, result);
+    }
+}
@@ -4,16 +4,43 @@ pub use shuffle::shuffle_shares;
 
 use crate::{
     error::Error,
+    ff::PrimeField,
     protocol::{
-        basics::{apply_permutation::apply_inv, Reshare},
-        context::Context,
-        sort::{generate_permutation::RevealedAndRandomPermutations, ApplyInvStep::ShuffleInputs},
+        basics::{apply_permutation::apply_inv, BasicProtocols, Reshare},
+        context::{
+            Context, UpgradableContext, UpgradeContext, UpgradeToMalicious, UpgradedContext,
+            Validator,
+        },
+        sort::{
+            generate_permutation::{
+                shuffle_and_reveal_permutation, RevealedAndRandomPermutations,
+                ShuffledPermutationWrapper,
+            },
+            ApplyInvStep::ShuffleInputs,
+            SortStep,
+        },
         RecordId,
     },
+    secret_sharing::{
+        replicated::{
+            malicious::{DowngradeMalicious, ExtendableField},
+            semi_honest::AdditiveShare as Replicated,
+        },
+        Linear as LinearSecretSharing,
+    },
 };
 
+/// Applies a sort permutation generated by [`generate_permutation_and_reveal_shuffled`] to `input`.
+///
+/// The same permutation can be applied to as many columns as needed, as long as every one of them
+/// has a row for every sort key and is applied on a step of its own. Rows stay aligned across
+/// columns.
+///
+/// [`generate_permutation_and_reveal_shuffled`]: super::generate_permutation::generate_permutation_and_reveal_shuffled
+///
 /// # Errors
-/// Propagates errors from shuffle/reshare
+/// If `input` and the permutation are not of the same length, or propagates errors from
+/// shuffle/reshare.
 #[tracing::instrument(name = "apply_sort", skip_all, fields(gate = %ctx.gate().as_ref()))]
 pub async fn apply_sort_permutation<C, I>(
     ctx: C,
@@ -24,6 +51,13 @@ where
     C: Context,
     I: Reshare<C, RecordId> + Send + Sync,
 {
+    if input.len() != sort_permutation.revealed.len() {
+        return Err(Error::PermutationLengthMismatch {
+            permutation: sort_permutation.revealed.len(),
+            values: input.len(),
+        });
+    }
+
     let mut shuffled_objects = shuffle_shares(
         input,
         (
@@ -38,18 +72,65 @@ where
     Ok(shuffled_objects)
 }
 
+/// Applies a secret-shared `permutation` to `input`: row `i` moves to position `permutation[i]`,
+/// the same way [`apply_inv`] moves rows for a permutation known in the clear. Sort permutations
+/// work this way too, so this can apply one that was computed outside of sort.
+///
+/// `permutation` is shuffled by a random permutation that no helper knows and revealed, which
+/// tells helpers nothing about it. Then [`apply_sort_permutation`] shuffles `input` the same way
+/// and applies the revealed permutation. In malicious contexts, shares of `permutation` are
+/// checked before they are revealed.
+///
+/// ## Errors
+/// If `input` and `permutation` are not of the same length, or if communication with peers
+/// fails.
+///
+/// ## Panics
+/// If `permutation` does not reveal to a permutation of `0..input.len()`.
+pub async fn apply_shared_permutation<F, C, S, I>(
+    ctx: C,
+    permutation: Vec<Replicated<F>>,
+    input: Vec<I>,
+) -> Result<Vec<I>, Error>
+where
+    F: PrimeField + ExtendableField,
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + BasicProtocols<C::UpgradedContext<F>, F> + 'static,
+    ShuffledPermutationWrapper<S, C::UpgradedContext<F>>: DowngradeMalicious<Target = Vec<u32>>,
+    for<'u> UpgradeContext<'u, C::UpgradedContext<F>, F>:
+        UpgradeToMalicious<'u, Vec<Replicated<F>>, Vec<S>>,
+    I: Reshare<C, RecordId> + Send + Sync,
+{
+    if input.len() != permutation.len() {
+        return Err(Error::PermutationLengthMismatch {
+            permutation: permutation.len(),
+            values: input.len(),
+        });
+    }
+
+    let validator = ctx
+        .narrow(&SortStep::ShuffleRevealPermutation)
+        .validator::<F>();
+    let m_ctx = validator.context();
+    let permutation = m_ctx.upgrade(permutation).await?;
+    let revealed = shuffle_and_reveal_permutation::<C, _, _>(m_ctx, permutation, validator).await?;
+    apply_sort_permutation(ctx, input, &revealed).await
+}
+
 #[cfg(all(test, unit_test))]
 mod tests {
     use futures::stream::iter as stream_iter;
 
     use crate::{
         accumulation_test_input,
-        ff::{Fp32BitPrime, GaloisField},
+        error::Error,
+        ff::{Field, Fp32BitPrime},
         protocol::{
             attribution::input::AccumulateCreditInputRow,
             context::Context,
             sort::{
-                apply_sort::apply_sort_permutation,
+                apply_sort::{apply_shared_permutation, apply_sort_permutation},
                 generate_permutation::generate_permutation_and_reveal_shuffled,
             },
             BreakdownKey, MatchKey,
@@ -117,4 +198,185 @@ mod tests {
 
         assert_eq!(&expected[..], &result[..]);
     }
+
+    /// Sorts two columns separately with the same permutation.
+    #[tokio::test]
+    pub async fn columns_stay_aligned() {
+        const COUNT: usize = 10;
+        const NUM_MULTI_BITS: u32 = 3;
+
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+
+        let mut match_keys = Vec::with_capacity(COUNT);
+        match_keys.resize_with(COUNT, || rng.gen::<MatchKey>());
+        let timestamps = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+        let trigger_values = (0..COUNT)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+
+        let permutation =
+            permutation::sort(match_keys.iter().map(|mk| mk.as_u128()).collect::<Vec<_>>());
+        let expected = (
+            permutation.apply_slice(&timestamps),
+            permutation.apply_slice(&trigger_values),
+        );
+
+        let result: (Vec<Fp32BitPrime>, Vec<Fp32BitPrime>) = world
+            .semi_honest(
+                (
+                    match_keys.into_iter(),
+                    (timestamps.into_iter(), trigger_values.into_iter()),
+                ),
+                |ctx,
+                 (mk_shares, (timestamps, trigger_values)): (Vec<_>, (Vec<_>, Vec<_>))| async move {
+                    let sort_permutation =
+                        generate_permutation_and_reveal_shuffled::<Fp32BitPrime, _, _, _>(
+                            ctx.narrow("convert_all_bits"),
+                            stream_iter(mk_shares),
+                            NUM_MULTI_BITS,
+                            MatchKey::BITS,
+                        )
+                        .await
+                        .unwrap();
+
+                    let timestamps = apply_sort_permutation(
+                        ctx.narrow("timestamps"),
+                        timestamps,
+                        &sort_permutation,
+                    )
+                    .await
+                    .unwrap();
+                    let trigger_values = apply_sort_permutation(
+                        ctx.narrow("trigger_values"),
+                        trigger_values,
+                        &sort_permutation,
+                    )
+                    .await
+                    .unwrap();
+
+                    (timestamps, trigger_values)
+                },
+            )
+            .await
+            .reconstruct();
+
+        assert_eq!(expected, result);
+    }
+
+    #[tokio::test]
+    pub async fn length_mismatch() {
+        const COUNT: usize = 5;
+        const NUM_MULTI_BITS: u32 = 3;
+
+        let world = TestWorld::default();
+        let mut rng = thread_rng();
+
+        let mut match_keys = Vec::with_capacity(COUNT);
+        match_keys.resize_with(COUNT, || rng.gen::<MatchKey>());
+        let values = (0..COUNT - 1)
+            .map(|_| rng.gen::<Fp32BitPrime>())
+            .collect::<Vec<_>>();
+
+        let result = world
+            .semi_honest(
+                (match_keys.into_iter(), values.into_iter()),
+                |ctx,
+                 (mk_shares, values): (
+                    Vec<Replicated<MatchKey>>,
+                    Vec<Replicated<Fp32BitPrime>>,
+                )| async move {
+                    let sort_permutation =
+                        generate_permutation_and_reveal_shuffled::<Fp32BitPrime, _, _, _>(
+                            ctx.narrow("convert_all_bits"),
+                            stream_iter(mk_shares),
+                            NUM_MULTI_BITS,
+                            MatchKey::BITS,
+                        )
+                        .await
+                        .unwrap();
+
+                    apply_sort_permutation(ctx.narrow("values"), values, &sort_permutation).await
+                },
+            )
+            .await;
+
+        for r in result {
+            assert!(
+                matches!(
+                    r,
+                    Err(Error::PermutationLengthMismatch {
+                        permutation: COUNT,
+                        values,
+                    }) if values == COUNT - 1
+                ),
+                "{r:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    pub async fn shared_permutation() {
+        let permutation = [2_u32, 4, 0, 1, 3, 5].map(Fp32BitPrime::truncate_from);
+        let input = [10_u32, 11, 12, 13, 14, 15].map(Fp32BitPrime::truncate_from);
+        let expected = [12_u32, 13, 10, 14, 11, 15].map(Fp32BitPrime::truncate_from);
+
+        let world = TestWorld::default();
+        let result: Vec<Fp32BitPrime> = world
+            .semi_honest(
+                (permutation.into_iter(), input.into_iter()),
+                |ctx, (permutation, input)| async move {
+                    apply_shared_permutation(ctx, permutation, input)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct();
+        let m_result: Vec<Fp32BitPrime> = world
+            .malicious(
+                (permutation.into_iter(), input.into_iter()),
+                |ctx, (permutation, input)| async move {
+                    apply_shared_permutation(ctx, permutation, input)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct();
+
+        assert_eq!(expected.to_vec(), result);
+        assert_eq!(expected.to_vec(), m_result);
+    }
+
+    #[tokio::test]
+    pub async fn shared_permutation_length_mismatch() {
+        let world = TestWorld::default();
+        let result = world
+            .semi_honest(
+                (
+                    [Fp32BitPrime::ZERO, Fp32BitPrime::ONE].into_iter(),
+                    [Fp32BitPrime::ONE].into_iter(),
+                ),
+                |ctx, (permutation, input)| async move {
+                    apply_shared_permutation(ctx, permutation, input).await
+                },
+            )
+            .await;
+
+        for r in result {
+            assert!(
+                matches!(
+                    r,
+                    Err(Error::PermutationLengthMismatch {
+                        permutation: 2,
+                        values: 1
+                    })
+                ),
+                "{r:?}"
+            );
+        }
+    }
 }
@@ -6,9 +6,13 @@ pub mod generate_permutation_opt;
 mod compose;
 mod multi_bit_permutation;
 mod secureapplyinv;
-mod shuffle;
+pub(crate) mod shuffle;
+#[cfg(feature = "descriptive-gate")]
+mod sort_by_key;
 
 use ipa_macros::Step;
+#[cfg(feature = "descriptive-gate")]
+pub use sort_by_key::sort;
 
 use crate::{
     error::Error,
@@ -0,0 +1,276 @@
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::PrimeField,
+    protocol::{
+        boolean::{
+            bit_decomposition::{bit_decompose_low, BitDecompositionStep},
+            random_bits_generator::RandomBitsGenerator,
+        },
+        context::{Context, UpgradableContext, UpgradedContext, Validator},
+        sort::{
+            apply_sort::apply_sort_permutation,
+            compose::compose,
+            generate_permutation::{
+                shuffle_and_reveal_permutation, RevealedAndRandomPermutations,
+                ShuffledPermutationWrapper,
+            },
+            multi_bit_permutation::multi_bit_permutation,
+            secureapplyinv::secureapplyinv_multi,
+            SortStep,
+        },
+        step::BitOpStep,
+        RecordId,
+    },
+    secret_sharing::{
+        replicated::{
+            malicious::{DowngradeMalicious, ExtendableField},
+            semi_honest::AdditiveShare as Replicated,
+        },
+        BitDecomposed,
+    },
+    seq_join::SeqJoin,
+};
+
+/// Not a part of any protocol yet, so it is not in `steps.txt`.
+#[derive(Step)]
+pub(crate) enum Step {
+    DecomposeKeys,
+    SortBits,
+    ApplySort,
+}
+
+/// Number of bits sorted at once, the same as IPA uses.
+const NUM_MULTI_BITS: usize = 3;
+
+/// Sorts `records` by their keys in ascending order, keeping each key with its payload. Keys are
+/// field elements that are known to be less than `2^num_bits`. Records with equal keys keep their
+/// order, so does every record if `num_bits` is zero.
+///
+/// This is the radix sort that IPA runs on match keys, except that keys are arithmetic shares.
+/// All of them are decomposed into `num_bits` bits with [`bit_decompose_low`] first. Then the sort
+/// permutation is built from the least significant bits up, [`NUM_MULTI_BITS`] bits at a time,
+/// the same way [`generate_permutation_opt`] does it. Chunk `i` runs on `BitOpStep::from(i)`.
+/// Finally, the permutation is applied to keys and payloads together, as rows that are shuffled
+/// and then sorted in the clear. Helpers learn nothing but the number of records.
+///
+/// Only semi-honest contexts can sort, because keys stay decomposed across the validators of the
+/// chunks.
+///
+/// [`generate_permutation_opt`]: super::generate_permutation_opt::generate_permutation_opt
+///
+/// ## Errors
+/// If communication with peers fails.
+///
+/// ## Panics
+/// If `num_bits` is more than it takes to write down `p - 1`.
+pub async fn sort<F, C>(
+    ctx: C,
+    records: Vec<(Replicated<F>, Vec<Replicated<F>>)>,
+    num_bits: u32,
+) -> Result<Vec<(Replicated<F>, Vec<Replicated<F>>)>, Error>
+where
+    F: PrimeField + ExtendableField,
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = Replicated<F>>,
+    ShuffledPermutationWrapper<Replicated<F>, C::UpgradedContext<F>>:
+        DowngradeMalicious<Target = Vec<u32>>,
+{
+    let num_bits = usize::try_from(num_bits).unwrap();
+    if records.is_empty() || num_bits == 0 {
+        return Ok(records);
+    }
+
+    let key_bits = decompose_keys(ctx.narrow(&Step::DecomposeKeys), &records, num_bits).await?;
+
+    let sort_ctx = ctx.narrow(&Step::SortBits);
+    let mut sorted_so_far: Option<RevealedAndRandomPermutations> = None;
+    for (chunk_num, chunk_start) in (0..num_bits).step_by(NUM_MULTI_BITS).enumerate() {
+        let validator = sort_ctx
+            .narrow(&BitOpStep::from(chunk_num))
+            .validator::<F>();
+        let m_ctx = validator.context();
+        let chunk = chunk_start..num_bits.min(chunk_start + NUM_MULTI_BITS);
+        let key_chunk = key_bits
+            .iter()
+            .map(|bits| BitDecomposed::new(bits[chunk.clone()].iter().cloned()))
+            .collect::<Vec<_>>();
+
+        let permutation = match &sorted_so_far {
+            None => {
+                multi_bit_permutation(m_ctx.narrow(&SortStep::BitPermutation), &key_chunk).await?
+            }
+            Some(sorted_so_far) => {
+                let randoms = (
+                    sorted_so_far.randoms_for_shuffle.0.as_slice(),
+                    sorted_so_far.randoms_for_shuffle.1.as_slice(),
+                );
+                let revealed = sorted_so_far.revealed.as_slice();
+                let key_chunk = secureapplyinv_multi(
+                    m_ctx.narrow(&SortStep::MultiApplyInv(chunk_num.try_into().unwrap())),
+                    key_chunk,
+                    randoms,
+                    revealed,
+                )
+                .await?;
+                let permutation =
+                    multi_bit_permutation(m_ctx.narrow(&SortStep::BitPermutation), &key_chunk)
+                        .await?;
+                compose(
+                    m_ctx.narrow(&SortStep::Compose),
+                    randoms,
+                    revealed,
+                    permutation,
+                )
+                .await?
+            }
+        };
+
+        sorted_so_far = Some(
+            shuffle_and_reveal_permutation::<C, _, _>(
+                m_ctx.narrow(&SortStep::ShuffleRevealPermutation),
+                permutation,
+                validator,
+            )
+            .await?,
+        );
+    }
+
+    let rows = records
+        .into_iter()
+        .map(|(key, payload)| {
+            let mut row = Vec::with_capacity(payload.len() + 1);
+            row.push(key);
+            row.extend(payload);
+            row
+        })
+        .collect::<Vec<_>>();
+    let sorted = apply_sort_permutation(
+        ctx.narrow(&Step::ApplySort),
+        rows,
+        sorted_so_far.as_ref().unwrap(),
+    )
+    .await?;
+
+    Ok(sorted
+        .into_iter()
+        .map(|mut row| {
+            let payload = row.split_off(1);
+            (row.pop().unwrap(), payload)
+        })
+        .collect())
+}
+
+/// Decomposes the key of every record into its `num_bits` least significant bits.
+async fn decompose_keys<F, C>(
+    ctx: C,
+    records: &[(Replicated<F>, Vec<Replicated<F>>)],
+    num_bits: usize,
+) -> Result<Vec<Vec<Replicated<F>>>, Error>
+where
+    F: PrimeField + ExtendableField,
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = Replicated<F>>,
+{
+    let validator = ctx.validator::<F>();
+    let ctx = validator.context().set_total_records(records.len());
+    let rbg = RandomBitsGenerator::new(ctx.narrow(&BitDecompositionStep::RandomMask));
+    ctx.try_join(records.iter().enumerate().map(|(i, (key, _))| {
+        bit_decompose_low(ctx.clone(), RecordId::from(i), &rbg, key, num_bits)
+    }))
+    .await
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::zip;
+
+    use super::sort;
+    use crate::{
+        ff::{Field, Fp32BitPrime},
+        rand::{thread_rng, Rng},
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    /// Sorts `keys`, carrying their positions and squares as payload, and checks that the result
+    /// is what a stable sort in the clear gives.
+    async fn sorts(keys: Vec<u32>, num_bits: u32) {
+        let records = keys
+            .iter()
+            .enumerate()
+            .map(|(i, &key)| {
+                (
+                    Fp32BitPrime::truncate_from(key),
+                    vec![
+                        Fp32BitPrime::truncate_from(u32::try_from(i).unwrap()),
+                        Fp32BitPrime::truncate_from(key * key),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut expected = records.clone();
+        expected.sort_by_key(|(key, _)| key.as_u128());
+
+        // Payloads are shared column by column, so that the input implements `IntoShares`.
+        let (input_keys, payloads): (Vec<_>, Vec<_>) = records.into_iter().unzip();
+        let (positions, squares): (Vec<_>, Vec<_>) =
+            payloads.into_iter().map(|p| (p[0], p[1])).unzip();
+        let input = (
+            input_keys.into_iter(),
+            (positions.into_iter(), squares.into_iter()),
+        );
+
+        let world = TestWorld::default();
+        let (keys, payloads): (Vec<Fp32BitPrime>, Vec<Vec<Fp32BitPrime>>) = world
+            .semi_honest(input, |ctx, (keys, (positions, squares))| async move {
+                let records = zip(keys, zip(positions, squares))
+                    .map(|(key, (position, square))| (key, vec![position, square]))
+                    .collect();
+                sort(ctx, records, num_bits)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .unzip::<_, _, Vec<_>, Vec<_>>()
+            })
+            .await
+            .reconstruct();
+
+        assert_eq!(expected.len(), keys.len());
+        for ((key, payload), (expected_key, expected_payload)) in zip(zip(keys, payloads), expected)
+        {
+            assert_eq!(expected_key, key);
+            assert_eq!(expected_payload, payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn small() {
+        sorts(vec![5, 1, 7, 0, 3, 3, 6, 2, 1], 3).await;
+    }
+
+    #[tokio::test]
+    async fn random() {
+        const NUM_BITS: u32 = 10;
+        let mut rng = thread_rng();
+        let keys = (0..30)
+            .map(|_| rng.gen_range(0..1 << NUM_BITS))
+            .collect::<Vec<_>>();
+        sorts(keys, NUM_BITS).await;
+    }
+
+    #[tokio::test]
+    async fn empty() {
+        sorts(Vec::new(), 8).await;
+    }
+
+    #[tokio::test]
+    async fn no_bits() {
+        sorts(vec![0; 4], 0).await;
+    }
+
+    #[tokio::test]
+    async fn single() {
+        sorts(vec![42], 8).await;
+    }
+}
@@ -75,6 +75,33 @@ impl From<usize> for BitOpStep {
     }
 }
 
+/// Level of a tree-shaped circuit, see [`TreeStep`]. It is a step type of its own, so that tree
+/// nodes never share a step with loops that narrow through [`BitOpStep`] twice.
+#[derive(Step)]
+pub enum TreeLevel {
+    #[dynamic(64)]
+    Level(usize),
+}
+
+impl From<usize> for TreeLevel {
+    fn from(v: usize) -> Self {
+        Self::Level(v)
+    }
+}
+
+/// Position of a multiplication in a tree-shaped circuit, such as a level of a prefix scan and the
+/// bit computed at that level. Contexts are narrowed to [`TreeLevel`] `level` first and then to
+/// [`BitOpStep`] `node`, so that nodes of different levels never share a step.
+///
+/// Use [`Context::narrow_tree`] to narrow to it.
+///
+/// [`Context::narrow_tree`]: crate::protocol::context::Context::narrow_tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TreeStep {
+    pub level: usize,
+    pub node: usize,
+}
+
 /// Set of steps that define the IPA protocol.
 #[derive(Step)]
 pub(crate) enum IpaProtocolStep {
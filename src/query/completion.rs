@@ -8,7 +8,7 @@ use futures::FutureExt;
 
 use crate::query::{
     runner::QueryResult,
-    state::{RemoveQuery, RunningQuery},
+    state::{QueryCancelled, RemoveQuery, RunningQuery},
 };
 
 /// Query completion polls the tokio task to get the results and cleans up the query state after.
@@ -18,7 +18,7 @@ pub struct Handle<'a> {
 }
 
 impl<'a> Future for Handle<'a> {
-    type Output = QueryResult;
+    type Output = Result<QueryResult, QueryCancelled>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         self.inner.poll_unpin(cx)
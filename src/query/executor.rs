@@ -1,6 +1,10 @@
 use std::{
+    any::Any,
     fmt::Debug,
     future::{ready, Future},
+    iter,
+    num::NonZeroUsize,
+    panic::AssertUnwindSafe,
     pin::Pin,
     sync::Arc,
 };
@@ -16,9 +20,10 @@ use typenum::Unsigned;
 
 use super::runner::OprfIpaQuery;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
-use crate::query::runner::execute_test_multiply;
+use crate::query::runner::{execute_test_add, execute_test_multiply};
 use crate::{
-    ff::{FieldType, Fp32BitPrime, Serializable},
+    error::Error as ProtocolError,
+    ff::{FieldType, Fp32BitPrime, PrimeField, Serializable},
     helpers::{
         negotiate_prss,
         query::{QueryConfig, QueryType},
@@ -27,33 +32,130 @@ use crate::{
     hpke::{KeyPair, KeyRegistry},
     protocol::{
         context::{MaliciousContext, SemiHonestContext},
+        ipa::IPAInputRow,
         prss::Endpoint as PrssEndpoint,
         step::{Gate, StepNarrow},
+        BreakdownKey, MatchKey,
     },
     query::{
         runner::{IpaQuery, QueryResult, SparseAggregateQuery},
         state::RunningQuery,
     },
+    secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
 };
 
 pub trait Result: Send + Debug {
     fn into_bytes(self: Box<Self>) -> Vec<u8>;
+
+    /// Appends the same bytes [`into_bytes`] produces to `buf`. Callers that serialize many
+    /// results can reuse one buffer for all of them, instead of allocating a new one each time.
+    ///
+    /// [`into_bytes`]: Self::into_bytes
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend(self.clone_boxed().into_bytes());
+    }
+
+    /// Serializes this result lazily, producing chunks that hold at most `rows_per_chunk` rows
+    /// each. Concatenating all chunks yields the same buffer as [`into_bytes`].
+    ///
+    /// [`into_bytes`]: Self::into_bytes
+    fn into_byte_chunks(
+        self: Box<Self>,
+        rows_per_chunk: NonZeroUsize,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send>;
+
+    /// Copies this result, so it can be handed out more than once.
+    fn clone_boxed(&self) -> Box<dyn Result>;
+
+    /// Exposes the concrete type of this result. See [`downcast_result`].
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// Returns the output of the query as `T`, if that is the type the protocol produced. It lets
+/// in-process callers use the result without serializing it to bytes and parsing it back.
+#[must_use]
+pub fn downcast_result<T: 'static>(result: &dyn Result) -> Option<&T> {
+    result.as_any().downcast_ref()
 }
 
 impl<T> Result for Vec<T>
 where
-    T: Serializable,
+    T: Serializable + Clone + Send + 'static,
     Vec<T>: Debug + Send,
 {
     fn into_bytes(self: Box<Self>) -> Vec<u8> {
-        let mut r = vec![0u8; self.len() * T::Size::USIZE];
-        for (i, row) in self.into_iter().enumerate() {
+        let mut r = Vec::with_capacity(self.len() * T::Size::USIZE);
+        self.write_to(&mut r);
+
+        r
+    }
+
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        let start = buf.len();
+        buf.resize(start + self.len() * T::Size::USIZE, 0);
+        for (i, row) in self.iter().enumerate() {
+            let offset = start + i * T::Size::USIZE;
             row.serialize(GenericArray::from_mut_slice(
-                &mut r[(i * T::Size::USIZE)..((i + 1) * T::Size::USIZE)],
+                &mut buf[offset..offset + T::Size::USIZE],
             ));
         }
+    }
 
-        r
+    fn into_byte_chunks(
+        self: Box<Self>,
+        rows_per_chunk: NonZeroUsize,
+    ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+        // Rows are serialized straight into the chunk, only when the chunk is asked for.
+        let mut rows = self.into_iter().peekable();
+        Box::new(iter::from_fn(move || {
+            rows.peek()?;
+            let mut chunk = Vec::with_capacity(rows_per_chunk.get() * T::Size::USIZE);
+            for row in rows.by_ref().take(rows_per_chunk.get()) {
+                let mut buf = GenericArray::default();
+                row.serialize(&mut buf);
+                chunk.extend_from_slice(&buf);
+            }
+            Some(chunk)
+        }))
+    }
+
+    fn clone_boxed(&self) -> Box<dyn Result> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Returns the size in bytes of a single input record expected by the query described by `config`,
+/// or `None` if input records don't have a fixed size, for example when they are encrypted.
+#[must_use]
+pub fn input_record_size(config: &QueryConfig) -> Option<usize> {
+    fn record_size<F: PrimeField>(query_type: QueryType) -> Option<usize>
+    where
+        Replicated<F>: Serializable,
+        IPAInputRow<F, MatchKey, BreakdownKey>: Serializable,
+    {
+        match query_type {
+            // every record of test queries is a pair of shares
+            #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+            QueryType::TestMultiply | QueryType::TestAdd => {
+                Some(2 * <Replicated<F> as Serializable>::Size::USIZE)
+            }
+            QueryType::SemiHonestIpa(ipa_config) | QueryType::MaliciousIpa(ipa_config)
+                if ipa_config.plaintext_match_keys =>
+            {
+                Some(<IPAInputRow<F, MatchKey, BreakdownKey> as Serializable>::Size::USIZE)
+            }
+            _ => None,
+        }
+    }
+
+    match config.field_type {
+        #[cfg(any(test, feature = "weak-field"))]
+        FieldType::Fp31 => record_size::<crate::ff::Fp31>(config.query_type),
+        FieldType::Fp32BitPrime => record_size::<Fp32BitPrime>(config.query_type),
     }
 }
 
@@ -67,16 +169,36 @@ pub fn execute(
     match (config.query_type, config.field_type) {
         #[cfg(any(test, feature = "weak-field"))]
         (QueryType::TestMultiply, FieldType::Fp31) => {
-            do_query(config, gateway, input, |prss, gateway, _config, input| {
+            do_query(config, gateway, input, |prss, gateway, config, input| {
                 Box::pin(execute_test_multiply::<crate::ff::Fp31>(
-                    prss, gateway, input,
+                    prss,
+                    gateway,
+                    config.security_model,
+                    input,
                 ))
             })
         }
         #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
         (QueryType::TestMultiply, FieldType::Fp32BitPrime) => {
+            do_query(config, gateway, input, |prss, gateway, config, input| {
+                Box::pin(execute_test_multiply::<Fp32BitPrime>(
+                    prss,
+                    gateway,
+                    config.security_model,
+                    input,
+                ))
+            })
+        }
+        #[cfg(any(test, feature = "weak-field"))]
+        (QueryType::TestAdd, FieldType::Fp31) => {
             do_query(config, gateway, input, |prss, gateway, _config, input| {
-                Box::pin(execute_test_multiply::<Fp32BitPrime>(prss, gateway, input))
+                Box::pin(execute_test_add::<crate::ff::Fp31>(prss, gateway, input))
+            })
+        }
+        #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+        (QueryType::TestAdd, FieldType::Fp32BitPrime) => {
+            do_query(config, gateway, input, |prss, gateway, _config, input| {
+                Box::pin(execute_test_add::<Fp32BitPrime>(prss, gateway, input))
             })
         }
         #[cfg(any(test, feature = "weak-field"))]
@@ -233,6 +355,21 @@ pub fn execute(
     }
 }
 
+/// Randomness for PRSS negotiation. Tests can make it deterministic, by setting a seed on
+/// [`GatewayConfig`].
+///
+/// [`GatewayConfig`]: crate::helpers::GatewayConfig
+#[cfg_attr(not(any(test, feature = "test-fixture")), allow(unused_variables))] // For gateway.
+fn prss_rng(gateway: &Gateway) -> StdRng {
+    #[cfg(any(test, feature = "test-fixture"))]
+    if let Some(seed) = gateway.config().seed() {
+        // helpers must not end up with the same keys.
+        return StdRng::seed_from_u64(seed.wrapping_add(gateway.role() as u64));
+    }
+
+    StdRng::from_entropy()
+}
+
 pub fn do_query<F>(
     config: QueryConfig,
     gateway: Gateway,
@@ -252,14 +389,28 @@ where
     let (tx, rx) = oneshot::channel();
 
     let join_handle = tokio::spawn(async move {
-        // TODO: make it a generic argument for this function
-        let mut rng = StdRng::from_entropy();
-        // Negotiate PRSS first
-        let step = Gate::default().narrow(&config.query_type);
-        let prss = negotiate_prss(&gateway, &step, &mut rng).await.unwrap();
-
-        tx.send(query_impl(&prss, &gateway, &config, input_stream).await)
-            .unwrap();
+        let run = async {
+            // TODO: make it a generic argument for this function
+            let mut rng = prss_rng(&gateway);
+            // Negotiate PRSS first
+            let step = Gate::default().narrow(&config.query_type);
+            let prss = negotiate_prss(&gateway, &step, &mut rng).await.unwrap();
+
+            query_impl(&prss, &gateway, &config, input_stream).await
+        };
+        // A panic must not take the result channel down with it, otherwise the query would look
+        // cancelled rather than failed.
+        let run = AssertUnwindSafe(run).catch_unwind().map(|result| {
+            result.unwrap_or_else(|panic| Err(ProtocolError::QueryPanicked(panic_message(&*panic))))
+        });
+        let result = match config.timeout {
+            Some(timeout) => ::tokio::time::timeout(timeout, run)
+                .await
+                .unwrap_or(Err(ProtocolError::QueryTimedOut(timeout))),
+            None => run.await,
+        };
+        gateway.close();
+        tx.send(result).unwrap();
     });
 
     RunningQuery {
@@ -268,12 +419,30 @@ where
     }
 }
 
+/// Extracts the message `panic!` was called with, if there is one.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| (*s).to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
 #[cfg(all(test, unit_test))]
 mod tests {
+    use std::{any::Any, iter, num::NonZeroUsize};
+
+    use generic_array::GenericArray;
+    use typenum::U1;
+
     use crate::{
-        ff::{Field, Fp31},
+        ff::{Field, Fp31, Serializable},
         query::ProtocolResult,
         secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
     };
 
     #[test]
@@ -286,4 +455,102 @@ mod tests {
             AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
         );
     }
+
+    #[test]
+    fn serialize_result_in_chunks() {
+        let [input, ..] = (0u128..=6).map(Fp31::truncate_from).share();
+        let expected = Box::new(input.clone()).into_bytes();
+        let chunks = Box::new(input)
+            .into_byte_chunks(NonZeroUsize::new(3).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(3, chunks.len());
+        assert_eq!(expected, chunks.concat());
+    }
+
+    /// Row that counts how many times rows sharing its counter were serialized.
+    #[derive(Clone, Debug)]
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Serializable for Counted {
+        type Size = U1;
+
+        fn serialize(&self, buf: &mut GenericArray<u8, Self::Size>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            buf[0] = 1;
+        }
+
+        fn deserialize(_buf: &GenericArray<u8, Self::Size>) -> Self {
+            unimplemented!()
+        }
+    }
+
+    /// Rows of a chunk are serialized only when the consumer asks for that chunk.
+    #[test]
+    fn chunks_are_lazy() {
+        const ROWS: usize = 100;
+        let serialized = Arc::new(AtomicUsize::new(0));
+        let rows = vec![Counted(Arc::clone(&serialized)); ROWS];
+
+        let mut chunks = Box::new(rows).into_byte_chunks(NonZeroUsize::new(10).unwrap());
+        assert_eq!(0, serialized.load(Ordering::Relaxed));
+        for i in 1..=3 {
+            assert_eq!(vec![1; 10], chunks.next().unwrap());
+            assert_eq!(10 * i, serialized.load(Ordering::Relaxed));
+        }
+        assert_eq!(7, chunks.count());
+        assert_eq!(ROWS, serialized.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn write_to_appends() {
+        let [first, second, ..] = (0u128..=4).map(Fp31::truncate_from).share();
+        let mut expected = vec![42];
+        for row in first.iter().chain(&second) {
+            let mut bytes = GenericArray::default();
+            row.serialize(&mut bytes);
+            expected.extend_from_slice(&bytes);
+        }
+
+        let mut buf = vec![42];
+        first.write_to(&mut buf);
+        (&second as &dyn ProtocolResult).write_to(&mut buf);
+
+        assert_eq!(expected, buf);
+    }
+
+    /// Result that relies on the default [`ProtocolResult::write_to`].
+    #[derive(Clone, Debug)]
+    struct RawResult(Vec<u8>);
+
+    impl ProtocolResult for RawResult {
+        fn into_bytes(self: Box<Self>) -> Vec<u8> {
+            self.0
+        }
+
+        fn into_byte_chunks(
+            self: Box<Self>,
+            _rows_per_chunk: NonZeroUsize,
+        ) -> Box<dyn Iterator<Item = Vec<u8>> + Send> {
+            Box::new(iter::once(self.0))
+        }
+
+        fn clone_boxed(&self) -> Box<dyn ProtocolResult> {
+            Box::new(self.clone())
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn default_write_to_appends() {
+        let result = RawResult(vec![1, 2, 3]);
+        let mut buf = vec![42];
+        result.write_to(&mut buf);
+        (&result as &dyn ProtocolResult).write_to(&mut buf);
+
+        assert_eq!(vec![42, 1, 2, 3, 1, 2, 3], buf);
+    }
 }
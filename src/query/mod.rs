@@ -5,7 +5,7 @@ mod runner;
 mod state;
 
 use completion::Handle as CompletionHandle;
-pub use executor::Result as ProtocolResult;
+pub use executor::{downcast_result, Result as ProtocolResult};
 pub use processor::{
     NewQueryError, PrepareQueryError, Processor as QueryProcessor, QueryCompletionError,
     QueryInputError, QueryStatusError,
@@ -1,24 +1,35 @@
 use std::{
-    collections::hash_map::Entry,
+    collections::{hash_map::Entry, HashMap},
     fmt::{Debug, Formatter},
+    num::NonZeroUsize,
     sync::Arc,
 };
 
-use futures::{future::try_join, stream};
+use futures::{future::try_join, stream, Stream, StreamExt};
+use tracing::{field, Span};
 
 use crate::{
     error::Error as ProtocolError,
+    ff::FieldType,
     helpers::{
-        query::{PrepareQuery, QueryConfig, QueryInput},
-        Gateway, GatewayConfig, Role, RoleAssignment, Transport, TransportError, TransportImpl,
+        query::{
+            PrepareQuery, QueryConfig, QueryConfigError, QueryInput, QuerySize, SecurityModel,
+        },
+        BodyStream, Gateway, GatewayConfig, Role, RoleAssignment, StatsSnapshot, Transport,
+        TransportError, TransportImpl, UnsupportedTransport,
     },
     hpke::{KeyPair, KeyRegistry},
     protocol::QueryId,
     query::{
         executor,
-        state::{QueryState, QueryStatus, RemoveQuery, RunningQueries, StateError},
+        state::{QueryCancelled, QueryState, QueryStatus, RemoveQuery, RunningQueries, StateError},
         CompletionHandle, ProtocolResult,
     },
+    rand::{thread_rng, RngCore},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
 };
 
 /// `Processor` accepts and tracks requests to initiate new queries on this helper party
@@ -41,23 +52,51 @@ use crate::{
 pub struct Processor {
     queries: RunningQueries,
     key_registry: Arc<KeyRegistry<KeyPair>>,
+    /// Transport every running query uses, to collect statistics and release its resources
+    /// once the query completes.
+    transports: Mutex<HashMap<QueryId, TransportImpl>>,
+    /// Role of this helper in every query it knows about. Query state only keeps the assignment
+    /// of roles to helpers until the query starts, and the processor itself does not know the
+    /// identity of the helper it runs on, so the role is resolved upfront.
+    roles: Mutex<HashMap<QueryId, Role>>,
+    /// Id of the next query this helper coordinates. It starts at a random value, so ids
+    /// assigned by different helpers are unlikely to collide.
+    next_query_id: AtomicU64,
+    /// Fields this helper accepts queries in. Followers reject queries in any other field when
+    /// they are asked to prepare for them, rather than when inputs arrive.
+    field_types: Vec<FieldType>,
+    /// Security models this helper is willing to run queries under.
+    security_models: Vec<SecurityModel>,
+    /// The largest query this helper accepts, if there is a limit.
+    max_query_size: Option<QuerySize>,
+    /// Inputs appended to queries that are still awaiting inputs, in the order they arrived.
+    /// They are handed to the query when its inputs are closed.
+    pending_inputs: Mutex<HashMap<QueryId, Vec<QueryInput>>>,
+    /// Configuration of gateways for every query this helper runs. If it is not set, it is
+    /// derived from the config of each query.
+    gateway_config: Option<GatewayConfig>,
 }
 
 impl Default for Processor {
     fn default() -> Self {
-        Self {
-            queries: RunningQueries::default(),
-            key_registry: Arc::new(KeyRegistry::<KeyPair>::empty()),
-        }
+        Self::new(KeyRegistry::<KeyPair>::empty())
     }
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum NewQueryError {
+    #[error(transparent)]
+    InvalidConfig(#[from] QueryConfigError),
     #[error(transparent)]
     State(#[from] StateError),
     #[error(transparent)]
     Transport(#[from] TransportError),
+    #[error("This helper does not accept new queries")]
+    NotAccepted,
+    #[error("This helper does not support queries in field {0:?}")]
+    UnsupportedField(FieldType),
+    #[error("This helper rejected the query config: {reason}")]
+    ConfigRejected { reason: String },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -66,6 +105,14 @@ pub enum PrepareQueryError {
     WrongTarget,
     #[error("Query is already running")]
     AlreadyRunning,
+    #[error("Query already exists, its results must be discarded first")]
+    AlreadyExists,
+    #[error("This helper does not participate in queries")]
+    NotAccepted,
+    #[error("This helper does not support queries in field {0:?}")]
+    UnsupportedField(FieldType),
+    #[error("This helper rejected the query config: {reason}")]
+    ConfigRejected { reason: String },
     #[error(transparent)]
     StateError {
         #[from]
@@ -78,6 +125,16 @@ pub enum QueryInputError {
     #[error("The query with id {0:?} does not exist")]
     NoSuchQuery(QueryId),
     #[error(transparent)]
+    UnsupportedTransport(#[from] UnsupportedTransport),
+    #[error("Input length {length} is not a multiple of the record size {record_size}")]
+    MalformedInput { length: usize, record_size: usize },
+    #[error("Query expects {expected} records of {record_size} bytes, but its input is {length} bytes long")]
+    UnexpectedSize {
+        expected: usize,
+        record_size: usize,
+        length: usize,
+    },
+    #[error(transparent)]
     StateError {
         #[from]
         source: StateError,
@@ -88,6 +145,11 @@ pub enum QueryInputError {
 pub enum QueryStatusError {
     #[error("The query with id {0:?} does not exist")]
     NoSuchQuery(QueryId),
+    #[error("The query with id {query_id:?} has not completed yet, it is {status:?}")]
+    NotCompleted {
+        query_id: QueryId,
+        status: QueryStatus,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -101,8 +163,86 @@ pub enum QueryCompletionError {
     },
     #[error("query execution failed: {0}")]
     ExecutionError(#[from] ProtocolError),
+    #[error("The query with id {0:?} was cancelled before it completed")]
+    Cancelled(QueryId),
+    #[error("The query with id {0:?} did not complete before its deadline")]
+    TimedOut(QueryId),
+}
+
+impl QueryCompletionError {
+    fn execution(query_id: QueryId, error: ProtocolError) -> Self {
+        match error {
+            ProtocolError::QueryTimedOut(_) => Self::TimedOut(query_id),
+            error => Self::ExecutionError(error),
+        }
+    }
+}
+
+/// Makes sure that the input can be split into whole records, if its length is known upfront.
+fn validate_input_length(config: &QueryConfig, input: &QueryInput) -> Result<(), QueryInputError> {
+    match (input.length, executor::input_record_size(config)) {
+        (Some(length), Some(record_size)) if length % record_size != 0 => {
+            Err(QueryInputError::MalformedInput {
+                length,
+                record_size,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Makes sure that the whole input of a query holds as many records as its config says, if the
+/// length of the input is known upfront.
+fn validate_input_size(config: &QueryConfig, input: &QueryInput) -> Result<(), QueryInputError> {
+    validate_input_length(config, input)?;
+    match (input.length, executor::input_record_size(config)) {
+        (Some(length), Some(record_size)) if length != usize::from(config.size) * record_size => {
+            Err(QueryInputError::UnexpectedSize {
+                expected: usize::from(config.size),
+                record_size,
+                length,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counts the bytes of `input` as the query reads them, and fails the stream at its end if they
+/// don't add up to as many records as the query expects. Length of the input is rarely known
+/// upfront, so [`validate_input_size`] alone lets inputs of any size through.
+fn count_input_records(config: &QueryConfig, input: BodyStream) -> BodyStream {
+    let Some(record_size) = executor::input_record_size(config) else {
+        return input;
+    };
+    let expected = usize::from(config.size);
+
+    BodyStream::from_bytes_stream(stream::unfold(
+        Some((Box::pin(input), 0)),
+        move |state| async move {
+            let (mut input, length) = state?;
+            match input.next().await {
+                Some(Ok(bytes)) => {
+                    let length = length + bytes.len();
+                    Some((Ok(bytes), Some((input, length))))
+                }
+                Some(Err(e)) => Some((Err(e), Some((input, length)))),
+                None if length == expected * record_size => None,
+                None => {
+                    let error = QueryInputError::UnexpectedSize {
+                        expected,
+                        record_size,
+                        length,
+                    };
+                    Some((Err(error.into()), None))
+                }
+            }
+        },
+    ))
 }
 
+/// The number of output rows serialized into a single chunk by [`Processor::complete_stream`].
+const RESULT_CHUNK_ROWS: usize = 4096;
+
 impl Debug for Processor {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "QueryProcessor[{:?}]", self.queries)
@@ -115,9 +255,52 @@ impl Processor {
         Self {
             queries: RunningQueries::default(),
             key_registry: Arc::new(key_registry),
+            transports: Mutex::new(HashMap::default()),
+            roles: Mutex::new(HashMap::default()),
+            pending_inputs: Mutex::new(HashMap::default()),
+            next_query_id: AtomicU64::new(thread_rng().next_u64()),
+            field_types: FieldType::ALL.to_vec(),
+            security_models: vec![SecurityModel::SemiHonest, SecurityModel::Malicious],
+            max_query_size: None,
+            gateway_config: None,
         }
     }
 
+    /// Restricts queries this helper accepts to the given fields. By default, it accepts queries
+    /// in every field this build supports.
+    #[must_use]
+    pub fn with_field_types<I: IntoIterator<Item = FieldType>>(mut self, field_types: I) -> Self {
+        self.field_types = field_types.into_iter().collect();
+        self
+    }
+
+    /// Restricts queries this helper accepts to the given security models. By default, it accepts
+    /// queries under any of them.
+    #[must_use]
+    pub fn with_security_models<I: IntoIterator<Item = SecurityModel>>(
+        mut self,
+        security_models: I,
+    ) -> Self {
+        self.security_models = security_models.into_iter().collect();
+        self
+    }
+
+    /// Rejects queries larger than `max_size`. By default, query size is not limited beyond
+    /// [`QuerySize::MAX`].
+    #[must_use]
+    pub fn with_max_query_size(mut self, max_size: QuerySize) -> Self {
+        self.max_query_size = Some(max_size);
+        self
+    }
+
+    /// Makes every query this helper runs use the given gateway configuration, instead of the one
+    /// derived from the query config.
+    #[must_use]
+    pub fn with_gateway_config(mut self, config: GatewayConfig) -> Self {
+        self.gateway_config = Some(config);
+        self
+    }
+
     /// Upon receiving a new query request:
     /// * processor generates new query id
     /// * assigns roles to helpers in the ring. Helper that received new query request becomes `Role::H1` (aka coordinator).
@@ -128,20 +311,32 @@ impl Processor {
     /// * returns query configuration
     ///
     /// ## Errors
-    /// When other peers failed to acknowledge this query
+    /// When query configuration is invalid, this helper would refuse it as a follower, or other
+    /// peers failed to acknowledge this query
     #[allow(clippy::missing_panics_doc)]
-    pub async fn new_query(
+    #[tracing::instrument(level = "info", skip_all, fields(query_id = field::Empty, status = field::Empty))]
+    pub async fn new_query<T: Transport<Error = TransportError>>(
         &self,
-        transport: TransportImpl,
+        transport: T,
         req: QueryConfig,
     ) -> Result<PrepareQuery, NewQueryError> {
-        let query_id = QueryId;
+        let query_id = QueryId::from(self.next_query_id.fetch_add(1, Ordering::Relaxed));
+        Span::current().record("query_id", field::debug(query_id));
+        let _record = RecordStatus::new(query_id, &self.queries);
+
+        req.validate()?;
+        if !self.field_types.contains(&req.field_type) {
+            return Err(NewQueryError::UnsupportedField(req.field_type));
+        }
+        self.check_config(&req)
+            .map_err(|reason| NewQueryError::ConfigRejected { reason })?;
+
         let handle = self.queries.handle(query_id);
         handle.set_state(QueryState::Preparing(req))?;
         let guard = handle.remove_query_on_drop();
 
         let id = transport.identity();
-        let [right, left] = id.others();
+        let (right, left) = (id.next(), id.prev());
 
         let roles = RoleAssignment::try_from([(id, Role::H1), (right, Role::H2), (left, Role::H3)])
             .unwrap();
@@ -161,6 +356,7 @@ impl Processor {
         .map_err(NewQueryError::Transport)?;
 
         handle.set_state(QueryState::AwaitingInputs(query_id, req, roles))?;
+        self.roles.lock().unwrap().insert(query_id, Role::H1);
 
         guard.restore();
         Ok(prepare_request)
@@ -168,25 +364,42 @@ impl Processor {
 
     /// On prepare, each follower:
     /// * ensures that it is not the leader on this query
+    /// * ensures that it supports the field of this query
+    /// * ensures that it agrees with the rest of the query config
     /// * query is not registered yet
     /// * creates gateway and network
     /// * registers query
     ///
     /// ## Errors
-    /// if query is already running or this helper cannot be a follower in it
+    /// if query is already running, its results are still kept, or this helper cannot be a follower
+    /// in it
+    ///
+    /// ## Panics
+    /// If the roles mutex is poisoned.
+    #[tracing::instrument(level = "info", skip_all, fields(query_id = ?req.query_id, status = field::Empty))]
     pub fn prepare(
         &self,
         transport: &TransportImpl,
         req: PrepareQuery,
     ) -> Result<(), PrepareQueryError> {
+        let _record = RecordStatus::new(req.query_id, &self.queries);
         let my_role = req.roles.role(transport.identity());
 
         if my_role == Role::H1 {
             return Err(PrepareQueryError::WrongTarget);
         }
+        if !self.field_types.contains(&req.config.field_type) {
+            return Err(PrepareQueryError::UnsupportedField(req.config.field_type));
+        }
+        self.check_config(&req.config)
+            .map_err(|reason| PrepareQueryError::ConfigRejected { reason })?;
         let handle = self.queries.handle(req.query_id);
-        if handle.status().is_some() {
-            return Err(PrepareQueryError::AlreadyRunning);
+        match handle.status() {
+            None => {}
+            Some(QueryStatus::Completed | QueryStatus::Failed) => {
+                return Err(PrepareQueryError::AlreadyExists);
+            }
+            Some(_) => return Err(PrepareQueryError::AlreadyRunning),
         }
 
         handle.set_state(QueryState::AwaitingInputs(
@@ -194,6 +407,29 @@ impl Processor {
             req.config,
             req.roles,
         ))?;
+        self.roles.lock().unwrap().insert(req.query_id, my_role);
+
+        Ok(())
+    }
+
+    /// Checks `config` against the constraints this helper enforces. Coordinator checks the
+    /// config before it sends it out, but followers can't rely on that and check it again.
+    fn check_config(&self, config: &QueryConfig) -> Result<(), String> {
+        config.validate().map_err(|e| e.to_string())?;
+        if !self.security_models.contains(&config.security_model) {
+            return Err(format!(
+                "{:?} security is not allowed on this helper",
+                config.security_model
+            ));
+        }
+        if let Some(max_size) = self.max_query_size {
+            if config.size > max_size {
+                return Err(format!(
+                    "query size {} is larger than {max_size}, the limit on this helper",
+                    config.size
+                ));
+            }
+        }
 
         Ok(())
     }
@@ -201,37 +437,69 @@ impl Processor {
     /// Receive inputs for the specified query. That triggers query processing
     ///
     /// ## Errors
-    /// if query is not registered on this helper.
+    /// if query is not registered on this helper, or if the length of `input` is known upfront
+    /// and it does not hold as many records as the query expects. Inputs of unknown length are
+    /// counted as the query reads them, and the query fails if the count is off.
+    ///
+    /// Queries run on [`TransportImpl`] only, so inputs are rejected if `transport` can't be
+    /// converted into it.
     ///
     /// ## Panics
     /// If failed to obtain an exclusive access to the query collection.
-    pub fn receive_inputs(
+    #[tracing::instrument(level = "info", skip_all, fields(query_id = ?input.query_id, status = field::Empty))]
+    pub fn receive_inputs<T: Transport>(
         &self,
-        transport: TransportImpl,
+        transport: T,
         input: QueryInput,
     ) -> Result<(), QueryInputError> {
+        let _record = RecordStatus::new(input.query_id, &self.queries);
+        self.start(transport, input).map_err(|(e, _)| e)
+    }
+
+    /// Starts the query `input` belongs to, the way [`receive_inputs`] describes it. If the query
+    /// can't be started, `input` is returned along with the error, untouched.
+    ///
+    /// [`receive_inputs`]: Self::receive_inputs
+    fn start<T: Transport>(
+        &self,
+        transport: T,
+        input: QueryInput,
+    ) -> Result<(), (QueryInputError, QueryInput)> {
+        let transport = match transport.into_impl() {
+            Ok(transport) => transport,
+            Err(e) => return Err((e.into(), input)),
+        };
         let mut queries = self.queries.inner.lock().unwrap();
         match queries.entry(input.query_id) {
             Entry::Occupied(entry) => {
+                if let QueryState::AwaitingInputs(_, config, _) = entry.get() {
+                    if let Err(e) = validate_input_size(config, &input) {
+                        return Err((e, input));
+                    }
+                }
                 let state = entry.remove();
                 if let QueryState::AwaitingInputs(query_id, config, role_assignment) = state {
                     assert_eq!(
                         input.query_id, query_id,
                         "received inputs for a different query"
                     );
-                    let gateway = Gateway::new(
-                        query_id,
-                        GatewayConfig::from(&config),
-                        role_assignment,
-                        transport,
-                    );
+                    self.transports
+                        .lock()
+                        .unwrap()
+                        .insert(query_id, Transport::clone_ref(&transport));
+                    let gateway_config = self
+                        .gateway_config
+                        .unwrap_or_else(|| GatewayConfig::from(&config));
+                    let gateway =
+                        Gateway::new(query_id, gateway_config, role_assignment, transport);
+                    let input_stream = count_input_records(&config, input.input_stream);
                     queries.insert(
                         input.query_id,
                         QueryState::Running(executor::execute(
                             config,
                             Arc::clone(&self.key_registry),
                             gateway,
-                            input.input_stream,
+                            input_stream,
                         )),
                     );
                     Ok(())
@@ -241,17 +509,109 @@ impl Processor {
                         to: QueryStatus::Running,
                     };
                     queries.insert(input.query_id, state);
-                    Err(QueryInputError::StateError { source: error })
+                    Err((QueryInputError::StateError { source: error }, input))
                 }
             }
-            Entry::Vacant(_) => Err(QueryInputError::NoSuchQuery(input.query_id)),
+            Entry::Vacant(_) => Err((QueryInputError::NoSuchQuery(input.query_id), input)),
+        }
+    }
+
+    /// Adds `input` to the inputs of a query that is awaiting them, without starting it. Inputs
+    /// can be appended any number of times, until [`close_inputs`] hands all of them to the query
+    /// in the order they were appended.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper or is not awaiting inputs, or if the length of
+    /// `input` is known upfront and it is not a whole number of records.
+    ///
+    /// ## Panics
+    /// If failed to obtain an exclusive access to the query collection.
+    ///
+    /// [`close_inputs`]: Self::close_inputs
+    #[tracing::instrument(level = "info", skip_all, fields(query_id = ?input.query_id, status = field::Empty))]
+    pub fn append_inputs(&self, input: QueryInput) -> Result<(), QueryInputError> {
+        let _record = RecordStatus::new(input.query_id, &self.queries);
+        let queries = self.queries.inner.lock().unwrap();
+        match queries.get(&input.query_id) {
+            Some(QueryState::AwaitingInputs(_, config, _)) => {
+                validate_input_length(config, &input)?;
+                self.pending_inputs
+                    .lock()
+                    .unwrap()
+                    .entry(input.query_id)
+                    .or_default()
+                    .push(input);
+                Ok(())
+            }
+            Some(state) => Err(QueryInputError::StateError {
+                source: StateError::InvalidState {
+                    from: QueryStatus::from(state),
+                    to: QueryStatus::AwaitingInputs,
+                },
+            }),
+            None => Err(QueryInputError::NoSuchQuery(input.query_id)),
         }
     }
 
+    /// Starts the query with all the inputs appended to it so far, the same way
+    /// [`receive_inputs`] does with a single input. If nothing was appended, the query runs on
+    /// an empty input. If the query can't be started, inputs are kept for the next attempt.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper or is not awaiting inputs, or for any other
+    /// reason [`receive_inputs`] rejects inputs for.
+    ///
+    /// ## Panics
+    /// If failed to obtain an exclusive access to the pending inputs.
+    ///
+    /// [`receive_inputs`]: Self::receive_inputs
+    #[tracing::instrument(level = "info", skip_all, fields(query_id = ?query_id, status = field::Empty))]
+    pub fn close_inputs<T: Transport>(
+        &self,
+        transport: T,
+        query_id: QueryId,
+    ) -> Result<(), QueryInputError> {
+        let inputs = self
+            .pending_inputs
+            .lock()
+            .unwrap()
+            .remove(&query_id)
+            .unwrap_or_default();
+        let length = inputs
+            .iter()
+            .map(|input| input.length)
+            .sum::<Option<usize>>();
+        let input_stream = BodyStream::from_bytes_stream(
+            stream::iter(inputs.into_iter().map(|input| input.input_stream)).flatten(),
+        );
+
+        let input = QueryInput {
+            query_id,
+            input_stream,
+            length,
+        };
+
+        let _record = RecordStatus::new(query_id, &self.queries);
+        self.start(transport, input).map_err(|(e, input)| {
+            // The query is gone along with its inputs, there is nothing to keep them for.
+            if !matches!(e, QueryInputError::NoSuchQuery(_)) {
+                // Inputs appended since then go after these.
+                self.pending_inputs
+                    .lock()
+                    .unwrap()
+                    .entry(query_id)
+                    .or_default()
+                    .insert(0, input);
+            }
+            e
+        })
+    }
+
     /// Returns the query status.
     ///
     /// ## Errors
-    /// If query is not registered on this helper.
+    /// If query is not registered on this helper. Queries whose task was dropped before it
+    /// completed are removed once this method notices it, so they are not registered either.
     ///
     /// ## Panics
     /// If the query collection mutex is poisoned.
@@ -262,8 +622,20 @@ impl Processor {
         };
 
         if let QueryState::Running(ref mut running) = state {
-            if let Some(result) = running.try_complete() {
-                state = QueryState::Completed(result);
+            match running.try_complete() {
+                Ok(Some(Ok(result))) => state = QueryState::Completed(result),
+                Ok(Some(Err(e))) => {
+                    tracing::warn!(?query_id, "query failed: {e}");
+                    state = QueryState::Failed(e);
+                }
+                Ok(None) => {}
+                // Nothing is ever going to complete this query, so forget about it.
+                Err(QueryCancelled) => {
+                    drop(queries);
+                    tracing::warn!(?query_id, "query task was dropped before it completed");
+                    self.release(query_id);
+                    return Err(QueryStatusError::NoSuchQuery(query_id));
+                }
             }
         }
 
@@ -272,7 +644,12 @@ impl Processor {
         Ok(status)
     }
 
-    /// Awaits the query completion
+    /// Awaits the query completion and returns its result as it is, so in-process callers can
+    /// use it without parsing it back from bytes, see [`downcast_result`]. Callers that hand the
+    /// result over as bytes use [`complete_stream`] instead.
+    ///
+    /// [`downcast_result`]: crate::query::downcast_result
+    /// [`complete_stream`]: Self::complete_stream
     ///
     /// ## Errors
     /// if query is not registered on this helper.
@@ -283,11 +660,40 @@ impl Processor {
         &self,
         query_id: QueryId,
     ) -> Result<Box<dyn ProtocolResult>, QueryCompletionError> {
+        Ok(self.complete_with_stats(query_id).await?.0)
+    }
+
+    /// Awaits the query completion and returns its result along with the statistics about
+    /// records data this helper sent to and received from its peers while executing it.
+    ///
+    /// Results of the query that completed successfully are kept, so asking for them again
+    /// returns the same result. Statistics are reported only the first time.
+    ///
+    /// ## Errors
+    /// if query is not registered on this helper.
+    ///
+    /// ## Panics
+    /// If failed to obtain an exclusive access to the query collection.
+    #[tracing::instrument(level = "info", "complete", skip_all, fields(query_id = ?query_id, status = field::Empty))]
+    pub async fn complete_with_stats(
+        &self,
+        query_id: QueryId,
+    ) -> Result<(Box<dyn ProtocolResult>, StatsSnapshot), QueryCompletionError> {
+        let _record = RecordStatus::new(query_id, &self.queries);
         let handle = {
             let mut queries = self.queries.inner.lock().unwrap();
 
             match queries.remove(&query_id) {
-                Some(QueryState::Completed(result)) => return result.map_err(Into::into),
+                Some(QueryState::Completed(result)) => {
+                    let stats = self.release(query_id);
+                    let copy = result.clone_boxed();
+                    queries.insert(query_id, QueryState::Completed(result));
+                    return Ok((copy, stats));
+                }
+                Some(QueryState::Failed(error)) => {
+                    self.release(query_id);
+                    return Err(QueryCompletionError::execution(query_id, error));
+                }
                 Some(QueryState::Running(handle)) => {
                     queries.insert(query_id, QueryState::AwaitingCompletion);
                     CompletionHandle::new(RemoveQuery::new(query_id, &self.queries), handle)
@@ -306,25 +712,182 @@ impl Processor {
             }
         }; // release mutex before await
 
-        Ok(handle.await?)
+        let result = handle.await;
+        let stats = self.release(query_id);
+        let result = result
+            .map_err(|QueryCancelled| QueryCompletionError::Cancelled(query_id))?
+            .map_err(|e| QueryCompletionError::execution(query_id, e))?;
+        // Handle removed the query once it completed. Unless another query with the same id has
+        // taken its place since then, keep the result for those who ask for it again.
+        self.queries
+            .inner
+            .lock()
+            .unwrap()
+            .entry(query_id)
+            .or_insert_with(|| QueryState::Completed(result.clone_boxed()));
+
+        Ok((result, stats))
+    }
+
+    /// Discards the results of the query that completed, once whoever collects them no longer
+    /// needs them. Until then, results are kept and [`complete`] returns them every time it is
+    /// called.
+    ///
+    /// [`complete`]: Self::complete
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper or it has not completed yet.
+    ///
+    /// ## Panics
+    /// If the query collection mutex is poisoned.
+    pub fn discard(&self, query_id: QueryId) -> Result<(), QueryStatusError> {
+        let mut queries = self.queries.inner.lock().unwrap();
+        match queries.remove(&query_id) {
+            Some(QueryState::Completed(_) | QueryState::Failed(_)) => {
+                drop(queries);
+                self.release(query_id);
+                Ok(())
+            }
+            Some(state) => {
+                let status = QueryStatus::from(&state);
+                queries.insert(query_id, state);
+                Err(QueryStatusError::NotCompleted { query_id, status })
+            }
+            None => Err(QueryStatusError::NoSuchQuery(query_id)),
+        }
+    }
+
+    /// Returns the role this helper plays in the given query, as long as the query is known to
+    /// this helper, i.e. it was created or prepared here and its results haven't been collected
+    /// yet.
+    ///
+    /// ## Panics
+    /// If the roles mutex is poisoned.
+    #[must_use]
+    pub fn role(&self, query_id: QueryId) -> Option<Role> {
+        self.roles.lock().unwrap().get(&query_id).copied()
+    }
+
+    /// Returns the id the next query this helper coordinates is going to get.
+    #[cfg(all(test, unit_test))]
+    fn next_query_id(&self) -> QueryId {
+        QueryId::from(self.next_query_id.load(Ordering::Relaxed))
+    }
+
+    /// Forgets everything this helper kept for the query that reached a terminal state and makes
+    /// the transport do the same. Returns the statistics the transport collected for this query.
+    fn release(&self, query_id: QueryId) -> StatsSnapshot {
+        self.roles.lock().unwrap().remove(&query_id);
+        self.pending_inputs.lock().unwrap().remove(&query_id);
+        self.transports
+            .lock()
+            .unwrap()
+            .remove(&query_id)
+            .map(|transport| {
+                let stats = transport.stats().take(query_id, false);
+                transport.cleanup(query_id);
+                stats
+            })
+            .unwrap_or_default()
+    }
+
+    /// Shuts this helper down. Queries that are still running are aborted and then the
+    /// transport is shut down, so peers observe the end of every stream this helper was sending
+    /// to them, instead of waiting for more data forever.
+    ///
+    /// ## Panics
+    /// If failed to obtain an exclusive access to the query collection.
+    pub async fn shutdown(&self, transport: &TransportImpl) {
+        let queries = std::mem::take(&mut *self.queries.inner.lock().unwrap());
+        for (query_id, state) in queries {
+            if let QueryState::Running(running) = state {
+                tracing::info!(?query_id, "aborting query on shutdown");
+                running.join_handle.abort();
+            }
+        }
+        self.transports.lock().unwrap().clear();
+        self.roles.lock().unwrap().clear();
+
+        transport.shutdown().await;
+    }
+
+    /// Awaits the query completion and returns its output serialized in chunks, instead of
+    /// materializing the entire result in a single buffer. Rows are serialized into a chunk only
+    /// when the stream is polled for it. Concatenating all the chunks yields the same bytes as
+    /// [`ProtocolResult::into_bytes`] would for the result of [`complete`].
+    ///
+    /// [`complete`]: Self::complete
+    ///
+    /// ## Errors
+    /// if query is not registered on this helper.
+    ///
+    /// ## Panics
+    /// If failed to obtain an exclusive access to the query collection.
+    pub async fn complete_stream(
+        &self,
+        query_id: QueryId,
+    ) -> Result<impl Stream<Item = Vec<u8>>, QueryCompletionError> {
+        let (result, _) = self.complete_with_stats(query_id).await?;
+
+        Ok(stream::iter(result.into_byte_chunks(
+            NonZeroUsize::new(RESULT_CHUNK_ROWS).unwrap(),
+        )))
+    }
+}
+
+/// Records the status of the query on the span of the processor method that created it, once that
+/// method returns. Nothing is recorded if the query is gone by then.
+struct RecordStatus<'a> {
+    query_id: QueryId,
+    queries: &'a RunningQueries,
+    span: Span,
+}
+
+impl<'a> RecordStatus<'a> {
+    fn new(query_id: QueryId, queries: &'a RunningQueries) -> Self {
+        Self {
+            query_id,
+            queries,
+            span: Span::current(),
+        }
+    }
+}
+
+impl Drop for RecordStatus<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            return;
+        }
+        if let Some(status) = self.queries.handle(self.query_id).status() {
+            self.span.record("status", field::debug(status));
+        }
     }
 }
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use std::{array, future::Future, sync::Arc};
+    use std::{array, future::Future, io, time::Duration};
 
+    use async_trait::async_trait;
+    use bytes::Bytes;
     use futures::pin_mut;
-    use futures_util::future::poll_immediate;
-    use tokio::sync::Barrier;
+    use futures_util::future::{poll_immediate, try_join_all};
 
     use super::*;
     use crate::{
-        ff::FieldType,
+        error::BoxError,
+        ff::{Field, FieldType, Fp31, Fp32BitPrime},
         helpers::{
-            query::{QueryType, QueryType::TestMultiply},
-            HelperIdentity, InMemoryNetwork, PrepareQueryCallback, TransportCallbacks,
+            query::{IpaQueryConfig, QueryType, QueryType::TestMultiply, SecurityModel},
+            BodyStream, HelperIdentity, InMemoryNetwork, InMemoryTransport, NoResourceIdentifier,
+            PrepareQueryCallback, QueryIdBinding, RouteId, RouteParams, StepBinding,
+            TransportCallbacks, TransportStats,
         },
+        protocol::{step::Gate, RecordId},
+        query::downcast_result,
+        rand::Rng,
+        secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, IntoShares},
+        test_fixture::{Delay, DelayedTransport, FailingTransport, Reconstruct, SpanCapture},
     };
 
     fn prepare_query_callback<T, F, Fut>(cb: F) -> Box<dyn PrepareQueryCallback<T>>
@@ -339,52 +902,46 @@ mod tests {
         QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap()
     }
 
+    fn accept_prepare_callbacks() -> [TransportCallbacks<InMemoryTransport>; 3] {
+        array::from_fn(|_| TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
+            ..Default::default()
+        })
+    }
+
     #[tokio::test]
     async fn new_query() {
-        let barrier = Arc::new(Barrier::new(3));
-        let cb2_barrier = Arc::clone(&barrier);
-        let cb3_barrier = Arc::clone(&barrier);
-        let cb2 = TransportCallbacks {
-            prepare_query: prepare_query_callback(move |_, _| {
-                let barrier = Arc::clone(&cb2_barrier);
-                async move {
-                    barrier.wait().await;
-                    Ok(())
-                }
-            }),
-            ..Default::default()
-        };
-        let cb3 = TransportCallbacks {
-            prepare_query: prepare_query_callback(move |_, _| {
-                let barrier = Arc::clone(&cb3_barrier);
-                async move {
-                    barrier.wait().await;
-                    Ok(())
-                }
-            }),
-            ..Default::default()
-        };
-        let network = InMemoryNetwork::new([TransportCallbacks::default(), cb2, cb3]);
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
         let [t0, _, _] = network.transports();
+        let t0 = DelayedTransport::builder(t0)
+            .delay(RouteId::PrepareQuery, HelperIdentity::TWO, Delay::Tokens(1))
+            .delay(
+                RouteId::PrepareQuery,
+                HelperIdentity::THREE,
+                Delay::Tokens(1),
+            )
+            .build();
         let p0 = Processor::default();
         let request = test_multiply_config();
+        let query_id = p0.next_query_id();
 
-        let qc_future = p0.new_query(t0, request);
+        let qc_future = p0.new_query(Transport::clone_ref(&t0), request);
         pin_mut!(qc_future);
 
         // poll future once to trigger query status change
         let _qc = poll_immediate(&mut qc_future).await;
 
-        assert_eq!(QueryStatus::Preparing, p0.query_status(QueryId).unwrap());
+        assert_eq!(QueryStatus::Preparing, p0.query_status(query_id).unwrap());
         // unblock sends
-        barrier.wait().await;
+        t0.release(RouteId::PrepareQuery, HelperIdentity::TWO);
+        t0.release(RouteId::PrepareQuery, HelperIdentity::THREE);
 
         let qc = qc_future.await.unwrap();
         let expected_assignment = RoleAssignment::new(HelperIdentity::make_three());
 
         assert_eq!(
             PrepareQuery {
-                query_id: QueryId,
+                query_id,
                 config: request,
                 roles: expected_assignment,
             },
@@ -392,60 +949,340 @@ mod tests {
         );
         assert_eq!(
             QueryStatus::AwaitingInputs,
-            p0.query_status(QueryId).unwrap()
+            p0.query_status(query_id).unwrap()
         );
     }
 
     #[tokio::test]
-    async fn rejects_duplicate_query_id() {
-        let cb = array::from_fn(|_| TransportCallbacks {
-            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
-            ..Default::default()
-        });
-        let network = InMemoryNetwork::new(cb);
+    async fn new_query_span() {
+        let capture = SpanCapture::default();
+        let _guard = capture.install();
+
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default();
+        let prepare = p0.new_query(t0, test_multiply_config()).await.unwrap();
+
+        let spans = capture.find("new_query");
+        assert_eq!(1, spans.len());
+        assert_eq!(format!("{:?}", prepare.query_id), spans[0]["query_id"]);
+        assert_eq!(
+            format!("{:?}", QueryStatus::AwaitingInputs),
+            spans[0]["status"]
+        );
+    }
+
+    #[tokio::test]
+    async fn new_query_waits_for_every_prepare() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let [t0, _, _] = network.transports();
+        let t0 = DelayedTransport::builder(t0)
+            .delay(
+                RouteId::PrepareQuery,
+                HelperIdentity::THREE,
+                Delay::Tokens(1),
+            )
+            .build();
+        let p0 = Processor::default();
+        let query_id = p0.next_query_id();
+
+        let qc_future = p0.new_query(Transport::clone_ref(&t0), test_multiply_config());
+        pin_mut!(qc_future);
+
+        // prepare to H2 is free to complete, but the one to H3 is held.
+        for _ in 0..10 {
+            assert!(poll_immediate(&mut qc_future).await.is_none());
+            assert_eq!(QueryStatus::Preparing, p0.query_status(query_id).unwrap());
+            tokio::task::yield_now().await;
+        }
+
+        t0.release(RouteId::PrepareQuery, HelperIdentity::THREE);
+        qc_future.await.unwrap();
+        assert_eq!(
+            QueryStatus::AwaitingInputs,
+            p0.query_status(query_id).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_assigned_roles() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let [t0, t1, t2] = network.transports();
+        let [p0, p1, p2] = array::from_fn(|_| Processor::default());
+
+        assert_eq!(None, p0.role(p0.next_query_id()));
+
+        let prepare = p0
+            .new_query(Transport::clone_ref(&t0), test_multiply_config())
+            .await
+            .unwrap();
+        let query_id = prepare.query_id;
+        p1.prepare(&t1, prepare.clone()).unwrap();
+        p2.prepare(&t2, prepare).unwrap();
+
+        assert_eq!(Some(Role::H1), p0.role(query_id));
+        assert_eq!(Some(Role::H2), p1.role(query_id));
+        assert_eq!(Some(Role::H3), p2.role(query_id));
+    }
+
+    #[tokio::test]
+    async fn processor_callbacks_create_query() {
+        let processors: [Arc<Processor>; 3] = array::from_fn(|_| Arc::new(Processor::default()));
+        let network = InMemoryNetwork::new(array::from_fn(|i| {
+            TransportCallbacks::from_processor(Arc::clone(&processors[i]))
+        }));
+        let [_, t1, _] = network.transports();
+        // H2 asks H1 to coordinate the query.
+        let query_id = processors[0].next_query_id();
+
+        t1.send(
+            HelperIdentity::ONE,
+            &test_multiply_config(),
+            stream::empty(),
+        )
+        .await
+        .unwrap();
+
+        for (processor, role) in processors.iter().zip(Role::all()) {
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(query_id).unwrap()
+            );
+            assert_eq!(Some(*role), processor.role(query_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn assigns_unique_query_ids() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
         let [t0, _, _] = network.transports();
         let p0 = Processor::default();
         let request = test_multiply_config();
 
-        let _qc = p0
+        let first = p0
             .new_query(Transport::clone_ref(&t0), request)
             .await
             .unwrap();
-        assert!(matches!(
-            p0.new_query(t0, request).await,
-            Err(NewQueryError::State(StateError::AlreadyRunning)),
-        ));
+        let second = p0.new_query(t0, request).await.unwrap();
+
+        assert_ne!(first.query_id, second.query_id);
+        for query_id in [first.query_id, second.query_id] {
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                p0.query_status(query_id).unwrap()
+            );
+            assert_eq!(Some(Role::H1), p0.role(query_id));
+        }
     }
 
     #[tokio::test]
-    async fn prepare_error() {
-        let cb2 = TransportCallbacks {
-            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
-            ..Default::default()
-        };
-        let cb3 = TransportCallbacks {
-            prepare_query: prepare_query_callback(|_, _| async {
-                Err(PrepareQueryError::WrongTarget)
-            }),
-            ..Default::default()
-        };
-        let network = InMemoryNetwork::new([TransportCallbacks::default(), cb2, cb3]);
+    async fn rejects_invalid_config() {
+        let network = InMemoryNetwork::default();
         let [t0, _, _] = network.transports();
         let p0 = Processor::default();
-        let request = test_multiply_config();
+        let request = QueryConfig::new(
+            QueryType::SemiHonestIpa(IpaQueryConfig {
+                max_breakdown_key: 0,
+                ..Default::default()
+            }),
+            FieldType::Fp31,
+            1,
+        )
+        .unwrap();
+        let query_id = p0.next_query_id();
 
         assert!(matches!(
             p0.new_query(t0, request).await.unwrap_err(),
-            NewQueryError::Transport(_)
+            NewQueryError::InvalidConfig(QueryConfigError::ZeroIpaParameter("max_breakdown_key"))
+        ));
+        assert!(matches!(
+            p0.query_status(query_id).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
         ));
     }
 
     #[tokio::test]
-    async fn can_recover_from_prepare_error() {
-        let cb2 = TransportCallbacks {
-            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
-            ..Default::default()
-        };
+    async fn rejects_security_model_mismatch() {
+        let network = InMemoryNetwork::default();
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default();
+        let request = QueryConfig::new(
+            QueryType::MaliciousIpa(IpaQueryConfig::default()),
+            FieldType::Fp31,
+            1,
+        )
+        .unwrap()
+        .with_security_model(SecurityModel::SemiHonest);
+
+        assert!(matches!(
+            p0.new_query(t0, request).await.unwrap_err(),
+            NewQueryError::InvalidConfig(QueryConfigError::SecurityModelMismatch {
+                expected: SecurityModel::Malicious,
+                requested: SecurityModel::SemiHonest,
+                ..
+            })
+        ));
+    }
+
+    /// Coordinator refuses queries that it would refuse as a follower, before it asks followers
+    /// to prepare them.
+    #[tokio::test]
+    async fn rejects_disallowed_config() {
+        let network = InMemoryNetwork::default();
+        let [t0, _, _] = network.transports();
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 11).unwrap();
+
+        let p0 = Processor::default().with_field_types([FieldType::Fp32BitPrime]);
+        assert!(matches!(
+            p0.new_query(Transport::clone_ref(&t0), config)
+                .await
+                .unwrap_err(),
+            NewQueryError::UnsupportedField(FieldType::Fp31)
+        ));
+
+        let p0 = Processor::default().with_security_models([SecurityModel::SemiHonest]);
+        assert!(matches!(
+            p0.new_query(
+                Transport::clone_ref(&t0),
+                config.with_security_model(SecurityModel::Malicious)
+            )
+            .await
+            .unwrap_err(),
+            NewQueryError::ConfigRejected { reason } if reason.contains("Malicious")
+        ));
+
+        let p0 = Processor::default().with_max_query_size(QuerySize::try_from(10_u32).unwrap());
+        let query_id = p0.next_query_id();
+        assert!(matches!(
+            p0.new_query(t0, config).await.unwrap_err(),
+            NewQueryError::ConfigRejected { .. }
+        ));
+        assert!(matches!(
+            p0.query_status(query_id).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_transport() {
+        /// Transport that forwards everything to [`TransportImpl`], but can't be converted to it.
+        #[derive(Clone)]
+        struct OpaqueTransport(TransportImpl);
+
+        #[async_trait]
+        impl Transport for OpaqueTransport {
+            type RecordsStream = <TransportImpl as Transport>::RecordsStream;
+            type Error = TransportError;
+
+            fn identity(&self) -> HelperIdentity {
+                self.0.identity()
+            }
+
+            async fn send<D, Q, S, R>(
+                &self,
+                dest: HelperIdentity,
+                route: R,
+                data: D,
+            ) -> Result<(), Self::Error>
+            where
+                Option<QueryId>: From<Q>,
+                Option<Gate>: From<S>,
+                Q: QueryIdBinding,
+                S: StepBinding,
+                R: RouteParams<RouteId, Q, S>,
+                D: Stream<Item = Vec<u8>> + Send + 'static,
+            {
+                self.0.send(dest, route, data).await
+            }
+
+            fn receive<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+                &self,
+                from: HelperIdentity,
+                route: R,
+            ) -> Self::RecordsStream {
+                self.0.receive(from, route)
+            }
+
+            fn receive_from<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+                &self,
+                from: HelperIdentity,
+                route: R,
+                start: RecordId,
+                record_size: usize,
+            ) -> Self::RecordsStream {
+                self.0.receive_from(from, route, start, record_size)
+            }
+
+            fn receive_query_records(
+                &self,
+                from: HelperIdentity,
+                query_id: QueryId,
+            ) -> Self::RecordsStream {
+                self.0.receive_query_records(from, query_id)
+            }
+
+            fn stats(&self) -> TransportStats {
+                self.0.stats()
+            }
+        }
+
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+
+        assert!(Transport::clone_ref(&transport).into_impl().is_ok());
+        // Fixtures that wrap `TransportImpl` can't be stripped of their behavior either.
+        assert!(matches!(
+            DelayedTransport::builder(Transport::clone_ref(&transport))
+                .build()
+                .into_impl(),
+            Err(UnsupportedTransport { .. })
+        ));
+
+        let err = processor
+            .receive_inputs(
+                OpaqueTransport(transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap_err();
+        assert!(matches!(err, QueryInputError::UnsupportedTransport(_)));
+        assert!(err.to_string().contains("OpaqueTransport"), "{err}");
+        assert_eq!(
+            QueryStatus::AwaitingInputs,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn prepare_error() {
+        let cb2 = TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
+            ..Default::default()
+        };
+        let cb3 = TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async {
+                Err(PrepareQueryError::WrongTarget)
+            }),
+            ..Default::default()
+        };
+        let network = InMemoryNetwork::new([TransportCallbacks::default(), cb2, cb3]);
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default();
+        let request = test_multiply_config();
+
+        assert!(matches!(
+            p0.new_query(t0, request).await.unwrap_err(),
+            NewQueryError::Transport(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn can_recover_from_prepare_error() {
+        let cb2 = TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
+            ..Default::default()
+        };
         let cb3 = TransportCallbacks {
             prepare_query: prepare_query_callback(|_, _| async {
                 Err(PrepareQueryError::WrongTarget)
@@ -464,12 +1301,682 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn prepare_rejected() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let [t0, _, _] = network.transports();
+        let t0 = FailingTransport::new(t0, |route, _, _| {
+            (route == RouteId::PrepareQuery).then(|| io::Error::from(io::ErrorKind::BrokenPipe))
+        });
+        let p0 = Processor::default();
+        let query_id = p0.next_query_id();
+
+        assert!(matches!(
+            p0.new_query(t0, test_multiply_config()).await.unwrap_err(),
+            NewQueryError::Transport(_)
+        ));
+        assert!(matches!(
+            p0.query_status(query_id).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rolls_back_if_one_prepare_fails() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let [t0, _, _] = network.transports();
+        // only the second send fails, so one of the followers accepts the query.
+        let t0 = FailingTransport::new(t0, |_, _, attempt| {
+            (attempt == 1).then(|| io::Error::from(io::ErrorKind::ConnectionReset))
+        });
+        let p0 = Processor::default();
+        let request = test_multiply_config();
+        let failed_id = p0.next_query_id();
+
+        assert!(matches!(
+            p0.new_query(Transport::clone_ref(&t0), request)
+                .await
+                .unwrap_err(),
+            NewQueryError::Transport(_)
+        ));
+        assert!(matches!(
+            p0.query_status(failed_id).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
+        ));
+
+        let prepare = p0.new_query(t0, request).await.unwrap();
+        assert_ne!(failed_id, prepare.query_id);
+        assert_eq!(
+            QueryStatus::AwaitingInputs,
+            p0.query_status(prepare.query_id).unwrap()
+        );
+    }
+
+    fn prepare_test_multiply(processor: &Processor, transport: &TransportImpl) {
+        prepare_with_config(processor, transport, test_multiply_config());
+    }
+
+    fn prepare_with_config(processor: &Processor, transport: &TransportImpl, config: QueryConfig) {
+        processor
+            .prepare(
+                transport,
+                PrepareQuery {
+                    query_id: QueryId::default(),
+                    config,
+                    roles: RoleAssignment::new(HelperIdentity::make_three()),
+                },
+            )
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn accepts_well_formed_input() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+
+        processor
+            .receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+        assert_eq!(
+            QueryStatus::Running,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_input() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+
+        // a record of two Fp31 shares takes 4 bytes
+        let input = QueryInput {
+            query_id: QueryId::default(),
+            input_stream: BodyStream::from(vec![0_u8; 3]),
+            length: Some(3),
+        };
+        assert!(matches!(
+            processor.receive_inputs(Transport::clone_ref(&transport), input),
+            Err(QueryInputError::MalformedInput {
+                length: 3,
+                record_size: 4
+            })
+        ));
+        assert_eq!(
+            QueryStatus::AwaitingInputs,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_input_of_unexpected_size() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+
+        // the query expects a single record
+        assert!(matches!(
+            processor.receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 4]),
+            ),
+            Err(QueryInputError::UnexpectedSize {
+                expected: 1,
+                record_size: 4,
+                length: 8
+            })
+        ));
+        assert_eq!(
+            QueryStatus::AwaitingInputs,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+    }
+
+    /// Length of the input is not known upfront, so its records are counted as they are read.
+    #[tokio::test]
+    async fn counts_input_records() {
+        async fn read(records: usize) -> Vec<Result<Bytes, BoxError>> {
+            let input = BodyStream::from_bytes_stream(stream::iter(
+                std::iter::repeat_with(|| Ok(Bytes::from(vec![0_u8; 4]))).take(records),
+            ));
+            count_input_records(&test_multiply_config(), input)
+                .collect()
+                .await
+        }
+
+        let input = read(1).await;
+        assert_eq!(1, input.len());
+        assert!(input[0].is_ok());
+
+        for records in [0, 2] {
+            let input = read(records).await;
+            let error = input.last().unwrap().as_ref().unwrap_err();
+            assert!(
+                matches!(
+                    error.downcast_ref::<QueryInputError>(),
+                    Some(QueryInputError::UnexpectedSize { expected: 1, record_size: 4, length })
+                        if *length == records * 4
+                ),
+                "{error:?}"
+            );
+        }
+    }
+
+    /// Runs the query on processors directly, to look at what they return before it is turned
+    /// into bytes.
+    #[tokio::test]
+    async fn downcast_test_multiply_result() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let [t0, t1, t2] = network.transports();
+        let processors: [_; 3] = array::from_fn(|_| Processor::default());
+        let prepare = processors[0]
+            .new_query(Transport::clone_ref(&t0), test_multiply_config())
+            .await
+            .unwrap();
+        let query_id = prepare.query_id;
+        processors[1].prepare(&t1, prepare.clone()).unwrap();
+        processors[2].prepare(&t2, prepare).unwrap();
+
+        let (a, b) = (Fp31::truncate_from(4_u128), Fp31::truncate_from(5_u128));
+        let shares = [a, b].into_iter().share();
+        for ((processor, transport), shares) in processors.iter().zip([t0, t1, t2]).zip(&shares) {
+            processor
+                .receive_inputs(transport, QueryInput::from_records(query_id, shares))
+                .unwrap();
+        }
+        let results = try_join_all(processors.iter().map(|p| p.complete(query_id)))
+            .await
+            .unwrap();
+
+        let products = results
+            .iter()
+            .map(|result| {
+                downcast_result::<Vec<Replicated<Fp31>>>(result.as_ref())
+                    .unwrap()
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![a * b],
+            <[_; 3]>::try_from(products).unwrap().reconstruct()
+        );
+        assert!(downcast_result::<Vec<Replicated<Fp32BitPrime>>>(results[0].as_ref()).is_none());
+    }
+
+    #[tokio::test]
+    async fn inputs_in_chunks() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let transports = network.transports();
+        let processors: [_; 3] = array::from_fn(|_| Processor::default());
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap();
+        let prepare = processors[0]
+            .new_query(Transport::clone_ref(&transports[0]), config)
+            .await
+            .unwrap();
+        let query_id = prepare.query_id;
+        for (processor, transport) in processors.iter().zip(&transports).skip(1) {
+            processor.prepare(transport, prepare.clone()).unwrap();
+        }
+
+        let mut rng = thread_rng();
+        let values = (0..4).map(|_| rng.gen::<Fp31>()).collect::<Vec<_>>();
+        let shares = values.clone().into_iter().share();
+        for (processor, shares) in processors.iter().zip(&shares) {
+            // every chunk carries whole (a, b) pairs
+            for chunk in shares.chunks(2) {
+                processor
+                    .append_inputs(QueryInput::from_records(query_id, chunk))
+                    .unwrap();
+            }
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(query_id).unwrap()
+            );
+        }
+        for (processor, transport) in processors.iter().zip(&transports) {
+            processor
+                .close_inputs(Transport::clone_ref(transport), query_id)
+                .unwrap();
+        }
+
+        let results = try_join_all(processors.iter().map(|p| p.complete(query_id)))
+            .await
+            .unwrap();
+        let products = results
+            .iter()
+            .map(|result| {
+                downcast_result::<Vec<Replicated<Fp31>>>(result.as_ref())
+                    .unwrap()
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![values[0] * values[1], values[2] * values[3]],
+            <[_; 3]>::try_from(products).unwrap().reconstruct()
+        );
+    }
+
+    #[tokio::test]
+    async fn discard_results() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let transports = network.transports();
+        let processors: [_; 3] = array::from_fn(|_| Processor::default());
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap();
+        let prepare = processors[0]
+            .new_query(Transport::clone_ref(&transports[0]), config)
+            .await
+            .unwrap();
+        let query_id = prepare.query_id;
+        for (processor, transport) in processors.iter().zip(&transports).skip(1) {
+            processor.prepare(transport, prepare.clone()).unwrap();
+        }
+        assert!(matches!(
+            processors[0].discard(query_id),
+            Err(QueryStatusError::NotCompleted {
+                status: QueryStatus::AwaitingInputs,
+                ..
+            })
+        ));
+
+        let shares = [Fp31::truncate_from(4u128), Fp31::truncate_from(5u128)]
+            .into_iter()
+            .share();
+        for ((processor, transport), shares) in processors.iter().zip(&transports).zip(&shares) {
+            processor
+                .receive_inputs(
+                    Transport::clone_ref(transport),
+                    QueryInput::from_records(query_id, shares),
+                )
+                .unwrap();
+        }
+        for processor in &processors {
+            processor.complete(query_id).await.unwrap();
+            processor.complete(query_id).await.unwrap();
+            assert_eq!(1, processor.queries.count());
+        }
+        // results are not dropped by another query with the same id
+        for (processor, transport) in processors.iter().zip(&transports).skip(1) {
+            assert!(matches!(
+                processor.prepare(transport, prepare.clone()),
+                Err(PrepareQueryError::AlreadyExists)
+            ));
+            processor.complete(query_id).await.unwrap();
+        }
+
+        for processor in &processors {
+            processor.discard(query_id).unwrap();
+            assert_eq!(0, processor.queries.count());
+            assert!(matches!(
+                processor.complete(query_id).await,
+                Err(QueryCompletionError::NoSuchQuery(_))
+            ));
+            assert!(matches!(
+                processor.discard(query_id),
+                Err(QueryStatusError::NoSuchQuery(_))
+            ));
+        }
+        for (processor, transport) in processors.iter().zip(&transports).skip(1) {
+            processor.prepare(transport, prepare.clone()).unwrap();
+        }
+    }
+
+    /// Inputs appended to a query are still there if closing them fails.
+    #[tokio::test]
+    async fn close_inputs_failure_keeps_inputs() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let transports = network.transports();
+        let processors: [_; 3] = array::from_fn(|_| Processor::default());
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap();
+        let prepare = processors[0]
+            .new_query(Transport::clone_ref(&transports[0]), config)
+            .await
+            .unwrap();
+        let query_id = prepare.query_id;
+        for (processor, transport) in processors.iter().zip(&transports).skip(1) {
+            processor.prepare(transport, prepare.clone()).unwrap();
+        }
+
+        let (a, b) = (Fp31::truncate_from(4u128), Fp31::truncate_from(5u128));
+        let shares = [a, b].into_iter().share();
+        for ((processor, transport), shares) in processors.iter().zip(&transports).zip(&shares) {
+            processor
+                .append_inputs(QueryInput::from_records(query_id, shares))
+                .unwrap();
+            // Queries can't run on fixtures that wrap the transport.
+            assert!(matches!(
+                processor.close_inputs(
+                    DelayedTransport::builder(Transport::clone_ref(transport)).build(),
+                    query_id
+                ),
+                Err(QueryInputError::UnsupportedTransport(_))
+            ));
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(query_id).unwrap()
+            );
+            processor
+                .close_inputs(Transport::clone_ref(transport), query_id)
+                .unwrap();
+        }
+
+        let results = try_join_all(processors.iter().map(|p| p.complete(query_id)))
+            .await
+            .unwrap();
+        let products = results
+            .iter()
+            .map(|result| {
+                downcast_result::<Vec<Replicated<Fp31>>>(result.as_ref())
+                    .unwrap()
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![a * b],
+            <[_; 3]>::try_from(products).unwrap().reconstruct()
+        );
+    }
+
+    /// Two queries with different configurations share processors, but neither of them notices
+    /// the other one.
+    #[tokio::test]
+    async fn concurrent_queries() {
+        let network = InMemoryNetwork::new(accept_prepare_callbacks());
+        let transports = network.transports();
+        let processors: [_; 3] = array::from_fn(|_| Processor::default());
+        let mut prepares = Vec::new();
+        for config in [
+            test_multiply_config(),
+            QueryConfig::new(QueryType::TestAdd, FieldType::Fp32BitPrime, 1).unwrap(),
+        ] {
+            let prepare = processors[0]
+                .new_query(Transport::clone_ref(&transports[0]), config)
+                .await
+                .unwrap();
+            for (processor, transport) in processors.iter().zip(&transports).skip(1) {
+                processor.prepare(transport, prepare.clone()).unwrap();
+            }
+            prepares.push(prepare);
+        }
+        let (multiply, add) = (prepares[0].query_id, prepares[1].query_id);
+        assert_ne!(multiply, add);
+        for processor in &processors {
+            for query_id in [multiply, add] {
+                assert_eq!(
+                    QueryStatus::AwaitingInputs,
+                    processor.query_status(query_id).unwrap()
+                );
+            }
+        }
+
+        // Run the multiplication to completion while the other query is still waiting for inputs.
+        let (a, b) = (Fp31::truncate_from(4_u128), Fp31::truncate_from(5_u128));
+        let shares = [a, b].into_iter().share();
+        for ((processor, transport), shares) in processors.iter().zip(&transports).zip(&shares) {
+            processor
+                .receive_inputs(
+                    Transport::clone_ref(transport),
+                    QueryInput::from_records(multiply, shares),
+                )
+                .unwrap();
+        }
+        let results = try_join_all(processors.iter().map(|p| p.complete(multiply)))
+            .await
+            .unwrap();
+        let products = results
+            .iter()
+            .map(|result| {
+                downcast_result::<Vec<Replicated<Fp31>>>(result.as_ref())
+                    .unwrap()
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![a * b],
+            <[_; 3]>::try_from(products).unwrap().reconstruct()
+        );
+        for processor in &processors {
+            assert_eq!(
+                QueryStatus::Completed,
+                processor.query_status(multiply).unwrap()
+            );
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(add).unwrap()
+            );
+        }
+
+        let (a, b) = (
+            Fp32BitPrime::truncate_from(4_u128),
+            Fp32BitPrime::truncate_from(5_u128),
+        );
+        let shares = [a, b].into_iter().share();
+        for ((processor, transport), shares) in processors.iter().zip(&transports).zip(&shares) {
+            processor
+                .receive_inputs(
+                    Transport::clone_ref(transport),
+                    QueryInput::from_records(add, shares),
+                )
+                .unwrap();
+        }
+        let results = try_join_all(processors.iter().map(|p| p.complete(add)))
+            .await
+            .unwrap();
+        let sums = results
+            .iter()
+            .map(|result| {
+                downcast_result::<Vec<Replicated<Fp32BitPrime>>>(result.as_ref())
+                    .unwrap()
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(vec![a + b], <[_; 3]>::try_from(sums).unwrap().reconstruct());
+    }
+
+    #[tokio::test]
+    async fn shutdown_aborts_running_queries() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+        // Other helpers never receive their inputs, so this query can't complete.
+        processor
+            .receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+        assert_eq!(
+            QueryStatus::Running,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+
+        processor.shutdown(&transport).await;
+
+        assert!(matches!(
+            processor.query_status(QueryId::default()).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
+        ));
+        assert!(transport
+            .send(
+                HelperIdentity::ONE,
+                (RouteId::Records, QueryId::default(), Gate::default()),
+                stream::empty(),
+            )
+            .await
+            .is_err());
+    }
+
+    /// Aborts the task of the running query and waits until it is gone.
+    async fn drop_query_task(processor: &Processor, query_id: QueryId) {
+        let state = processor.queries.inner.lock().unwrap().remove(&query_id);
+        let Some(QueryState::Running(mut running)) = state else {
+            panic!("query {query_id:?} is not running");
+        };
+        running.join_handle.abort();
+        assert!((&mut running.join_handle).await.is_err());
+        processor
+            .queries
+            .inner
+            .lock()
+            .unwrap()
+            .insert(query_id, QueryState::Running(running));
+    }
+
+    #[tokio::test]
+    async fn dropped_query_is_not_running() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+        // Other helpers never receive their inputs, so this query can't complete.
+        processor
+            .receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+        assert_eq!(
+            QueryStatus::Running,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+
+        drop_query_task(&processor, QueryId::default()).await;
+
+        assert!(matches!(
+            processor.query_status(QueryId::default()).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
+        ));
+        assert_eq!(None, processor.role(QueryId::default()));
+        // the query id can be used again
+        prepare_test_multiply(&processor, &transport);
+    }
+
+    #[tokio::test]
+    async fn complete_dropped_query() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_test_multiply(&processor, &transport);
+        processor
+            .receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+
+        drop_query_task(&processor, QueryId::default()).await;
+
+        assert!(matches!(
+            processor.complete(QueryId::default()).await.unwrap_err(),
+            QueryCompletionError::Cancelled(_)
+        ));
+        assert!(matches!(
+            processor.query_status(QueryId::default()).unwrap_err(),
+            QueryStatusError::NoSuchQuery(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn query_times_out() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_with_config(
+            &processor,
+            &transport,
+            test_multiply_config().with_timeout(Duration::from_millis(50)),
+        );
+        // Other helpers never receive their inputs, so this query can't complete.
+        processor
+            .receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            processor.complete(QueryId::default()).await.unwrap_err(),
+            QueryCompletionError::TimedOut(_)
+        ));
+        // the query id can be used again
+        prepare_test_multiply(&processor, &transport);
+    }
+
+    /// Timeout is a part of the query configuration, so followers enforce the one the
+    /// coordinator was asked for.
+    #[tokio::test]
+    async fn followers_time_out() {
+        let processors: [Arc<Processor>; 3] = array::from_fn(|_| Arc::new(Processor::default()));
+        let network = InMemoryNetwork::new(array::from_fn(|i| {
+            TransportCallbacks::from_processor(Arc::clone(&processors[i]))
+        }));
+        let [t0, t1, _] = network.transports();
+        let config = test_multiply_config().with_timeout(Duration::from_millis(50));
+        let query_id = processors[0].new_query(t0, config).await.unwrap().query_id;
+
+        // Other helpers never receive their inputs, so this query can't complete.
+        processors[1]
+            .receive_inputs(
+                t1,
+                QueryInput::from_records(query_id, &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            processors[1].complete(query_id).await.unwrap_err(),
+            QueryCompletionError::TimedOut(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_query_status() {
+        let network = InMemoryNetwork::default();
+        let transport = network.transport(HelperIdentity::TWO);
+        let processor = Processor::default();
+        prepare_with_config(
+            &processor,
+            &transport,
+            test_multiply_config().with_timeout(Duration::from_millis(50)),
+        );
+        // Other helpers never receive their inputs, so this query fails once it times out.
+        processor
+            .receive_inputs(
+                Transport::clone_ref(&transport),
+                QueryInput::from_records(QueryId::default(), &[Replicated::<Fp31>::ZERO; 2]),
+            )
+            .unwrap();
+
+        while processor.query_status(QueryId::default()).unwrap() == QueryStatus::Running {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(
+            QueryStatus::Failed,
+            processor.query_status(QueryId::default()).unwrap()
+        );
+
+        // Status was polled before anyone asked for results, it makes no difference to them.
+        let error = processor.complete(QueryId::default()).await.unwrap_err();
+        assert!(
+            matches!(error, QueryCompletionError::TimedOut(_)),
+            "{error:?}"
+        );
+        // the query id can be used again
+        prepare_test_multiply(&processor, &transport);
+    }
+
     mod prepare {
         use super::*;
 
         fn prepare_query(identities: [HelperIdentity; 3]) -> PrepareQuery {
             PrepareQuery {
-                query_id: QueryId,
+                query_id: QueryId::default(),
                 config: test_multiply_config(),
                 roles: RoleAssignment::new(identities),
             }
@@ -484,13 +1991,13 @@ mod tests {
             let processor = Processor::default();
 
             assert!(matches!(
-                processor.query_status(QueryId).unwrap_err(),
+                processor.query_status(QueryId::default()).unwrap_err(),
                 QueryStatusError::NoSuchQuery(_)
             ));
             processor.prepare(&transport, req).unwrap();
             assert_eq!(
                 QueryStatus::AwaitingInputs,
-                processor.query_status(QueryId).unwrap()
+                processor.query_status(QueryId::default()).unwrap()
             );
         }
 
@@ -521,6 +2028,60 @@ mod tests {
                 Err(PrepareQueryError::AlreadyRunning)
             ));
         }
+
+        #[tokio::test]
+        async fn rejects_unsupported_field() {
+            let network = InMemoryNetwork::default();
+            let identities = HelperIdentity::make_three();
+            let req = prepare_query(identities);
+            let transport = network.transport(identities[1]);
+            let processor = Processor::default().with_field_types([FieldType::Fp32BitPrime]);
+
+            assert!(matches!(
+                processor.prepare(&transport, req),
+                Err(PrepareQueryError::UnsupportedField(FieldType::Fp31))
+            ));
+            assert!(matches!(
+                processor.query_status(QueryId::default()).unwrap_err(),
+                QueryStatusError::NoSuchQuery(_)
+            ));
+            assert_eq!(None, processor.role(QueryId::default()));
+        }
+
+        #[tokio::test]
+        async fn rejects_disallowed_security_model() {
+            let network = InMemoryNetwork::default();
+            let identities = HelperIdentity::make_three();
+            let mut req = prepare_query(identities);
+            req.config = req.config.with_security_model(SecurityModel::Malicious);
+            let transport = network.transport(identities[1]);
+            let processor = Processor::default().with_security_models([SecurityModel::SemiHonest]);
+
+            assert!(matches!(
+                processor.prepare(&transport, req),
+                Err(PrepareQueryError::ConfigRejected { reason }) if reason.contains("Malicious")
+            ));
+            assert!(matches!(
+                processor.query_status(QueryId::default()).unwrap_err(),
+                QueryStatusError::NoSuchQuery(_)
+            ));
+        }
+
+        #[tokio::test]
+        async fn rejects_too_large_query() {
+            let network = InMemoryNetwork::default();
+            let identities = HelperIdentity::make_three();
+            let mut req = prepare_query(identities);
+            req.config.size = QuerySize::try_from(11_u32).unwrap();
+            let transport = network.transport(identities[1]);
+            let processor =
+                Processor::default().with_max_query_size(QuerySize::try_from(10_u32).unwrap());
+
+            assert!(matches!(
+                processor.prepare(&transport, req),
+                Err(PrepareQueryError::ConfigRejected { .. })
+            ));
+        }
     }
 
     mod e2e {
@@ -531,7 +2092,7 @@ mod tests {
         use super::*;
         use crate::{
             error::BoxError,
-            ff::{Field, Fp31},
+            ff::{Field, Fp31, Fp32BitPrime},
             helpers::query::IpaQueryConfig,
             ipa_test_input,
             protocol::{ipa::IPAInputRow, BreakdownKey, MatchKey},
@@ -558,6 +2119,89 @@ mod tests {
             ))
         }
 
+        #[tokio::test]
+        async fn complete_query_test_multiply_security_models() -> Result<(), BoxError> {
+            let inputs = [4_u128, 5, 3, 6].map(Fp32BitPrime::truncate_from);
+            for security_model in [SecurityModel::SemiHonest, SecurityModel::Malicious] {
+                let app = TestApp::default();
+                let config = QueryConfig::new(QueryType::TestMultiply, FieldType::Fp32BitPrime, 2)
+                    .unwrap()
+                    .with_security_model(security_model);
+                let results = app.execute_query(inputs.into_iter(), config).await?;
+
+                let results = results.map(|bytes| {
+                    semi_honest::AdditiveShare::<Fp32BitPrime>::from_byte_slice(&bytes)
+                        .collect::<Vec<_>>()
+                });
+
+                assert_eq!(
+                    [20_u128, 18].map(Fp32BitPrime::truncate_from).to_vec(),
+                    results.reconstruct(),
+                    "{security_model:?}"
+                );
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn complete_query_test_add() -> Result<(), BoxError> {
+            let app = TestApp::default();
+            let a = Fp31::truncate_from(4u128);
+            let b = Fp31::truncate_from(30u128);
+            let results = app
+                .execute_query(
+                    vec![a, b].into_iter(),
+                    QueryConfig::new(QueryType::TestAdd, FieldType::Fp31, 1).unwrap(),
+                )
+                .await?;
+
+            let results = results.map(|bytes| {
+                semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+            });
+
+            Ok(assert_eq!(vec![a + b], results.reconstruct()))
+        }
+
+        #[tokio::test]
+        async fn seeded_queries_are_reproducible() -> Result<(), BoxError> {
+            let config = GatewayConfig::default().with_seed(42);
+            let a = Fp31::truncate_from(4u128);
+            let b = Fp31::truncate_from(5u128);
+
+            let first = TestApp::with_gateway_config(config)
+                .execute_query(vec![a, b].into_iter(), test_multiply_config())
+                .await?;
+            let second = TestApp::with_gateway_config(config)
+                .execute_query(vec![a, b].into_iter(), test_multiply_config())
+                .await?;
+
+            assert_eq!(first, second);
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn complete_query_test_multiply_stats() -> Result<(), BoxError> {
+            // PRSS negotiation sends a 32 byte public key to each peer, and multiplying two
+            // Fp31 shares sends one byte to the right peer.
+            const EXPECTED_BYTES: usize = 2 * 32 + 1;
+
+            let app = TestApp::default();
+            let a = Fp31::truncate_from(4u128);
+            let b = Fp31::truncate_from(5u128);
+            let query_id = app
+                .start_query(vec![a, b].into_iter(), test_multiply_config())
+                .await?;
+
+            for (_, stats) in app.complete_query_with_stats(query_id).await? {
+                assert_eq!(EXPECTED_BYTES, stats.total_received().bytes);
+                assert_eq!(EXPECTED_BYTES, stats.total_sent().bytes);
+            }
+
+            Ok(())
+        }
+
         #[tokio::test]
         async fn complete_query_status_poll() -> Result<(), BoxError> {
             let app = TestApp::default();
@@ -585,6 +2229,24 @@ mod tests {
             ))
         }
 
+        #[tokio::test]
+        async fn complete_is_idempotent() -> Result<(), BoxError> {
+            let app = TestApp::default();
+            let a = Fp31::truncate_from(4u128);
+            let b = Fp31::truncate_from(5u128);
+            let query_id = app
+                .start_query(vec![a, b].into_iter(), test_multiply_config())
+                .await?;
+
+            let first = app.complete_query(query_id).await?;
+            assert_eq!([QueryStatus::Completed; 3], app.query_status(query_id)?);
+            let second = app.complete_query(query_id).await?;
+
+            assert_eq!(first, second);
+
+            Ok(())
+        }
+
         #[tokio::test]
         async fn complete_query_ipa() -> Result<(), BoxError> {
             let app = TestApp::default();
@@ -598,8 +2260,52 @@ mod tests {
             ipa_query(&app).await
         }
 
-        async fn ipa_query(app: &TestApp) -> Result<(), BoxError> {
-            let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
+        #[tokio::test]
+        async fn complete_query_stream_ipa() -> Result<(), BoxError> {
+            let app = TestApp::default();
+            let (records, config) = ipa_query_input::<Fp31>(FieldType::Fp31);
+            let expected = app
+                .execute_query::<_, Vec<IPAInputRow<_, _, _>>>(records.clone().into_iter(), config)
+                .await?
+                .map(|bytes| {
+                    semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+                })
+                .reconstruct();
+
+            let query_id = app
+                .start_query::<_, Vec<IPAInputRow<_, _, _>>>(records.into_iter(), config)
+                .await?;
+            let streamed = app.complete_query_stream(query_id).await?.map(|bytes| {
+                semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+            });
+
+            Ok(assert_eq!(expected, streamed.reconstruct()))
+        }
+
+        #[tokio::test]
+        async fn complete_query_ipa_fp32_bit_prime() -> Result<(), BoxError> {
+            const EXPECTED: &[u128] = &[0, 2, 3];
+
+            let app = TestApp::default();
+            let (records, config) = ipa_query_input::<Fp32BitPrime>(FieldType::Fp32BitPrime);
+            let results = app
+                .execute_query::<_, Vec<IPAInputRow<_, _, _>>>(records.into_iter(), config)
+                .await?
+                .map(|bytes| {
+                    semi_honest::AdditiveShare::<Fp32BitPrime>::from_byte_slice(&bytes)
+                        .collect::<Vec<_>>()
+                });
+
+            Ok(assert_eq!(results.reconstruct(), EXPECTED))
+        }
+
+        fn ipa_query_input<F: Field>(
+            field_type: FieldType,
+        ) -> (
+            Vec<GenericReportTestInput<F, MatchKey, BreakdownKey>>,
+            QueryConfig,
+        ) {
+            let records: Vec<GenericReportTestInput<F, MatchKey, BreakdownKey>> = ipa_test_input!(
                 [
                     { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
                     { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
@@ -607,25 +2313,29 @@ mod tests {
                     { timestamp: 0, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
                     { timestamp: 0, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
                 ];
-                (Fp31, MatchKey, BreakdownKey)
+                (F, MatchKey, BreakdownKey)
             );
-            let record_count = records.len();
+            let config = QueryConfig::new(
+                QueryType::SemiHonestIpa(IpaQueryConfig {
+                    per_user_credit_cap: 3,
+                    max_breakdown_key: 3,
+                    attribution_window_seconds: None,
+                    num_multi_bits: 3,
+                    plaintext_match_keys: true,
+                }),
+                field_type,
+                records.len(),
+            )
+            .unwrap();
+
+            (records, config)
+        }
+
+        async fn ipa_query(app: &TestApp) -> Result<(), BoxError> {
+            let (records, config) = ipa_query_input::<Fp31>(FieldType::Fp31);
 
             let _results = app
-                .execute_query::<_, Vec<IPAInputRow<_, _, _>>>(
-                    records.into_iter(),
-                    QueryConfig {
-                        size: record_count.try_into().unwrap(),
-                        field_type: FieldType::Fp31,
-                        query_type: QueryType::SemiHonestIpa(IpaQueryConfig {
-                            per_user_credit_cap: 3,
-                            max_breakdown_key: 3,
-                            attribution_window_seconds: None,
-                            num_multi_bits: 3,
-                            plaintext_match_keys: true,
-                        }),
-                    },
-                )
+                .execute_query::<_, Vec<IPAInputRow<_, _, _>>>(records.into_iter(), config)
                 .await?;
 
             Ok(())
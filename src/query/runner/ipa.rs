@@ -112,6 +112,7 @@ where
             .zip(repeat(ctx.clone()))
             .map(|(res, ctx)| {
                 res.and_then(|report| {
+                    report.validate(&config)?;
                     let timestamp = Replicated::<F>::share_known_value(
                         &ctx,
                         F::try_from(report.timestamp.into())
@@ -149,6 +150,7 @@ where
 mod tests {
     use std::iter::zip;
 
+    use futures::future::join_all;
     use generic_array::GenericArray;
     use rand::rngs::StdRng;
     use rand_core::SeedableRng;
@@ -336,4 +338,62 @@ mod tests {
 
         assert_eq!(results.reconstruct(), EXPECTED);
     }
+
+    #[tokio::test]
+    async fn rejects_out_of_range_breakdown_key() {
+        let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
+            [
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 3, trigger_value: 0 },
+                { timestamp: 0, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+            ];
+            (Fp31, MatchKey, BreakdownKey)
+        );
+        let query_size = QuerySize::try_from(records.len()).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let key_registry = Arc::new(KeyRegistry::random(1, &mut rng));
+
+        let mut buffers: [_; 3] = std::array::from_fn(|_| Vec::new());
+        let shares: [Vec<Report<_, _, _>>; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for share in shares {
+                share
+                    .delimited_encrypt_to(DEFAULT_KEY_ID, key_registry.as_ref(), &mut rng, buf)
+                    .unwrap();
+            }
+        }
+
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        #[allow(clippy::large_futures)]
+        let results = join_all(buffers.into_iter().zip(contexts).map(|(buffer, ctx)| {
+            let query_config = IpaQueryConfig {
+                num_multi_bits: 3,
+                per_user_credit_cap: 3,
+                attribution_window_seconds: None,
+                max_breakdown_key: 3,
+                plaintext_match_keys: false,
+            };
+            IpaQuery::<Fp31, _, _>::new(query_config, Arc::clone(&key_registry)).execute(
+                ctx,
+                query_size,
+                BodyStream::from(buffer),
+            )
+        }))
+        .await;
+
+        for result in results {
+            assert!(
+                matches!(
+                    result,
+                    Err(Error::InvalidReport(InvalidReportError::BreakdownKey {
+                        breakdown_key: 3,
+                        max_breakdown_key: 3,
+                    }))
+                ),
+                "{result:?}"
+            );
+        }
+    }
 }
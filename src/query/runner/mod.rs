@@ -2,8 +2,12 @@ mod aggregate;
 mod ipa;
 mod oprf_ipa;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+mod test_add;
+#[cfg(any(test, feature = "cli", feature = "test-fixture"))]
 mod test_multiply;
 
+#[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+pub(super) use test_add::execute_test_add;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
 pub(super) use test_multiply::execute_test_multiply;
 
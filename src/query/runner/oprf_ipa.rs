@@ -97,12 +97,6 @@ where
             .collect::<Vec<_>>();
         // Until then, we convert the output to something next function is happy about.
 
-        let user_cap: i32 = config.per_user_credit_cap.try_into().unwrap();
-        assert!(
-            user_cap & (user_cap - 1) == 0,
-            "This code only works for a user cap which is a power of 2"
-        );
-
         attribution_and_capping_and_aggregation::<
             C,
             BreakdownKey,
@@ -113,7 +107,7 @@ where
         >(
             ctx,
             sharded_input,
-            user_cap.ilog2().try_into().unwrap(),
+            config.per_user_credit_cap,
             config.attribution_window_seconds,
             ref_to_histogram,
         )
@@ -0,0 +1,98 @@
+use futures::StreamExt;
+
+use crate::{
+    error::Error,
+    ff::{PrimeField, Serializable},
+    helpers::{BodyStream, Gateway, RecordsStream},
+    protocol::prss::Endpoint as PrssEndpoint,
+    query::runner::QueryResult,
+    secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
+};
+
+/// Adds up pairs of input shares. Unlike [`execute_test_multiply`], it does not need to talk to
+/// other helpers after PRSS is negotiated, which helps to tell apart bugs in transport
+/// from bugs in multiplication.
+///
+/// [`execute_test_multiply`]: super::execute_test_multiply
+pub async fn execute_test_add<'a, F>(
+    _prss: &'a PrssEndpoint,
+    _gateway: &'a Gateway,
+    input: BodyStream,
+) -> QueryResult
+where
+    F: PrimeField,
+    Replicated<F>: Serializable,
+{
+    Ok(Box::new(execute_test_add_internal::<F>(input).await?))
+}
+
+pub async fn execute_test_add_internal<F>(
+    input_stream: BodyStream,
+) -> Result<Vec<Replicated<F>>, Error>
+where
+    F: PrimeField,
+    Replicated<F>: Serializable,
+{
+    let mut input = Box::pin(RecordsStream::<Replicated<F>, _>::new(input_stream));
+    let mut results = Vec::new();
+    while let Some(v) = input.next().await {
+        // add pairs
+        let mut a = None;
+        for share in v.unwrap() {
+            match a.take() {
+                None => a = Some(share),
+                Some(a_v) => results.push(a_v + share),
+            }
+        }
+
+        assert!(a.is_none());
+    }
+
+    Ok(results)
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use generic_array::GenericArray;
+    use typenum::Unsigned;
+
+    use super::*;
+    use crate::{
+        ff::{Field, Fp31},
+        secret_sharing::IntoShares,
+        test_fixture::{join3v, Reconstruct},
+    };
+
+    #[tokio::test]
+    async fn add() {
+        let a = [Fp31::truncate_from(4u128), Fp31::truncate_from(5u128)];
+        let b = [Fp31::truncate_from(3u128), Fp31::truncate_from(30u128)];
+
+        let helper_shares = (a.into_iter(), b.into_iter()).share().map(|(a, b)| {
+            const SIZE: usize = <Replicated<Fp31> as Serializable>::Size::USIZE;
+            a.into_iter()
+                .zip(b)
+                .flat_map(|(a, b)| {
+                    let mut slice = [0_u8; 2 * SIZE];
+                    a.serialize(GenericArray::from_mut_slice(&mut slice[..SIZE]));
+                    b.serialize(GenericArray::from_mut_slice(&mut slice[SIZE..]));
+
+                    slice
+                })
+                .collect::<Vec<_>>()
+                .into()
+        });
+
+        let results = join3v(
+            helper_shares
+                .into_iter()
+                .map(execute_test_add_internal::<Fp31>),
+        )
+        .await;
+
+        assert_eq!(
+            vec![Fp31::truncate_from(7u128), Fp31::truncate_from(4u128)],
+            results.reconstruct()
+        );
+    }
+}
@@ -1,32 +1,49 @@
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 
 use crate::{
     error::Error,
     ff::{PrimeField, Serializable},
-    helpers::{BodyStream, Gateway, RecordsStream, TotalRecords},
+    helpers::{query::SecurityModel, BodyStream, Gateway, RecordsStream, TotalRecords},
     protocol::{
         basics::SecureMul,
-        context::{Context, SemiHonestContext},
+        context::{
+            Context, MaliciousContext, SemiHonestContext, UpgradableContext, UpgradedContext,
+            Validator,
+        },
         prss::Endpoint as PrssEndpoint,
         RecordId,
     },
     query::runner::QueryResult,
-    secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
+    secret_sharing::replicated::{
+        malicious::ExtendableField, semi_honest::AdditiveShare as Replicated,
+    },
+    seq_join::SeqJoin,
 };
 
 pub async fn execute_test_multiply<'a, F>(
     prss: &'a PrssEndpoint,
     gateway: &'a Gateway,
+    security_model: SecurityModel,
     input: BodyStream,
 ) -> QueryResult
 where
-    F: PrimeField,
+    F: PrimeField + ExtendableField,
     Replicated<F>: Serializable,
 {
-    let ctx = SemiHonestContext::new(prss, gateway);
-    Ok(Box::new(
-        execute_test_multiply_internal::<F>(ctx, input).await?,
-    ))
+    match security_model {
+        SecurityModel::SemiHonest => {
+            let ctx = SemiHonestContext::new(prss, gateway);
+            Ok(Box::new(
+                execute_test_multiply_internal::<F>(ctx, input).await?,
+            ))
+        }
+        SecurityModel::Malicious => {
+            let ctx = MaliciousContext::new(prss, gateway);
+            Ok(Box::new(
+                execute_malicious_test_multiply_internal::<F>(ctx, input).await?,
+            ))
+        }
+    }
 }
 
 pub async fn execute_test_multiply_internal<F>(
@@ -41,10 +58,11 @@ where
 
     let mut input = Box::pin(RecordsStream::<Replicated<F>, _>::new(input_stream));
     let mut results = Vec::new();
+    // pairs and record ids carry over from one chunk of the input to the next
+    let mut a = None;
+    let mut record_id = 0_u32;
     while let Some(v) = input.next().await {
         // multiply pairs
-        let mut a = None;
-        let mut record_id = 0_u32;
         for share in v.unwrap() {
             match a {
                 None => a = Some(share),
@@ -59,13 +77,55 @@ where
                 }
             }
         }
-
-        assert!(a.is_none());
     }
+    assert!(a.is_none());
 
     Ok(results)
 }
 
+/// Multiplies pairs of input shares the same way [`execute_test_multiply_internal`] does, but with
+/// malicious security. Unlike the semi-honest version, it needs to see the whole input before it
+/// can start, because every share must be upgraded and validated under the same validator.
+///
+/// ## Errors
+/// If communication with peers fails, or if validation finds that any of the helpers cheated.
+///
+/// ## Panics
+/// If the input has an odd number of shares.
+pub async fn execute_malicious_test_multiply_internal<F>(
+    ctx: MaliciousContext<'_>,
+    input_stream: BodyStream,
+) -> Result<Vec<Replicated<F>>, Error>
+where
+    F: PrimeField + ExtendableField,
+    Replicated<F>: Serializable,
+{
+    let input = RecordsStream::<Replicated<F>, _>::new(input_stream)
+        .try_concat()
+        .await?;
+    assert_eq!(0, input.len() % 2, "input must consist of pairs of shares");
+    let (a, b): (Vec<_>, Vec<_>) = input
+        .chunks_exact(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .unzip();
+
+    let validator = ctx.validator::<F>();
+    let m_ctx = validator.context();
+    let (a, b) = m_ctx.upgrade((a, b)).await?;
+
+    let m_ctx = m_ctx.set_total_records(a.len());
+    let results = m_ctx
+        .try_join(
+            a.iter()
+                .zip(&b)
+                .enumerate()
+                .map(|(i, (a, b))| a.multiply(b, m_ctx.clone(), RecordId::from(i))),
+        )
+        .await?;
+
+    validator.validate(results).await
+}
+
 #[cfg(all(test, unit_test))]
 mod tests {
     use generic_array::GenericArray;
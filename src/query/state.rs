@@ -10,9 +10,10 @@ use futures::{ready, FutureExt};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    error::Error as ProtocolError,
     helpers::{query::QueryConfig, RoleAssignment},
     protocol::QueryId,
-    query::runner::QueryResult,
+    query::{runner::QueryResult, ProtocolResult},
     sync::Mutex,
     task::JoinHandle,
 };
@@ -32,8 +33,12 @@ pub enum QueryStatus {
     Running,
     /// Complete API has been called and is waiting for query to finish.
     AwaitingCompletion,
-    /// Query has finished and results are available.
+    /// Query has finished and results are available. Results are kept until they are discarded,
+    /// and no other query can take this id before that.
     Completed,
+    /// Query task returned an error or panicked, so there are no results. Calling complete API
+    /// returns the error.
+    Failed,
 }
 
 impl From<&QueryState> for QueryStatus {
@@ -45,6 +50,7 @@ impl From<&QueryState> for QueryStatus {
             QueryState::Running(_) => QueryStatus::Running,
             QueryState::AwaitingCompletion => QueryStatus::AwaitingCompletion,
             QueryState::Completed(_) => QueryStatus::Completed,
+            QueryState::Failed(_) => QueryStatus::Failed,
         }
     }
 }
@@ -56,18 +62,25 @@ pub enum QueryState {
     AwaitingInputs(QueryId, QueryConfig, RoleAssignment),
     Running(RunningQuery),
     AwaitingCompletion,
-    Completed(QueryResult),
+    Completed(Box<dyn ProtocolResult>),
+    /// Query task returned this error. It is kept as is, so whoever asks for results gets the
+    /// same error no matter how the failure was noticed.
+    Failed(ProtocolError),
 }
 
 impl QueryState {
     pub fn transition(cur_state: &Self, new_state: Self) -> Result<Self, StateError> {
-        use QueryState::{AwaitingInputs, Empty, Preparing};
+        use QueryState::{AwaitingInputs, Completed, Empty, Failed, Preparing};
 
         match (cur_state, &new_state) {
             // If query is not running, coordinator initial state is preparing
             // and followers initial state is awaiting inputs
             (Empty, Preparing(_) | AwaitingInputs(_, _, _))
             | (Preparing(_), AwaitingInputs(_, _, _)) => Ok(new_state),
+            // Results of the finished query must be discarded before its id is used again
+            (Completed(_) | Failed(_), Preparing(_) | AwaitingInputs(_, _, _)) => {
+                Err(StateError::AlreadyExists)
+            }
             (_, Preparing(_)) => Err(StateError::AlreadyRunning),
             (_, _) => Err(StateError::InvalidState {
                 from: cur_state.into(),
@@ -78,6 +91,9 @@ impl QueryState {
 }
 
 pub struct RunningQuery {
+    /// Receives the result of the query task. The task owns the other end of this channel, so if
+    /// it goes away without sending anything, because it was aborted or dropped by the runtime,
+    /// the channel closes and the query is reported as [`QueryCancelled`].
     pub result: Receiver<QueryResult>,
 
     /// `JoinHandle` for the query task.
@@ -91,38 +107,42 @@ pub struct RunningQuery {
 }
 
 impl RunningQuery {
-    pub fn try_complete(&mut self) -> Option<QueryResult> {
+    /// Returns the result of the query if it is finished.
+    ///
+    /// ## Errors
+    /// If the query task was dropped before it produced a result.
+    pub fn try_complete(&mut self) -> Result<Option<QueryResult>, QueryCancelled> {
         match self.result.try_recv() {
-            Ok(result) => Some(result),
-            Err(TryRecvError::Closed) => {
-                panic!("query completed without returning a result");
-            }
-            Err(TryRecvError::Empty) => None,
+            Ok(result) => Ok(Some(result)),
+            Err(TryRecvError::Closed) => Err(QueryCancelled),
+            Err(TryRecvError::Empty) => Ok(None),
         }
     }
 }
 
 impl Future for RunningQuery {
-    type Output = QueryResult;
+    type Output = Result<QueryResult, QueryCancelled>;
 
-    #[allow(clippy::match_wild_err_arm)] // The error is a RecvError, which has no detail to report.
     fn poll(
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Self::Output> {
-        match ready!(self.result.poll_unpin(cx)) {
-            Ok(result) => Poll::Ready(result),
-            Err(_) => {
-                panic!("query completed without returning a result");
-            }
-        }
+        // The only error is a RecvError, which means the sender is gone.
+        Poll::Ready(ready!(self.result.poll_unpin(cx)).map_err(|_| QueryCancelled))
     }
 }
 
+/// The query task was dropped before it produced a result.
+#[derive(Debug, thiserror::Error)]
+#[error("query was cancelled before it completed")]
+pub struct QueryCancelled;
+
 #[derive(Debug, thiserror::Error)]
 pub enum StateError {
     #[error("Query is already running")]
     AlreadyRunning,
+    #[error("Query already exists, its results must be discarded first")]
+    AlreadyExists,
     #[error("Cannot transition from state {from:?} to state {to:?}")]
     InvalidState { from: QueryStatus, to: QueryStatus },
 }
@@ -155,14 +175,22 @@ impl QueryHandle<'_> {
     pub fn set_state(&self, new_state: QueryState) -> Result<(), StateError> {
         let mut inner = self.queries.inner.lock().unwrap();
         let entry = inner.entry(self.query_id);
-        match entry {
+        let (from, to) = match entry {
             Entry::Occupied(mut entry) => {
-                entry.insert(QueryState::transition(entry.get(), new_state)?);
+                let from = QueryStatus::from(entry.get());
+                let state = QueryState::transition(entry.get(), new_state)?;
+                let to = QueryStatus::from(&state);
+                entry.insert(state);
+                (Some(from), to)
             }
             Entry::Vacant(entry) => {
-                entry.insert(QueryState::transition(&QueryState::Empty, new_state)?);
+                let state = QueryState::transition(&QueryState::Empty, new_state)?;
+                let to = QueryStatus::from(&state);
+                entry.insert(state);
+                (None, to)
             }
-        }
+        };
+        tracing::debug!(query_id = ?self.query_id, ?from, ?to, "query state changed");
 
         Ok(())
     }
@@ -184,6 +212,15 @@ impl RunningQueries {
             queries: self,
         }
     }
+
+    /// Returns the number of queries in any state, including those that completed.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    #[cfg(all(test, unit_test))]
+    pub fn count(&self) -> usize {
+        self.inner.lock().unwrap().len()
+    }
 }
 
 /// RAII guard to clean up query state when dropped.
@@ -13,6 +13,7 @@ use typenum::{Unsigned, U1, U8, U9};
 
 use crate::{
     ff::{GaloisField, Gf40Bit, Gf8Bit, PrimeField, Serializable},
+    helpers::query::IpaQueryConfig,
     hpke::{
         open_in_place, seal_in_place, CryptError, FieldShareCrypt, Info, KeyPair, KeyRegistry,
         PublicKeyRegistry,
@@ -150,6 +151,11 @@ pub enum InvalidReportError {
     NonAsciiString(#[from] NonAsciiStringError),
     #[error("timestamp {0} out of range")]
     Timestamp(Timestamp),
+    #[error("breakdown key {breakdown_key} is out of range, query supports {max_breakdown_key} breakdown keys")]
+    BreakdownKey {
+        breakdown_key: u128,
+        max_breakdown_key: u32,
+    },
     #[error("en/decryption failure: {0}")]
     Crypt(#[from] CryptError),
 }
@@ -327,6 +333,32 @@ where
     pub site_domain: String,
 }
 
+impl<F, MK, BK> Report<F, MK, BK>
+where
+    F: PrimeField,
+    Replicated<F>: Serializable,
+    MK: FieldShareCrypt,
+    BK: GaloisField,
+{
+    /// Checks the fields of this report that helpers see in the clear against the query
+    /// configuration. Event type is checked when the report is parsed already, and everything
+    /// else is secret-shared, so no single helper can tell whether it is valid.
+    ///
+    /// ## Errors
+    /// If breakdown key is not less than `config.max_breakdown_key`.
+    pub fn validate(&self, config: &IpaQueryConfig) -> Result<(), InvalidReportError> {
+        let breakdown_key: u128 = self.breakdown_key.into();
+        if breakdown_key >= u128::from(config.max_breakdown_key) {
+            return Err(InvalidReportError::BreakdownKey {
+                breakdown_key,
+                max_breakdown_key: config.max_breakdown_key,
+            });
+        }
+
+        Ok(())
+    }
+}
+
 impl<F> Report<F, Gf40Bit, Gf8Bit>
 where
     F: PrimeField,
@@ -7,6 +7,10 @@ pub trait IntoShares<T>: Sized {
     fn share(self) -> [T; 3] {
         self.share_with(&mut thread_rng())
     }
+    /// Same as [`share`], but draws randomness from `rng`, so a seeded one gives the same shares
+    /// every time.
+    ///
+    /// [`share`]: IntoShares::share
     fn share_with<R: Rng>(self, rng: &mut R) -> [T; 3];
 }
 
@@ -106,11 +106,14 @@ where
 
 #[cfg(all(test, unit_test))]
 mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
     use crate::{
-        ff::Fp31,
+        ff::{Fp31, Fp32BitPrime},
+        rand::{thread_rng, Rng},
         secret_sharing::{
             replicated::{malicious, semi_honest},
-            Linear, LinearRefOps, SharedValue,
+            IntoShares, Linear, LinearRefOps, SharedValue,
         },
     };
 
@@ -174,4 +177,17 @@ mod tests {
         arithmetic::<malicious::AdditiveShare<Fp31>, _>();
         trait_bounds::<malicious::AdditiveShare<Fp31>, _>();
     }
+
+    #[test]
+    fn share_with_seeded_rng() {
+        let seed = thread_rng().gen::<u64>();
+        let value = thread_rng().gen::<Fp32BitPrime>();
+
+        let shares = value.share_with(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(shares, value.share_with(&mut StdRng::seed_from_u64(seed)));
+        assert_eq!(
+            value,
+            semi_honest::AdditiveShare::reconstruct(shares).unwrap()
+        );
+    }
 }
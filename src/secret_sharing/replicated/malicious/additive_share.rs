@@ -314,7 +314,7 @@ where
             ));
         let rx = <SemiHonestAdditiveShare<V::ExtendedField> as Serializable>::deserialize(
             GenericArray::from_slice(
-                &buf[<SemiHonestAdditiveShare<V::ExtendedField> as Serializable>::Size::USIZE..],
+                &buf[<SemiHonestAdditiveShare<V> as Serializable>::Size::USIZE..],
             ),
         );
         Self { x, rx }
@@ -396,9 +396,14 @@ impl<T> ThisCodeIsAuthorizedToDowngradeFromMalicious<T> for UnauthorizedDowngrad
 
 #[cfg(all(test, unit_test))]
 mod tests {
-    use super::{AdditiveShare, Downgrade, ThisCodeIsAuthorizedToDowngradeFromMalicious};
+    use generic_array::GenericArray;
+    use rand::distributions::{Distribution, Standard};
+
+    use super::{
+        AdditiveShare, Downgrade, ExtendableField, ThisCodeIsAuthorizedToDowngradeFromMalicious,
+    };
     use crate::{
-        ff::{Field, Fp31},
+        ff::{Field, Fp31, Fp32BitPrime, Gf2, Serializable},
         helpers::Role,
         rand::{thread_rng, Rng},
         secret_sharing::{
@@ -496,4 +501,37 @@ mod tests {
         let m = AdditiveShare::new(x.clone(), y);
         assert_eq!(x, Downgrade::downgrade(m).await.access_without_downgrade());
     }
+
+    fn serde_round_trip<V: ExtendableField>()
+    where
+        AdditiveShare<V>: Serializable,
+        Standard: Distribution<V> + Distribution<V::ExtendedField>,
+    {
+        let mut rng = thread_rng();
+        for _ in 0..10 {
+            let share = AdditiveShare::<V>::new(
+                SemiHonestAdditiveShare::new(rng.gen(), rng.gen()),
+                SemiHonestAdditiveShare::new(rng.gen(), rng.gen()),
+            );
+            let mut buf = GenericArray::default();
+            share.serialize(&mut buf);
+            let deserialized = AdditiveShare::<V>::deserialize(&buf);
+
+            assert_eq!(share.rx(), deserialized.rx());
+            assert_eq!(share, deserialized);
+        }
+    }
+
+    #[test]
+    fn serde_prime_field() {
+        serde_round_trip::<Fp31>();
+        serde_round_trip::<Fp32BitPrime>();
+    }
+
+    /// Shares of bits carry their MACs in a wider field, so the two halves of the serialized
+    /// share have different sizes.
+    #[test]
+    fn serde_extended_field() {
+        serde_round_trip::<Gf2>();
+    }
 }
@@ -7,7 +7,9 @@ use generic_array::{ArrayLength, GenericArray};
 use typenum::Unsigned;
 
 use crate::{
+    error::{Error, MaliciousError},
     ff::Serializable,
+    helpers::Role,
     secret_sharing::{
         replicated::ReplicatedSecretSharing, Linear as LinearSecretSharing, SecretSharing,
         SharedValue, WeakSharedValue,
@@ -41,6 +43,33 @@ impl<V: WeakSharedValue> AdditiveShare<V> {
     pub fn as_tuple(&self) -> (V, V) {
         (self.0, self.1)
     }
+
+    /// Adds `c`, which all helpers know, to the shared value. This does not need to communicate,
+    /// but every helper must pass its own role: `c` goes into the one share of the three that
+    /// `H1` and `H3` both hold, so that their copies of it stay the same.
+    #[must_use]
+    pub fn add_constant(&self, c: V, role: Role) -> Self {
+        match role {
+            Role::H1 => Self(self.0 + c, self.1),
+            Role::H2 => self.clone(),
+            Role::H3 => Self(self.0, self.1 + c),
+        }
+    }
+
+    /// Reconstructs the secret from the shares of all three helpers, in the order of their roles.
+    ///
+    /// ## Errors
+    /// If the shares are not halves of the same replicated sharing, i.e. the right half of any
+    /// share is not the left half of the next one.
+    #[allow(clippy::needless_pass_by_value)] // to take the shares as they come from the helpers
+    pub fn reconstruct(shares: [Self; 3]) -> Result<V, Error> {
+        let [Self(a0, a1), Self(b1, b2), Self(c2, c0)] = shares;
+        if a1 == b1 && b2 == c2 && c0 == a0 {
+            Ok(a0 + a1 + b2)
+        } else {
+            Err(Error::Malicious(MaliciousError::InconsistentShares))
+        }
+    }
 }
 
 impl<V: WeakSharedValue> ReplicatedSecretSharing<V> for AdditiveShare<V> {
@@ -239,10 +268,15 @@ where
 
 #[cfg(all(test, unit_test))]
 mod tests {
+    use proptest::proptest;
+    use rand::{rngs::StdRng, SeedableRng};
+
     use super::AdditiveShare;
     use crate::{
-        ff::{Field, Fp31},
-        secret_sharing::replicated::ReplicatedSecretSharing,
+        error::{Error, MaliciousError},
+        ff::{Field, Fp31, Fp32BitPrime},
+        helpers::Role,
+        secret_sharing::{replicated::ReplicatedSecretSharing, IntoShares},
     };
 
     fn secret_share(
@@ -379,4 +413,34 @@ mod tests {
         mult_by_constant_test_case((0, 0, 1), 2, 2);
         mult_by_constant_test_case((0, 0, 0), 2, 0);
     }
+
+    #[test]
+    fn reconstruct() {
+        let (s0, s1, s2) = secret_share(10, 20, 30);
+        assert_eq!(
+            Fp31::truncate_from(60_u8),
+            AdditiveShare::reconstruct([s0, s1, s2]).unwrap()
+        );
+    }
+
+    #[test]
+    fn reconstruct_inconsistent() {
+        let (s0, s1, s2) = secret_share(10, 20, 30);
+        let tampered = AdditiveShare::new(s1.left(), s1.right() + Fp31::ONE);
+        assert!(matches!(
+            AdditiveShare::reconstruct([s0, tampered, s2]),
+            Err(Error::Malicious(MaliciousError::InconsistentShares))
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn add_constant(x: u32, c: u32, seed: u64) {
+            let (x, c) = (Fp32BitPrime::truncate_from(x), Fp32BitPrime::truncate_from(c));
+            let shares = x.share_with(&mut StdRng::seed_from_u64(seed));
+            let [h1, h2, h3] = Role::all().map(|role| shares[role].add_constant(c, role));
+
+            assert_eq!(x + c, AdditiveShare::reconstruct([h1, h2, h3]).unwrap());
+        }
+    }
 }
@@ -1,3 +1,5 @@
 mod additive_share;
+mod vector;
 
 pub use additive_share::AdditiveShare;
+pub use vector::ReplicatedVec;
@@ -0,0 +1,191 @@
+use std::{
+    iter::zip,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+use generic_array::{ArrayLength, GenericArray};
+use typenum::{Prod, Unsigned};
+
+use crate::{
+    ff::{Field, Serializable},
+    helpers::Message,
+    secret_sharing::replicated::{semi_honest::AdditiveShare, ReplicatedSecretSharing},
+};
+
+/// `N` replicated shares of values in `F`, stored as two contiguous arrays of their left and right
+/// halves. Every operation is applied to all lanes at once. Multiplying two of them takes the same
+/// single round as multiplying two [`AdditiveShare`]s, but helpers send the values for all lanes
+/// in one message, instead of one message per lane.
+///
+/// The width is a part of the type, because every message helpers exchange has a fixed size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplicatedVec<F: Field, N: ArrayLength> {
+    left: GenericArray<F, N>,
+    right: GenericArray<F, N>,
+}
+
+impl<F: Field, N: ArrayLength> ReplicatedVec<F, N> {
+    #[must_use]
+    pub fn new(left: GenericArray<F, N>, right: GenericArray<F, N>) -> Self {
+        Self { left, right }
+    }
+
+    /// Packs `N` shares into lanes of a vector, in the order `lanes` yields them.
+    ///
+    /// ## Panics
+    /// If `lanes` does not yield exactly `N` shares.
+    pub fn from_lanes<I: IntoIterator<Item = AdditiveShare<F>>>(lanes: I) -> Self {
+        let (left, right): (Vec<_>, Vec<_>) = lanes.into_iter().map(|s| s.as_tuple()).unzip();
+        assert_eq!(N::USIZE, left.len(), "expected {} lanes", N::USIZE);
+
+        Self::new(
+            GenericArray::from_slice(&left).clone(),
+            GenericArray::from_slice(&right).clone(),
+        )
+    }
+
+    #[must_use]
+    pub fn left(&self) -> &GenericArray<F, N> {
+        &self.left
+    }
+
+    #[must_use]
+    pub fn right(&self) -> &GenericArray<F, N> {
+        &self.right
+    }
+
+    /// Unpacks the shares in every lane.
+    pub fn lanes(&self) -> impl Iterator<Item = AdditiveShare<F>> + '_ {
+        zip(&self.left, &self.right).map(|(&l, &r)| AdditiveShare::new(l, r))
+    }
+
+    fn zip_with<O: Fn(F, F) -> F>(&self, rhs: &Self, op: O) -> Self {
+        Self::new(
+            zip(&self.left, &rhs.left)
+                .map(|(&a, &b)| op(a, b))
+                .collect(),
+            zip(&self.right, &rhs.right)
+                .map(|(&a, &b)| op(a, b))
+                .collect(),
+        )
+    }
+}
+
+impl<F: Field, N: ArrayLength> Add<&Self> for ReplicatedVec<F, N> {
+    type Output = Self;
+
+    fn add(self, rhs: &Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a + b)
+    }
+}
+
+impl<F: Field, N: ArrayLength> Sub<&Self> for ReplicatedVec<F, N> {
+    type Output = Self;
+
+    fn sub(self, rhs: &Self) -> Self::Output {
+        self.zip_with(rhs, |a, b| a - b)
+    }
+}
+
+impl<F: Field, N: ArrayLength> Neg for ReplicatedVec<F, N> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::new(
+            self.left.iter().map(|&v| -v).collect(),
+            self.right.iter().map(|&v| -v).collect(),
+        )
+    }
+}
+
+/// Multiplies every lane by the same constant.
+impl<F: Field, N: ArrayLength> Mul<F> for ReplicatedVec<F, N> {
+    type Output = Self;
+
+    fn mul(self, rhs: F) -> Self::Output {
+        Self::new(
+            self.left.iter().map(|&v| v * rhs).collect(),
+            self.right.iter().map(|&v| v * rhs).collect(),
+        )
+    }
+}
+
+/// Values of all lanes, serialized one after another. This is what helpers send to each other
+/// when they multiply [`ReplicatedVec`]s.
+impl<F: Field, N: ArrayLength> Serializable for GenericArray<F, N>
+where
+    F::Size: Mul<N>,
+    Prod<F::Size, N>: ArrayLength,
+{
+    type Size = Prod<F::Size, N>;
+
+    fn serialize(&self, buf: &mut GenericArray<u8, Self::Size>) {
+        for (v, chunk) in zip(self, buf.chunks_mut(F::Size::USIZE)) {
+            v.serialize(GenericArray::from_mut_slice(chunk));
+        }
+    }
+
+    fn deserialize(buf: &GenericArray<u8, Self::Size>) -> Self {
+        buf.chunks(F::Size::USIZE)
+            .map(|chunk| F::deserialize(GenericArray::from_slice(chunk)))
+            .collect()
+    }
+}
+
+impl<F: Field, N: ArrayLength> Message for GenericArray<F, N>
+where
+    F::Size: Mul<N>,
+    Prod<F::Size, N>: ArrayLength,
+{
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use generic_array::GenericArray;
+    use typenum::U4;
+
+    use super::ReplicatedVec;
+    use crate::{
+        ff::{Field, Fp31, Serializable},
+        rand::{thread_rng, Rng},
+        secret_sharing::replicated::{semi_honest::AdditiveShare, ReplicatedSecretSharing},
+    };
+
+    #[test]
+    fn lanes_round_trip() {
+        let mut rng = thread_rng();
+        let lanes = (0..4)
+            .map(|_| AdditiveShare::new(rng.gen::<Fp31>(), rng.gen::<Fp31>()))
+            .collect::<Vec<_>>();
+        let v = ReplicatedVec::<Fp31, U4>::from_lanes(lanes.clone());
+
+        assert_eq!(lanes, v.lanes().collect::<Vec<_>>());
+        assert_eq!(
+            lanes
+                .iter()
+                .map(|s| s.clone() * Fp31::truncate_from(3_u128))
+                .collect::<Vec<_>>(),
+            (v * Fp31::truncate_from(3_u128))
+                .lanes()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 4 lanes")]
+    fn wrong_number_of_lanes() {
+        let _ = ReplicatedVec::<Fp31, U4>::from_lanes(vec![AdditiveShare::ZERO; 3]);
+    }
+
+    #[test]
+    fn serde() {
+        let mut rng = thread_rng();
+        let values = (0..4)
+            .map(|_| rng.gen::<Fp31>())
+            .collect::<GenericArray<_, U4>>();
+        let mut buf = GenericArray::default();
+        values.serialize(&mut buf);
+
+        assert_eq!(values, GenericArray::<Fp31, U4>::deserialize(&buf));
+    }
+}
@@ -47,6 +47,7 @@ where
     SequentialFutures {
         source: source.fuse(),
         active: VecDeque::with_capacity(active.get()),
+        capacity: active.get(),
     }
 }
 
@@ -95,7 +96,7 @@ pub trait SeqJoin {
     fn active_work(&self) -> NonZeroUsize;
 }
 
-type SeqTryJoinAll<I, F> = SequentialFutures<StreamIter<<I as IntoIterator>::IntoIter>, F>;
+pub type SeqTryJoinAll<I, F> = SequentialFutures<StreamIter<<I as IntoIterator>::IntoIter>, F>;
 
 /// A substitute for [`futures::future::try_join_all`] that uses [`seq_join`].
 /// This awaits all the provided futures in order,
@@ -158,6 +159,9 @@ where
     #[pin]
     source: futures::stream::Fuse<S>,
     active: VecDeque<ActiveItem<F>>,
+    /// `VecDeque::capacity` may be larger than what was asked for, so it can't be used to bound
+    /// the number of active futures.
+    capacity: usize,
 }
 
 impl<S, F> Stream for SequentialFutures<S, F>
@@ -171,7 +175,7 @@ where
         let mut this = self.project();
 
         // Draw more values from the input, up to the capacity.
-        while this.active.len() < this.active.capacity() {
+        while this.active.len() < *this.capacity {
             if let Poll::Ready(Some(f)) = this.source.as_mut().poll_next(cx) {
                 this.active
                     .push_back(ActiveItem::Pending(Box::pin(f.into_future())));
@@ -221,7 +225,10 @@ mod test {
         iter::once,
         num::NonZeroUsize,
         ptr::null,
-        sync::{Arc, Mutex},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
         task::{Context, Poll, Waker},
     };
 
@@ -230,6 +237,7 @@ mod test {
         stream::{iter, poll_fn, poll_immediate, repeat_with},
         Future, StreamExt,
     };
+    use tokio::task::yield_now;
 
     use crate::seq_join::{seq_join, seq_try_join_all};
 
@@ -299,6 +307,71 @@ mod test {
         assert_eq!(err, ERROR);
     }
 
+    /// Future `k` fails while futures before and after it are still pending. The error is what
+    /// the join resolves to, and futures more than `active` items past `k` are never started.
+    #[tokio::test]
+    async fn try_join_error_in_the_middle() {
+        const K: usize = 5;
+        let active = NonZeroUsize::new(3).unwrap();
+        let started = AtomicUsize::new(0);
+
+        let err = seq_try_join_all(
+            active,
+            (0..20).map(|i| {
+                let started = &started;
+                async move {
+                    started.fetch_add(1, Ordering::Relaxed);
+                    yield_now().await;
+                    if i == K {
+                        Err(i)
+                    } else {
+                        yield_now().await;
+                        Ok(i)
+                    }
+                }
+            }),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(K, err);
+        assert!(started.load(Ordering::Relaxed) <= K + active.get());
+    }
+
+    /// No more than `active` futures are in progress at any time, and the join does use all of
+    /// them.
+    #[tokio::test]
+    async fn bounded_concurrency() {
+        const COUNT: usize = 50;
+        // Not a power of two, so that it doesn't match what `VecDeque` allocates.
+        let active = NonZeroUsize::new(5).unwrap();
+        let in_progress = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let values = seq_join(
+            active,
+            iter((0..COUNT).map(|i| {
+                let (in_progress, peak) = (&in_progress, &peak);
+                async move {
+                    let now = in_progress.fetch_add(1, Ordering::Relaxed) + 1;
+                    peak.fetch_max(now, Ordering::Relaxed);
+                    // Later futures finish sooner, so that completion order differs from the
+                    // order of the output.
+                    for _ in 0..(COUNT - i) % 7 {
+                        yield_now().await;
+                    }
+                    in_progress.fetch_sub(1, Ordering::Relaxed);
+                    i
+                }
+            })),
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+        assert_eq!((0..COUNT).collect::<Vec<_>>(), values);
+        assert_eq!(active.get(), peak.load(Ordering::Relaxed));
+    }
+
     fn fake_waker() -> Waker {
         use std::task::{RawWaker, RawWakerVTable};
         const fn fake_raw_waker() -> RawWaker {
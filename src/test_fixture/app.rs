@@ -1,6 +1,9 @@
-use std::iter::zip;
+use std::{array, iter::zip};
 
+use futures::StreamExt;
 use generic_array::GenericArray;
+use rand::rngs::StdRng;
+use rand_core::SeedableRng;
 use typenum::Unsigned;
 
 use crate::{
@@ -8,10 +11,10 @@ use crate::{
     ff::Serializable,
     helpers::{
         query::{QueryConfig, QueryInput},
-        InMemoryNetwork, InMemoryTransport,
+        GatewayConfig, InMemoryNetwork, InMemoryTransport, StatsSnapshot, Transport,
     },
     protocol::QueryId,
-    query::QueryStatus,
+    query::{QueryProcessor, QueryStatus},
     secret_sharing::IntoShares,
     test_fixture::try_join3_array,
     AppSetup, HelperApp,
@@ -51,6 +54,8 @@ where
 pub struct TestApp {
     drivers: [HelperApp; 3],
     network: InMemoryNetwork,
+    /// Seed for sharing query inputs, set when helpers run with seeded [`GatewayConfig`].
+    input_seed: Option<u64>,
 }
 
 fn unzip_tuple_array<T, U>(input: [(T, U); 3]) -> ([T; 3], [U; 3]) {
@@ -60,10 +65,32 @@ fn unzip_tuple_array<T, U>(input: [(T, U); 3]) -> ([T; 3], [U; 3]) {
 
 impl Default for TestApp {
     fn default() -> Self {
-        let (setup, callbacks) =
-            unzip_tuple_array([AppSetup::new(), AppSetup::new(), AppSetup::new()]);
+        Self::build(None)
+    }
+}
+
+impl TestApp {
+    /// Creates an app where every helper runs queries using `config`, instead of the one derived
+    /// from the query. If `config` has a seed, inputs are shared deterministically too, so the
+    /// same query with the same inputs always produces identical results.
+    #[must_use]
+    pub fn with_gateway_config(config: GatewayConfig) -> Self {
+        Self::build(Some(config))
+    }
 
-        let network = InMemoryNetwork::new(callbacks);
+    fn build(config: Option<GatewayConfig>) -> Self {
+        let (setup, callbacks) = unzip_tuple_array(array::from_fn(|_| {
+            let processor = QueryProcessor::default();
+            AppSetup::with_processor(match config {
+                Some(config) => processor.with_gateway_config(config),
+                None => processor,
+            })
+        }));
+
+        let network = match config {
+            Some(config) => InMemoryNetwork::with_config([config; 3], callbacks),
+            None => InMemoryNetwork::new(callbacks),
+        };
         let drivers = network
             .transports()
             .iter()
@@ -74,11 +101,13 @@ impl Default for TestApp {
             .map_err(|_| "infallible")
             .unwrap();
 
-        Self { drivers, network }
+        Self {
+            drivers,
+            network,
+            input_seed: config.and_then(|c| c.seed()),
+        }
     }
-}
 
-impl TestApp {
     /// Initiates a new query on all helpers and drives it to completion.
     ///
     /// ## Errors
@@ -93,7 +122,11 @@ impl TestApp {
         I: IntoShares<A>,
         A: IntoBuf,
     {
-        let helpers_input = input.share().map(IntoBuf::into_buf);
+        let helpers_input = match self.input_seed {
+            Some(seed) => input.share_with(&mut StdRng::seed_from_u64(seed)),
+            None => input.share(),
+        }
+        .map(IntoBuf::into_buf);
 
         // helper 1 initiates the query
         let query_id = self.drivers[0].start_query(query_config).await?;
@@ -105,6 +138,7 @@ impl TestApp {
             .map(|(i, input)| {
                 self.drivers[i].execute_query(QueryInput {
                     query_id,
+                    length: Some(input.len()),
                     input_stream: input.into(),
                 })
             })
@@ -136,6 +170,56 @@ impl TestApp {
         results
     }
 
+    /// Same as [`complete_query`], but also returns the statistics about records data each
+    /// helper exchanged with its peers.
+    ///
+    /// [`complete_query`]: Self::complete_query
+    ///
+    /// ## Errors
+    /// Returns an error if one or more helpers can't finish the processing.
+    /// ## Panics
+    /// Never.
+    pub async fn complete_query_with_stats(
+        &self,
+        query_id: QueryId,
+    ) -> Result<[(Vec<u8>, StatsSnapshot); 3], Error> {
+        let mut results =
+            try_join3_array([0, 1, 2].map(|i| self.drivers[i].complete_query_with_stats(query_id)))
+                .await;
+        // Data sent to a peer is accounted for as the peer consumes it, which may happen after
+        // the sender completed the query. Once every helper completed it, all of it is consumed.
+        if let Ok(results) = &mut results {
+            for ((_, stats), transport) in results.iter_mut().zip(self.network.transports()) {
+                stats.merge(transport.stats().take(query_id, false));
+            }
+        }
+        self.network.reset();
+        results
+    }
+
+    /// Same as [`complete_query`], but collects the results that helpers stream back in chunks.
+    ///
+    /// [`complete_query`]: Self::complete_query
+    ///
+    /// ## Errors
+    /// Returns an error if one or more helpers can't finish the processing.
+    /// ## Panics
+    /// Never.
+    pub async fn complete_query_stream(&self, query_id: QueryId) -> Result<[Vec<u8>; 3], Error> {
+        let results = try_join3_array([0, 1, 2].map(|i| async move {
+            Ok::<_, Error>(
+                self.drivers[i]
+                    .complete_query_stream(query_id)
+                    .await?
+                    .concat()
+                    .await,
+            )
+        }))
+        .await;
+        self.network.reset();
+        results
+    }
+
     /// Initiates a new query on all helpers and drives it to completion.
     ///
     /// ## Errors
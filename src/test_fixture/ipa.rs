@@ -260,12 +260,6 @@ pub async fn test_oprf_ipa<F>(
         test_fixture::Runner,
     };
 
-    let user_cap: i32 = config.per_user_credit_cap.try_into().unwrap();
-    assert!(
-        user_cap & (user_cap - 1) == 0,
-        "This code only works for a user cap which is a power of 2"
-    );
-
     //TODO(richaj) This manual sorting will be removed once we have the PRF sharding in place
     records.sort_by(|a, b| b.user_id.cmp(&a.user_id));
 
@@ -304,7 +298,7 @@ pub async fn test_oprf_ipa<F>(
                 >(
                     ctx,
                     sharded_input,
-                    user_cap.ilog2().try_into().unwrap(),
+                    config.per_user_credit_cap,
                     config.attribution_window_seconds,
                     ref_to_histogram,
                 )
@@ -14,6 +14,13 @@ mod event_gen;
 pub mod ipa;
 pub mod logging;
 pub mod metrics;
+#[cfg(feature = "in-memory-infra")]
+mod network;
+#[cfg(all(test, unit_test))]
+mod span_capture;
+#[cfg(feature = "in-memory-infra")]
+mod traffic;
+mod transport;
 
 use std::fmt::Debug;
 
@@ -21,9 +28,18 @@ use std::fmt::Debug;
 pub use app::TestApp;
 pub use event_gen::{Config as EventGeneratorConfig, EventGenerator};
 use futures::TryFuture;
+#[cfg(feature = "in-memory-infra")]
+pub use network::Tampering;
 use rand::{distributions::Standard, prelude::Distribution, rngs::mock::StepRng};
 use rand_core::{CryptoRng, RngCore};
 pub use sharing::{get_bits, into_bits, Reconstruct};
+#[cfg(all(test, unit_test))]
+pub use span_capture::SpanCapture;
+#[cfg(feature = "in-memory-infra")]
+pub use traffic::{TrafficRecord, TrafficRecorder};
+pub use transport::{
+    Delay, DelayedTransport, DelayedTransportBuilder, FailingStream, FailingTransport,
+};
 #[cfg(feature = "in-memory-infra")]
 pub use world::{Runner, TestWorld, TestWorldConfig};
 
@@ -0,0 +1,73 @@
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+
+use crate::{
+    helpers::{HelperIdentity, SendInterceptor},
+    protocol::step::Gate,
+    sync::Arc,
+};
+
+/// Decides what a malicious helper sends instead of a chunk of records data. It is called with
+/// the sender, the destination, the step and the index of the chunk within its stream, and
+/// returns the chunks to send in its place.
+type TamperPolicy =
+    dyn Fn(HelperIdentity, HelperIdentity, &Gate, usize, Vec<u8>) -> Vec<Vec<u8>> + Send + Sync;
+
+/// Makes in-memory helpers act maliciously by tampering with records data they send, see
+/// [`TestWorldConfig::tampering`].
+///
+/// The policy is shared by every stream, so besides flipping bits in a chunk, dropping it (no
+/// chunks are returned) or duplicating it (the same chunk is returned twice), it can hold chunks
+/// back and send them later, on the same stream or on a different one.
+///
+/// [`TestWorldConfig::tampering`]: crate::test_fixture::TestWorldConfig::tampering
+#[derive(Clone)]
+pub struct Tampering {
+    policy: Arc<TamperPolicy>,
+}
+
+impl Tampering {
+    #[must_use]
+    pub fn new<P>(policy: P) -> Self
+    where
+        P: Fn(HelperIdentity, HelperIdentity, &Gate, usize, Vec<u8>) -> Vec<Vec<u8>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self {
+            policy: Arc::new(policy),
+        }
+    }
+
+    /// Tampering that changes chunks in place. Chunks left empty are dropped.
+    #[must_use]
+    pub fn in_place<P>(policy: P) -> Self
+    where
+        P: Fn(HelperIdentity, HelperIdentity, &Gate, usize, &mut Vec<u8>) + Send + Sync + 'static,
+    {
+        Self::new(move |from, to, gate, i, mut chunk| {
+            policy(from, to, gate, i, &mut chunk);
+            if chunk.is_empty() {
+                Vec::new()
+            } else {
+                vec![chunk]
+            }
+        })
+    }
+
+    /// Returns the hook that applies this tampering to records streams in-memory helpers send.
+    pub(super) fn interceptor(&self) -> Arc<SendInterceptor> {
+        let policy = Arc::clone(&self.policy);
+        Arc::new(
+            move |from, to, gate: &Gate, data: BoxStream<'static, Vec<u8>>| {
+                let (policy, gate) = (Arc::clone(&policy), gate.clone());
+                data.enumerate()
+                    .flat_map(move |(i, chunk)| stream::iter(policy(from, to, &gate, i, chunk)))
+                    .boxed()
+            },
+        )
+    }
+}
@@ -7,7 +7,6 @@ use crate::{
         replicated::{
             malicious::{AdditiveShare as MaliciousReplicated, ExtendableField},
             semi_honest::AdditiveShare as Replicated,
-            ReplicatedSecretSharing,
         },
         BitDecomposed, SecretSharing,
     },
@@ -39,20 +38,7 @@ pub trait Reconstruct<T> {
 
 impl<F: Field> Reconstruct<F> for [&Replicated<F>; 3] {
     fn reconstruct(&self) -> F {
-        let s0 = &self[0];
-        let s1 = &self[1];
-        let s2 = &self[2];
-
-        assert_eq!(
-            s0.left() + s1.left() + s2.left(),
-            s0.right() + s1.right() + s2.right(),
-        );
-
-        assert_eq!(s0.right(), s1.left());
-        assert_eq!(s1.right(), s2.left());
-        assert_eq!(s2.right(), s0.left());
-
-        s0.left() + s1.left() + s2.left()
+        Replicated::reconstruct(self.map(Clone::clone)).unwrap()
     }
 }
 
@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    subscriber::DefaultGuard,
+    Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    util::SubscriberInitExt,
+    Layer,
+};
+
+/// Captures the fields of every span created while it is installed as the default subscriber.
+#[derive(Clone, Default)]
+pub struct SpanCapture {
+    inner: Arc<Mutex<CapturedSpans>>,
+}
+
+#[derive(Default)]
+struct CapturedSpans {
+    spans: Vec<(&'static str, HashMap<&'static str, String>)>,
+    ids: HashMap<Id, usize>,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SpanCapture {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let mut inner = self.inner.lock().unwrap();
+        let idx = inner.spans.len();
+        inner.spans.push((attrs.metadata().name(), fields));
+        inner.ids.insert(id.clone(), idx);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(&idx) = inner.ids.get(id) {
+            values.record(&mut FieldVisitor(&mut inner.spans[idx].1));
+        }
+    }
+}
+
+impl SpanCapture {
+    /// Installs this capture as the default subscriber of the current thread, until the returned
+    /// guard is dropped.
+    ///
+    /// Global logging is set up first. Installing any subscriber sets the global logger as well,
+    /// so tests that set up logging afterwards would fail otherwise.
+    #[must_use]
+    pub fn install(&self) -> DefaultGuard {
+        super::logging::setup();
+        tracing_subscriber::registry()
+            .with(self.clone())
+            .set_default()
+    }
+
+    /// Returns the fields of every captured span with the given name, in the order they were
+    /// created.
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    #[must_use]
+    pub fn find(&self, name: &str) -> Vec<HashMap<&'static str, String>> {
+        self.inner
+            .lock()
+            .unwrap()
+            .spans
+            .iter()
+            .filter(|(span_name, _)| *span_name == name)
+            .map(|(_, fields)| fields.clone())
+            .collect()
+    }
+}
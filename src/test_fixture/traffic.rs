@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use crate::{
+    helpers::{HelperIdentity, SendObserver},
+    protocol::{step::Gate, QueryId},
+    sync::{Arc, Mutex},
+};
+
+/// A single chunk of records data sent from one helper to another.
+#[derive(Clone, Debug)]
+pub struct TrafficRecord {
+    pub from: HelperIdentity,
+    pub to: HelperIdentity,
+    pub query_id: QueryId,
+    pub gate: Gate,
+    pub data: Vec<u8>,
+}
+
+/// Collects all the data helpers send to each other, to see what exactly went over the wire when
+/// protocol misbehaves.
+///
+/// ```ignore
+/// let recorder = TrafficRecorder::default();
+/// let world = TestWorld::new_with(TestWorldConfig {
+///     send_observer: Some(recorder.observer()),
+///     ..TestWorldConfig::default()
+/// });
+/// // ... run the protocol
+/// let sent = recorder.with_step_prefix("protocol/multiply");
+/// ```
+#[derive(Clone, Default)]
+pub struct TrafficRecorder {
+    records: Arc<Mutex<Vec<TrafficRecord>>>,
+}
+
+impl TrafficRecorder {
+    /// Returns the observer to install on in-memory transports.
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    #[must_use]
+    pub fn observer(&self) -> Arc<SendObserver> {
+        let records = Arc::clone(&self.records);
+        Arc::new(
+            move |from: HelperIdentity,
+                  to: HelperIdentity,
+                  query_id: QueryId,
+                  gate: &Gate,
+                  data: &[u8]| {
+                records.lock().unwrap().push(TrafficRecord {
+                    from,
+                    to,
+                    query_id,
+                    gate: gate.clone(),
+                    data: data.to_vec(),
+                });
+            },
+        )
+    }
+
+    /// Returns everything recorded so far, in the order it was sent.
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    #[must_use]
+    pub fn records(&self) -> Vec<TrafficRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Returns the records sent on steps with paths that start with `prefix`.
+    ///
+    /// ## Panics
+    /// If mutex is poisoned.
+    #[must_use]
+    pub fn with_step_prefix(&self, prefix: &str) -> Vec<TrafficRecord> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| record.gate.as_ref().starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the number of bytes `helper` sent on steps that have `step` anywhere in their
+    /// paths. Protocols that take a single round send everything to one peer, this checks it too.
+    ///
+    /// ## Panics
+    /// If `helper` sent data on these steps to more than one peer, or if mutex is poisoned.
+    #[must_use]
+    pub fn bytes_sent_by(&self, helper: HelperIdentity, step: &str) -> usize {
+        let records = self.records.lock().unwrap();
+        let sent = records
+            .iter()
+            .filter(|record| {
+                record.from == helper && record.gate.as_ref().split('/').any(|s| s == step)
+            })
+            .collect::<Vec<_>>();
+        assert!(
+            sent.iter().all(|record| record.to == sent[0].to),
+            "{helper:?} sent data on {step} to more than one peer"
+        );
+
+        sent.iter().map(|record| record.data.len()).sum()
+    }
+
+    /// Returns the number of rounds and multiplications seen in the traffic sent on steps below
+    /// `name`. Protocols that multiply on `{name}/{round}/{i}` steps, such as trees and prefix
+    /// circuits, take as many rounds as there are distinct `round` steps, and make a
+    /// multiplication for every distinct pair of `round` and `i`.
+    ///
+    /// ## Panics
+    /// If a step below `name` has less than two parts, or if mutex is poisoned.
+    #[must_use]
+    pub fn rounds_and_multiplications(&self, name: &str) -> (usize, usize) {
+        let marker = format!("/{name}/");
+        let mut rounds = HashSet::new();
+        let mut multiplications = HashSet::new();
+        for record in self.with_step_prefix("protocol") {
+            let gate = record.gate.as_ref();
+            let Some(start) = gate.find(&marker) else {
+                continue;
+            };
+            let mut position = gate[start + marker.len()..].split('/');
+            let (round, i) = (position.next().unwrap(), position.next().unwrap());
+            rounds.insert(round.to_owned());
+            multiplications.insert((round.to_owned(), i.to_owned()));
+        }
+
+        (rounds.len(), multiplications.len())
+    }
+}
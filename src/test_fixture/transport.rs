@@ -0,0 +1,443 @@
+use std::{
+    collections::HashMap,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tokio::sync::Semaphore;
+
+use crate::{
+    helpers::{
+        HelperIdentity, NoResourceIdentifier, QueryIdBinding, RouteId, RouteParams, StepBinding,
+        Transport, TransportStats,
+    },
+    protocol::{step::Gate, QueryId, RecordId},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Specifies for how long [`DelayedTransport`] holds a send before handing it over to the
+/// transport it wraps.
+#[derive(Debug, Clone, Copy)]
+pub enum Delay {
+    /// Every send is held until [`DelayedTransport::release`] is called this many times for its
+    /// route and destination.
+    Tokens(u32),
+    /// Every send is held for the given amount of time.
+    Duration(Duration),
+}
+
+struct Hold {
+    delay: Delay,
+    released: Semaphore,
+}
+
+impl Hold {
+    fn new(delay: Delay) -> Self {
+        Self {
+            delay,
+            released: Semaphore::new(0),
+        }
+    }
+
+    async fn wait(&self) {
+        match self.delay {
+            Delay::Tokens(tokens) => self
+                .released
+                .acquire_many(tokens)
+                .await
+                .expect("semaphore is never closed")
+                .forget(),
+            Delay::Duration(duration) => tokio::time::sleep(duration).await,
+        }
+    }
+}
+
+/// Transport that holds sends to specific helpers until tests allow them to proceed. Sends are
+/// matched by their route and destination, the ones that don't have a delay configured go
+/// through immediately.
+///
+/// ```ignore
+/// let transport = DelayedTransport::builder(transport)
+///     .delay(RouteId::PrepareQuery, HelperIdentity::THREE, Delay::Tokens(1))
+///     .build();
+/// // ... prepare requests to H3 are held until
+/// transport.release(RouteId::PrepareQuery, HelperIdentity::THREE);
+/// ```
+#[derive(Clone)]
+pub struct DelayedTransport<T> {
+    inner: T,
+    holds: Arc<HashMap<(RouteId, HelperIdentity), Hold>>,
+}
+
+pub struct DelayedTransportBuilder<T> {
+    inner: T,
+    delays: HashMap<(RouteId, HelperIdentity), Delay>,
+}
+
+impl<T: Transport> DelayedTransportBuilder<T> {
+    /// Delays every send on `route` to `dest`. Calling it again for the same pair overrides the
+    /// previous delay.
+    #[must_use]
+    pub fn delay(mut self, route: RouteId, dest: HelperIdentity, delay: Delay) -> Self {
+        self.delays.insert((route, dest), delay);
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> DelayedTransport<T> {
+        DelayedTransport {
+            inner: self.inner,
+            holds: Arc::new(
+                self.delays
+                    .into_iter()
+                    .map(|(key, delay)| (key, Hold::new(delay)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<T: Transport> DelayedTransport<T> {
+    /// Creates a transport that does not delay anything.
+    #[must_use]
+    pub fn new(inner: T) -> Self {
+        Self::builder(inner).build()
+    }
+
+    #[must_use]
+    pub fn builder(inner: T) -> DelayedTransportBuilder<T> {
+        DelayedTransportBuilder {
+            inner,
+            delays: HashMap::new(),
+        }
+    }
+
+    /// Gives one release token to sends on `route` to `dest`.
+    ///
+    /// ## Panics
+    /// If sends on `route` to `dest` are not delayed by [`Delay::Tokens`].
+    pub fn release(&self, route: RouteId, dest: HelperIdentity) {
+        match self.holds.get(&(route, dest)) {
+            Some(
+                hold @ Hold {
+                    delay: Delay::Tokens(_),
+                    ..
+                },
+            ) => hold.released.add_permits(1),
+            _ => panic!("Sends on {route:?} to {dest:?} are not delayed by tokens"),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for DelayedTransport<T> {
+    type RecordsStream = T::RecordsStream;
+    type Error = T::Error;
+
+    fn identity(&self) -> HelperIdentity {
+        self.inner.identity()
+    }
+
+    async fn send<D, Q, S, R>(
+        &self,
+        dest: HelperIdentity,
+        route: R,
+        data: D,
+    ) -> Result<(), Self::Error>
+    where
+        Option<QueryId>: From<Q>,
+        Option<Gate>: From<S>,
+        Q: QueryIdBinding,
+        S: StepBinding,
+        R: RouteParams<RouteId, Q, S>,
+        D: Stream<Item = Vec<u8>> + Send + 'static,
+    {
+        if let Some(hold) = self.holds.get(&(route.resource_identifier(), dest)) {
+            hold.wait().await;
+        }
+        self.inner.send(dest, route, data).await
+    }
+
+    fn receive<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+    ) -> Self::RecordsStream {
+        self.inner.receive(from, route)
+    }
+
+    fn receive_from<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+        start: RecordId,
+        record_size: usize,
+    ) -> Self::RecordsStream {
+        self.inner.receive_from(from, route, start, record_size)
+    }
+
+    fn receive_query_records(
+        &self,
+        from: HelperIdentity,
+        query_id: QueryId,
+    ) -> Self::RecordsStream {
+        self.inner.receive_query_records(from, query_id)
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.inner.stats()
+    }
+
+    async fn ping(&self, dest: HelperIdentity, timeout: Duration) -> Result<(), io::Error> {
+        self.inner.ping(dest, timeout).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await;
+    }
+}
+
+/// Decides whether a send must fail, given its route, destination and the number of sends made
+/// through the transport before it.
+type SendPolicy = dyn Fn(RouteId, HelperIdentity, usize) -> Option<io::Error> + Send + Sync;
+
+/// Makes the error that a stream returned from [`FailingTransport::receive`] fails with.
+type ReceiveError = dyn Fn() -> io::Error + Send + Sync;
+
+/// Transport that injects failures into the transport it wraps. Every send is checked against a
+/// policy and fails with the error it returns, without reaching the inner transport.
+///
+/// Streams returned from `receive` can be made to fail too, see [`fail_receive_after`].
+///
+/// [`fail_receive_after`]: Self::fail_receive_after
+#[derive(Clone)]
+pub struct FailingTransport<T> {
+    inner: T,
+    send_policy: Arc<SendPolicy>,
+    attempts: Arc<AtomicUsize>,
+    receive_failure: Option<(usize, Arc<ReceiveError>)>,
+}
+
+impl<T: Transport> FailingTransport<T> {
+    #[must_use]
+    pub fn new<P>(inner: T, send_policy: P) -> Self
+    where
+        P: Fn(RouteId, HelperIdentity, usize) -> Option<io::Error> + Send + Sync + 'static,
+    {
+        Self {
+            inner,
+            send_policy: Arc::new(send_policy),
+            attempts: Arc::new(AtomicUsize::new(0)),
+            receive_failure: None,
+        }
+    }
+
+    /// Makes every stream returned from `receive` fail with the error `error` makes, once it
+    /// yields `chunks` chunks of data.
+    #[must_use]
+    pub fn fail_receive_after<E>(mut self, chunks: usize, error: E) -> Self
+    where
+        E: Fn() -> io::Error + Send + Sync + 'static,
+    {
+        self.receive_failure = Some((chunks, Arc::new(error)));
+        self
+    }
+
+    fn failing<S>(&self, inner: S) -> FailingStream<S> {
+        FailingStream {
+            inner,
+            failure: self.receive_failure.clone(),
+            error: None,
+        }
+    }
+}
+
+/// Records stream returned from [`FailingTransport::receive`].
+///
+/// Records streams have no way to yield an error, so this stream fails the same way transports
+/// fail streams that they can't read from the network: the error is logged and the stream ends.
+/// The error is kept, so tests can check that the stream failed rather than finished, see
+/// [`error`].
+///
+/// [`error`]: Self::error
+pub struct FailingStream<S> {
+    inner: S,
+    failure: Option<(usize, Arc<ReceiveError>)>,
+    error: Option<io::Error>,
+}
+
+impl<S> FailingStream<S> {
+    /// Returns the error this stream failed with, if it did.
+    #[must_use]
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl<S: Stream<Item = Vec<u8>> + Unpin> Stream for FailingStream<S> {
+    type Item = Vec<u8>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = Pin::get_mut(self);
+        if this.error.is_some() {
+            return Poll::Ready(None);
+        }
+        match &mut this.failure {
+            Some((0, error)) => {
+                let error = error();
+                tracing::error!("error reading records: {error}");
+                this.error = Some(error);
+                Poll::Ready(None)
+            }
+            Some((chunks, _)) => {
+                let chunk = ready!(this.inner.poll_next_unpin(cx));
+                if chunk.is_some() {
+                    *chunks -= 1;
+                }
+                Poll::Ready(chunk)
+            }
+            None => this.inner.poll_next_unpin(cx),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Transport for FailingTransport<T>
+where
+    T: Transport,
+    T::Error: From<io::Error>,
+{
+    type RecordsStream = FailingStream<T::RecordsStream>;
+    type Error = T::Error;
+
+    fn identity(&self) -> HelperIdentity {
+        self.inner.identity()
+    }
+
+    async fn send<D, Q, S, R>(
+        &self,
+        dest: HelperIdentity,
+        route: R,
+        data: D,
+    ) -> Result<(), Self::Error>
+    where
+        Option<QueryId>: From<Q>,
+        Option<Gate>: From<S>,
+        Q: QueryIdBinding,
+        S: StepBinding,
+        R: RouteParams<RouteId, Q, S>,
+        D: Stream<Item = Vec<u8>> + Send + 'static,
+    {
+        let attempt = self.attempts.fetch_add(1, Ordering::Relaxed);
+        if let Some(e) = (self.send_policy)(route.resource_identifier(), dest, attempt) {
+            return Err(e.into());
+        }
+        self.inner.send(dest, route, data).await
+    }
+
+    fn receive<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+    ) -> Self::RecordsStream {
+        self.failing(self.inner.receive(from, route))
+    }
+
+    fn receive_from<R: RouteParams<NoResourceIdentifier, QueryId, Gate>>(
+        &self,
+        from: HelperIdentity,
+        route: R,
+        start: RecordId,
+        record_size: usize,
+    ) -> Self::RecordsStream {
+        self.failing(self.inner.receive_from(from, route, start, record_size))
+    }
+
+    fn receive_query_records(
+        &self,
+        from: HelperIdentity,
+        query_id: QueryId,
+    ) -> Self::RecordsStream {
+        self.failing(self.inner.receive_query_records(from, query_id))
+    }
+
+    fn stats(&self) -> TransportStats {
+        self.inner.stats()
+    }
+
+    async fn ping(&self, dest: HelperIdentity, timeout: Duration) -> Result<(), io::Error> {
+        self.inner.ping(dest, timeout).await
+    }
+
+    async fn shutdown(&self) {
+        self.inner.shutdown().await;
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::io;
+
+    use futures::{stream, StreamExt};
+
+    use super::FailingTransport;
+    use crate::{
+        helpers::{HelperIdentity, InMemoryNetwork, RouteId, Transport},
+        protocol::{step::Gate, QueryId},
+    };
+
+    #[tokio::test]
+    async fn receive_fails_after_chunks() {
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let t2 = FailingTransport::new(t2, |_, _, _| None)
+            .fail_receive_after(2, || io::Error::from(io::ErrorKind::ConnectionAborted));
+        let gate = Gate::from("failing-transport");
+
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), gate.clone()),
+            stream::iter(vec![vec![1], vec![2], vec![3]]),
+        )
+        .await
+        .unwrap();
+        let mut recv = t2.receive(HelperIdentity::ONE, (QueryId::default(), gate));
+
+        assert_eq!(
+            vec![vec![1], vec![2]],
+            (&mut recv).collect::<Vec<_>>().await
+        );
+        assert_eq!(
+            Some(io::ErrorKind::ConnectionAborted),
+            recv.error().map(io::Error::kind)
+        );
+    }
+
+    #[tokio::test]
+    async fn receive_finishes_before_failure() {
+        let network = InMemoryNetwork::default();
+        let [t1, t2, _] = network.transports();
+        let t2 = FailingTransport::new(t2, |_, _, _| None)
+            .fail_receive_after(2, || io::Error::from(io::ErrorKind::ConnectionAborted));
+        let gate = Gate::from("failing-transport");
+
+        t1.send(
+            HelperIdentity::TWO,
+            (RouteId::Records, QueryId::default(), gate.clone()),
+            stream::iter(vec![vec![1]]),
+        )
+        .await
+        .unwrap();
+        let mut recv = t2.receive(HelperIdentity::ONE, (QueryId::default(), gate));
+
+        assert_eq!(vec![vec![1]], (&mut recv).collect::<Vec<_>>().await);
+        assert!(recv.error().is_none());
+    }
+}
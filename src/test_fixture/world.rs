@@ -1,4 +1,4 @@
-use std::{fmt::Debug, io::stdout, iter::zip};
+use std::{array, fmt::Debug, io::stdout, iter::zip};
 
 use async_trait::async_trait;
 use futures::{future::join_all, Future};
@@ -7,7 +7,10 @@ use rand_core::{RngCore, SeedableRng};
 use tracing::{Instrument, Level, Span};
 
 use crate::{
-    helpers::{Gateway, GatewayConfig, InMemoryNetwork, Role, RoleAssignment},
+    helpers::{
+        Gateway, GatewayConfig, HelperIdentity, InMemoryNetwork, InMemorySetup, NetworkConfig,
+        Role, RoleAssignment, SendObserver, TransportCallbacks,
+    },
     protocol::{
         context::{
             Context, MaliciousContext, SemiHonestContext, UpgradableContext, UpgradeContext,
@@ -27,7 +30,8 @@ use crate::{
     },
     telemetry::{stats::Metrics, StepStatsCsvExporter},
     test_fixture::{
-        logging, make_participants, metrics::MetricsHandle, sharing::ValidateMalicious, Reconstruct,
+        logging, make_participants, metrics::MetricsHandle, sharing::ValidateMalicious,
+        Reconstruct, Tampering,
     },
 };
 
@@ -54,6 +58,15 @@ pub struct TestWorldConfig {
     pub role_assignment: Option<RoleAssignment>,
     /// Seed for random generators used in PRSS
     pub seed: u64,
+    /// Conditions of the network between helpers. Data is delivered instantly by default.
+    pub network_config: NetworkConfig,
+    /// If set, every chunk of data sent between helpers is reported to this observer.
+    /// [`TrafficRecorder`] can be used to collect it.
+    ///
+    /// [`TrafficRecorder`]: crate::test_fixture::TrafficRecorder
+    pub send_observer: Option<Arc<SendObserver>>,
+    /// If set, helpers tamper with data they send, to emulate malicious ones.
+    pub tampering: Option<Tampering>,
 }
 
 impl Default for TestWorldConfig {
@@ -66,6 +79,9 @@ impl Default for TestWorldConfig {
             metrics_level: Level::DEBUG,
             role_assignment: None,
             seed: thread_rng().next_u64(),
+            network_config: NetworkConfig::default(),
+            send_observer: None,
+            tampering: None,
         }
     }
 }
@@ -100,20 +116,33 @@ impl TestWorld {
 
         let metrics_handle = MetricsHandle::new(config.metrics_level);
         let participants = make_participants(&mut StdRng::seed_from_u64(config.seed));
-        let network = InMemoryNetwork::default();
+        let network = InMemoryNetwork::from_setups(
+            HelperIdentity::make_three().map(|id| {
+                let mut setup = InMemorySetup::new(id)
+                    .with_gateway_config(config.gateway_config)
+                    .with_network_config(config.network_config);
+                if let Some(observer) = &config.send_observer {
+                    setup = setup.with_send_observer(Arc::clone(observer));
+                }
+                if let Some(tampering) = &config.tampering {
+                    setup = setup.with_send_interceptor(tampering.interceptor());
+                }
+                setup
+            }),
+            array::from_fn(|_| TransportCallbacks::default()),
+        );
         let role_assignment = config
             .role_assignment
             .unwrap_or_else(|| RoleAssignment::new(network.helper_identities()));
 
         let mut gateways = [None, None, None];
-        for i in 0..3 {
-            let transport = &network.transports[i];
+        for transport in network.transports() {
             let role_assignment = role_assignment.clone();
             let gateway = Gateway::new(
-                QueryId,
+                QueryId::default(),
                 config.gateway_config,
                 role_assignment,
-                Arc::downgrade(transport),
+                transport,
             );
             let role = gateway.role();
             gateways[role] = Some(gateway);